@@ -1,36 +1,55 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::time::{Duration, Instant};
 use std::sync::Arc;
 
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::broadcast::{Receiver, Sender};
 use tokio::time::{sleep_until as tokio_sleep_until, Sleep, Instant as TokioInstant};
 
 use crate::bus::Envelope;
 use koi::policy::{DefaultPolicy, NullPolicy};
 
+/// Name Act registers under with the sim clock.
+const CONTROLLER: &str = "act";
+/// Ticks without a `/sim/time` before Act drops the sim clock and goes back to wall-clock ticks.
+const SIM_SILENCE_TICKS: u32 = 5;
+
 /// Spawns the Act (fast, System-1) control loop.
 ///
 /// * `tx` – broadcast sender for publishing actuator commands.
 /// * `rx` – receiver for subscribing to all bus traffic (sensors, etc.).
+///
+/// The loop ticks on the wall clock until a simulator publishes `/sim/time`; from then on it
+/// ticks on sim time, registers with the sim clock and acknowledges every sim step on
+/// `/sim/clock/ack` after handling it, so a lockstep sim waits for it.  If the sim goes quiet
+/// for [`SIM_SILENCE_TICKS`] ticks, Act unregisters and ticks on the wall clock again until the
+/// next `/sim/time`.  It also unregisters when the bus closes; see [`unregister`] for shutdown.
 pub fn spawn_act(tx: Sender<Envelope>, mut rx: Receiver<Envelope>) {
     tokio::spawn(async move {
         let mut sensors: HashMap<String, Vec<u8>> = HashMap::new();
+        // Motors currently online on the CAN bridge (`/sensor/motor/<id>/online`)
+        let mut motors: BTreeSet<u32> = BTreeSet::new();
         let tick = Duration::from_millis(20); // 50 Hz
         let mut next_tick = Instant::now() + tick;
+        // Sim time (s) of the next tick once a simulator drives the clock
+        let mut next_sim_tick: Option<f64> = None;
+        // Wall-clock time the last `/sim/time` arrived
+        let mut last_sim = Instant::now();
 
         // Load policy model (path via KOI_ACT_MODEL)
-        let policy: Arc<dyn koi::policy::PolicyModel> = {
+        // Motors are only driven by a loaded model, never by the NullPolicy placeholder
+        let (policy, drive_motors): (Arc<dyn koi::policy::PolicyModel>, bool) = {
             let model_path = std::env::var("KOI_ACT_MODEL").ok();
             if let Some(p) = model_path {
                 match DefaultPolicy::load(&p) {
-                    Ok(m) => Arc::new(m),
+                    Ok(m) => (Arc::new(m), true),
                     Err(e) => {
                         eprintln!("[act] Failed to load policy '{p}': {e}. Falling back to NullPolicy");
-                        Arc::new(NullPolicy)
+                        (Arc::new(NullPolicy), false)
                     }
                 }
             } else {
-                Arc::new(NullPolicy)
+                (Arc::new(NullPolicy), false)
             }
         };
 
@@ -38,22 +57,72 @@ pub fn spawn_act(tx: Sender<Envelope>, mut rx: Receiver<Envelope>) {
             tokio::select! {
                 // Gather incoming sensor data
                 msg = rx.recv() => {
-                    if let Ok(env) = msg {
-                        if env.topic.starts_with("/sensor/") {
-                            sensors.insert(env.topic.clone(), env.data.clone());
+                    let env = match msg {
+                        Ok(env) => env,
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => {
+                            if next_sim_tick.is_some() {
+                                unregister(&tx);
+                            }
+                            break;
+                        }
+                    };
+                    if let Some((time, step)) = sim_time(&env) {
+                        last_sim = Instant::now();
+                        let due = next_sim_tick.get_or_insert_with(|| {
+                            let _ = tx.send(Envelope { topic: "/sim/clock/register".into(), data: CONTROLLER.into() });
+                            time
+                        });
+                        if time >= *due {
+                            run_control_step(&policy, &sensors, &tx);
+                            if drive_motors {
+                                run_motor_step(&policy, &sensors, &motors, &tx);
+                            }
+                            *due = (*due + tick.as_secs_f64()).max(time);
                         }
+                        let mut ack = step.to_le_bytes().to_vec();
+                        ack.extend_from_slice(CONTROLLER.as_bytes());
+                        let _ = tx.send(Envelope { topic: "/sim/clock/ack".into(), data: ack });
+                    } else if env.topic.starts_with("/sensor/") {
+                        if let Some(id) = motor_online_topic(&env.topic) {
+                            if env.data.first() == Some(&1) {
+                                motors.insert(id);
+                            } else {
+                                motors.remove(&id);
+                            }
+                        }
+                        sensors.insert(env.topic.clone(), env.data.clone());
                     }
                 }
 
                 _ = sleep_until(next_tick) => {
+                    let now = Instant::now();
+                    if next_sim_tick.is_some() {
+                        if now.duration_since(last_sim) < tick * SIM_SILENCE_TICKS {
+                            next_tick = now + tick;
+                            continue;
+                        }
+                        eprintln!("[act] No /sim/time for {SIM_SILENCE_TICKS} ticks; back on the wall clock");
+                        next_sim_tick = None;
+                        unregister(&tx);
+                    }
                     run_control_step(&policy, &sensors, &tx);
-                    next_tick += tick;
+                    if drive_motors {
+                        run_motor_step(&policy, &sensors, &motors, &tx);
+                    }
+                    next_tick = (next_tick + tick).max(now);
                 }
             }
         }
     });
 }
 
+/// Tells the sim clock to stop waiting for Act, so a lockstep sim isn't left stalled on a
+/// controller that has gone away.  Harmless if Act never registered.
+pub fn unregister(tx: &Sender<Envelope>) {
+    let _ = tx.send(Envelope { topic: "/sim/clock/unregister".into(), data: CONTROLLER.into() });
+}
+
 fn sleep_until(deadline: Instant) -> Sleep {
     let tokio_deadline: TokioInstant = deadline.into();
     tokio_sleep_until(tokio_deadline)
@@ -74,4 +143,51 @@ fn run_control_step(policy: &std::sync::Arc<dyn koi::policy::PolicyModel>, senso
             });
         }
     }
-}
\ No newline at end of file
+}
+
+/// Sim time (s) and step of a `/sim/time` message.
+fn sim_time(env: &Envelope) -> Option<(f64, u64)> {
+    if env.topic != "/sim/time" {
+        return None;
+    }
+    let time = f64::from_le_bytes(env.data.get(..8)?.try_into().ok()?);
+    let step = u64::from_le_bytes(env.data.get(8..16)?.try_into().ok()?);
+    Some((time, step))
+}
+
+/// Motor ID of a `/sensor/motor/<id>/online` topic.
+fn motor_online_topic(topic: &str) -> Option<u32> {
+    topic.strip_prefix("/sensor/motor/")?.strip_suffix("/online")?.parse().ok()
+}
+
+fn sensor_f32(sensors: &HashMap<String, Vec<u8>>, topic: &str) -> f32 {
+    sensors
+        .get(topic)
+        .and_then(|b| b.get(..4))
+        .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+        .unwrap_or(0.0)
+}
+
+/// Feeds `[angle, speed]` of every online motor (ordered by ID) to the policy and publishes
+/// one speed target (deg/s) per motor on `/actuator/motor/<id>/speed`.
+fn run_motor_step(policy: &std::sync::Arc<dyn koi::policy::PolicyModel>, sensors: &HashMap<String, Vec<u8>>, motors: &BTreeSet<u32>, tx: &Sender<Envelope>) {
+    if motors.is_empty() {
+        return;
+    }
+    let input: Vec<f32> = motors
+        .iter()
+        .flat_map(|id| {
+            [
+                sensor_f32(sensors, &format!("/sensor/motor/{id}/angle")),
+                sensor_f32(sensors, &format!("/sensor/motor/{id}/speed")),
+            ]
+        })
+        .collect();
+    let Ok(output) = policy.infer(&input) else { return };
+    for (id, speed) in motors.iter().zip(output) {
+        let _ = tx.send(Envelope {
+            topic: format!("/actuator/motor/{id}/speed"),
+            data: speed.to_le_bytes().to_vec(),
+        });
+    }
+}
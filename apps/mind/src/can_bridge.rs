@@ -0,0 +1,232 @@
+use std::ops::RangeInclusive;
+use std::sync::mpsc::{self, TryRecvError};
+use std::time::{Duration, Instant};
+
+use can::motors::{MotorBus, MotorEvent, MotorState};
+use can::rmd::{RmdCommand, RmdReply};
+use can::safety::{SafeBus, SafetyConfig};
+use can::threaded::BusThread;
+use can::{Bitrate, CanBus, CanFrame, SlcanError};
+use prost::Message;
+use tokio::sync::broadcast::{error::RecvError, Receiver, Sender};
+
+use crate::bus::{DeviceDescriptor, Envelope};
+
+/// How often every known motor is asked for angle and status2.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often the ID range is probed again while no motor has answered yet.
+const DISCOVER_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Where and what to bridge.
+#[derive(Debug, Clone)]
+pub struct CanBridgeConfig {
+    /// SLCAN serial port, SocketCAN interface or `mock:`.
+    pub port: String,
+    pub bitrate: Bitrate,
+    pub serial_baud: u32,
+    /// RMD motor IDs probed at startup; motors outside the range are still picked up when they talk.
+    pub motor_ids: RangeInclusive<u32>,
+    /// Limits and command watchdog applied to every actuator command.
+    pub safety: SafetyConfig,
+}
+
+/// Bridges the CAN bus and the mind bus.
+///
+/// CAN → bus (all payloads little-endian `f32` unless noted):
+/// * `/sensor/motor/<id>/{angle,speed,current,temperature,voltage}` – decoded RMD telemetry
+///   (degrees, deg/s, A, °C, V)
+/// * `/sensor/motor/<id>/errors` – status1 error bits (`u16`)
+/// * `/sensor/motor/<id>/online` – `1`/`0` (`u8`) on online/offline transitions
+/// * `/sensor/motor/<id>/frame` – raw reply payload; other frames go to `/sensor/can/<ID>`
+/// * `/device/announce` – a `DeviceDescriptor` (`motor_<id>`, ACTUATOR) whenever a motor comes online
+/// * `/log/can` – safety events (limited commands, watchdog stops) as text
+///
+/// Bus → CAN: `/actuator/motor/<id>/{speed,position,torque}` (`f32`: deg/s, degrees, A) and
+/// `/actuator/motor/<id>/stop` (any payload) become RMD commands. They pass through a
+/// [`SafeBus`]: a motor whose command is not repeated within `safety.command_timeout` is
+/// stopped and braked.
+///
+/// The CAN side runs on its own thread; SLCAN adapters are supervised and reconnect on their own.
+/// Transport errors go to `/log/can` and the bridge keeps running until mind shuts down.
+pub fn spawn_can_bridge(config: CanBridgeConfig, tx: Sender<Envelope>, mut rx: Receiver<Envelope>) {
+    let (cmd_tx, cmd_rx) = mpsc::channel::<(u32, RmdCommand)>();
+
+    // Forward actuator envelopes to the blocking CAN loop
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(env) => {
+                    if let Some(cmd) = parse_actuator(&env) {
+                        if cmd_tx.send(cmd).is_err() {
+                            break; // CAN loop exited
+                        }
+                    }
+                }
+                Err(RecvError::Lagged(_)) => {
+                    // skip
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+
+    std::thread::Builder::new()
+        .name("can-bridge".into())
+        .spawn(move || {
+            if let Err(e) = run(&config, &tx, cmd_rx) {
+                eprintln!("[can] bridge on {} stopped: {e}", config.port);
+            }
+        })
+        .expect("spawn can-bridge thread");
+}
+
+fn run(config: &CanBridgeConfig, tx: &Sender<Envelope>, commands: mpsc::Receiver<(u32, RmdCommand)>) -> Result<(), SlcanError> {
+    let (bus, link) = can::supervised::open_bus(&config.port, config.bitrate, config.serial_baud)?;
+    let bus = SafeBus::new(bus, config.safety.clone());
+    let safety = bus.handle().events();
+    let thread = BusThread::spawn(Box::new(bus));
+    let mut client = thread.client();
+    client.set_read_timeout(Duration::from_millis(20));
+    let mut motors = MotorBus::new(client);
+
+    let mut last_error = None;
+    let mut next_discover = Instant::now();
+    let mut next_poll = Instant::now();
+    loop {
+        while let Ok(event) = link.try_recv() {
+            println!("[can] {event}");
+        }
+        while let Ok(event) = safety.try_recv() {
+            println!("[can] {event}");
+            publish(tx, "/log/can".into(), event.to_string().into_bytes());
+        }
+        loop {
+            match commands.try_recv() {
+                Ok((id, cmd)) => {
+                    if let Err(e) = motors.send(id, cmd) {
+                        report(tx, &mut last_error, &format!("motor {id}: {cmd:?} not sent: {e}"));
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(()), // mind is shutting down
+            }
+        }
+        // Motors powered after mind started only show up when probed
+        if motors.motors().next().is_none() && Instant::now() >= next_discover {
+            match motors.discover(config.motor_ids.clone(), Duration::from_millis(500)) {
+                Ok(found) => println!("[can] {} motor(s) on {}: {:?}", found.len(), config.port, found),
+                Err(e) => report(tx, &mut last_error, &format!("discovery failed: {e}")),
+            }
+            next_discover = Instant::now() + DISCOVER_INTERVAL;
+        }
+        if Instant::now() >= next_poll {
+            if let Err(e) = motors.request_state() {
+                report(tx, &mut last_error, &format!("state request failed: {e}"));
+            }
+            next_poll = Instant::now() + POLL_INTERVAL;
+        }
+
+        match motors.bus_mut().recv() {
+            Ok(frame) => {
+                last_error = None;
+                match motors.handle_frame(&frame) {
+                    Some(id) => publish_telemetry(tx, motors.motor(id).expect("motor just updated"), &frame),
+                    None => publish(tx, format!("/sensor/can/{}", id_hex(&frame)), frame.data.clone()),
+                }
+            }
+            Err(SlcanError::Io(_)) => {} // quiet bus
+            Err(e) => {
+                report(tx, &mut last_error, &format!("receive failed: {e}"));
+                // Avoid spinning on a persistently failing transport
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+
+        motors.check_timeouts(Instant::now());
+        for event in motors.take_events() {
+            match event {
+                MotorEvent::Online(id) => {
+                    announce(tx, id);
+                    publish(tx, format!("/sensor/motor/{id}/online"), vec![1]);
+                }
+                MotorEvent::Offline(id) => publish(tx, format!("/sensor/motor/{id}/online"), vec![0]),
+            }
+        }
+    }
+}
+
+fn publish(tx: &Sender<Envelope>, topic: String, data: Vec<u8>) {
+    let _ = tx.send(Envelope { topic, data });
+}
+
+/// Log an error on `/log/can`, skipping repeats until a frame gets through again.
+fn report(tx: &Sender<Envelope>, last: &mut Option<String>, message: &str) {
+    if last.as_deref() != Some(message) {
+        eprintln!("[can] {message}");
+        publish(tx, "/log/can".into(), message.as_bytes().to_vec());
+        *last = Some(message.to_string());
+    }
+}
+
+fn publish_f32(tx: &Sender<Envelope>, id: u32, name: &str, value: f32) {
+    publish(tx, format!("/sensor/motor/{id}/{name}"), value.to_le_bytes().to_vec());
+}
+
+/// Publish the fields carried by the reply that just updated `state`.
+fn publish_telemetry(tx: &Sender<Envelope>, state: &MotorState, frame: &CanFrame) {
+    let id = state.id;
+    publish(tx, format!("/sensor/motor/{id}/frame"), frame.data.clone());
+    let Some(reply) = state.last_reply else { return };
+    if let Some(t) = reply.temperature_c() {
+        publish_f32(tx, id, "temperature", t as f32);
+    }
+    match reply {
+        RmdReply::MultiTurnAngle { .. } => {
+            if let Some(a) = state.angle_deg {
+                publish_f32(tx, id, "angle", a as f32);
+            }
+        }
+        RmdReply::Status1 { voltage_x10, error_flags, .. } => {
+            publish_f32(tx, id, "voltage", voltage_x10 as f32 / 10.0);
+            publish(tx, format!("/sensor/motor/{id}/errors"), error_flags.to_le_bytes().to_vec());
+        }
+        RmdReply::Status2(fb) | RmdReply::Torque(fb) | RmdReply::Speed(fb) | RmdReply::Position(fb) => {
+            publish_f32(tx, id, "speed", fb.speed_dps as f32);
+            publish_f32(tx, id, "current", fb.iq_x100 as f32 / 100.0);
+        }
+        _ => {}
+    }
+}
+
+fn announce(tx: &Sender<Envelope>, id: u32) {
+    let desc = DeviceDescriptor {
+        id: format!("motor_{id}"),
+        kind: 1, // ACTUATOR
+        data_type: "float32".into(),
+        tags: vec!["motor".into(), "rmd".into(), "can".into(), format!("motor_id={id}")],
+    };
+    let mut buf = Vec::new();
+    desc.encode(&mut buf).unwrap();
+    publish(tx, "/device/announce".into(), buf);
+}
+
+/// `/actuator/motor/<id>/<kind>` with an `f32` payload → `(motor_id, command)`.
+fn parse_actuator(env: &Envelope) -> Option<(u32, RmdCommand)> {
+    let rest = env.topic.strip_prefix("/actuator/motor/")?;
+    let (id, kind) = rest.split_once('/')?;
+    let id: u32 = id.parse().ok()?;
+    let value = || env.data.get(..4).map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64);
+    let cmd = match kind {
+        "speed" => RmdCommand::Speed { speed_x100: (value()? * 100.0).round() as i32 },
+        "position" => RmdCommand::Position { max_speed_dps: 0, angle_x100: (value()? * 100.0).round() as i32 },
+        "torque" => RmdCommand::Torque { iq_x100: (value()? * 100.0).round() as i16 },
+        "stop" => RmdCommand::Stop,
+        _ => return None,
+    };
+    Some((id, cmd))
+}
+
+fn id_hex(frame: &CanFrame) -> String {
+    if frame.extended { format!("{:08X}", frame.id) } else { format!("{:03X}", frame.id) }
+}
@@ -26,6 +26,7 @@ mod plan;
 mod dream;
 mod debug_mode;
 mod morphology;
+mod can_bridge;
 // external crate `sim` is used via Cargo dependency
 
 #[derive(Parser, Debug)]
@@ -50,6 +51,43 @@ struct Cli {
     /// Operational mode: awake, dream, debug
     #[arg(long, default_value = "awake")]
     mode: String,
+
+    /// Bridge RMD motors on this CAN port (SLCAN serial port, SocketCAN interface or `mock:`).
+    #[arg(long)]
+    can: Option<String>,
+
+    /// CAN bitrate in bit/s.
+    #[arg(long, default_value_t = 1_000_000)]
+    can_bitrate: u32,
+
+    /// Serial baud rate of the SLCAN adapter.
+    #[arg(long, default_value_t = 115_200)]
+    can_serial_baud: u32,
+
+    /// RMD motor IDs to discover, e.g. `1-32`.
+    #[arg(long, default_value = "1-32")]
+    can_motors: String,
+
+    /// Drive a simulated robot instead of hardware, with this clock: `realtime`, `scaled:<N>`,
+    /// `free` or `lockstep`.
+    #[arg(long)]
+    sim: Option<String>,
+}
+
+/// Topics the simulator publishes, forwarded from the sim bus to the main bus only.
+fn from_sim(topic: &str) -> bool {
+    topic.starts_with("/log/") || topic.starts_with("/sensor/") || topic == sim::clock::TIME_TOPIC
+}
+
+/// Parse a motor ID range such as `1-32` or a single ID such as `3`.
+fn parse_id_range(s: &str) -> Option<std::ops::RangeInclusive<u32>> {
+    match s.split_once('-') {
+        Some((a, b)) => Some(a.trim().parse().ok()?..=b.trim().parse().ok()?),
+        None => {
+            let id = s.trim().parse().ok()?;
+            Some(id..=id)
+        }
+    }
 }
 
 struct BusImpl {
@@ -149,30 +187,38 @@ async fn main() -> Result<()> {
 
     // Bridge Pond bus envelopes into sim-local type.
     let (sim_tx, _) = broadcast::channel(1024);
-    // Forward in background
+    // Forward in background (skipping what came from the sim, which would loop)
     {
         let mut fwd_rx = tx.subscribe();
         let sim_tx_fwd = sim_tx.clone();
         tokio::spawn(async move {
             while let Ok(env) = fwd_rx.recv().await {
-                let _ = sim_tx_fwd.send(sim::bus_types::Envelope { topic: env.topic.clone(), data: env.data.clone() });
+                if !from_sim(&env.topic) {
+                    let _ = sim_tx_fwd.send(sim::bus_types::Envelope { topic: env.topic.clone(), data: env.data.clone() });
+                }
             }
         });
     }
 
-    // Forward diagnostics from sim back to main bus for logging (reserved for future)
+    // Forward diagnostics, simulated sensors and sim time from sim back to main bus
     {
         let mut diag_rx = sim_tx.subscribe();
         let tx_out = tx.clone();
         tokio::spawn(async move {
             while let Ok(env) = diag_rx.recv().await {
-                if env.topic.starts_with("/log/") {
+                if from_sim(&env.topic) {
                     let _ = tx_out.send(bus::Envelope { topic: env.topic.clone(), data: env.data.clone() });
                 }
             }
         });
     }
 
+    if let Some(mode) = &cli.sim {
+        let clock: sim::clock::ClockMode = mode.parse()?;
+        let config = sim::SimConfig { clock, ..Default::default() };
+        let _ = sim::spawn_bus_sim(config, sim_tx.clone(), sim_tx.subscribe());
+    }
+
     // Start sim server for external clients (pad/sim-view)
     {
         let state = sim::server::ServerState::new();
@@ -218,6 +264,22 @@ async fn main() -> Result<()> {
 
     println!("Mind bus listening on {}", cli.uds_path);
 
+    // Bridge real motors before the control loops start consuming their topics
+    if let Some(port) = cli.can.clone() {
+        let bitrate = can::Bitrate::from_bps(cli.can_bitrate)
+            .ok_or_else(|| anyhow::anyhow!("unsupported CAN bitrate {}", cli.can_bitrate))?;
+        let motor_ids = parse_id_range(&cli.can_motors)
+            .ok_or_else(|| anyhow::anyhow!("invalid motor ID range '{}'", cli.can_motors))?;
+        let config = can_bridge::CanBridgeConfig {
+            port,
+            bitrate,
+            serial_baud: cli.can_serial_baud,
+            motor_ids,
+            safety: can::safety::SafetyConfig::default(),
+        };
+        can_bridge::spawn_can_bridge(config, tx.clone(), tx.subscribe());
+    }
+
     // Spawn reflex (System-1) control loop.
     match cli.mode.to_ascii_lowercase().as_str() {
         "awake" | "" => {
@@ -240,8 +302,10 @@ async fn main() -> Result<()> {
         }
     }
 
-    // Keep alive
-    loop {
-        sleep(Duration::from_secs(3600)).await;
-    }
+    // Run until Ctrl-C, then let a lockstep sim stop waiting for Act
+    tokio::signal::ctrl_c().await?;
+    act::unregister(&tx);
+    // Give the forwarders a moment to deliver it
+    sleep(Duration::from_millis(100)).await;
+    Ok(())
 }
\ No newline at end of file
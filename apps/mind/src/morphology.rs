@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 use tokio::sync::broadcast::{Receiver, Sender};
 use prost::Message;
 
@@ -13,13 +13,15 @@ const URDF_TOPIC: &str = "/description/urdf";
 /// Devices announce themselves by sending a `/device/announce` envelope containing the
 /// `DeviceDescriptor` proto.  We generate a very simple URDF that contains a fixed head
 /// link (always present) and attaches every announced device to the head via a fixed joint.
+/// Devices tagged `motor` (e.g. RMD motors from the CAN bridge) get a continuous joint instead.
 ///
 /// The generated URDF is *not* spatially accurate – it just gives higher-level software a
 /// live self-image of what modules are present.
 pub fn spawn_morphology(tx: Sender<Envelope>, mut rx: Receiver<Envelope>) {
     tokio::spawn(async move {
-        let mut devices: HashSet<String> = HashSet::new();
-        devices.insert("head".into()); // the mandatory core module
+        // device id -> tags
+        let mut devices: HashMap<String, Vec<String>> = HashMap::new();
+        devices.insert("head".into(), Vec::new()); // the mandatory core module
 
         // Emit initial URDF so that consumers have something immediately.
         publish_urdf(&tx, &devices);
@@ -28,7 +30,8 @@ pub fn spawn_morphology(tx: Sender<Envelope>, mut rx: Receiver<Envelope>) {
             match rx.recv().await {
                 Ok(env) if env.topic == "/device/announce" => {
                     if let Ok(desc) = crate::bus::DeviceDescriptor::decode(&*env.data) {
-                        if devices.insert(desc.id.clone()) {
+                        if devices.get(&desc.id) != Some(&desc.tags) {
+                            devices.insert(desc.id.clone(), desc.tags);
                             publish_urdf(&tx, &devices);
                         }
                     }
@@ -43,7 +46,7 @@ pub fn spawn_morphology(tx: Sender<Envelope>, mut rx: Receiver<Envelope>) {
     });
 }
 
-fn publish_urdf(tx: &Sender<Envelope>, devices: &HashSet<String>) {
+fn publish_urdf(tx: &Sender<Envelope>, devices: &HashMap<String, Vec<String>>) {
     let urdf = build_urdf(devices);
     let _ = tx.send(Envelope {
         topic: URDF_TOPIC.into(),
@@ -51,7 +54,7 @@ fn publish_urdf(tx: &Sender<Envelope>, devices: &HashSet<String>) {
     });
 }
 
-fn build_urdf(devices: &HashSet<String>) -> String {
+fn build_urdf(devices: &HashMap<String, Vec<String>>) -> String {
     let mut xml = String::new();
     xml.push_str("<robot name=\"frog\">\n");
     // Head link with visual mesh
@@ -62,12 +65,16 @@ fn build_urdf(devices: &HashSet<String>) -> String {
     xml.push_str("    <child link=\"head\"/>\n");
     xml.push_str("  </joint>\n");
 
-    for id in devices {
+    for (id, tags) in devices {
         if id == "head" { continue; }
+        let is_motor = tags.iter().any(|t| t == "motor");
         xml.push_str(&format!("  <link name=\"{}\"/>\n", id));
-        xml.push_str(&format!("  <joint name=\"joint_{}\" type=\"fixed\">\n", id));
+        xml.push_str(&format!("  <joint name=\"joint_{}\" type=\"{}\">\n", id, if is_motor { "continuous" } else { "fixed" }));
         xml.push_str("    <parent link=\"head\"/>\n");
         xml.push_str(&format!("    <child link=\"{}\"/>\n", id));
+        if is_motor {
+            xml.push_str("    <axis xyz=\"0 0 1\"/>\n");
+        }
         xml.push_str("  </joint>\n");
     }
     xml.push_str("</robot>\n");
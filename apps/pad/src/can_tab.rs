@@ -2,9 +2,24 @@
 //! Mirrors the functionality of the standalone CAN TUI
 
 use bevy::prelude::*;
-use can::{Bitrate, CanFrame, Slcan, SlcanError};
+use can::rmd::{self, RmdCommand, RmdReply};
+use can::params::{MotorParams, Param};
+use can::record::Direction;
+use can::safety::{SafeBus, SafetyConfig, SafetyEvent, SafetyHandle};
+use can::stats::BusStats;
+use can::supervised::ConnectionEvent;
+use can::threaded::{BusThread, Traffic};
+use can::trajectory::{JointLimits, Profile, Streamer, TrackingError, Trajectory, Waypoint};
+use can::{Bitrate, BusStatus, CanBus, CanFrame, SlcanError};
+use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
+
+/// Resend interval of the held speed/position command, inside the 500 ms safety watchdog.
+const KEEPALIVE: Duration = Duration::from_millis(200);
+
+/// Setpoint rate of trajectory moves.
+const MOVE_RATE_HZ: f64 = 50.0;
 
 #[derive(Resource)]
 pub struct CanState {
@@ -14,6 +29,8 @@ pub struct CanState {
     pub motor_id: u32,
     pub serial_baud: u32,
     pub angle_x100: i32,
+    /// When `angle_x100` was last reported; moves only start from a fresh angle.
+    pub angle_seen: Option<Instant>,
     pub speed_target_x100: i32,
     pub status2: u8,
     pub frames: Vec<FrameRow>,
@@ -21,8 +38,34 @@ pub struct CanState {
     pub speed_history: Vec<(f64, f64)>, // (time_s, speed_dps)
     pub last_rx: Option<Instant>,
     pub connection_state: ConnectionState,
+    /// Recent link events (reconnects, bus-off), newest last.
+    pub link_log: Vec<String>,
     pub scroll_offset: usize,
     pub can_subtab: CanSubTab,
+    /// Motion command resent every `KEEPALIVE` so the safety watchdog stays fed.
+    pub held: Option<RmdCommand>,
+    pub last_keepalive: Option<Instant>,
+    pub estopped: bool,
+    pub last_safety_event: Option<String>,
+    /// Parameters sub-tab: last values reported by the motor, edited values per `Param::ALL`
+    /// row, and a flash write or reset awaiting confirmation.
+    pub params: MotorParams,
+    pub param_drafts: [Option<i64>; Param::ALL.len()],
+    pub param_confirm: Option<(String, RmdCommand)>,
+    pub param_note: Option<String>,
+    /// Trajectory move being streamed, its settings, and the tracking error of the current or
+    /// last move.
+    pub trajectory: Option<Streamer>,
+    pub move_target_deg: f64,
+    pub move_profile: Profile,
+    pub move_limits: JointLimits,
+    pub move_tracking: Option<TrackingError>,
+    pub move_note: Option<String>,
+    /// Bus sub-tab: rolling load/rate/jitter statistics of all traffic, and the last adapter
+    /// status (polled once per second).
+    pub stats: BusStats,
+    pub bus_status: BusStatus,
+    pub last_status_poll: Option<Instant>,
 }
 
 impl Default for CanState {
@@ -34,6 +77,7 @@ impl Default for CanState {
             motor_id: 1,
             serial_baud: 115_200,
             angle_x100: 0,
+            angle_seen: None,
             speed_target_x100: 0,
             status2: 0,
             frames: Vec::new(),
@@ -41,8 +85,26 @@ impl Default for CanState {
             speed_history: Vec::new(),
             last_rx: None,
             connection_state: ConnectionState::default(),
+            link_log: Vec::new(),
             scroll_offset: 0,
             can_subtab: CanSubTab::default(),
+            held: None,
+            last_keepalive: None,
+            estopped: false,
+            last_safety_event: None,
+            params: MotorParams::default(),
+            param_drafts: [None; Param::ALL.len()],
+            param_confirm: None,
+            param_note: None,
+            trajectory: None,
+            move_target_deg: 90.0,
+            move_profile: Profile::Trapezoidal,
+            move_limits: JointLimits { max_velocity_dps: 90.0, max_accel_dps2: 180.0 },
+            move_tracking: None,
+            move_note: None,
+            stats: BusStats::new(Bitrate::B500k),
+            bus_status: BusStatus::default(),
+            last_status_poll: None,
         }
     }
 }
@@ -52,6 +114,8 @@ pub enum CanSubTab {
     Telemetry,
     Frames,
     Controls,
+    Parameters,
+    Bus,
 }
 
 impl Default for CanSubTab {
@@ -64,6 +128,9 @@ impl Default for CanSubTab {
 pub enum ConnectionState {
     Disconnected,
     Connected,
+    /// Adapter lost; the supervisor is trying to reopen it.
+    Reconnecting,
+    BusOff,
     Error,
 }
 
@@ -83,101 +150,160 @@ pub struct FrameRow {
     pub ts: Instant,
 }
 
-/// Shared handle to the CAN connection (runs in background thread)
+/// Shared handle to the CAN connection.
+/// The bus (SLCAN serial port or SocketCAN interface) is owned by a `BusThread`; the UI only
+/// drains its own subscription, so a quiet bus never stalls a frame. Commands pass through a
+/// `can::safety::SafeBus`, which stops and brakes motors that are not commanded in time.
 #[derive(Resource)]
 pub struct CanHandle {
-    pub slcan: Arc<Mutex<Option<Slcan>>>,
+    pub bus: Arc<Mutex<Option<BusThread>>>,
+    rx: Mutex<Option<Receiver<Traffic>>>,
+    events: Mutex<Option<Receiver<ConnectionEvent>>>,
+    safety: Mutex<Option<(SafetyHandle, Receiver<SafetyEvent>)>>,
 }
 
 impl CanHandle {
     pub fn new() -> Self {
         Self {
-            slcan: Arc::new(Mutex::new(None)),
+            bus: Arc::new(Mutex::new(None)),
+            rx: Mutex::new(None),
+            events: Mutex::new(None),
+            safety: Mutex::new(None),
         }
     }
 
+    /// Open `port`; SLCAN adapters are supervised and reconnect on their own.
     pub fn connect(&self, port: &str, bitrate: Bitrate, serial_baud: u32) -> anyhow::Result<()> {
-        let mut guard = self.slcan.lock().unwrap();
-        let slcan = Slcan::open_with_baud(port, bitrate, serial_baud)?;
-        *guard = Some(slcan);
+        let (bus, events) = can::supervised::open_bus(port, bitrate, serial_baud)?;
+        self.attach(bus);
+        *self.events.lock().unwrap() = Some(events);
         Ok(())
     }
 
+    /// Install an already-open bus (e.g. `can::mock::MockBus` for tests and demos).
+    pub fn attach(&self, bus: Box<dyn CanBus>) {
+        let bus = SafeBus::new(bus, SafetyConfig::default());
+        let safety = bus.handle();
+        let safety_events = safety.events();
+        *self.safety.lock().unwrap() = Some((safety, safety_events));
+        let thread = BusThread::spawn(Box::new(bus));
+        *self.rx.lock().unwrap() = Some(thread.subscribe());
+        *self.bus.lock().unwrap() = Some(thread);
+    }
+
     pub fn disconnect(&self) {
-        let mut guard = self.slcan.lock().unwrap();
-        *guard = None;
+        *self.rx.lock().unwrap() = None;
+        *self.events.lock().unwrap() = None;
+        *self.safety.lock().unwrap() = None;
+        // Dropping the thread joins it and closes the port
+        *self.bus.lock().unwrap() = None;
     }
 
     pub fn is_connected(&self) -> bool {
-        self.slcan.lock().unwrap().is_some()
+        self.bus.lock().unwrap().is_some()
+    }
+
+    /// Adapter error flags; blocks for one status round trip.
+    pub fn status(&self) -> Result<BusStatus, SlcanError> {
+        match self.bus.lock().unwrap().as_ref() {
+            Some(bus) => bus.status(),
+            None => Err(SlcanError::Protocol("not connected")),
+        }
     }
 
     pub fn send(&self, frame: &CanFrame) -> Result<(), SlcanError> {
-        let mut guard = self.slcan.lock().unwrap();
-        if let Some(ref mut slcan) = *guard {
-            slcan.send(frame)
+        let guard = self.bus.lock().unwrap();
+        if let Some(ref bus) = *guard {
+            bus.send(frame.clone())
         } else {
             Err(SlcanError::Protocol("not connected"))
         }
     }
 
-    pub fn read(&self) -> Result<CanFrame, SlcanError> {
-        let mut guard = self.slcan.lock().unwrap();
-        if let Some(ref mut slcan) = *guard {
-            slcan.read()
-        } else {
-            Err(SlcanError::Protocol("not connected"))
+    /// Next received or sent frame, without blocking.
+    pub fn try_recv(&self) -> Option<Traffic> {
+        self.rx.lock().unwrap().as_ref()?.try_recv().ok()
+    }
+
+    /// Next connection event (connected, disconnected, bus-off, ...), without blocking.
+    pub fn try_event(&self) -> Option<ConnectionEvent> {
+        self.events.lock().unwrap().as_ref()?.try_recv().ok()
+    }
+
+    /// Next safety event (limit applied, watchdog trip, e-stop), without blocking.
+    pub fn try_safety_event(&self) -> Option<SafetyEvent> {
+        self.safety.lock().unwrap().as_ref()?.1.try_recv().ok()
+    }
+
+    /// Stop and brake every motor seen on the bus; motion is rejected until `release_estop`.
+    pub fn estop(&self) {
+        if let Some((safety, _)) = self.safety.lock().unwrap().as_ref() {
+            safety.estop();
         }
     }
+
+    pub fn release_estop(&self) {
+        if let Some((safety, _)) = self.safety.lock().unwrap().as_ref() {
+            safety.release();
+        }
+    }
+
+    pub fn is_estopped(&self) -> bool {
+        self.safety.lock().unwrap().as_ref().is_some_and(|(safety, _)| safety.is_estopped())
+    }
 }
 
-pub fn send_cmd(handle: &CanHandle, motor_id: u32, data: [u8; 8]) -> anyhow::Result<()> {
-    let id = 0x140 + motor_id;
-    handle.send(&CanFrame {
-        id,
-        data: data.to_vec(),
-        extended: false,
-        rtr: false,
-    })?;
+pub fn send_rmd(handle: &CanHandle, motor_id: u32, cmd: RmdCommand) -> anyhow::Result<()> {
+    handle.send(&cmd.to_frame(motor_id))?;
     Ok(())
 }
 
-pub fn send_speed(handle: &CanHandle, motor_id: u32, speed_x100: i32) -> anyhow::Result<()> {
-    let mut data = [0u8; 8];
-    data[0] = 0xA2;
-    data[4..8].copy_from_slice(&speed_x100.to_le_bytes());
-    send_cmd(handle, motor_id, data)
+/// Send a speed command and hold it (resent by `can_poll_system`).
+pub fn send_speed(handle: &CanHandle, state: &mut CanState, speed_x100: i32) -> anyhow::Result<()> {
+    hold(handle, state, RmdCommand::Speed { speed_x100 })
 }
 
-pub fn send_position(handle: &CanHandle, motor_id: u32, angle_x100: i32) -> anyhow::Result<()> {
-    let mut data = [0u8; 8];
-    data[0] = 0xA4;
-    data[4..8].copy_from_slice(&angle_x100.to_le_bytes());
-    send_cmd(handle, motor_id, data)
+/// Send a position command and hold it (resent by `can_poll_system`).
+pub fn send_position(handle: &CanHandle, state: &mut CanState, angle_x100: i32) -> anyhow::Result<()> {
+    hold(handle, state, RmdCommand::Position { max_speed_dps: 0, angle_x100 })
+}
+
+/// Stream a trajectory from the motor's current angle to `move_target_deg` under
+/// `move_limits`; `can_poll_system` sends the setpoints and holds the target at the end.
+pub fn start_move(handle: &CanHandle, state: &mut CanState) -> anyhow::Result<()> {
+    let fresh = state.angle_seen.is_some_and(|t| t.elapsed() < Duration::from_secs(1));
+    let from_deg = match state.held {
+        _ if fresh => state.angle_x100 as f64 / 100.0,
+        Some(RmdCommand::Position { angle_x100, .. }) => angle_x100 as f64 / 100.0,
+        _ => {
+            send_rmd(handle, state.motor_id, RmdCommand::ReadMultiTurnAngle)?;
+            anyhow::bail!("angle unknown; reading it, try again");
+        }
+    };
+    let trajectory = Trajectory::plan(state.move_profile, &[from_deg], &[Waypoint::new(vec![state.move_target_deg])], &[state.move_limits])?;
+    state.trajectory = Some(Streamer::new(trajectory, vec![state.motor_id], MOVE_RATE_HZ)?);
+    state.held = None;
+    state.move_tracking = None;
+    Ok(())
+}
+
+fn hold(handle: &CanHandle, state: &mut CanState, cmd: RmdCommand) -> anyhow::Result<()> {
+    state.trajectory = None;
+    state.held = Some(cmd);
+    state.last_keepalive = Some(Instant::now());
+    send_rmd(handle, state.motor_id, cmd)
 }
 
 pub fn classify_frame(id: u32, motor_id: u32, data: &[u8]) -> (&'static str, String) {
-    let dir = if id == 0x140 + motor_id {
+    let dir = if id == rmd::tx_id(motor_id) {
         "TX"
-    } else if id == 0x240 + motor_id {
+    } else if id == rmd::rx_id(motor_id) {
         "RX"
     } else {
         "--"
     };
     let kind = if let Some(cmd) = data.first() {
-        match *cmd {
-            0x92 => "angle",
-            0x9A => "status1",
-            0x9C => "status2",
-            0xA2 => "speed",
-            0xA4 => "position",
-            0x77 => "brake_rel",
-            0x78 => "brake_lock",
-            0x81 => "stop",
-            0xB6 => "active_reply",
-            _ => "other",
-        }
-        .to_string()
+        rmd::command_name(*cmd).to_string()
     } else {
         String::from("")
     };
@@ -193,69 +319,195 @@ pub fn can_poll_system(handle: Res<CanHandle>, mut state: ResMut<CanState>, time
 
     state.connected = true;
 
-    // Poll multiple frames per tick
-    for _ in 0..64 {
-        match handle.read() {
-            Ok(frame) => {
-                // Update telemetry if this is from our motor
-                if frame.id == 0x240 + state.motor_id && !frame.data.is_empty() {
-                    match frame.data[0] {
-                        0x92 if frame.data.len() >= 8 => {
-                            // Angle multi-turn
-                            let mut le = [0u8; 4];
-                            le.copy_from_slice(&frame.data[4..8]);
-                            state.angle_x100 = i32::from_le_bytes(le);
-
-                            // Update histories
-                            let t = time.elapsed_seconds_f64();
-                            let angle_deg = (state.angle_x100 as f64) / 100.0;
-                            state.angle_history.push((t, angle_deg));
-
-                            // Calculate speed from angle delta
-                            if state.angle_history.len() >= 2 {
-                                let prev = state.angle_history[state.angle_history.len() - 2];
-                                let dt = t - prev.0;
-                                if dt > 0.0 {
-                                    let speed = (angle_deg - prev.1) / dt;
-                                    state.speed_history.push((t, speed));
-                                }
-                            }
-
-                            // Keep only last 10 seconds
-                            let cutoff = t - 10.0;
-                            state
-                                .angle_history
-                                .retain(|(time, _)| *time >= cutoff);
-                            state
-                                .speed_history
-                                .retain(|(time, _)| *time >= cutoff);
-                        }
-                        0x9C if frame.data.len() >= 2 => {
-                            state.status2 = frame.data[1];
-                        }
-                        _ => {}
-                    }
+    while let Some(event) = handle.try_event() {
+        state.connection_state = match event {
+            ConnectionEvent::Connected { .. } | ConnectionEvent::BusRecovered => ConnectionState::Connected,
+            ConnectionEvent::Disconnected { .. } | ConnectionEvent::ReconnectFailed { .. } => ConnectionState::Reconnecting,
+            ConnectionEvent::BusOff => ConnectionState::BusOff,
+        };
+        state.link_log.push(event.to_string());
+        if state.link_log.len() > 20 {
+            state.link_log.remove(0);
+        }
+    }
+
+    while let Some(event) = handle.try_safety_event() {
+        if matches!(event, SafetyEvent::WatchdogTripped { .. } | SafetyEvent::EmergencyStop { .. }) {
+            state.held = None;
+            state.trajectory = None;
+        }
+        state.last_safety_event = Some(event.to_string());
+    }
+    state.estopped = handle.is_estopped();
+
+    if state.last_status_poll.is_none_or(|t| t.elapsed() >= Duration::from_secs(1)) {
+        if let Ok(status) = handle.status() {
+            state.stats.record_status(&status);
+            state.bus_status = status;
+        }
+        state.last_status_poll = Some(Instant::now());
+    }
+
+    // Stream the active move; at the end its target becomes the held position
+    if let Some(streamer) = state.trajectory.as_mut() {
+        for frame in streamer.tick(Instant::now()) {
+            let _ = handle.send(&frame);
+        }
+        if streamer.is_finished() {
+            let target = streamer.trajectory().sample(streamer.trajectory().duration()).position_deg[0];
+            let _ = send_position(&handle, &mut state, (target * 100.0).round() as i32);
+        }
+    }
+
+    if let Some(cmd) = state.held {
+        if !state.estopped && state.last_keepalive.is_none_or(|t| t.elapsed() >= KEEPALIVE) {
+            let _ = send_rmd(&handle, state.motor_id, cmd);
+            state.last_keepalive = Some(Instant::now());
+        }
+    }
+
+    // Drain what the bus thread queued since the last tick (bounded so a flood cannot stall the UI)
+    for _ in 0..1024 {
+        let Some(Traffic { direction, frame }) = handle.try_recv() else { break };
+        state.stats.record(&frame);
+        if direction == Direction::Rx {
+            let mut tracking = None;
+            if let Some(streamer) = state.trajectory.as_mut() {
+                if streamer.handle_frame(&frame, Instant::now()) {
+                    tracking = streamer.tracking().first().copied();
                 }
+            }
+            if tracking.is_some() {
+                state.move_tracking = tracking;
+            }
+        }
+        // Update telemetry if this is from our motor
+        match RmdReply::from_frame(&frame) {
+            Some((id, RmdReply::MultiTurnAngle { angle_x100 })) if id == state.motor_id => {
+                state.angle_x100 = angle_x100;
+                state.angle_seen = Some(Instant::now());
+
+                // Update histories
+                let t = time.elapsed_seconds_f64();
+                let angle_deg = (state.angle_x100 as f64) / 100.0;
+                state.angle_history.push((t, angle_deg));
 
-                // Add to frames log
-                state.frames.push(FrameRow {
-                    id: frame.id,
-                    data: frame.data,
-                    extended: frame.extended,
-                    ts: Instant::now(),
-                });
-
-                // Keep last 1000 frames
-                if state.frames.len() > 1000 {
-                    let excess = state.frames.len() - 1000;
-                    state.frames.drain(0..excess);
+                // Calculate speed from angle delta
+                if state.angle_history.len() >= 2 {
+                    let prev = state.angle_history[state.angle_history.len() - 2];
+                    let dt = t - prev.0;
+                    if dt > 0.0 {
+                        let speed = (angle_deg - prev.1) / dt;
+                        state.speed_history.push((t, speed));
+                    }
                 }
 
-                state.last_rx = Some(Instant::now());
+                // Keep only last 10 seconds
+                let cutoff = t - 10.0;
+                state
+                    .angle_history
+                    .retain(|(time, _)| *time >= cutoff);
+                state
+                    .speed_history
+                    .retain(|(time, _)| *time >= cutoff);
+            }
+            Some((id, RmdReply::Status2(fb))) if id == state.motor_id => {
+                state.status2 = fb.temperature_c as u8;
             }
-            Err(SlcanError::Io(_)) => break, // Timeout, stop polling
-            Err(_) => continue,
+            Some((id, reply)) if id == state.motor_id => {
+                state.params.apply(&reply);
+            }
+            _ => {}
+        }
+
+        // Add to frames log
+        state.frames.push(FrameRow {
+            id: frame.id,
+            data: frame.data,
+            extended: frame.extended,
+            ts: Instant::now(),
+        });
+
+        // Keep last 1000 frames
+        if state.frames.len() > 1000 {
+            let excess = state.frames.len() - 1000;
+            state.frames.drain(0..excess);
+        }
+
+        if direction == Direction::Rx {
+            state.last_rx = Some(Instant::now());
         }
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use can::mock::{MockBus, Rule};
+
+    /// App running only `can_poll_system`, connected to `mock` through the bus thread.
+    fn app_with(mock: &MockBus) -> App {
+        mock.set_read_timeout(Duration::from_millis(5));
+        let handle = CanHandle::new();
+        handle.attach(Box::new(mock.clone()));
+        let mut app = App::new();
+        app.insert_resource(handle).init_resource::<CanState>().init_resource::<Time>().add_systems(Update, can_poll_system);
+        app
+    }
+
+    /// Run the system until `done` holds; the bus thread delivers frames asynchronously.
+    fn update_until(app: &mut App, done: impl Fn(&CanState) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while Instant::now() < deadline {
+            app.update();
+            if done(app.world.resource::<CanState>()) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        panic!("condition not met within 2 s");
+    }
+
+    #[test]
+    fn decodes_replies_from_our_motor() {
+        let mock = MockBus::new();
+        mock.add_rule(Rule::on(rmd::tx_id(1), &[rmd::code::READ_MULTI_TURN_ANGLE]).reply_frame(RmdReply::MultiTurnAngle { angle_x100: -4500 }.to_frame(1)));
+        mock.add_rule(Rule::on(rmd::tx_id(1), &[rmd::code::READ_ENCODER]).reply_frame(RmdReply::Encoder { counts: 777 }.to_frame(1)));
+        // Another motor's reply only goes to the frame log
+        mock.inject(RmdReply::MultiTurnAngle { angle_x100: 100 }.to_frame(2));
+        let mut app = app_with(&mock);
+        {
+            let handle = app.world.resource::<CanHandle>();
+            send_rmd(handle, 1, RmdCommand::ReadMultiTurnAngle).unwrap();
+            send_rmd(handle, 1, RmdCommand::ReadEncoder).unwrap();
+        }
+
+        update_until(&mut app, |s| s.angle_seen.is_some() && s.params.encoder.is_some());
+        let state = app.world.resource::<CanState>();
+        assert!(state.connected);
+        assert_eq!(state.angle_x100, -4500);
+        assert_eq!(state.angle_history, vec![(0.0, -45.0)]);
+        assert_eq!(state.params.encoder, Some(777));
+        assert!(state.frames.iter().any(|f| f.id == rmd::rx_id(2)));
+        assert!(state.last_rx.is_some());
+    }
+
+    #[test]
+    fn polls_status_and_estop() {
+        let mock = MockBus::new();
+        let status = BusStatus { error_passive: true, ..BusStatus::default() };
+        mock.set_status(status);
+        let mut app = app_with(&mock);
+        app.world.resource_mut::<CanState>().held = Some(RmdCommand::Speed { speed_x100: 1000 });
+
+        update_until(&mut app, |s| s.bus_status == status);
+        app.world.resource::<CanHandle>().estop();
+        update_until(&mut app, |s| s.estopped && s.held.is_none());
+        assert!(app.world.resource::<CanState>().last_safety_event.is_some());
+
+        app.world.resource::<CanHandle>().disconnect();
+        app.update();
+        assert!(!app.world.resource::<CanState>().connected);
+    }
+}
@@ -14,6 +14,9 @@ use bevy_egui::{egui, EguiContexts, EguiPlugin};
 use clap::Parser;
 use sim_view::{SimViewConfig, FollowCamera, RobotMarker};
 use can::Bitrate;
+use can::params::{FlashWrite, MotorParams, Param};
+use can::rmd::RmdCommand;
+use can::trajectory::Profile;
 
 mod can_tab;
 use can_tab::*;
@@ -420,6 +423,8 @@ fn show_can_tab(ui: &mut egui::Ui, state: &mut CanState, handle: &CanHandle, tim
         ui.selectable_value(&mut state.can_subtab, CanSubTab::Telemetry, "📊 Telemetry");
         ui.selectable_value(&mut state.can_subtab, CanSubTab::Frames, "📜 Frames");
         ui.selectable_value(&mut state.can_subtab, CanSubTab::Controls, "🎮 Controls");
+        ui.selectable_value(&mut state.can_subtab, CanSubTab::Parameters, "🔧 Parameters");
+        ui.selectable_value(&mut state.can_subtab, CanSubTab::Bus, "📈 Bus");
     });
     ui.separator();
 
@@ -427,6 +432,174 @@ fn show_can_tab(ui: &mut egui::Ui, state: &mut CanState, handle: &CanHandle, tim
         CanSubTab::Telemetry => show_can_telemetry(ui, state, time),
         CanSubTab::Frames => show_can_frames(ui, state),
         CanSubTab::Controls => show_can_controls(ui, state, handle),
+        CanSubTab::Parameters => show_can_parameters(ui, state, handle),
+        CanSubTab::Bus => show_can_bus(ui, state),
+    }
+}
+
+fn show_can_bus(ui: &mut egui::Ui, state: &mut CanState) {
+    ui.heading("CAN Bus Statistics");
+    if !state.connected {
+        ui.label("Connect in the Controls sub-tab first.");
+        return;
+    }
+    let snap = state.stats.snapshot_now();
+
+    let load_color = if snap.load < 0.5 {
+        egui::Color32::GREEN
+    } else if snap.load < 0.8 {
+        egui::Color32::YELLOW
+    } else {
+        egui::Color32::RED
+    };
+    ui.horizontal(|ui| {
+        ui.label(format!("Load @ {:?}:", state.bitrate));
+        ui.add(
+            egui::ProgressBar::new(snap.load.clamp(0.0, 1.0) as f32)
+                .text(egui::RichText::new(format!("{:.1}%", snap.load * 100.0)).color(load_color))
+                .animate(false),
+        );
+    });
+    ui.label(format!("{:.0} frames/s, {} total (1 s window, load includes bit stuffing)", snap.frames_per_sec, snap.total_frames));
+    let errors_color = if snap.errors.total() == 0 { egui::Color32::GREEN } else { egui::Color32::RED };
+    ui.horizontal(|ui| {
+        ui.label("Errors:");
+        ui.colored_label(errors_color, snap.errors.to_string());
+    });
+    ui.label(format!("Adapter status: {}", state.bus_status));
+    if ui.button("🔄 Reset Statistics").clicked() {
+        state.stats.reset();
+    }
+    ui.separator();
+
+    let ms = |d: Option<std::time::Duration>| d.map_or("-".to_string(), |d| format!("{:.2}", d.as_secs_f64() * 1000.0));
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        egui::Grid::new("can_bus_grid").striped(true).num_columns(8).show(ui, |ui| {
+            for title in ["ID", "Count", "Rate Hz", "Period ms", "Jitter ms", "Missing", "Last ms", "State"] {
+                ui.label(egui::RichText::new(title).strong());
+            }
+            ui.end_row();
+
+            for id in &snap.ids {
+                ui.label(if id.extended { format!("{:08X}", id.id) } else { format!("{:03X}", id.id) });
+                ui.label(id.count.to_string());
+                ui.label(format!("{:.1}", id.rate_hz));
+                ui.label(ms(id.period));
+                ui.label(ms(id.jitter));
+                ui.label(id.missing.to_string());
+                ui.label(format!("{:.0}", id.since_last.as_secs_f64() * 1000.0));
+                if id.overdue {
+                    ui.colored_label(egui::Color32::RED, "overdue");
+                } else if id.missing > 0 {
+                    ui.colored_label(egui::Color32::YELLOW, "gaps");
+                } else if id.period.is_some() {
+                    ui.colored_label(egui::Color32::GREEN, "periodic");
+                } else {
+                    ui.label("");
+                }
+                ui.end_row();
+            }
+        });
+    });
+}
+
+fn show_can_parameters(ui: &mut egui::Ui, state: &mut CanState, handle: &CanHandle) {
+    ui.heading(format!("Motor {} Parameters", state.motor_id));
+    if !state.connected {
+        ui.label("Connect in the Controls sub-tab first.");
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        if ui.button("📥 Read All").clicked() {
+            for cmd in MotorParams::read_commands() {
+                let _ = send_rmd(handle, state.motor_id, cmd);
+            }
+            state.param_drafts = [None; Param::ALL.len()];
+            state.param_note = Some("Reading parameters…".into());
+        }
+        if ui.button("📍 Zero Here…").clicked() {
+            let write = FlashWrite::zero_at_current_position();
+            state.param_confirm = Some((write.to_string(), write.command()));
+        }
+        if ui.button("✖ Clear Zero…").clicked() {
+            let write = FlashWrite::clear_zero();
+            state.param_confirm = Some((write.to_string(), write.command()));
+        }
+        if ui.button("🔄 Reset Motor…").clicked() {
+            state.param_confirm = Some(("reset the motor controller".into(), RmdCommand::SystemReset));
+        }
+    });
+    let counts = |v: Option<i32>| v.map_or("-".to_string(), |v| v.to_string());
+    ui.label(format!("Encoder: {} counts (raw {})", counts(state.params.encoder), counts(state.params.encoder_raw)));
+    ui.add_space(10.0);
+
+    egui::Grid::new("can_params_grid").striped(true).num_columns(5).show(ui, |ui| {
+        ui.label(egui::RichText::new("Parameter").strong());
+        ui.label(egui::RichText::new("Motor").strong());
+        ui.label(egui::RichText::new("New").strong());
+        ui.label("");
+        ui.label("");
+        ui.end_row();
+
+        for (i, param) in Param::ALL.into_iter().enumerate() {
+            let current = param.get(&state.params);
+            ui.label(format!("{} {}", param.label(), param.unit()));
+            ui.label(current.map_or("-".to_string(), |v| v.to_string()));
+            match current {
+                Some(v) => {
+                    let draft = state.param_drafts[i].get_or_insert(v);
+                    ui.add(egui::DragValue::new(draft).clamp_range(param.range()).speed(param.step() as f64));
+                }
+                None => {
+                    ui.label("-");
+                }
+            }
+            let draft = state.param_drafts[i];
+            if param.is_gain() {
+                if ui.add_enabled(draft.is_some(), egui::Button::new("RAM")).clicked() {
+                    if let Some(cmd) = draft.and_then(|v| state.params.ram_write(param, v)) {
+                        let _ = send_rmd(handle, state.motor_id, cmd);
+                        state.param_note = Some(format!("{} written to RAM", param.label()));
+                    }
+                }
+            } else {
+                ui.label("");
+            }
+            if ui.add_enabled(draft.is_some(), egui::Button::new("Flash…")).clicked() {
+                if let Some(write) = draft.and_then(|v| state.params.flash_write(param, v)) {
+                    state.param_confirm = Some((write.to_string(), write.command()));
+                }
+            }
+            ui.end_row();
+        }
+    });
+
+    if let Some(note) = &state.param_note {
+        ui.add_space(10.0);
+        ui.label(note);
+    }
+
+    // Flash writes and resets only go out after an explicit yes
+    if let Some((what, cmd)) = state.param_confirm.clone() {
+        egui::Window::new("Confirm")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ui.ctx(), |ui| {
+                ui.label(format!("Motor {}: {}?", state.motor_id, what));
+                ui.label("Flash has limited write cycles; a new encoder zero applies after a reset.");
+                ui.horizontal(|ui| {
+                    if ui.button("Confirm").clicked() {
+                        let _ = send_rmd(handle, state.motor_id, cmd);
+                        state.param_note = Some(format!("Sent: {}", what));
+                        state.param_confirm = None;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        state.param_confirm = None;
+                    }
+                });
+            });
     }
 }
 
@@ -434,12 +607,16 @@ fn show_can_telemetry(ui: &mut egui::Ui, state: &CanState, _time: &Time) {
     ui.heading("CAN Telemetry");
 
     // Connection status
-    let status_text = if state.connected {
-        format!("✅ Connected to {}", state.port)
-    } else {
-        "❌ Disconnected".to_string()
+    let status_text = match (state.connected, state.connection_state) {
+        (false, _) => "❌ Disconnected".to_string(),
+        (true, ConnectionState::Reconnecting) => format!("🔄 Reconnecting to {}…", state.port),
+        (true, ConnectionState::BusOff) => "⚠ Bus-off, restarting channel".to_string(),
+        (true, _) => format!("✅ Connected to {}", state.port),
     };
     ui.label(status_text);
+    if let Some(last) = state.link_log.last() {
+        ui.small(format!("Last link event: {}", last));
+    }
     ui.separator();
 
     // Angle display
@@ -579,7 +756,7 @@ fn show_can_controls(ui: &mut egui::Ui, state: &mut CanState, handle: &CanHandle
     if !state.connected {
         ui.label("Connection Settings:");
         ui.horizontal(|ui| {
-            ui.label("Port:");
+            ui.label("Port / Interface:");
             ui.text_edit_singleline(&mut state.port);
         });
 
@@ -603,6 +780,7 @@ fn show_can_controls(ui: &mut egui::Ui, state: &mut CanState, handle: &CanHandle
         if ui.button("🔌 Connect").clicked() {
             match handle.connect(&state.port, state.bitrate, state.serial_baud) {
                 Ok(_) => {
+                    state.stats = can::stats::BusStats::new(state.bitrate);
                     state.connection_state = ConnectionState::Connected;
                     state.connected = true;
                 }
@@ -621,18 +799,44 @@ fn show_can_controls(ui: &mut egui::Ui, state: &mut CanState, handle: &CanHandle
         }
         ui.separator();
 
+        // Safety: e-stop stops and brakes every motor seen on the bus
+        ui.horizontal(|ui| {
+            let estop = egui::Button::new(egui::RichText::new("⛔ E-STOP").strong().color(egui::Color32::WHITE))
+                .fill(egui::Color32::from_rgb(200, 30, 30));
+            if ui.add(estop).clicked() {
+                handle.estop();
+                state.held = None;
+                state.trajectory = None;
+                state.speed_target_x100 = 0;
+            }
+            if state.estopped {
+                ui.colored_label(egui::Color32::RED, "E-stop active");
+                if ui.button("Release").clicked() {
+                    handle.release_estop();
+                }
+            }
+        });
+        if let Some(event) = &state.last_safety_event {
+            ui.label(format!("Safety: {}", event));
+        }
+        ui.separator();
+
         // Motor controls
         ui.heading("Motor Commands");
 
         ui.horizontal(|ui| {
             if ui.button("🔓 Release Brake").clicked() {
-                let _ = send_cmd(handle, state.motor_id, [0x77, 0, 0, 0, 0, 0, 0, 0]);
+                let _ = send_rmd(handle, state.motor_id, RmdCommand::BrakeRelease);
             }
             if ui.button("🔒 Lock Brake").clicked() {
-                let _ = send_cmd(handle, state.motor_id, [0x78, 0, 0, 0, 0, 0, 0, 0]);
+                state.held = None;
+                state.trajectory = None;
+                let _ = send_rmd(handle, state.motor_id, RmdCommand::BrakeLock);
             }
             if ui.button("🛑 Stop").clicked() {
-                let _ = send_cmd(handle, state.motor_id, [0x81, 0, 0, 0, 0, 0, 0, 0]);
+                state.held = None;
+                state.trajectory = None;
+                let _ = send_rmd(handle, state.motor_id, RmdCommand::Stop);
                 state.speed_target_x100 = 0;
             }
         });
@@ -644,25 +848,64 @@ fn show_can_controls(ui: &mut egui::Ui, state: &mut CanState, handle: &CanHandle
         ui.horizontal(|ui| {
             if ui.button("➖ -5 dps").clicked() {
                 state.speed_target_x100 = state.speed_target_x100.saturating_sub(500);
-                let _ = send_speed(handle, state.motor_id, state.speed_target_x100);
+                let target = state.speed_target_x100;
+                let _ = send_speed(handle, state, target);
             }
             ui.label(format!("{:.2} deg/s", (state.speed_target_x100 as f32) / 100.0));
             if ui.button("➕ +5 dps").clicked() {
                 state.speed_target_x100 = state.speed_target_x100.saturating_add(500);
-                let _ = send_speed(handle, state.motor_id, state.speed_target_x100);
+                let target = state.speed_target_x100;
+                let _ = send_speed(handle, state, target);
             }
             if ui.button("0️⃣ Zero").clicked() {
                 state.speed_target_x100 = 0;
-                let _ = send_speed(handle, state.motor_id, 0);
+                let _ = send_speed(handle, state, 0);
             }
         });
 
         ui.add_space(10.0);
 
-        // Position control
+        // Position control: a trajectory from the current angle, streamed at 50 Hz
         ui.label("Position Control:");
-        if ui.button("📐 Go to 90°").clicked() {
-            let _ = send_position(handle, state.motor_id, 9000); // 90° × 100
+        ui.horizontal(|ui| {
+            ui.label("Target:");
+            ui.add(egui::DragValue::new(&mut state.move_target_deg).speed(1.0).clamp_range(-3600.0..=3600.0).suffix("°"));
+            ui.radio_value(&mut state.move_profile, Profile::Trapezoidal, "Trapezoidal");
+            ui.radio_value(&mut state.move_profile, Profile::Cubic, "Cubic");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Max velocity:");
+            ui.add(egui::DragValue::new(&mut state.move_limits.max_velocity_dps).speed(1.0).clamp_range(1.0..=720.0).suffix(" dps"));
+            ui.label("Max accel:");
+            ui.add(egui::DragValue::new(&mut state.move_limits.max_accel_dps2).speed(5.0).clamp_range(10.0..=5000.0).suffix(" dps²"));
+        });
+        ui.horizontal(|ui| {
+            if ui.button("📐 Move").clicked() {
+                state.move_note = start_move(handle, state).err().map(|e| e.to_string());
+            }
+            if ui.button("0° / 90°").on_hover_text("Toggle the target between 0° and 90°").clicked() {
+                state.move_target_deg = if state.move_target_deg == 90.0 { 0.0 } else { 90.0 };
+            }
+            if state.trajectory.is_some() && ui.button("⏹ Cancel").clicked() {
+                // Stay where the motor is instead of jumping to the target
+                state.trajectory = None;
+                let angle_x100 = state.angle_x100;
+                let _ = send_position(handle, state, angle_x100);
+            }
+        });
+        if let Some(streamer) = &state.trajectory {
+            let error = state.move_tracking.and_then(|t| t.last_deg).map_or("-".into(), |e| format!("{:+.2}°", e));
+            ui.label(format!(
+                "Moving: {:.1} / {:.1} s, tracking error {}",
+                streamer.elapsed(std::time::Instant::now()).as_secs_f64(),
+                streamer.trajectory().duration().as_secs_f64(),
+                error
+            ));
+        } else if let Some(t) = state.move_tracking {
+            ui.label(format!("Last move: tracking error max {:.2}°, rms {:.2}° ({} samples)", t.max_abs_deg, t.rms_deg(), t.samples));
+        }
+        if let Some(note) = &state.move_note {
+            ui.label(note);
         }
 
         ui.add_space(10.0);
@@ -671,16 +914,16 @@ fn show_can_controls(ui: &mut egui::Ui, state: &mut CanState, handle: &CanHandle
         ui.label("Telemetry Reads:");
         ui.horizontal(|ui| {
             if ui.button("📏 Read Angle (0x92)").clicked() {
-                let _ = send_cmd(handle, state.motor_id, [0x92, 0, 0, 0, 0, 0, 0, 0]);
+                let _ = send_rmd(handle, state.motor_id, RmdCommand::ReadMultiTurnAngle);
             }
             if ui.button("📊 Read Status2 (0x9C)").clicked() {
-                let _ = send_cmd(handle, state.motor_id, [0x9C, 0, 0, 0, 0, 0, 0, 0]);
+                let _ = send_rmd(handle, state.motor_id, RmdCommand::ReadStatus2);
             }
         });
 
         if ui.button("🔁 Enable Active Reply (Angle @ 50ms)").clicked() {
-            let data = [0xB6, 0x92, 0x01, 0x05, 0x00, 0x00, 0x00, 0x00];
-            let _ = send_cmd(handle, state.motor_id, data);
+            let cmd = RmdCommand::ActiveReply { cmd: can::rmd::code::READ_MULTI_TURN_ANGLE, enable: true, interval_10ms: 5 };
+            let _ = send_rmd(handle, state.motor_id, cmd);
         }
     }
 }
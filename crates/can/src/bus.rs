@@ -0,0 +1,140 @@
+//! Transport-agnostic CAN bus interface.
+//!
+//! `Slcan` (USB serial adapters) and `SocketCan` (Linux `can0`/`vcan0`) both implement
+//! [`CanBus`], so tools and apps can hold a `Box<dyn CanBus>` and not care which one
+//! is underneath. Use [`open_bus`] to pick the backend from a port/interface name.
+
+use crate::{Bitrate, CanFrame, Slcan, SlcanError};
+
+/// Acceptance filter: a frame passes when `frame.id & mask == id & mask`
+/// and the frame format (standard/extended) matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanFilter {
+    pub id: u32,
+    pub mask: u32,
+    pub extended: bool,
+}
+
+impl CanFilter {
+    /// Match exactly one standard (11-bit) ID.
+    pub fn standard(id: u32) -> Self {
+        Self { id: id & 0x7FF, mask: 0x7FF, extended: false }
+    }
+
+    /// Match exactly one extended (29-bit) ID.
+    pub fn extended(id: u32) -> Self {
+        Self { id: id & 0x1FFF_FFFF, mask: 0x1FFF_FFFF, extended: true }
+    }
+
+    pub fn matches(&self, frame: &CanFrame) -> bool {
+        frame.extended == self.extended && (frame.id & self.mask) == (self.id & self.mask)
+    }
+}
+
+/// Returns true when `frame` passes any of `filters` (an empty list accepts everything).
+pub fn filters_accept(filters: &[CanFilter], frame: &CanFrame) -> bool {
+    filters.is_empty() || filters.iter().any(|f| f.matches(frame))
+}
+
+/// Controller error state as reported by the transport.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BusStatus {
+    pub error_warning: bool,
+    pub error_passive: bool,
+    pub overrun: bool,
+    pub arbitration_lost: bool,
+    /// A bus error (bit, stuff, form, CRC or missing ACK) was seen; the controller is still on the bus.
+    pub bus_error: bool,
+    pub bus_off: bool,
+    /// Error frames received since the transport was opened. Only SocketCAN delivers them;
+    /// other transports leave this at 0.
+    pub error_frames: u64,
+}
+
+impl BusStatus {
+    /// No error flags set (past error frames do not count).
+    pub fn is_ok(&self) -> bool {
+        BusStatus { error_frames: 0, ..*self } == BusStatus::default()
+    }
+}
+
+/// `ok`, or the set flags joined by commas (`warning,bus_off`).
+impl std::fmt::Display for BusStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_ok() {
+            return f.write_str("ok");
+        }
+        let flags = [
+            (self.error_warning, "warning"),
+            (self.error_passive, "passive"),
+            (self.overrun, "overrun"),
+            (self.arbitration_lost, "arb_lost"),
+            (self.bus_error, "bus_error"),
+            (self.bus_off, "bus_off"),
+        ];
+        let set: Vec<&str> = flags.iter().filter(|(on, _)| *on).map(|(_, name)| *name).collect();
+        f.write_str(&set.join(","))
+    }
+}
+
+/// Common surface of every CAN transport.
+///
+/// `recv` follows the `Slcan::read` contract: it returns `SlcanError::Io` with
+/// `ErrorKind::TimedOut` when the bus is quiet, so callers can poll without blocking.
+pub trait CanBus: Send {
+    fn send(&mut self, frame: &CanFrame) -> Result<(), SlcanError>;
+    fn recv(&mut self) -> Result<CanFrame, SlcanError>;
+    /// Replace the active acceptance filters. An empty slice accepts all frames.
+    fn set_filters(&mut self, filters: &[CanFilter]) -> Result<(), SlcanError>;
+    fn status(&mut self) -> Result<BusStatus, SlcanError>;
+}
+
+impl<B: CanBus + ?Sized> CanBus for Box<B> {
+    fn send(&mut self, frame: &CanFrame) -> Result<(), SlcanError> { (**self).send(frame) }
+    fn recv(&mut self) -> Result<CanFrame, SlcanError> { (**self).recv() }
+    fn set_filters(&mut self, filters: &[CanFilter]) -> Result<(), SlcanError> { (**self).set_filters(filters) }
+    fn status(&mut self) -> Result<BusStatus, SlcanError> { (**self).status() }
+}
+
+/// True when `name` refers to a SocketCAN network interface (e.g. `can0`, `vcan0`)
+/// rather than a serial device path.
+pub fn is_socketcan_interface(name: &str) -> bool {
+    if name.contains('/') || name.is_empty() {
+        return false;
+    }
+    cfg!(target_os = "linux") && std::path::Path::new("/sys/class/net").join(name).exists()
+}
+
+/// Scheme that selects an in-memory [`MockBus`](crate::mock::MockBus) loopback instead of
+/// real hardware, e.g. `-p mock:`.  Anything after the colon is ignored for now.
+///
+/// ```
+/// use can::{open_bus, Bitrate, CanBus};
+///
+/// let mut bus = open_bus("mock:", Bitrate::B1M, 115_200).unwrap();
+/// bus.send(&can::rmd::RmdCommand::ReadMultiTurnAngle.to_frame(1)).unwrap();
+/// assert_eq!(bus.recv().unwrap().id, 0x141);
+/// // A bare `mock` is a serial port name like any other
+/// assert!(!can::bus::is_mock("mock"));
+/// ```
+pub const MOCK_SCHEME: &str = "mock:";
+
+/// True when `name` asks for the in-memory mock bus ([`MOCK_SCHEME`]).
+pub fn is_mock(name: &str) -> bool {
+    name.starts_with(MOCK_SCHEME)
+}
+
+/// Open a bus by name: SocketCAN interfaces (`can0`, `vcan0`) on Linux, `mock:` for an
+/// in-memory loopback bus, otherwise an SLCAN serial port. `bitrate` and `serial_baud`
+/// only apply to SLCAN; SocketCAN interfaces are configured with
+/// `ip link set can0 type can bitrate ...`.
+pub fn open_bus(name: &str, bitrate: Bitrate, serial_baud: u32) -> Result<Box<dyn CanBus>, SlcanError> {
+    if is_mock(name) {
+        return Ok(Box::new(crate::mock::MockBus::loopback()));
+    }
+    #[cfg(target_os = "linux")]
+    if is_socketcan_interface(name) {
+        return Ok(Box::new(crate::socketcan::SocketCan::open(name)?));
+    }
+    Ok(Box::new(Slcan::open_with_baud(name, bitrate, serial_baud)?))
+}
@@ -0,0 +1,1393 @@
+//! Minimal CANopen (CiA 301) master: NMT, SDO, PDO and heartbeat consumer.
+//!
+//! - NMT: [`nmt_frame`] / [`Master::nmt`] switch nodes between pre-operational,
+//!   operational and stopped, or reset them.
+//! - SDO: [`SdoClient`] reads (upload) and writes (download) object dictionary entries,
+//!   expedited for up to 4 bytes and segmented above. Server aborts fail with
+//!   [`SlcanError::Protocol`] carrying the abort text; the raw code is kept in
+//!   [`SdoClient::last_abort`]. Missing replies fail with a `TimedOut` I/O error.
+//! - PDO: [`Eds`] reads the subset of an EDS file needed for process data (object names,
+//!   data types, access, defaults and the PDO communication/mapping objects). The resulting
+//!   [`PdoMapping`]s pack and unpack PDO payloads and can be written to a node over SDO.
+//! - Heartbeat: [`HeartbeatMonitor`] tracks the NMT state of each node from its heartbeat
+//!   and reports boot-ups, state changes, timeouts and recoveries.
+//!
+//! [`SimNode`] is a simulated slave with an object dictionary, SDO server, NMT state machine,
+//! heartbeat producer and PDOs, for tests and demos on a loopback bus:
+//!
+//! ```
+//! use std::time::{Duration, Instant};
+//! use can::canopen::{Event, HeartbeatEvent, Master, NmtCommand, NmtState, SimNode};
+//! use can::mock::MockBus;
+//! use can::threaded::BusThread;
+//!
+//! let bus = BusThread::spawn(Box::new(MockBus::loopback()));
+//! let node = SimNode::new(5).spawn(bus.client());
+//! let mut master = Master::new(bus.client());
+//!
+//! let mut sdo = master.sdo(5);
+//! assert_eq!(sdo.read_u32(0x1000, 0).unwrap(), 0x0002_0192); // expedited
+//! assert_eq!(sdo.read_string(0x1008, 0).unwrap(), "sim node"); // segmented
+//! sdo.download(0x1008, 0, b"left hind knee").unwrap_err(); // read-only: aborted
+//! assert_eq!(sdo.last_abort(), Some(can::canopen::abort::READ_ONLY));
+//! sdo.write_u16(0x1017, 0, 20).unwrap(); // heartbeat every 20 ms
+//!
+//! master.watch_heartbeat(5, Duration::from_millis(100));
+//! master.nmt(NmtCommand::Start, 5).unwrap();
+//! let deadline = Instant::now() + Duration::from_secs(2);
+//! while master.heartbeat().state(5) != Some(NmtState::Operational) && Instant::now() < deadline {
+//!     master.poll().unwrap();
+//! }
+//! assert_eq!(master.heartbeat().state(5), Some(NmtState::Operational));
+//!
+//! // A node that goes quiet is reported once its heartbeat times out
+//! drop(node);
+//! let mut events = Vec::new();
+//! while !events.contains(&Event::Heartbeat(HeartbeatEvent::Timeout { node: 5 })) && Instant::now() < deadline {
+//!     events.extend(master.poll().unwrap());
+//! }
+//! assert!(!master.heartbeat().is_alive(5));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::bus::CanBus;
+use crate::{CanFrame, SlcanError};
+
+/// Function codes of the predefined connection set; node-specific IDs add the node ID.
+pub mod cob {
+    pub const NMT: u32 = 0x000;
+    pub const SYNC: u32 = 0x080;
+    pub const EMCY: u32 = 0x080;
+    pub const TPDO: [u32; 4] = [0x180, 0x280, 0x380, 0x480];
+    pub const RPDO: [u32; 4] = [0x200, 0x300, 0x400, 0x500];
+    /// Server → client (SDO responses).
+    pub const SDO_TX: u32 = 0x580;
+    /// Client → server (SDO requests).
+    pub const SDO_RX: u32 = 0x600;
+    pub const HEARTBEAT: u32 = 0x700;
+}
+
+/// SDO abort codes (CiA 301 table 22) used here.
+pub mod abort {
+    pub const TOGGLE: u32 = 0x0503_0000;
+    pub const TIMEOUT: u32 = 0x0504_0000;
+    pub const COMMAND: u32 = 0x0504_0001;
+    pub const UNSUPPORTED: u32 = 0x0601_0000;
+    pub const WRITE_ONLY: u32 = 0x0601_0001;
+    pub const READ_ONLY: u32 = 0x0601_0002;
+    pub const NO_OBJECT: u32 = 0x0602_0000;
+    pub const NOT_MAPPABLE: u32 = 0x0604_0041;
+    pub const PDO_LENGTH: u32 = 0x0604_0042;
+    pub const LENGTH: u32 = 0x0607_0010;
+    pub const NO_SUBINDEX: u32 = 0x0609_0011;
+    pub const VALUE: u32 = 0x0609_0030;
+    pub const GENERAL: u32 = 0x0800_0000;
+    pub const TRANSFER: u32 = 0x0800_0020;
+    pub const DEVICE_STATE: u32 = 0x0800_0022;
+}
+
+/// Text for an SDO abort code.
+pub fn abort_description(code: u32) -> &'static str {
+    match code {
+        abort::TOGGLE => "sdo abort: toggle bit not alternated",
+        abort::TIMEOUT => "sdo abort: protocol timed out",
+        abort::COMMAND => "sdo abort: invalid command specifier",
+        abort::UNSUPPORTED => "sdo abort: unsupported access to object",
+        abort::WRITE_ONLY => "sdo abort: object is write-only",
+        abort::READ_ONLY => "sdo abort: object is read-only",
+        abort::NO_OBJECT => "sdo abort: object does not exist",
+        abort::NOT_MAPPABLE => "sdo abort: object cannot be mapped to a PDO",
+        abort::PDO_LENGTH => "sdo abort: mapping exceeds PDO length",
+        abort::LENGTH => "sdo abort: data type or length mismatch",
+        abort::NO_SUBINDEX => "sdo abort: sub-index does not exist",
+        abort::VALUE => "sdo abort: invalid value",
+        abort::GENERAL => "sdo abort: general error",
+        abort::TRANSFER => "sdo abort: data cannot be transferred or stored",
+        abort::DEVICE_STATE => "sdo abort: not possible in the present device state",
+        _ => "sdo abort: unknown abort code",
+    }
+}
+
+fn frame(id: u32, data: &[u8]) -> CanFrame {
+    CanFrame { id, data: data.to_vec(), extended: false, rtr: false, fd: false, brs: false, timestamp: None }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmtCommand {
+    Start,
+    Stop,
+    EnterPreOperational,
+    ResetNode,
+    ResetCommunication,
+}
+
+impl NmtCommand {
+    pub fn code(self) -> u8 {
+        match self {
+            NmtCommand::Start => 0x01,
+            NmtCommand::Stop => 0x02,
+            NmtCommand::EnterPreOperational => 0x80,
+            NmtCommand::ResetNode => 0x81,
+            NmtCommand::ResetCommunication => 0x82,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        Some(match code {
+            0x01 => NmtCommand::Start,
+            0x02 => NmtCommand::Stop,
+            0x80 => NmtCommand::EnterPreOperational,
+            0x81 => NmtCommand::ResetNode,
+            0x82 => NmtCommand::ResetCommunication,
+            _ => return None,
+        })
+    }
+}
+
+/// NMT command for `node`; node 0 addresses every node.
+pub fn nmt_frame(cmd: NmtCommand, node: u8) -> CanFrame {
+    frame(cob::NMT, &[cmd.code(), node])
+}
+
+pub fn sync_frame() -> CanFrame {
+    frame(cob::SYNC, &[])
+}
+
+/// NMT state as reported in the heartbeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmtState {
+    BootUp,
+    Stopped,
+    Operational,
+    PreOperational,
+}
+
+impl NmtState {
+    pub fn from_byte(b: u8) -> Option<Self> {
+        Some(match b & 0x7F {
+            0x00 => NmtState::BootUp,
+            0x04 => NmtState::Stopped,
+            0x05 => NmtState::Operational,
+            0x7F => NmtState::PreOperational,
+            _ => return None,
+        })
+    }
+
+    pub fn byte(self) -> u8 {
+        match self {
+            NmtState::BootUp => 0x00,
+            NmtState::Stopped => 0x04,
+            NmtState::Operational => 0x05,
+            NmtState::PreOperational => 0x7F,
+        }
+    }
+}
+
+impl fmt::Display for NmtState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NmtState::BootUp => "boot-up",
+            NmtState::Stopped => "stopped",
+            NmtState::Operational => "operational",
+            NmtState::PreOperational => "pre-operational",
+        })
+    }
+}
+
+fn sdo_request(cmd: u8, index: u16, sub: u8, data: &[u8]) -> [u8; 8] {
+    let mut req = [cmd, index as u8, (index >> 8) as u8, sub, 0, 0, 0, 0];
+    req[4..4 + data.len()].copy_from_slice(data);
+    req
+}
+
+fn abort_frame(node: u8, to_server: bool, index: u16, sub: u8, code: u32) -> CanFrame {
+    let id = if to_server { cob::SDO_RX } else { cob::SDO_TX } + node as u32;
+    frame(id, &sdo_request(0x80, index, sub, &code.to_le_bytes()))
+}
+
+/// Blocking SDO client for one node, on a bus borrowed for the duration of the transfer.
+/// Frames that are not this node's SDO responses are skipped.
+pub struct SdoClient<'a> {
+    bus: &'a mut dyn CanBus,
+    node: u8,
+    timeout: Duration,
+    last_abort: Option<u32>,
+}
+
+impl<'a> SdoClient<'a> {
+    pub fn new(bus: &'a mut dyn CanBus, node: u8) -> Self {
+        SdoClient { bus, node, timeout: Duration::from_millis(500), last_abort: None }
+    }
+
+    /// How long to wait for each response (default 500 ms).
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Abort code of the last transfer the server aborted.
+    pub fn last_abort(&self) -> Option<u32> {
+        self.last_abort
+    }
+
+    /// Send one request and wait for the response; `object` is checked against initiate responses.
+    fn exchange(&mut self, req: [u8; 8], object: Option<(u16, u8)>) -> Result<[u8; 8], SlcanError> {
+        self.bus.send(&frame(cob::SDO_RX + self.node as u32, &req))?;
+        let start = Instant::now();
+        loop {
+            match self.bus.recv() {
+                Ok(f) if f.id == cob::SDO_TX + self.node as u32 && !f.extended && f.data.len() == 8 => {
+                    let mut resp = [0u8; 8];
+                    resp.copy_from_slice(&f.data);
+                    let resp_object = (u16::from_le_bytes([resp[1], resp[2]]), resp[3]);
+                    if resp[0] == 0x80 {
+                        let code = u32::from_le_bytes([resp[4], resp[5], resp[6], resp[7]]);
+                        self.last_abort = Some(code);
+                        return Err(SlcanError::Protocol(abort_description(code)));
+                    }
+                    if object.is_none_or(|o| o == resp_object) {
+                        return Ok(resp);
+                    }
+                }
+                Ok(_) => {}
+                Err(SlcanError::Io(e)) if e.kind() == io::ErrorKind::TimedOut => {}
+                Err(e) => return Err(e),
+            }
+            if start.elapsed() >= self.timeout {
+                if let Some((index, sub)) = object {
+                    let _ = self.bus.send(&abort_frame(self.node, true, index, sub, abort::TIMEOUT));
+                }
+                return Err(SlcanError::Io(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("node {}: no SDO response within {:?}", self.node, self.timeout),
+                )));
+            }
+        }
+    }
+
+    fn fail(&mut self, index: u16, sub: u8, code: u32, what: &'static str) -> SlcanError {
+        let _ = self.bus.send(&abort_frame(self.node, true, index, sub, code));
+        SlcanError::Protocol(what)
+    }
+
+    /// Read an object, expedited or segmented as the server chooses.
+    pub fn upload(&mut self, index: u16, sub: u8) -> Result<Vec<u8>, SlcanError> {
+        self.last_abort = None;
+        let resp = self.exchange(sdo_request(0x40, index, sub, &[]), Some((index, sub)))?;
+        if resp[0] & 0xE0 != 0x40 {
+            return Err(self.fail(index, sub, abort::COMMAND, "sdo: unexpected upload response"));
+        }
+        let (expedited, sized) = (resp[0] & 0x02 != 0, resp[0] & 0x01 != 0);
+        if expedited {
+            let n = if sized { 4 - ((resp[0] >> 2) & 0x03) as usize } else { 4 };
+            return Ok(resp[4..4 + n].to_vec());
+        }
+        let size = sized.then(|| u32::from_le_bytes([resp[4], resp[5], resp[6], resp[7]]) as usize);
+
+        let mut data = Vec::with_capacity(size.unwrap_or(0));
+        let mut toggle = 0u8;
+        loop {
+            let resp = self.exchange(sdo_request(0x60 | (toggle << 4), 0, 0, &[]), None)?;
+            if resp[0] & 0xE0 != 0x00 {
+                return Err(self.fail(index, sub, abort::COMMAND, "sdo: unexpected upload segment"));
+            }
+            if (resp[0] >> 4) & 1 != toggle {
+                return Err(self.fail(index, sub, abort::TOGGLE, "sdo: toggle bit not alternated"));
+            }
+            let n = 7 - ((resp[0] >> 1) & 0x07) as usize;
+            data.extend_from_slice(&resp[1..1 + n]);
+            if resp[0] & 0x01 != 0 {
+                break;
+            }
+            toggle ^= 1;
+        }
+        if size.is_some_and(|s| s != data.len()) {
+            return Err(SlcanError::Protocol("sdo: upload length differs from announced size"));
+        }
+        Ok(data)
+    }
+
+    /// Write an object: expedited for up to 4 bytes, segmented above.
+    pub fn download(&mut self, index: u16, sub: u8, data: &[u8]) -> Result<(), SlcanError> {
+        self.last_abort = None;
+        if data.is_empty() {
+            return Err(SlcanError::Protocol("sdo: empty download"));
+        }
+        if data.len() <= 4 {
+            let cmd = 0x23 | (((4 - data.len()) as u8) << 2);
+            let resp = self.exchange(sdo_request(cmd, index, sub, data), Some((index, sub)))?;
+            if resp[0] != 0x60 {
+                return Err(self.fail(index, sub, abort::COMMAND, "sdo: unexpected download response"));
+            }
+            return Ok(());
+        }
+        let size = u32::try_from(data.len()).map_err(|_| SlcanError::Protocol("sdo: download too long"))?;
+        let resp = self.exchange(sdo_request(0x21, index, sub, &size.to_le_bytes()), Some((index, sub)))?;
+        if resp[0] != 0x60 {
+            return Err(self.fail(index, sub, abort::COMMAND, "sdo: unexpected download response"));
+        }
+        let mut toggle = 0u8;
+        let mut chunks = data.chunks(7).peekable();
+        while let Some(chunk) = chunks.next() {
+            let last = chunks.peek().is_none();
+            let mut req = [0u8; 8];
+            req[0] = (toggle << 4) | (((7 - chunk.len()) as u8) << 1) | last as u8;
+            req[1..1 + chunk.len()].copy_from_slice(chunk);
+            let resp = self.exchange(req, None)?;
+            if resp[0] & 0xE0 != 0x20 {
+                return Err(self.fail(index, sub, abort::COMMAND, "sdo: unexpected download segment response"));
+            }
+            if (resp[0] >> 4) & 1 != toggle {
+                return Err(self.fail(index, sub, abort::TOGGLE, "sdo: toggle bit not alternated"));
+            }
+            toggle ^= 1;
+        }
+        Ok(())
+    }
+
+    fn read_exact<const N: usize>(&mut self, index: u16, sub: u8) -> Result<[u8; N], SlcanError> {
+        let data = self.upload(index, sub)?;
+        data.get(..N).and_then(|d| d.try_into().ok()).ok_or(SlcanError::Protocol("sdo: object shorter than requested type"))
+    }
+
+    pub fn read_u8(&mut self, index: u16, sub: u8) -> Result<u8, SlcanError> {
+        Ok(self.read_exact::<1>(index, sub)?[0])
+    }
+
+    pub fn read_u16(&mut self, index: u16, sub: u8) -> Result<u16, SlcanError> {
+        self.read_exact(index, sub).map(u16::from_le_bytes)
+    }
+
+    pub fn read_u32(&mut self, index: u16, sub: u8) -> Result<u32, SlcanError> {
+        self.read_exact(index, sub).map(u32::from_le_bytes)
+    }
+
+    pub fn read_i32(&mut self, index: u16, sub: u8) -> Result<i32, SlcanError> {
+        self.read_exact(index, sub).map(i32::from_le_bytes)
+    }
+
+    /// VISIBLE_STRING, with trailing NULs removed.
+    pub fn read_string(&mut self, index: u16, sub: u8) -> Result<String, SlcanError> {
+        let data = self.upload(index, sub)?;
+        Ok(String::from_utf8_lossy(&data).trim_end_matches('\0').to_string())
+    }
+
+    pub fn write_u8(&mut self, index: u16, sub: u8, value: u8) -> Result<(), SlcanError> {
+        self.download(index, sub, &[value])
+    }
+
+    pub fn write_u16(&mut self, index: u16, sub: u8, value: u16) -> Result<(), SlcanError> {
+        self.download(index, sub, &value.to_le_bytes())
+    }
+
+    pub fn write_u32(&mut self, index: u16, sub: u8, value: u32) -> Result<(), SlcanError> {
+        self.download(index, sub, &value.to_le_bytes())
+    }
+
+    pub fn write_i32(&mut self, index: u16, sub: u8, value: i32) -> Result<(), SlcanError> {
+        self.download(index, sub, &value.to_le_bytes())
+    }
+
+    /// Program `mapping` into the node: disable the PDO, write the mapping entries and the
+    /// transmission type, then re-enable it with its COB-ID (CiA 301 mapping procedure).
+    pub fn write_pdo_mapping(&mut self, mapping: &PdoMapping) -> Result<(), SlcanError> {
+        let (comm, map) = mapping.parameter_indices();
+        self.write_u32(comm, 1, mapping.cob_id | COB_INVALID)?;
+        self.write_u8(map, 0, 0)?;
+        for (i, entry) in mapping.entries.iter().enumerate() {
+            self.write_u32(map, i as u8 + 1, entry.mapping_value())?;
+        }
+        self.write_u8(map, 0, mapping.entries.len() as u8)?;
+        self.write_u8(comm, 2, mapping.transmission_type)?;
+        if mapping.enabled {
+            self.write_u32(comm, 1, mapping.cob_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// COB-ID bit 31: PDO does not exist / is disabled.
+const COB_INVALID: u32 = 0x8000_0000;
+
+/// EDS data types with a fixed size, as (code, bits, signed).
+const DATA_TYPES: [(u16, u8, bool); 10] = [
+    (0x0001, 1, false),  // BOOLEAN
+    (0x0002, 8, true),   // INTEGER8
+    (0x0003, 16, true),  // INTEGER16
+    (0x0004, 32, true),  // INTEGER32
+    (0x0005, 8, false),  // UNSIGNED8
+    (0x0006, 16, false), // UNSIGNED16
+    (0x0007, 32, false), // UNSIGNED32
+    (0x0008, 32, false), // REAL32, raw bits
+    (0x0015, 64, true),  // INTEGER64
+    (0x001B, 64, false), // UNSIGNED64
+];
+
+fn type_info(data_type: u16) -> Option<(u8, bool)> {
+    DATA_TYPES.iter().find(|(code, _, _)| *code == data_type).map(|&(_, bits, signed)| (bits, signed))
+}
+
+/// Object mapped into a PDO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdoEntry {
+    pub index: u16,
+    pub subindex: u8,
+    pub bits: u8,
+    /// Sign-extend when unpacking (from the EDS data type).
+    pub signed: bool,
+    /// Parameter name from the EDS, empty otherwise.
+    pub name: String,
+}
+
+impl PdoEntry {
+    /// Decode a mapping object value (`0xIIIISSLL`: index, sub-index, length in bits).
+    pub fn from_mapping_value(value: u32) -> Self {
+        PdoEntry { index: (value >> 16) as u16, subindex: (value >> 8) as u8, bits: value as u8, signed: false, name: String::new() }
+    }
+
+    pub fn mapping_value(&self) -> u32 {
+        (self.index as u32) << 16 | (self.subindex as u32) << 8 | self.bits as u32
+    }
+}
+
+/// One PDO: its COB-ID, transmission type and the objects packed into it, LSB first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdoMapping {
+    /// Transmit PDOs are sent by the node (TPDO), receive PDOs by the master (RPDO).
+    pub transmit: bool,
+    /// 1-based PDO number.
+    pub number: u16,
+    pub cob_id: u32,
+    pub transmission_type: u8,
+    pub enabled: bool,
+    pub entries: Vec<PdoEntry>,
+}
+
+impl PdoMapping {
+    /// Communication and mapping parameter indices (0x1800/0x1A00 or 0x1400/0x1600 + n − 1).
+    pub fn parameter_indices(&self) -> (u16, u16) {
+        let n = self.number.saturating_sub(1);
+        if self.transmit { (0x1800 + n, 0x1A00 + n) } else { (0x1400 + n, 0x1600 + n) }
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.entries.iter().map(|e| e.bits as u32).sum()
+    }
+
+    /// Values of the entries, in order, sign-extended for signed entries.
+    /// `None` if `data` is shorter than the mapping.
+    pub fn unpack(&self, data: &[u8]) -> Option<Vec<i64>> {
+        if (data.len() as u32) * 8 < self.bits() {
+            return None;
+        }
+        let mut offset = 0u32;
+        let values = self
+            .entries
+            .iter()
+            .map(|e| {
+                let raw = get_bits(data, offset, e.bits);
+                offset += e.bits as u32;
+                if e.signed && e.bits > 0 && e.bits < 64 {
+                    let shift = 64 - e.bits as u32;
+                    ((raw << shift) as i64) >> shift
+                } else {
+                    raw as i64
+                }
+            })
+            .collect();
+        Some(values)
+    }
+
+    /// Payload for `values` (one per entry; missing ones are 0).
+    pub fn pack(&self, values: &[i64]) -> Vec<u8> {
+        let mut data = vec![0u8; self.bits().div_ceil(8) as usize];
+        let mut offset = 0u32;
+        for (i, e) in self.entries.iter().enumerate() {
+            set_bits(&mut data, offset, e.bits, values.get(i).copied().unwrap_or(0) as u64);
+            offset += e.bits as u32;
+        }
+        data
+    }
+
+    pub fn frame(&self, values: &[i64]) -> CanFrame {
+        frame(self.cob_id & 0x7FF, &self.pack(values))
+    }
+}
+
+fn get_bits(data: &[u8], offset: u32, bits: u8) -> u64 {
+    (0..bits as u32).fold(0u64, |acc, i| {
+        let bit = offset + i;
+        acc | (((data[(bit / 8) as usize] >> (bit % 8)) & 1) as u64) << i
+    })
+}
+
+fn set_bits(data: &mut [u8], offset: u32, bits: u8, value: u64) {
+    for i in 0..bits as u32 {
+        let bit = offset + i;
+        if (value >> i) & 1 != 0 {
+            data[(bit / 8) as usize] |= 1 << (bit % 8);
+        }
+    }
+}
+
+/// One object dictionary entry from an EDS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdsObject {
+    pub index: u16,
+    pub subindex: u8,
+    pub name: String,
+    pub data_type: u16,
+    /// `ro`, `wo`, `rw`, `rww`, `rwr` or `const`.
+    pub access: String,
+    /// Raw `DefaultValue`, possibly with `$NODEID`.
+    pub default: Option<String>,
+    pub pdo_mappable: bool,
+}
+
+impl EdsObject {
+    pub fn writable(&self) -> bool {
+        self.access.starts_with("rw") || self.access == "wo"
+    }
+}
+
+/// The part of an Electronic Data Sheet needed for SDO and PDO work: object sections
+/// (`[6041]`, `[1A00sub1]`) with `ParameterName`, `DataType`, `AccessType`, `DefaultValue`
+/// and `PDOMapping`. Other sections (file/device info, object lists, comments) are ignored.
+///
+/// ```
+/// use std::time::{Duration, Instant};
+/// use can::canopen::{Eds, Event, Master, NmtCommand, SimNode};
+/// use can::mock::MockBus;
+/// use can::threaded::BusThread;
+///
+/// let eds = Eds::parse(r#"
+/// [1800sub1]
+/// ParameterName=COB-ID used by TPDO 1
+/// DataType=0x0007
+/// AccessType=rw
+/// DefaultValue=$NODEID+0x180
+/// [1800sub2]
+/// ParameterName=Transmission type
+/// DataType=0x0005
+/// AccessType=rw
+/// DefaultValue=1
+/// [1A00sub0]
+/// ParameterName=Number of mapped objects
+/// DataType=0x0005
+/// AccessType=rw
+/// DefaultValue=2
+/// [1A00sub1]
+/// ParameterName=Mapped object 1
+/// DataType=0x0007
+/// AccessType=rw
+/// DefaultValue=0x60410010
+/// [1A00sub2]
+/// ParameterName=Mapped object 2
+/// DataType=0x0007
+/// AccessType=rw
+/// DefaultValue=0x60640020
+/// [6064]
+/// ParameterName=Position actual value
+/// DataType=0x0004
+/// AccessType=ro
+/// PDOMapping=1
+/// "#).unwrap();
+/// let tpdo = &eds.tpdos(3)[0];
+/// assert_eq!((tpdo.cob_id, tpdo.transmission_type, tpdo.entries.len()), (0x183, 1, 2));
+/// assert_eq!(tpdo.unpack(&[0x37, 0x06, 0xFE, 0xFF, 0xFF, 0xFF]), Some(vec![0x0637, -2]));
+///
+/// let bus = BusThread::spawn(Box::new(MockBus::loopback()));
+/// let node = SimNode::new(3).spawn(bus.client());
+/// let mut master = Master::new(bus.client());
+/// master.configure_pdos(3, &eds).unwrap();
+/// node.with(|n| n.set(0x6064, 0, &(-1234i32).to_le_bytes()));
+/// master.nmt(NmtCommand::Start, 3).unwrap();
+///
+/// let deadline = Instant::now() + Duration::from_secs(2);
+/// let values = loop {
+///     master.sync().unwrap();
+///     let pdo = master.poll().unwrap().into_iter().find_map(|e| match e {
+///         Event::Pdo { node: 3, number: 1, values } => Some(values),
+///         _ => None,
+///     });
+///     if let Some(values) = pdo { break values; }
+///     assert!(Instant::now() < deadline, "no TPDO");
+/// };
+/// assert_eq!(values, vec![0, -1234]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Eds {
+    objects: BTreeMap<(u16, u8), EdsObject>,
+}
+
+/// Integer in EDS notation: `0x1A`, decimal, or octal with a leading 0.
+fn parse_eds_int(s: &str) -> Option<u64> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else if s.len() > 1 && s.starts_with('0') {
+        u64::from_str_radix(&s[1..], 8).ok()
+    } else if let Some(neg) = s.strip_prefix('-') {
+        neg.parse::<i64>().ok().map(|v| (-v) as u64)
+    } else {
+        s.parse().ok()
+    }
+}
+
+impl Eds {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SlcanError> {
+        Eds::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, SlcanError> {
+        let mut objects = BTreeMap::new();
+        let mut current: Option<(EdsObject, bool)> = None;
+        let mut finish = |current: Option<(EdsObject, bool)>| {
+            // Headers of arrays/records (`SubNumber=`) only describe their sub-entries
+            if let Some((obj, false)) = current {
+                objects.insert((obj.index, obj.subindex), obj);
+            }
+        };
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                finish(current.take());
+                let lower = section.to_ascii_lowercase();
+                let (index, sub) = match lower.split_once("sub") {
+                    Some((i, s)) => (i, u8::from_str_radix(s, 16).ok()),
+                    None => (lower.as_str(), Some(0)),
+                };
+                if let (Ok(index), Some(subindex)) = (u16::from_str_radix(index, 16), sub) {
+                    let obj = EdsObject { index, subindex, name: String::new(), data_type: 0, access: "rw".into(), default: None, pdo_mappable: false };
+                    current = Some((obj, false));
+                }
+                continue;
+            }
+            let Some((obj, header)) = current.as_mut() else { continue };
+            let (key, value) = line.split_once('=').ok_or_else(|| SlcanError::Parse(format!("eds line {}: expected key=value", n + 1)))?;
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "parametername" => obj.name = value.to_string(),
+                "datatype" => {
+                    obj.data_type = parse_eds_int(value).and_then(|v| u16::try_from(v).ok()).ok_or_else(|| SlcanError::Parse(format!("eds line {}: bad DataType {}", n + 1, value)))?
+                }
+                "accesstype" => obj.access = value.to_ascii_lowercase(),
+                "defaultvalue" if !value.is_empty() => obj.default = Some(value.to_string()),
+                "pdomapping" => obj.pdo_mappable = value == "1",
+                "subnumber" => *header = true,
+                _ => {}
+            }
+        }
+        finish(current.take());
+        Ok(Eds { objects })
+    }
+
+    pub fn object(&self, index: u16, subindex: u8) -> Option<&EdsObject> {
+        self.objects.get(&(index, subindex))
+    }
+
+    pub fn objects(&self) -> impl Iterator<Item = &EdsObject> {
+        self.objects.values()
+    }
+
+    /// Numeric default of an object, with `$NODEID` replaced by `node`.
+    pub fn default_value(&self, index: u16, subindex: u8, node: u8) -> Option<u64> {
+        let text = self.object(index, subindex)?.default.as_deref()?;
+        let mut total = 0u64;
+        for term in text.split('+') {
+            let term = term.trim();
+            total = total.wrapping_add(if term.eq_ignore_ascii_case("$NODEID") { node as u64 } else { parse_eds_int(term)? });
+        }
+        Some(total)
+    }
+
+    /// Default value of an object as it is stored in the dictionary (little endian, sized
+    /// by the data type; strings as bytes).
+    pub fn default_bytes(&self, index: u16, subindex: u8, node: u8) -> Option<Vec<u8>> {
+        let obj = self.object(index, subindex)?;
+        match type_info(obj.data_type) {
+            Some((bits, _)) => {
+                let value = self.default_value(index, subindex, node).unwrap_or(0);
+                Some(value.to_le_bytes()[..(bits as usize).div_ceil(8)].to_vec())
+            }
+            None => Some(obj.default.clone().unwrap_or_default().into_bytes()),
+        }
+    }
+
+    fn pdos(&self, transmit: bool, node: u8) -> Vec<PdoMapping> {
+        let (comm_base, map_base) = if transmit { (0x1800u16, 0x1A00u16) } else { (0x1400, 0x1600) };
+        (0..512u16)
+            .filter_map(|n| {
+                let cob = self.default_value(comm_base + n, 1, node)? as u32;
+                let count = self.default_value(map_base + n, 0, node).unwrap_or(0) as u8;
+                let entries = (1..=count)
+                    .filter_map(|i| self.default_value(map_base + n, i, node))
+                    .map(|v| {
+                        let mut entry = PdoEntry::from_mapping_value(v as u32);
+                        if let Some(obj) = self.object(entry.index, entry.subindex) {
+                            entry.name = obj.name.clone();
+                            entry.signed = type_info(obj.data_type).is_some_and(|(_, signed)| signed);
+                        }
+                        entry
+                    })
+                    .collect();
+                Some(PdoMapping {
+                    transmit,
+                    number: n + 1,
+                    cob_id: cob & !COB_INVALID,
+                    transmission_type: self.default_value(comm_base + n, 2, node).unwrap_or(255) as u8,
+                    enabled: cob & COB_INVALID == 0,
+                    entries,
+                })
+            })
+            .collect()
+    }
+
+    /// Transmit PDOs (node → master) defined in the EDS, with COB-IDs for `node`.
+    pub fn tpdos(&self, node: u8) -> Vec<PdoMapping> {
+        self.pdos(true, node)
+    }
+
+    /// Receive PDOs (master → node) defined in the EDS, with COB-IDs for `node`.
+    pub fn rpdos(&self, node: u8) -> Vec<PdoMapping> {
+        self.pdos(false, node)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatEvent {
+    BootUp { node: u8 },
+    StateChanged { node: u8, state: NmtState },
+    /// No heartbeat within the consumer time.
+    Timeout { node: u8 },
+    /// Heartbeat again after a timeout.
+    Recovered { node: u8, state: NmtState },
+}
+
+impl fmt::Display for HeartbeatEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeartbeatEvent::BootUp { node } => write!(f, "node {} booted", node),
+            HeartbeatEvent::StateChanged { node, state } => write!(f, "node {} {}", node, state),
+            HeartbeatEvent::Timeout { node } => write!(f, "node {} heartbeat lost", node),
+            HeartbeatEvent::Recovered { node, state } => write!(f, "node {} heartbeat back ({})", node, state),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Consumer {
+    timeout: Option<Duration>,
+    last: Option<Instant>,
+    state: Option<NmtState>,
+    timed_out: bool,
+}
+
+/// Heartbeat consumer: NMT state per node and timeouts for the watched ones.
+#[derive(Debug, Clone, Default)]
+pub struct HeartbeatMonitor {
+    nodes: BTreeMap<u8, Consumer>,
+}
+
+impl HeartbeatMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expect a heartbeat from `node` at least every `timeout` (typically 1.5× its producer time).
+    /// The timer starts now.
+    pub fn watch(&mut self, node: u8, timeout: Duration, now: Instant) {
+        let c = self.nodes.entry(node).or_insert(Consumer { timeout: None, last: None, state: None, timed_out: false });
+        c.timeout = Some(timeout);
+        c.last.get_or_insert(now);
+    }
+
+    pub fn unwatch(&mut self, node: u8) {
+        self.nodes.remove(&node);
+    }
+
+    /// Handle a heartbeat or boot-up frame; other frames are ignored.
+    pub fn handle_frame(&mut self, frame: &CanFrame, now: Instant) -> Option<HeartbeatEvent> {
+        if frame.extended || frame.rtr || frame.data.len() != 1 || !(cob::HEARTBEAT + 1..=cob::HEARTBEAT + 127).contains(&frame.id) {
+            return None;
+        }
+        let node = (frame.id - cob::HEARTBEAT) as u8;
+        let state = NmtState::from_byte(frame.data[0])?;
+        let c = self.nodes.entry(node).or_insert(Consumer { timeout: None, last: None, state: None, timed_out: false });
+        let previous = c.state.replace(state);
+        c.last = Some(now);
+        if std::mem::take(&mut c.timed_out) {
+            return Some(HeartbeatEvent::Recovered { node, state });
+        }
+        match state {
+            NmtState::BootUp => Some(HeartbeatEvent::BootUp { node }),
+            _ if previous != Some(state) => Some(HeartbeatEvent::StateChanged { node, state }),
+            _ => None,
+        }
+    }
+
+    /// Report watched nodes whose heartbeat is overdue (once per outage).
+    pub fn check(&mut self, now: Instant) -> Vec<HeartbeatEvent> {
+        let mut events = Vec::new();
+        for (&node, c) in self.nodes.iter_mut() {
+            let (Some(timeout), Some(last)) = (c.timeout, c.last) else { continue };
+            if !c.timed_out && now.saturating_duration_since(last) > timeout {
+                c.timed_out = true;
+                events.push(HeartbeatEvent::Timeout { node });
+            }
+        }
+        events
+    }
+
+    /// Last state reported by `node`.
+    pub fn state(&self, node: u8) -> Option<NmtState> {
+        self.nodes.get(&node)?.state
+    }
+
+    /// Heard from and not timed out.
+    pub fn is_alive(&self, node: u8) -> bool {
+        self.nodes.get(&node).is_some_and(|c| c.state.is_some() && !c.timed_out)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (u8, Option<NmtState>)> + '_ {
+        self.nodes.iter().map(|(&n, c)| (n, c.state))
+    }
+}
+
+/// What [`Master::poll`] saw on the bus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Heartbeat(HeartbeatEvent),
+    /// A registered TPDO, unpacked per its mapping.
+    Pdo { node: u8, number: u16, values: Vec<i64> },
+    Emergency { node: u8, code: u16, register: u8, data: [u8; 5] },
+}
+
+/// CANopen master on one bus: NMT, SYNC, SDO access, TPDO decoding and heartbeat consumer.
+pub struct Master<B: CanBus = Box<dyn CanBus>> {
+    bus: B,
+    heartbeat: HeartbeatMonitor,
+    tpdos: Vec<(u8, PdoMapping)>,
+}
+
+impl<B: CanBus> Master<B> {
+    pub fn new(bus: B) -> Self {
+        Master { bus, heartbeat: HeartbeatMonitor::new(), tpdos: Vec::new() }
+    }
+
+    pub fn bus_mut(&mut self) -> &mut B {
+        &mut self.bus
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bus
+    }
+
+    pub fn nmt(&mut self, cmd: NmtCommand, node: u8) -> Result<(), SlcanError> {
+        self.bus.send(&nmt_frame(cmd, node))
+    }
+
+    pub fn sync(&mut self) -> Result<(), SlcanError> {
+        self.bus.send(&sync_frame())
+    }
+
+    /// SDO client for `node`. Traffic received during a transfer is not seen by [`Master::poll`].
+    pub fn sdo(&mut self, node: u8) -> SdoClient<'_> {
+        SdoClient::new(&mut self.bus, node)
+    }
+
+    pub fn heartbeat(&self) -> &HeartbeatMonitor {
+        &self.heartbeat
+    }
+
+    pub fn watch_heartbeat(&mut self, node: u8, timeout: Duration) {
+        self.heartbeat.watch(node, timeout, Instant::now());
+    }
+
+    /// Decode `mapping` (a TPDO of `node`) in [`Master::poll`].
+    pub fn add_tpdo(&mut self, node: u8, mapping: PdoMapping) {
+        self.tpdos.retain(|(n, m)| !(*n == node && m.number == mapping.number));
+        self.tpdos.push((node, mapping));
+    }
+
+    pub fn tpdos(&self) -> impl Iterator<Item = &(u8, PdoMapping)> {
+        self.tpdos.iter()
+    }
+
+    /// Write every PDO of the EDS to `node` over SDO and decode its TPDOs from now on.
+    /// Do this in pre-operational; the node applies the mapping when started.
+    pub fn configure_pdos(&mut self, node: u8, eds: &Eds) -> Result<(), SlcanError> {
+        let tpdos = eds.tpdos(node);
+        let mut sdo = self.sdo(node);
+        for mapping in tpdos.iter().chain(eds.rpdos(node).iter()) {
+            sdo.write_pdo_mapping(mapping)?;
+        }
+        for mapping in tpdos {
+            self.add_tpdo(node, mapping);
+        }
+        Ok(())
+    }
+
+    /// Send an RPDO with one value per mapped entry.
+    pub fn send_pdo(&mut self, mapping: &PdoMapping, values: &[i64]) -> Result<(), SlcanError> {
+        self.bus.send(&mapping.frame(values))
+    }
+
+    /// Handle a frame received elsewhere (e.g. a [`crate::threaded::BusThread`] subscription).
+    pub fn handle_frame(&mut self, frame: &CanFrame, now: Instant) -> Option<Event> {
+        if frame.extended || frame.rtr {
+            return None;
+        }
+        if let Some(ev) = self.heartbeat.handle_frame(frame, now) {
+            return Some(Event::Heartbeat(ev));
+        }
+        if let Some((node, mapping)) = self.tpdos.iter().find(|(_, m)| m.enabled && m.cob_id & 0x7FF == frame.id) {
+            return mapping.unpack(&frame.data).map(|values| Event::Pdo { node: *node, number: mapping.number, values });
+        }
+        if (cob::EMCY + 1..=cob::EMCY + 127).contains(&frame.id) && frame.data.len() == 8 {
+            let d = &frame.data;
+            let mut data = [0u8; 5];
+            data.copy_from_slice(&d[3..8]);
+            return Some(Event::Emergency { node: (frame.id - cob::EMCY) as u8, code: u16::from_le_bytes([d[0], d[1]]), register: d[2], data });
+        }
+        None
+    }
+
+    /// Handle pending frames and check heartbeat timeouts; blocks for at most one read
+    /// timeout of the underlying bus.
+    pub fn poll(&mut self) -> Result<Vec<Event>, SlcanError> {
+        let mut events = Vec::new();
+        loop {
+            match self.bus.recv() {
+                Ok(frame) => events.extend(self.handle_frame(&frame, Instant::now())),
+                Err(SlcanError::Io(e)) if e.kind() == io::ErrorKind::TimedOut => break,
+                Err(e) => return Err(e),
+            }
+        }
+        events.extend(self.heartbeat.check(Instant::now()).into_iter().map(Event::Heartbeat));
+        Ok(events)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct OdEntry {
+    value: Vec<u8>,
+    writable: bool,
+    /// Strings and domains accept writes of any length.
+    variable: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Transfer {
+    Upload { index: u16, sub: u8, data: Vec<u8>, pos: usize, toggle: u8 },
+    Download { index: u16, sub: u8, data: Vec<u8>, toggle: u8 },
+}
+
+/// Simulated CANopen slave: object dictionary, SDO server (expedited and segmented), NMT
+/// state machine, heartbeat producer (0x1017) and up to four TPDOs/RPDOs mapped through
+/// 0x1400–0x1A03. TPDOs go out on SYNC (transmission type 0–240) or on their event timer
+/// (254/255, 0x180x sub 5); RPDOs write their mapped objects. Only in operational state.
+#[derive(Debug, Clone)]
+pub struct SimNode {
+    node: u8,
+    od: BTreeMap<(u16, u8), OdEntry>,
+    defaults: BTreeMap<(u16, u8), OdEntry>,
+    state: NmtState,
+    transfer: Option<Transfer>,
+    last_heartbeat: Option<Instant>,
+    last_event: [Option<Instant>; 4],
+}
+
+impl SimNode {
+    /// A node with the communication objects and a few CiA 402 drive objects
+    /// (controlword 0x6040, statusword 0x6041, position actual 0x6064, target 0x607A).
+    /// TPDO1 and RPDO1 are enabled with their default COB-IDs and nothing mapped.
+    pub fn new(node: u8) -> Self {
+        let mut sim = SimNode {
+            node,
+            od: BTreeMap::new(),
+            defaults: BTreeMap::new(),
+            state: NmtState::BootUp,
+            transfer: None,
+            last_heartbeat: None,
+            last_event: [None; 4],
+        };
+        let n = node as u32;
+        sim.define(0x1000, 0, &0x0002_0192u32.to_le_bytes(), false);
+        sim.define(0x1001, 0, &[0], false);
+        sim.define_variable(0x1008, 0, b"sim node", false);
+        sim.define(0x1017, 0, &0u16.to_le_bytes(), true);
+        sim.define(0x1018, 0, &[4], false);
+        for sub in 1..=4 {
+            sim.define(0x1018, sub, &0u32.to_le_bytes(), false);
+        }
+        for i in 0..4u16 {
+            let disabled = if i == 0 { 0 } else { COB_INVALID };
+            sim.define(0x1400 + i, 0, &[2], false);
+            sim.define(0x1400 + i, 1, &((cob::RPDO[i as usize] + n) | disabled).to_le_bytes(), true);
+            sim.define(0x1400 + i, 2, &[255], true);
+            sim.define(0x1800 + i, 0, &[5], false);
+            sim.define(0x1800 + i, 1, &((cob::TPDO[i as usize] + n) | disabled).to_le_bytes(), true);
+            sim.define(0x1800 + i, 2, &[255], true);
+            sim.define(0x1800 + i, 3, &0u16.to_le_bytes(), true);
+            sim.define(0x1800 + i, 5, &0u16.to_le_bytes(), true);
+            for map in [0x1600 + i, 0x1A00 + i] {
+                sim.define(map, 0, &[0], true);
+                for sub in 1..=8 {
+                    sim.define(map, sub, &0u32.to_le_bytes(), true);
+                }
+            }
+        }
+        sim.define(0x6040, 0, &0u16.to_le_bytes(), true);
+        sim.define(0x6041, 0, &0u16.to_le_bytes(), false);
+        sim.define(0x6064, 0, &0i32.to_le_bytes(), false);
+        sim.define(0x607A, 0, &0i32.to_le_bytes(), true);
+        sim
+    }
+
+    /// [`SimNode::new`] with every object of `eds` added at its default value.
+    pub fn from_eds(eds: &Eds, node: u8) -> Self {
+        let mut sim = SimNode::new(node);
+        for obj in eds.objects() {
+            let value = eds.default_bytes(obj.index, obj.subindex, node).unwrap_or_default();
+            if type_info(obj.data_type).is_some() {
+                sim.define(obj.index, obj.subindex, &value, obj.writable());
+            } else {
+                sim.define_variable(obj.index, obj.subindex, &value, obj.writable());
+            }
+        }
+        sim
+    }
+
+    /// Add or replace a fixed-size object; it is restored to `value` on NMT reset node.
+    /// SDO writes of another length are aborted.
+    pub fn define(&mut self, index: u16, sub: u8, value: &[u8], writable: bool) {
+        self.insert(index, sub, OdEntry { value: value.to_vec(), writable, variable: false });
+    }
+
+    /// Like [`SimNode::define`] for a string or domain, which may be written at any length.
+    pub fn define_variable(&mut self, index: u16, sub: u8, value: &[u8], writable: bool) {
+        self.insert(index, sub, OdEntry { value: value.to_vec(), writable, variable: true });
+    }
+
+    fn insert(&mut self, index: u16, sub: u8, entry: OdEntry) {
+        self.defaults.insert((index, sub), entry.clone());
+        self.od.insert((index, sub), entry);
+    }
+
+    /// Change an object's value as the application would (ignores access rights).
+    pub fn set(&mut self, index: u16, sub: u8, value: &[u8]) {
+        if let Some(e) = self.od.get_mut(&(index, sub)) {
+            e.value = value.to_vec();
+        }
+    }
+
+    pub fn get(&self, index: u16, sub: u8) -> Option<&[u8]> {
+        self.od.get(&(index, sub)).map(|e| e.value.as_slice())
+    }
+
+    fn get_u64(&self, index: u16, sub: u8) -> u64 {
+        let mut bytes = [0u8; 8];
+        if let Some(v) = self.get(index, sub) {
+            let n = v.len().min(8);
+            bytes[..n].copy_from_slice(&v[..n]);
+        }
+        u64::from_le_bytes(bytes)
+    }
+
+    pub fn node(&self) -> u8 {
+        self.node
+    }
+
+    pub fn state(&self) -> NmtState {
+        self.state
+    }
+
+    /// Enter pre-operational and announce it with the boot-up message.
+    pub fn boot(&mut self) -> CanFrame {
+        self.state = NmtState::PreOperational;
+        self.transfer = None;
+        frame(cob::HEARTBEAT + self.node as u32, &[NmtState::BootUp.byte()])
+    }
+
+    /// Mapping of PDO `i` (0-based) as currently configured in the dictionary.
+    fn mapping(&self, transmit: bool, i: u16) -> Option<PdoMapping> {
+        let (comm, map) = if transmit { (0x1800 + i, 0x1A00 + i) } else { (0x1400 + i, 0x1600 + i) };
+        let cob = self.get_u64(comm, 1) as u32;
+        if cob & COB_INVALID != 0 {
+            return None;
+        }
+        let count = self.get_u64(map, 0) as u8;
+        let entries = (1..=count).map(|s| PdoEntry::from_mapping_value(self.get_u64(map, s) as u32)).collect();
+        Some(PdoMapping { transmit, number: i + 1, cob_id: cob, transmission_type: self.get_u64(comm, 2) as u8, enabled: true, entries })
+    }
+
+    fn tpdo_frame(&self, i: u16) -> Option<CanFrame> {
+        let mapping = self.mapping(true, i)?;
+        let values: Vec<i64> = mapping.entries.iter().map(|e| self.get_u64(e.index, e.subindex) as i64).collect();
+        Some(mapping.frame(&values))
+    }
+
+    /// React to one frame; returns the frames to send.
+    pub fn handle_frame(&mut self, f: &CanFrame, now: Instant) -> Vec<CanFrame> {
+        if f.extended || f.rtr {
+            return Vec::new();
+        }
+        let node = self.node as u32;
+        if f.id == cob::NMT && f.data.len() == 2 && (f.data[1] == 0 || f.data[1] == self.node) {
+            return match NmtCommand::from_code(f.data[0]) {
+                Some(NmtCommand::Start) => {
+                    self.state = NmtState::Operational;
+                    self.last_event = [Some(now); 4];
+                    Vec::new()
+                }
+                Some(NmtCommand::Stop) => {
+                    self.state = NmtState::Stopped;
+                    Vec::new()
+                }
+                Some(NmtCommand::EnterPreOperational) => {
+                    self.state = NmtState::PreOperational;
+                    Vec::new()
+                }
+                Some(NmtCommand::ResetNode) => {
+                    self.od = self.defaults.clone();
+                    vec![self.boot()]
+                }
+                Some(NmtCommand::ResetCommunication) => {
+                    for (key, entry) in &self.defaults {
+                        if (0x1000..0x2000).contains(&key.0) {
+                            self.od.insert(*key, entry.clone());
+                        }
+                    }
+                    vec![self.boot()]
+                }
+                None => Vec::new(),
+            };
+        }
+        if self.state == NmtState::Stopped {
+            return Vec::new();
+        }
+        if f.id == cob::SDO_RX + node && f.data.len() == 8 {
+            let mut req = [0u8; 8];
+            req.copy_from_slice(&f.data);
+            return self.sdo(req).map(|resp| vec![frame(cob::SDO_TX + node, &resp)]).unwrap_or_default();
+        }
+        if self.state != NmtState::Operational {
+            return Vec::new();
+        }
+        if f.id == cob::SYNC && f.data.len() <= 1 {
+            return (0..4).filter(|&i| self.get_u64(0x1800 + i, 2) <= 240).filter_map(|i| self.tpdo_frame(i)).collect();
+        }
+        for i in 0..4 {
+            if let Some(mapping) = self.mapping(false, i).filter(|m| m.cob_id & 0x7FF == f.id) {
+                if let Some(values) = mapping.unpack(&f.data) {
+                    for (e, v) in mapping.entries.iter().zip(values) {
+                        let len = (e.bits as usize).div_ceil(8);
+                        self.set(e.index, e.subindex, &v.to_le_bytes()[..len]);
+                    }
+                }
+            }
+        }
+        Vec::new()
+    }
+
+    /// Time-driven output: heartbeat and event-timer TPDOs that are due.
+    pub fn tick(&mut self, now: Instant) -> Vec<CanFrame> {
+        let mut out = Vec::new();
+        let period = Duration::from_millis(self.get_u64(0x1017, 0));
+        if !period.is_zero() && self.last_heartbeat.is_none_or(|t| now.saturating_duration_since(t) >= period) {
+            out.push(frame(cob::HEARTBEAT + self.node as u32, &[self.state.byte()]));
+            self.last_heartbeat = Some(now);
+        }
+        if self.state == NmtState::Operational {
+            for i in 0..4u16 {
+                let timer = Duration::from_millis(self.get_u64(0x1800 + i, 5));
+                let tt = self.get_u64(0x1800 + i, 2);
+                let last = &mut self.last_event[i as usize];
+                if tt >= 254 && !timer.is_zero() && last.is_none_or(|t| now.saturating_duration_since(t) >= timer) {
+                    *last = Some(now);
+                    out.extend(self.tpdo_frame(i));
+                }
+            }
+        }
+        out
+    }
+
+    fn abort(&mut self, index: u16, sub: u8, code: u32) -> Option<[u8; 8]> {
+        self.transfer = None;
+        Some(sdo_request(0x80, index, sub, &code.to_le_bytes()))
+    }
+
+    /// Abort code for an entry that is not in the dictionary.
+    fn missing(&self, index: u16) -> u32 {
+        if self.od.range((index, 0)..=(index, 255)).next().is_some() { abort::NO_SUBINDEX } else { abort::NO_OBJECT }
+    }
+
+    fn write(&mut self, index: u16, sub: u8, data: Vec<u8>) -> Result<(), u32> {
+        let missing = self.missing(index);
+        let entry = self.od.get_mut(&(index, sub)).ok_or(missing)?;
+        if !entry.writable {
+            return Err(abort::READ_ONLY);
+        }
+        if !entry.variable && data.len() != entry.value.len() {
+            return Err(abort::LENGTH);
+        }
+        entry.value = data;
+        Ok(())
+    }
+
+    fn sdo(&mut self, req: [u8; 8]) -> Option<[u8; 8]> {
+        let (index, sub) = (u16::from_le_bytes([req[1], req[2]]), req[3]);
+        match req[0] >> 5 {
+            // Initiate upload
+            2 => {
+                let Some(entry) = self.od.get(&(index, sub)) else {
+                    let code = self.missing(index);
+                    return self.abort(index, sub, code);
+                };
+                let data = entry.value.clone();
+                if data.len() <= 4 && !data.is_empty() {
+                    self.transfer = None;
+                    return Some(sdo_request(0x43 | (((4 - data.len()) as u8) << 2), index, sub, &data));
+                }
+                let size = (data.len() as u32).to_le_bytes();
+                self.transfer = Some(Transfer::Upload { index, sub, data, pos: 0, toggle: 0 });
+                Some(sdo_request(0x41, index, sub, &size))
+            }
+            // Upload segment
+            3 => {
+                let Some(Transfer::Upload { index, sub, data, pos, toggle }) = self.transfer.as_mut() else {
+                    return self.abort(index, sub, abort::COMMAND);
+                };
+                let (index, sub) = (*index, *sub);
+                if (req[0] >> 4) & 1 != *toggle {
+                    return self.abort(index, sub, abort::TOGGLE);
+                }
+                let chunk = data[*pos..].iter().take(7).copied().collect::<Vec<u8>>();
+                *pos += chunk.len();
+                let last = *pos == data.len();
+                let mut resp = [0u8; 8];
+                resp[0] = (*toggle << 4) | (((7 - chunk.len()) as u8) << 1) | last as u8;
+                resp[1..1 + chunk.len()].copy_from_slice(&chunk);
+                *toggle ^= 1;
+                if last {
+                    self.transfer = None;
+                }
+                Some(resp)
+            }
+            // Initiate download
+            1 => {
+                let (expedited, sized) = (req[0] & 0x02 != 0, req[0] & 0x01 != 0);
+                if expedited {
+                    let n = if sized { 4 - ((req[0] >> 2) & 0x03) as usize } else { 4 };
+                    return match self.write(index, sub, req[4..4 + n].to_vec()) {
+                        Ok(()) => {
+                            self.transfer = None;
+                            Some(sdo_request(0x60, index, sub, &[]))
+                        }
+                        Err(code) => self.abort(index, sub, code),
+                    };
+                }
+                if !self.od.contains_key(&(index, sub)) {
+                    let code = self.missing(index);
+                    return self.abort(index, sub, code);
+                }
+                self.transfer = Some(Transfer::Download { index, sub, data: Vec::new(), toggle: 0 });
+                Some(sdo_request(0x60, index, sub, &[]))
+            }
+            // Download segment
+            0 => {
+                let Some(Transfer::Download { index, sub, data, toggle }) = self.transfer.as_mut() else {
+                    return self.abort(index, sub, abort::COMMAND);
+                };
+                let (index, sub) = (*index, *sub);
+                if (req[0] >> 4) & 1 != *toggle {
+                    return self.abort(index, sub, abort::TOGGLE);
+                }
+                let n = 7 - ((req[0] >> 1) & 0x07) as usize;
+                data.extend_from_slice(&req[1..1 + n]);
+                let resp = sdo_request(0x20 | (*toggle << 4), 0, 0, &[]);
+                *toggle ^= 1;
+                if req[0] & 0x01 != 0 {
+                    let data = std::mem::take(data);
+                    self.transfer = None;
+                    if let Err(code) = self.write(index, sub, data) {
+                        return self.abort(index, sub, code);
+                    }
+                }
+                // Segment responses carry no index; zero the bytes `sdo_request` filled
+                let mut resp = resp;
+                resp[1..4].fill(0);
+                Some(resp)
+            }
+            // Abort from the client
+            4 => {
+                self.transfer = None;
+                None
+            }
+            _ => self.abort(index, sub, abort::COMMAND),
+        }
+    }
+
+    /// Run the node on its own thread: boot, then answer frames from `bus` and produce
+    /// heartbeats and PDOs until the handle is dropped.
+    pub fn spawn<B: CanBus + Send + 'static>(mut self, mut bus: B) -> SimNodeHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let _ = bus.send(&self.boot());
+        let node = Arc::new(Mutex::new(self));
+        let (thread_node, thread_stop) = (node.clone(), stop.clone());
+        let thread = std::thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                let received = match bus.recv() {
+                    Ok(f) => Some(f),
+                    Err(SlcanError::Io(e)) if e.kind() == io::ErrorKind::TimedOut => None,
+                    Err(_) => break,
+                };
+                let now = Instant::now();
+                let out = {
+                    let mut node = thread_node.lock().unwrap();
+                    let mut out = received.map(|f| node.handle_frame(&f, now)).unwrap_or_default();
+                    out.extend(node.tick(now));
+                    out
+                };
+                for f in out {
+                    let _ = bus.send(&f);
+                }
+            }
+        });
+        SimNodeHandle { node, stop, thread: Some(thread) }
+    }
+}
+
+/// A [`SimNode`] running on its own thread; dropping the handle stops it.
+pub struct SimNodeHandle {
+    node: Arc<Mutex<SimNode>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SimNodeHandle {
+    /// Access the running node, e.g. to change or inspect objects.
+    pub fn with<R>(&self, f: impl FnOnce(&mut SimNode) -> R) -> R {
+        f(&mut self.node.lock().unwrap())
+    }
+}
+
+impl Drop for SimNodeHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(t) = self.thread.take() {
+            let _ = t.join();
+        }
+    }
+}
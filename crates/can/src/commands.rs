@@ -0,0 +1,322 @@
+//! Non-interactive subcommands of the `can` CLI.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use can::dbc::Dbc;
+use can::isotp::{IsoTp, IsoTpConfig};
+use can::motors::{MotorBus, MotorEvent};
+use can::params::{FlashWrite, MotorParams, Param, ParamClient};
+use can::record::{self, Direction, Recorder, Timing};
+use can::rmd::{self, error_names};
+use can::safety::{SafeBus, SafetyConfig};
+use can::script::{Script, ScriptEvent};
+use can::trajectory::{JointLimits, Profile, Streamer, Trajectory, Waypoint};
+use can::{Bitrate, CanBus, CanFrame, DataBitrate, Slcan, SlcanError};
+
+use crate::PortArgs;
+
+fn id_hex(frame: &CanFrame) -> String {
+    if frame.extended { format!("{:08X}", frame.id) } else { format!("{:03X}", frame.id) }
+}
+
+fn is_timeout(e: &SlcanError) -> bool {
+    matches!(e, SlcanError::Io(io) if io.kind() == std::io::ErrorKind::TimedOut)
+}
+
+/// Flag set by Ctrl+C, so loops can finish their output files.
+fn interrupted() -> Arc<AtomicBool> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let handler_flag = flag.clone();
+    ctrlc::set_handler(move || handler_flag.store(true, Ordering::SeqCst)).ok();
+    flag
+}
+
+pub fn monitor(args: &PortArgs, dbc: Option<Dbc>) -> Result<()> {
+    let port = args.port()?;
+    let mut bus = can::open_bus(&port, args.bitrate(), args.serial_baud)?;
+    eprintln!("[monitor] {} at {:?}, Ctrl+C to quit", port, args.bitrate());
+    loop {
+        match bus.recv() {
+            Ok(frame) => {
+                let kind = if frame.fd { "fd" } else if frame.extended { "T" } else { "t" };
+                println!("{} {} dlc={} data={:02X?}", kind, id_hex(&frame), frame.data.len(), frame.data);
+                if let Some(decoded) = dbc.as_ref().and_then(|d| d.decode(&frame)) {
+                    println!("    {}", decoded);
+                }
+            }
+            Err(e) if is_timeout(&e) => {}
+            Err(e) => eprintln!("[monitor] {}", e),
+        }
+    }
+}
+
+pub fn send(args: &PortArgs, data_bitrate: Option<DataBitrate>, frames: &[String]) -> Result<()> {
+    let frames = frames.iter().map(|f| record::parse_frame(f)).collect::<Result<Vec<_>, _>>()?;
+    let port = args.port()?;
+    // SLCAN adapters need the data phase configured on open; SocketCAN sets it via `ip link`
+    let mut bus: Box<dyn CanBus> = match data_bitrate {
+        Some(db) if !can::bus::is_socketcan_interface(&port) => Box::new(Slcan::open_fd(&port, args.bitrate(), db, args.serial_baud)?),
+        _ => can::open_bus(&port, args.bitrate(), args.serial_baud)?,
+    };
+    for frame in &frames {
+        bus.send(frame)?;
+        println!("sent {}#{}", id_hex(frame), hex::encode_upper(&frame.data));
+    }
+    Ok(())
+}
+
+pub fn scan(args: &PortArgs, ids: std::ops::RangeInclusive<u32>, watch: bool) -> Result<()> {
+    let port = args.port()?;
+    // SocketCAN interfaces have their bitrate fixed by `ip link`, so one pass is enough
+    let candidates = match args.bitrate {
+        Some(b) => vec![b],
+        None if can::bus::is_socketcan_interface(&port) => vec![Bitrate::B1M],
+        None => vec![Bitrate::B1M, Bitrate::B500k],
+    };
+    for bitrate in candidates {
+        eprintln!("[scan] open {} baud={} can={:?} motors={}-{}", port, args.serial_baud, bitrate, ids.start(), ids.end());
+        let bus = match can::open_bus(&port, bitrate, args.serial_baud) {
+            Ok(b) => b,
+            Err(e) => {
+                eprintln!("[scan] open failed: {}", e);
+                continue;
+            }
+        };
+
+        let mut motors = MotorBus::new(bus);
+        let found = motors.discover(ids.clone(), Duration::from_secs(2))?;
+        motors.take_events();
+        if found.is_empty() {
+            eprintln!("[scan] no motors at {:?}", bitrate);
+            continue;
+        }
+        for m in motors.motors() {
+            println!(
+                "motor {:>2}  tx={:03X} rx={:03X}  angle={}  temp={}  voltage={}  errors={:?}",
+                m.id,
+                rmd::tx_id(m.id),
+                rmd::rx_id(m.id),
+                m.angle_deg.map_or("-".into(), |a| format!("{:.2}°", a)),
+                m.temperature_c.map_or("-".into(), |t| format!("{}°C", t)),
+                m.voltage_v.map_or("-".into(), |v| format!("{:.1}V", v)),
+                error_names(m.error_flags),
+            );
+        }
+
+        if watch {
+            eprintln!("[scan] watching {} motor(s), Ctrl+C to quit", found.len());
+            loop {
+                motors.request_state()?;
+                motors.poll()?;
+                for ev in motors.take_events() {
+                    match ev {
+                        MotorEvent::Online(id) => println!("motor {:>2} online", id),
+                        MotorEvent::Offline(id) => println!("motor {:>2} offline", id),
+                    }
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        }
+        return Ok(());
+    }
+    anyhow::bail!("no motors found")
+}
+
+pub fn record(args: &PortArgs, output: &Path, duration: Option<Duration>) -> Result<()> {
+    let port = args.port()?;
+    let channel = Path::new(&port).file_name().map_or_else(|| port.clone(), |n| n.to_string_lossy().to_string());
+    let mut bus = can::open_bus(&port, args.bitrate(), args.serial_baud)?;
+    let mut rec = Recorder::create(output, &channel).with_context(|| format!("create {}", output.display()))?;
+    let stop = interrupted();
+    let start = Instant::now();
+    let mut last_report = start;
+    eprintln!("[record] {} → {}, Ctrl+C to stop", port, output.display());
+    while !stop.load(Ordering::SeqCst) && duration.is_none_or(|d| start.elapsed() < d) {
+        match bus.recv() {
+            Ok(frame) => rec.record(&frame, Direction::Rx)?,
+            Err(e) if is_timeout(&e) => {}
+            Err(e) => eprintln!("[record] {}", e),
+        }
+        if last_report.elapsed() >= Duration::from_secs(1) {
+            eprint!("\r[record] {} frames", rec.frames());
+            last_report = Instant::now();
+        }
+    }
+    rec.finish()?;
+    eprintln!("\r[record] {} frames in {:.1}s", rec.frames(), start.elapsed().as_secs_f64());
+    Ok(())
+}
+
+pub fn replay(args: &PortArgs, input: &Path, speed: f64, fast: bool, include_tx: bool) -> Result<()> {
+    let timing = if fast { Timing::AsFastAsPossible } else { Timing::Original { speed } };
+    let entries = record::read_log(input).with_context(|| format!("read {}", input.display()))?;
+    eprintln!("[replay] {} frames from {} ({:?})", entries.len(), input.display(), timing);
+    let port = args.port()?;
+    let mut bus = can::open_bus(&port, args.bitrate(), args.serial_baud)?;
+    let start = Instant::now();
+    let sent = record::replay(bus.as_mut(), &entries, timing, include_tx)?;
+    eprintln!("[replay] sent {} frames in {:.3}s", sent, start.elapsed().as_secs_f64());
+    Ok(())
+}
+
+pub fn run_script(args: &PortArgs, path: &Path) -> Result<()> {
+    let script = Script::from_file(path).with_context(|| format!("load {}", path.display()))?;
+    let port = args.port()?;
+    let mut bus = can::open_bus(&port, args.bitrate(), args.serial_baud)?;
+    let summary = script.run(bus.as_mut(), |event| match event {
+        ScriptEvent::Sent { line, frame, count } if *count > 1 => {
+            println!("{:>4}  sent {}#{} ×{}", line, id_hex(frame), hex::encode_upper(&frame.data), count)
+        }
+        ScriptEvent::Sent { line, frame, .. } => println!("{:>4}  sent {}#{}", line, id_hex(frame), hex::encode_upper(&frame.data)),
+        ScriptEvent::Waiting { line, duration } => println!("{:>4}  wait {:?}", line, duration),
+        ScriptEvent::Matched { line, frame, after } => {
+            println!("{:>4}  got  {}#{} after {:.1} ms", line, id_hex(frame), hex::encode_upper(&frame.data), after.as_secs_f64() * 1e3)
+        }
+        ScriptEvent::Print { line, text } => println!("{:>4}  {}", line, text),
+    })?;
+    println!("ok: {} sent, {} expectations met", summary.sent, summary.matched);
+    Ok(())
+}
+
+fn print_params(motor_id: u32, params: &MotorParams) {
+    println!("motor {}", motor_id);
+    for param in Param::ALL {
+        let value = param.get(params).map_or("-".into(), |v| v.to_string());
+        println!("  {:<16} {:>10} {}", param.name(), value, param.unit());
+    }
+    let counts = |v: Option<i32>| v.map_or("-".into(), |v| v.to_string());
+    println!("  {:<16} {:>10} counts (raw {})", "encoder", counts(params.encoder), counts(params.encoder_raw));
+}
+
+/// Ask on stdin; only `y`/`yes` agrees.
+fn confirm(prompt: &str) -> Result<bool> {
+    eprint!("{}? [y/N] ", prompt);
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes"))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn params(args: &PortArgs, motor_id: u32, set: &[(Param, i64)], flash: bool, zero_here: bool, clear_zero: bool, reset: bool, yes: bool) -> Result<()> {
+    if let Some((param, _)) = set.iter().find(|(p, _)| !p.is_gain() && !flash) {
+        anyhow::bail!("{} has no RAM copy; pass --flash to store it", param.name());
+    }
+    let port = args.port()?;
+    let mut bus = can::open_bus(&port, args.bitrate(), args.serial_baud)?;
+    let mut client = ParamClient::new(bus.as_mut(), motor_id);
+    let params = client.read_all().with_context(|| format!("read parameters of motor {}", motor_id))?;
+    print_params(motor_id, &params);
+
+    let mut needs_reset = false;
+    let mut store = |client: &mut ParamClient, write: FlashWrite| -> Result<()> {
+        if !yes && !confirm(&format!("motor {}: {}", motor_id, write))? {
+            eprintln!("skipped");
+            return Ok(());
+        }
+        client.write_flash(&write)?;
+        needs_reset |= write.needs_reset();
+        println!("stored: {}", write);
+        Ok(())
+    };
+    for &(param, value) in set {
+        // Gain writes carry all six gains, so each one is built on the motor's echo of the last
+        if flash {
+            let write = client.params().flash_write(param, value).context("PID gains unknown")?;
+            store(&mut client, write)?;
+        } else {
+            let cmd = client.params().ram_write(param, value).context("PID gains unknown")?;
+            client.request(cmd)?;
+            println!("{} = {} (RAM)", param.name(), value);
+        }
+    }
+    if zero_here {
+        store(&mut client, FlashWrite::zero_at_current_position())?;
+    }
+    if clear_zero {
+        store(&mut client, FlashWrite::clear_zero())?;
+    }
+
+    if reset {
+        client.reset()?;
+        println!("motor {} reset", motor_id);
+    } else if needs_reset {
+        eprintln!("note: the new encoder zero applies after a reset (--reset)");
+    }
+    Ok(())
+}
+
+pub fn move_motors(args: &PortArgs, motor_ids: &[u32], waypoints: &[Waypoint], profile: Profile, limits: JointLimits, rate_hz: f64, safety: SafetyConfig) -> Result<()> {
+    if let Some(wp) = waypoints.iter().find(|w| w.position_deg.len() != motor_ids.len()) {
+        anyhow::bail!("waypoint {:?} has {} angle(s) for {} motor(s)", wp.position_deg, wp.position_deg.len(), motor_ids.len());
+    }
+    let port = args.port()?;
+    let bus = can::open_bus(&port, args.bitrate(), args.serial_baud)?;
+    let mut motors = MotorBus::new(SafeBus::new(bus, safety));
+    let (first, last) = (motor_ids.iter().min().copied().unwrap_or(1), motor_ids.iter().max().copied().unwrap_or(1));
+    motors.discover(first..=last, Duration::from_millis(300))?;
+    let start = motor_ids
+        .iter()
+        .map(|&id| motors.motor(id).and_then(|m| m.angle_deg).with_context(|| format!("no angle from motor {}", id)))
+        .collect::<Result<Vec<f64>>>()?;
+
+    let trajectory = Trajectory::plan(profile, &start, waypoints, &vec![limits; motor_ids.len()])?;
+    eprintln!("[move] {:?} profile, {} waypoint(s), {:.2} s at {} Hz", profile, waypoints.len(), trajectory.duration().as_secs_f64(), rate_hz);
+    let mut streamer = Streamer::new(trajectory, motor_ids.to_vec(), rate_hz)?;
+    let mut last_print = Duration::ZERO;
+    let tracking = streamer.run(motors.bus_mut(), |t, tracking| {
+        if t >= last_print + Duration::from_millis(250) {
+            last_print = t;
+            let errors: Vec<String> = tracking.iter().map(|e| format!("{}: {}", e.motor_id, e.last_deg.map_or("-".into(), |d| format!("{:+.2}°", d)))).collect();
+            eprintln!("[move] t={:>6.2}s  error {}", t.as_secs_f64(), errors.join("  "));
+        }
+    })?;
+    for e in &tracking {
+        println!("motor {:>2}  tracking error max={:.2}°  rms={:.2}°  samples={}", e.motor_id, e.max_abs_deg, e.rms_deg(), e.samples);
+    }
+    Ok(())
+}
+
+/// Send `data` (hex bytes or `@file`) as one ISO-TP message.
+pub fn isotp_send(args: &PortArgs, config: IsoTpConfig, data: &str) -> Result<()> {
+    let data = match data.strip_prefix('@') {
+        Some(path) => std::fs::read(path).with_context(|| format!("read {}", path))?,
+        None => hex::decode(data).map_err(|_| anyhow::anyhow!("not hex bytes: {} (e.g. 1122334455667788)", data))?,
+    };
+    let port = args.port()?;
+    let bus = can::open_bus(&port, args.bitrate(), args.serial_baud)?;
+    let tx_id = config.tx_id;
+    let mut isotp = IsoTp::new(bus, config);
+    isotp.send(&data)?;
+    println!("sent {} bytes on 0x{:X}", data.len(), tx_id);
+    Ok(())
+}
+
+/// Print ISO-TP messages until Ctrl+C, or save the first one to `out` and return.
+pub fn isotp_recv(args: &PortArgs, config: IsoTpConfig, out: Option<&Path>) -> Result<()> {
+    let port = args.port()?;
+    let bus = can::open_bus(&port, args.bitrate(), args.serial_baud)?;
+    eprintln!("[isotp] listening on 0x{:X}, flow control on 0x{:X}", config.rx_id, config.tx_id);
+    let mut isotp = IsoTp::new(bus, config);
+    let stop = interrupted();
+    while !stop.load(Ordering::SeqCst) {
+        match isotp.recv_timeout(Duration::from_secs(1)) {
+            Ok(Some(msg)) => {
+                if let Some(path) = out {
+                    std::fs::write(path, &msg).with_context(|| format!("write {}", path.display()))?;
+                    println!("saved {} bytes to {}", msg.len(), path.display());
+                    return Ok(());
+                }
+                println!("{} bytes: {}", msg.len(), hex::encode_upper(&msg));
+            }
+            Ok(None) => {}
+            // A broken transfer is reported; keep listening for the next one
+            Err(e) if is_timeout(&e) || matches!(e, SlcanError::Protocol(_)) => eprintln!("[isotp] {}", e),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
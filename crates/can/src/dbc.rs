@@ -0,0 +1,469 @@
+//! DBC database loading and signal decoding/encoding.
+//!
+//! Supports the subset that node vendors (BMS, IMU, …) actually ship:
+//! `BO_` messages, `SG_` signals (Intel/Motorola, signed/unsigned, factor/offset, units),
+//! simple multiplexing (`M` / `mN`) and `VAL_` value tables. Everything else is ignored.
+//!
+//! ```
+//! let dbc = can::dbc::Dbc::parse(r#"
+//! BO_ 256 BMS_Status: 8 BMS
+//!  SG_ PackVoltage : 0|16@1+ (0.01,0) [0|655.35] "V" Vector__XXX
+//!  SG_ State : 16|8@1+ (1,0) [0|3] "" Vector__XXX
+//! VAL_ 256 State 0 "Idle" 1 "Charging" 2 "Fault" ;
+//! "#).unwrap();
+//! let frame = dbc.encode("BMS_Status", &[("PackVoltage", 48.2), ("State", 1.0)]).unwrap();
+//! let decoded = dbc.decode(&frame).unwrap();
+//! assert_eq!(decoded.to_string(), "BMS_Status: PackVoltage=48.2 V State=Charging");
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+use crate::{CanFrame, SlcanError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// `@1`, little endian; start bit is the LSB.
+    Intel,
+    /// `@0`, big endian; start bit is the MSB in DBC sawtooth numbering.
+    Motorola,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplex {
+    None,
+    /// `M`: selects which multiplexed signals are present.
+    Multiplexor,
+    /// `mN`: present only when the multiplexor equals N.
+    Multiplexed(u64),
+}
+
+#[derive(Debug, Clone)]
+pub struct Signal {
+    pub name: String,
+    pub start_bit: u16,
+    pub size: u16,
+    pub byte_order: ByteOrder,
+    pub signed: bool,
+    pub factor: f64,
+    pub offset: f64,
+    pub min: f64,
+    pub max: f64,
+    pub unit: String,
+    pub multiplex: Multiplex,
+    pub values: BTreeMap<i64, String>,
+}
+
+impl Signal {
+    /// Raw (unscaled) value, sign-extended for signed signals.
+    pub fn raw(&self, data: &[u8]) -> Option<i64> {
+        let size = self.size as u32;
+        if size == 0 || size > 64 {
+            return None;
+        }
+        let bit = |n: u32| -> Option<u64> { data.get((n / 8) as usize).map(|b| ((b >> (n % 8)) & 1) as u64) };
+        let mut raw: u64 = 0;
+        match self.byte_order {
+            ByteOrder::Intel => {
+                for i in 0..size {
+                    raw |= bit(self.start_bit as u32 + i)? << i;
+                }
+            }
+            ByteOrder::Motorola => {
+                let mut n = self.start_bit as u32;
+                for _ in 0..size {
+                    raw = (raw << 1) | bit(n)?;
+                    n = if n.is_multiple_of(8) { n + 15 } else { n - 1 };
+                }
+            }
+        }
+        if self.signed && size < 64 && raw & (1 << (size - 1)) != 0 {
+            raw |= !0u64 << size;
+        }
+        Some(raw as i64)
+    }
+
+    /// Physical value: `raw * factor + offset`.
+    pub fn decode(&self, data: &[u8]) -> Option<f64> {
+        self.raw(data).map(|r| self.scale(r))
+    }
+
+    fn scale(&self, raw: i64) -> f64 {
+        let r = if self.signed { raw as f64 } else { raw as u64 as f64 };
+        r * self.factor + self.offset
+    }
+
+    /// Write `value` (physical units) into `data`, clamped to the signal's `[min|max]` range
+    /// (unless it is empty, like `[0|0]`) and then saturated to its bit width. Signals of 0
+    /// or more than 64 bits are left untouched.
+    ///
+    /// ```
+    /// let dbc = can::dbc::Dbc::parse(r#"
+    /// BO_ 1 Counter: 8 Node
+    ///  SG_ Ticks : 0|64@1+ (1,0) [0|0] "" Vector__XXX
+    /// BO_ 2 Fan: 1 Node
+    ///  SG_ Duty : 0|8@1+ (1,0) [0|100] "%" Vector__XXX
+    /// "#).unwrap();
+    /// let frame = dbc.encode("Counter", &[("Ticks", u64::MAX as f64)]).unwrap();
+    /// assert_eq!(frame.data, [0xFF; 8]);
+    /// // 8 bits would hold 250, but the signal range stops at 100
+    /// assert_eq!(dbc.encode("Fan", &[("Duty", 250.0)]).unwrap().data, [100]);
+    ///
+    /// assert!(can::dbc::Dbc::parse("BO_ 1 M: 8 N\n SG_ S : 0|65@1+ (1,0) [0|0] \"\" X\n").is_err());
+    /// ```
+    pub fn encode(&self, data: &mut [u8], value: f64) {
+        let size = self.size as u32;
+        if size == 0 || size > 64 {
+            return;
+        }
+        let value = if self.min < self.max { value.clamp(self.min, self.max) } else { value };
+        let factor = if self.factor == 0.0 { 1.0 } else { self.factor };
+        let mut raw = ((value - self.offset) / factor).round();
+        let (lo, hi) = if self.signed {
+            (-(2f64.powi(size as i32 - 1)), 2f64.powi(size as i32 - 1) - 1.0)
+        } else {
+            (0.0, 2f64.powi(size as i32) - 1.0)
+        };
+        raw = raw.clamp(lo, hi);
+        // Unsigned 64-bit values do not fit an i64; `as u64` keeps them (and saturates at the top)
+        let raw = if self.signed { raw as i64 as u64 } else { raw as u64 };
+        let mut set = |n: u32, v: u64| {
+            if let Some(b) = data.get_mut((n / 8) as usize) {
+                let mask = 1u8 << (n % 8);
+                if v & 1 != 0 { *b |= mask } else { *b &= !mask }
+            }
+        };
+        match self.byte_order {
+            ByteOrder::Intel => {
+                for i in 0..size {
+                    set(self.start_bit as u32 + i, raw >> i);
+                }
+            }
+            ByteOrder::Motorola => {
+                let mut n = self.start_bit as u32;
+                for i in (0..size).rev() {
+                    set(n, raw >> i);
+                    n = if n.is_multiple_of(8) { n + 15 } else { n - 1 };
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub id: u32,
+    pub extended: bool,
+    pub name: String,
+    pub dlc: u8,
+    pub sender: String,
+    pub signals: Vec<Signal>,
+}
+
+impl Message {
+    pub fn signal(&self, name: &str) -> Option<&Signal> {
+        self.signals.iter().find(|s| s.name == name)
+    }
+
+    fn multiplexor(&self) -> Option<&Signal> {
+        self.signals.iter().find(|s| s.multiplex == Multiplex::Multiplexor)
+    }
+}
+
+/// One decoded signal value.
+#[derive(Debug, Clone)]
+pub struct SignalValue<'a> {
+    pub signal: &'a Signal,
+    pub raw: i64,
+    pub value: f64,
+    /// `VAL_` label for the raw value, if any.
+    pub label: Option<&'a str>,
+}
+
+impl fmt::Display for SignalValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.label {
+            Some(l) => write!(f, "{}={}", self.signal.name, l),
+            None if self.signal.unit.is_empty() => write!(f, "{}={}", self.signal.name, self.value),
+            None => write!(f, "{}={} {}", self.signal.name, self.value, self.signal.unit),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodedMessage<'a> {
+    pub message: &'a Message,
+    pub signals: Vec<SignalValue<'a>>,
+}
+
+impl DecodedMessage<'_> {
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.signals.iter().find(|s| s.signal.name == name).map(|s| s.value)
+    }
+
+    /// `Sig=1.5 V Other=Label` without the message name.
+    pub fn signals_string(&self) -> String {
+        self.signals.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(" ")
+    }
+}
+
+impl fmt::Display for DecodedMessage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.message.name, self.signals_string())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Dbc {
+    pub messages: Vec<Message>,
+    by_id: HashMap<(u32, bool), usize>,
+}
+
+fn perr(line_no: usize, msg: impl fmt::Display) -> SlcanError {
+    SlcanError::Parse(format!("dbc line {}: {}", line_no, msg))
+}
+
+/// Split on whitespace, keeping `"quoted strings"` (without quotes) as single tokens.
+fn tokenize(s: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut cur = String::new();
+    let mut in_quote = false;
+    for c in s.chars() {
+        match c {
+            '"' => {
+                if in_quote {
+                    out.push(std::mem::take(&mut cur));
+                }
+                in_quote = !in_quote;
+            }
+            c if c.is_whitespace() && !in_quote => {
+                if !cur.is_empty() {
+                    out.push(std::mem::take(&mut cur));
+                }
+            }
+            c => cur.push(c),
+        }
+    }
+    if !cur.is_empty() {
+        out.push(cur);
+    }
+    out
+}
+
+fn parse_signal(rest: &str, line_no: usize) -> Result<Signal, SlcanError> {
+    // Name [M|mN] : start|size@order sign (factor,offset) [min|max] "unit" receivers
+    let (head, body) = rest.split_once(':').ok_or_else(|| perr(line_no, "SG_ missing ':'"))?;
+    let mut head = head.split_whitespace();
+    let name = head.next().ok_or_else(|| perr(line_no, "SG_ missing name"))?.to_string();
+    let multiplex = match head.next() {
+        None => Multiplex::None,
+        Some("M") => Multiplex::Multiplexor,
+        Some(m) if m.starts_with('m') => {
+            let n = m[1..].trim_end_matches('M');
+            Multiplex::Multiplexed(n.parse().map_err(|_| perr(line_no, format!("bad multiplex '{}'", m)))?)
+        }
+        Some(m) => return Err(perr(line_no, format!("bad multiplex '{}'", m))),
+    };
+
+    let body = body.trim();
+    let (layout, body) = body.split_once(char::is_whitespace).ok_or_else(|| perr(line_no, "SG_ truncated"))?;
+    let (pos, fmt) = layout.split_once('@').ok_or_else(|| perr(line_no, "SG_ missing '@'"))?;
+    let (start, size) = pos.split_once('|').ok_or_else(|| perr(line_no, "SG_ missing '|'"))?;
+    let start_bit: u16 = start.parse().map_err(|_| perr(line_no, "bad start bit"))?;
+    let size: u16 = size.parse().map_err(|_| perr(line_no, "bad size"))?;
+    if size == 0 || size > 64 {
+        return Err(perr(line_no, format!("signal {} has {} bits (1–64 supported)", name, size)));
+    }
+    let byte_order = match fmt.chars().next() {
+        Some('1') => ByteOrder::Intel,
+        Some('0') => ByteOrder::Motorola,
+        _ => return Err(perr(line_no, "bad byte order")),
+    };
+    let signed = fmt.ends_with('-');
+
+    let body = body.trim();
+    let fo = body.strip_prefix('(').and_then(|b| b.split_once(')')).ok_or_else(|| perr(line_no, "SG_ missing (factor,offset)"))?;
+    let (factor, offset) = fo.0.split_once(',').ok_or_else(|| perr(line_no, "bad (factor,offset)"))?;
+    let factor: f64 = factor.trim().parse().map_err(|_| perr(line_no, "bad factor"))?;
+    let offset: f64 = offset.trim().parse().map_err(|_| perr(line_no, "bad offset"))?;
+
+    let body = fo.1.trim();
+    let mm = body.strip_prefix('[').and_then(|b| b.split_once(']')).ok_or_else(|| perr(line_no, "SG_ missing [min|max]"))?;
+    let (min, max) = mm.0.split_once('|').ok_or_else(|| perr(line_no, "bad [min|max]"))?;
+    let min: f64 = min.trim().parse().map_err(|_| perr(line_no, "bad min"))?;
+    let max: f64 = max.trim().parse().map_err(|_| perr(line_no, "bad max"))?;
+
+    let body = mm.1.trim();
+    let unit = body
+        .strip_prefix('"')
+        .and_then(|b| b.split_once('"'))
+        .map(|(u, _)| u.to_string())
+        .unwrap_or_default();
+
+    Ok(Signal {
+        name,
+        start_bit,
+        size,
+        byte_order,
+        signed,
+        factor,
+        offset,
+        min,
+        max,
+        unit,
+        multiplex,
+        values: BTreeMap::new(),
+    })
+}
+
+impl Dbc {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SlcanError> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, SlcanError> {
+        let mut messages: Vec<Message> = Vec::new();
+        let mut value_tables: Vec<(u32, String, BTreeMap<i64, String>)> = Vec::new();
+        let mut in_string = false;
+        for (i, raw_line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            // Multi-line CM_ strings: skip until the closing quote
+            let quotes = raw_line.matches('"').count();
+            if in_string {
+                if quotes % 2 == 1 {
+                    in_string = false;
+                }
+                continue;
+            }
+            let line = raw_line.trim();
+            if let Some(rest) = line.strip_prefix("BO_ ") {
+                // BO_ 256 Name: 8 Sender
+                let (head, tail) = rest.split_once(':').ok_or_else(|| perr(line_no, "BO_ missing ':'"))?;
+                let mut head = head.split_whitespace();
+                let raw_id: u32 = head.next().and_then(|s| s.parse().ok()).ok_or_else(|| perr(line_no, "bad BO_ id"))?;
+                let name = head.next().ok_or_else(|| perr(line_no, "BO_ missing name"))?.to_string();
+                let mut tail = tail.split_whitespace();
+                let dlc: u8 = tail.next().and_then(|s| s.parse().ok()).ok_or_else(|| perr(line_no, "bad BO_ DLC"))?;
+                let sender = tail.next().unwrap_or("").to_string();
+                messages.push(Message {
+                    id: raw_id & 0x1FFF_FFFF,
+                    extended: raw_id & 0x8000_0000 != 0,
+                    name,
+                    dlc,
+                    sender,
+                    signals: Vec::new(),
+                });
+            } else if let Some(rest) = line.strip_prefix("SG_ ") {
+                let msg = messages.last_mut().ok_or_else(|| perr(line_no, "SG_ before any BO_"))?;
+                msg.signals.push(parse_signal(rest, line_no)?);
+            } else if let Some(rest) = line.strip_prefix("VAL_ ") {
+                // VAL_ 256 Signal 0 "Idle" 1 "Charging" ;
+                let toks = tokenize(rest.trim_end_matches(';'));
+                if toks.len() < 2 {
+                    continue;
+                }
+                let Ok(raw_id) = toks[0].parse::<u32>() else { continue }; // VAL_ on env vars
+                let mut table = BTreeMap::new();
+                for pair in toks[2..].chunks(2) {
+                    if let [v, label] = pair {
+                        let v: i64 = v.parse().map_err(|_| perr(line_no, "bad VAL_ value"))?;
+                        table.insert(v, label.clone());
+                    }
+                }
+                value_tables.push((raw_id, toks[1].clone(), table));
+            } else if quotes % 2 == 1 {
+                in_string = true;
+            }
+        }
+
+        for (raw_id, signal, table) in value_tables {
+            let key = (raw_id & 0x1FFF_FFFF, raw_id & 0x8000_0000 != 0);
+            if let Some(sig) = messages
+                .iter_mut()
+                .find(|m| (m.id, m.extended) == key)
+                .and_then(|m| m.signals.iter_mut().find(|s| s.name == signal))
+            {
+                sig.values = table;
+            }
+        }
+
+        let by_id = messages.iter().enumerate().map(|(i, m)| ((m.id, m.extended), i)).collect();
+        Ok(Dbc { messages, by_id })
+    }
+
+    pub fn message(&self, id: u32, extended: bool) -> Option<&Message> {
+        self.by_id.get(&(id, extended)).map(|&i| &self.messages[i])
+    }
+
+    pub fn message_by_name(&self, name: &str) -> Option<&Message> {
+        self.messages.iter().find(|m| m.name == name)
+    }
+
+    /// Decode `frame` into physical signal values, honouring multiplexing.
+    pub fn decode(&self, frame: &CanFrame) -> Option<DecodedMessage<'_>> {
+        if frame.rtr {
+            return None;
+        }
+        let message = self.message(frame.id, frame.extended)?;
+        let mux = message.multiplexor().and_then(|m| m.raw(&frame.data));
+        let signals = message
+            .signals
+            .iter()
+            .filter(|s| match s.multiplex {
+                Multiplex::Multiplexed(n) => mux == Some(n as i64),
+                _ => true,
+            })
+            .filter_map(|s| {
+                let raw = s.raw(&frame.data)?;
+                Some(SignalValue { signal: s, raw, value: s.scale(raw), label: s.values.get(&raw).map(String::as_str) })
+            })
+            .collect();
+        Some(DecodedMessage { message, signals })
+    }
+
+    /// Build a frame for `message` from physical values. Unlisted signals are zero; for
+    /// multiplexed messages only signals matching the given multiplexor value are written.
+    /// Messages longer than 8 bytes become CAN FD frames.
+    ///
+    /// ```
+    /// let dbc = can::dbc::Dbc::parse(r#"
+    /// BO_ 512 Cells: 64 BMS
+    ///  SG_ Cell31 : 496|16@1+ (0.0625,0) [0|4096] "V" Vector__XXX
+    /// "#).unwrap();
+    /// let frame = dbc.encode("Cells", &[("Cell31", 3.25)]).unwrap();
+    /// assert!(frame.fd && frame.data.len() == 64);
+    /// assert_eq!(dbc.decode(&frame).unwrap().get("Cell31"), Some(3.25));
+    /// ```
+    pub fn encode(&self, message: &str, values: &[(&str, f64)]) -> Result<CanFrame, SlcanError> {
+        let msg = self.message_by_name(message).ok_or_else(|| SlcanError::Parse(format!("unknown message {}", message)))?;
+        for (name, _) in values {
+            if msg.signal(name).is_none() {
+                return Err(SlcanError::Parse(format!("{} has no signal {}", message, name)));
+            }
+        }
+        let value_of = |name: &str| values.iter().find(|(n, _)| *n == name).map(|(_, v)| *v);
+        // A DBC length above 8 is a CAN FD message; pad it to a length FD can carry
+        let len = msg.dlc as usize;
+        let fd = len > 8;
+        let mut data = vec![0u8; if fd { crate::dlc_to_len(crate::len_to_dlc(len)) } else { len }];
+        let mux = msg.multiplexor().map(|m| {
+            let v = value_of(&m.name).unwrap_or(0.0);
+            m.encode(&mut data, v);
+            m.raw(&data).unwrap_or(0)
+        });
+        for sig in &msg.signals {
+            let present = match sig.multiplex {
+                Multiplex::Multiplexor => false, // already written
+                Multiplex::Multiplexed(n) => mux == Some(n as i64),
+                Multiplex::None => true,
+            };
+            // Unlisted signals stay raw zero, even if that is outside their range
+            if let (true, Some(v)) = (present, value_of(&sig.name)) {
+                sig.encode(&mut data, v);
+            }
+        }
+        Ok(CanFrame { id: msg.id, data, extended: msg.extended, rtr: false, fd, brs: false, timestamp: None })
+    }
+}
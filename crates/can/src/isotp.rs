@@ -0,0 +1,542 @@
+//! ISO-TP (ISO 15765-2) segmented transport for payloads that do not fit in one frame.
+//!
+//! [`IsoTp`] wraps any [`CanBus`] and exchanges whole messages with one peer over a pair
+//! of CAN IDs: single frames for up to 7 bytes, otherwise a first frame, flow control from
+//! the receiver and consecutive frames paced by the receiver's block size and STmin.
+//! Lengths above 4095 bytes use the 32-bit first-frame escape. With [`IsoTpConfig::fd`],
+//! frames carry up to 64 bytes: single frames up to 62 bytes use the escaped length byte,
+//! and frames above 8 bytes are padded to the next valid CAN FD length.
+//!
+//! Both directions block: [`IsoTp::send`] until the last consecutive frame is out,
+//! [`IsoTp::recv`] until a complete message arrived. Missing flow control or consecutive
+//! frames fail with a `TimedOut` I/O error after [`IsoTpConfig::timeout`]; sequence errors
+//! and overflow replies fail with [`SlcanError::Protocol`].
+//!
+//! ```
+//! use can::isotp::{IsoTp, IsoTpConfig};
+//! use can::mock::MockBus;
+//! use can::threaded::BusThread;
+//!
+//! // Two endpoints on one loopback bus: the tester on 0x7E0 → 0x7E8, the node the other way
+//! let bus = BusThread::spawn(Box::new(MockBus::loopback()));
+//! let mut tester = IsoTp::new(bus.client(), IsoTpConfig::new(0x7E0, 0x7E8));
+//! let mut node = IsoTp::new(bus.client(), IsoTpConfig { block_size: 4, ..IsoTpConfig::new(0x7E8, 0x7E0) });
+//!
+//! let blob: Vec<u8> = (0..=255).cycle().take(1000).collect();
+//! let expected = blob.clone();
+//! let receiver = std::thread::spawn(move || node.recv().unwrap());
+//! tester.send(&blob).unwrap();
+//! assert_eq!(receiver.join().unwrap(), expected);
+//! ```
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::bus::CanBus;
+use crate::{CanFrame, SlcanError};
+
+/// Largest length that fits the 12-bit first-frame length field.
+const MAX_SHORT_LEN: usize = 0xFFF;
+
+/// Largest single frame: 7 bytes in a classic frame, 62 behind the escaped length in CAN FD.
+const MAX_SINGLE_LEN: usize = 7;
+const MAX_SINGLE_LEN_FD: usize = 62;
+
+/// `WAIT` flow controls accepted in a row before the sender gives up (N_WFTmax).
+const MAX_WAIT_FRAMES: u32 = 16;
+
+mod pci {
+    pub const SINGLE: u8 = 0x0;
+    pub const FIRST: u8 = 0x1;
+    pub const CONSECUTIVE: u8 = 0x2;
+    pub const FLOW_CONTROL: u8 = 0x3;
+}
+
+/// Flow status of a flow-control frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowStatus {
+    ContinueToSend,
+    Wait,
+    Overflow,
+}
+
+#[derive(Debug, Clone)]
+pub struct IsoTpConfig {
+    /// ID this endpoint transmits on.
+    pub tx_id: u32,
+    /// ID the peer transmits on; every other frame is ignored.
+    pub rx_id: u32,
+    /// Both IDs are 29-bit.
+    pub extended: bool,
+    /// Consecutive frames the peer may send before waiting for the next flow control
+    /// (0 = no limit). Advertised when receiving.
+    pub block_size: u8,
+    /// Minimum gap between the peer's consecutive frames. Advertised when receiving;
+    /// rounded to whole ms, or 100–900 µs steps below 1 ms.
+    pub st_min: Duration,
+    /// How long to wait for flow control (N_Bs) or the next consecutive frame (N_Cr),
+    /// and for the first frame of a message in [`IsoTp::recv`].
+    pub timeout: Duration,
+    /// Pad every frame to 8 bytes with this value; `None` sends the minimal length.
+    pub padding: Option<u8>,
+    /// Longest message accepted; larger first frames are answered with overflow.
+    pub max_len: usize,
+    /// Send CAN FD frames of up to 64 bytes; the bus has to be opened for CAN FD.
+    pub fd: bool,
+}
+
+impl IsoTpConfig {
+    /// Standard IDs, classic frames, no block limit, STmin 0, 1 s timeouts, padding 0xCC,
+    /// messages up to 64 KiB.
+    pub fn new(tx_id: u32, rx_id: u32) -> Self {
+        IsoTpConfig {
+            tx_id,
+            rx_id,
+            extended: false,
+            block_size: 0,
+            st_min: Duration::ZERO,
+            timeout: Duration::from_secs(1),
+            padding: Some(0xCC),
+            max_len: 64 * 1024,
+            fd: false,
+        }
+    }
+
+    /// Largest frame this endpoint sends.
+    fn frame_len(&self) -> usize {
+        if self.fd { 64 } else { 8 }
+    }
+}
+
+/// STmin byte for `d` (ISO 15765-2 table: 0–127 ms, or 0xF1–0xF9 for 100–900 µs).
+pub fn encode_st_min(d: Duration) -> u8 {
+    let us = d.as_micros();
+    if us == 0 {
+        0
+    } else if us < 1000 {
+        0xF0 + (us.div_ceil(100) as u8).min(9)
+    } else {
+        (us.div_ceil(1000)).min(127) as u8
+    }
+}
+
+/// Gap encoded by an STmin byte; reserved values mean the maximum, 127 ms.
+pub fn decode_st_min(b: u8) -> Duration {
+    match b {
+        0x00..=0x7F => Duration::from_millis(b as u64),
+        0xF1..=0xF9 => Duration::from_micros((b - 0xF0) as u64 * 100),
+        _ => Duration::from_millis(127),
+    }
+}
+
+fn timed_out(what: &str) -> SlcanError {
+    SlcanError::Io(io::Error::new(io::ErrorKind::TimedOut, format!("isotp: {}", what)))
+}
+
+pub struct IsoTp<B: CanBus = Box<dyn CanBus>> {
+    bus: B,
+    config: IsoTpConfig,
+}
+
+impl<B: CanBus> IsoTp<B> {
+    pub fn new(bus: B, config: IsoTpConfig) -> Self {
+        IsoTp { bus, config }
+    }
+
+    pub fn config(&self) -> &IsoTpConfig {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut IsoTpConfig {
+        &mut self.config
+    }
+
+    pub fn bus_mut(&mut self) -> &mut B {
+        &mut self.bus
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bus
+    }
+
+    /// Send one message, segmenting it if needed. Empty messages are not allowed.
+    pub fn send(&mut self, data: &[u8]) -> Result<(), SlcanError> {
+        if data.is_empty() {
+            return Err(SlcanError::Protocol("isotp: empty message"));
+        }
+        if data.len() <= MAX_SINGLE_LEN {
+            let mut payload = vec![(pci::SINGLE << 4) | data.len() as u8];
+            payload.extend_from_slice(data);
+            return self.send_frame(payload);
+        }
+        if self.config.fd && data.len() <= MAX_SINGLE_LEN_FD {
+            let mut payload = vec![pci::SINGLE << 4, data.len() as u8];
+            payload.extend_from_slice(data);
+            return self.send_frame(payload);
+        }
+
+        let frame_len = self.config.frame_len();
+        let mut first = Vec::with_capacity(frame_len);
+        if data.len() <= MAX_SHORT_LEN {
+            first.extend_from_slice(&[(pci::FIRST << 4) | (data.len() >> 8) as u8, data.len() as u8]);
+        } else {
+            let len = u32::try_from(data.len()).map_err(|_| SlcanError::Protocol("isotp: message too long"))?;
+            first.extend_from_slice(&[pci::FIRST << 4, 0]);
+            first.extend_from_slice(&len.to_be_bytes());
+        }
+        let n = frame_len - first.len();
+        first.extend_from_slice(&data[..n]);
+        self.send_frame(first)?;
+
+        let mut rest = data[n..].chunks(frame_len - 1).peekable();
+        let mut sn: u8 = 1;
+        while rest.peek().is_some() {
+            let (block_size, st_min) = self.wait_flow_control()?;
+            let mut sent_in_block = 0u32;
+            while block_size == 0 || sent_in_block < block_size as u32 {
+                let Some(chunk) = rest.next() else { break };
+                if sent_in_block > 0 {
+                    std::thread::sleep(st_min);
+                }
+                let mut payload = vec![(pci::CONSECUTIVE << 4) | sn];
+                payload.extend_from_slice(chunk);
+                self.send_frame(payload)?;
+                sn = (sn + 1) & 0x0F;
+                sent_in_block += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Receive one complete message, waiting up to the configured timeout for it to start.
+    pub fn recv(&mut self) -> Result<Vec<u8>, SlcanError> {
+        self.recv_timeout(self.config.timeout)?.ok_or_else(|| timed_out("no message"))
+    }
+
+    /// Like [`IsoTp::recv`], but waits up to `wait` for a message to start and returns
+    /// `Ok(None)` if none did. Once started, the configured timeout applies per frame.
+    pub fn recv_timeout(&mut self, wait: Duration) -> Result<Option<Vec<u8>>, SlcanError> {
+        let deadline = Instant::now() + wait;
+        loop {
+            let Some(data) = self.recv_own(deadline)? else { return Ok(None) };
+            match data[0] >> 4 {
+                pci::SINGLE => {
+                    // A zero length nibble escapes to a length byte (CAN FD frames above 8 bytes)
+                    let (len, header) = match data[0] & 0x0F {
+                        0 if data.len() > 8 => (data[1] as usize, 2),
+                        n => (n as usize, 1),
+                    };
+                    if len == 0 || len > data.len() - header {
+                        continue; // malformed single frame, ignore
+                    }
+                    return Ok(Some(data[header..header + len].to_vec()));
+                }
+                pci::FIRST => return self.recv_segmented(&data).map(Some),
+                _ => {} // stray consecutive or flow control frame
+            }
+        }
+    }
+
+    fn recv_segmented(&mut self, first: &[u8]) -> Result<Vec<u8>, SlcanError> {
+        if first.len() < 8 {
+            return Err(SlcanError::Protocol("isotp: short first frame"));
+        }
+        let short_len = (((first[0] & 0x0F) as usize) << 8) | first[1] as usize;
+        let (len, header) = if short_len == 0 {
+            (u32::from_be_bytes([first[2], first[3], first[4], first[5]]) as usize, 6)
+        } else {
+            (short_len, 2)
+        };
+        if len > self.config.max_len {
+            self.send_flow_control(FlowStatus::Overflow)?;
+            return Err(SlcanError::Protocol("isotp: message exceeds max_len"));
+        }
+
+        let mut out = Vec::with_capacity(len);
+        out.extend_from_slice(&first[header..first.len().min(header + len)]);
+        let mut expected_sn: u8 = 1;
+        let block_size = self.config.block_size as u32;
+        while out.len() < len {
+            self.send_flow_control(FlowStatus::ContinueToSend)?;
+            let mut in_block = 0u32;
+            while out.len() < len && (block_size == 0 || in_block < block_size) {
+                let deadline = Instant::now() + self.config.timeout;
+                let data = loop {
+                    match self.recv_own(deadline)? {
+                        Some(d) if d.first().map(|b| b >> 4) == Some(pci::CONSECUTIVE) => break d,
+                        Some(_) => continue,
+                        None => return Err(timed_out("consecutive frame timeout")),
+                    }
+                };
+                if data[0] & 0x0F != expected_sn {
+                    return Err(SlcanError::Protocol("isotp: wrong sequence number"));
+                }
+                let take = (len - out.len()).min(data.len() - 1);
+                out.extend_from_slice(&data[1..1 + take]);
+                expected_sn = (expected_sn + 1) & 0x0F;
+                in_block += 1;
+            }
+        }
+        Ok(out)
+    }
+
+    /// Wait for a `ContinueToSend` flow control; returns the peer's block size and STmin.
+    fn wait_flow_control(&mut self) -> Result<(u8, Duration), SlcanError> {
+        let mut waits = 0;
+        let mut deadline = Instant::now() + self.config.timeout;
+        loop {
+            let Some(data) = self.recv_own(deadline)? else { return Err(timed_out("flow control timeout")) };
+            if data.len() < 3 || data[0] >> 4 != pci::FLOW_CONTROL {
+                continue;
+            }
+            match data[0] & 0x0F {
+                0 => return Ok((data[1], decode_st_min(data[2]))),
+                1 => {
+                    waits += 1;
+                    if waits > MAX_WAIT_FRAMES {
+                        return Err(SlcanError::Protocol("isotp: too many wait frames"));
+                    }
+                    deadline = Instant::now() + self.config.timeout;
+                }
+                2 => return Err(SlcanError::Protocol("isotp: receiver overflow")),
+                _ => return Err(SlcanError::Protocol("isotp: invalid flow status")),
+            }
+        }
+    }
+
+    fn send_flow_control(&mut self, status: FlowStatus) -> Result<(), SlcanError> {
+        let fs = match status {
+            FlowStatus::ContinueToSend => 0,
+            FlowStatus::Wait => 1,
+            FlowStatus::Overflow => 2,
+        };
+        self.send_frame(vec![(pci::FLOW_CONTROL << 4) | fs, self.config.block_size, encode_st_min(self.config.st_min)])
+    }
+
+    fn send_frame(&mut self, mut data: Vec<u8>) -> Result<(), SlcanError> {
+        if data.len() > 8 {
+            // CAN FD has no lengths between the valid DLCs; those bytes are always padded
+            data.resize(crate::dlc_to_len(crate::len_to_dlc(data.len())), self.config.padding.unwrap_or(0xCC));
+        } else if let Some(pad) = self.config.padding {
+            data.resize(8, pad);
+        }
+        let frame = CanFrame {
+            id: self.config.tx_id,
+            data,
+            extended: self.config.extended,
+            rtr: false,
+            fd: self.config.fd,
+            brs: false,
+            timestamp: None,
+        };
+        self.bus.send(&frame)
+    }
+
+    /// Next frame from the peer, or `None` once `deadline` passes.
+    fn recv_own(&mut self, deadline: Instant) -> Result<Option<Vec<u8>>, SlcanError> {
+        loop {
+            match self.bus.recv() {
+                Ok(f) if f.id == self.config.rx_id && f.extended == self.config.extended && !f.rtr && !f.data.is_empty() => {
+                    return Ok(Some(f.data));
+                }
+                Ok(_) => {}
+                Err(SlcanError::Io(e)) if e.kind() == io::ErrorKind::TimedOut => {}
+                Err(e) => return Err(e),
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockBus;
+
+    const TESTER: u32 = 0x7E0;
+    const NODE: u32 = 0x7E8;
+
+    fn frame(id: u32, data: &[u8]) -> CanFrame {
+        CanFrame { id, data: data.to_vec(), extended: false, rtr: false, fd: false, brs: false, timestamp: None }
+    }
+
+    /// Flow control from the node: status 0 CTS, 1 WAIT, 2 OVERFLOW.
+    fn flow_control(status: u8, block_size: u8, st_min: u8) -> CanFrame {
+        frame(NODE, &[0x30 | status, block_size, st_min, 0, 0, 0, 0, 0])
+    }
+
+    fn config() -> IsoTpConfig {
+        IsoTpConfig { timeout: Duration::from_millis(20), ..IsoTpConfig::new(TESTER, NODE) }
+    }
+
+    fn node_config(tester: &IsoTpConfig) -> IsoTpConfig {
+        IsoTpConfig { tx_id: NODE, rx_id: TESTER, ..tester.clone() }
+    }
+
+    fn payload(len: usize) -> Vec<u8> {
+        (0..=255).cycle().take(len).collect()
+    }
+
+    /// Send `data` to a node that allows everything in one block, then replay the frames to a
+    /// receiver. Returns the frames on the wire and the message the receiver put together.
+    fn round_trip(config: IsoTpConfig, data: &[u8]) -> (Vec<CanFrame>, Vec<u8>) {
+        let mock = MockBus::new();
+        mock.inject(flow_control(0, 0, 0));
+        IsoTp::new(mock.clone(), config.clone()).send(data).unwrap();
+        let frames = mock.take_sent();
+
+        let wire = MockBus::new();
+        frames.iter().for_each(|f| wire.inject(f.clone()));
+        let received = IsoTp::new(wire, node_config(&config)).recv().unwrap();
+        (frames, received)
+    }
+
+    fn is_timeout(result: Result<impl std::fmt::Debug, SlcanError>) -> bool {
+        matches!(result, Err(SlcanError::Io(e)) if e.kind() == io::ErrorKind::TimedOut)
+    }
+
+    #[test]
+    fn st_min_encoding() {
+        let cases = [(0, 0x00), (100, 0xF1), (150, 0xF2), (900, 0xF9), (999, 0xF9), (1_000, 0x01), (1_500, 0x02), (127_000, 0x7F), (500_000, 0x7F)];
+        for (us, byte) in cases {
+            assert_eq!(encode_st_min(Duration::from_micros(us)), byte, "{} µs", us);
+        }
+        for b in 0xF1..=0xF9u8 {
+            assert_eq!(decode_st_min(b), Duration::from_micros((b - 0xF0) as u64 * 100));
+            assert_eq!(encode_st_min(decode_st_min(b)), b);
+        }
+        assert_eq!(decode_st_min(0x7F), Duration::from_millis(127));
+        for reserved in [0x80, 0xF0, 0xFA, 0xFF] {
+            assert_eq!(decode_st_min(reserved), Duration::from_millis(127));
+        }
+    }
+
+    #[test]
+    fn block_size_and_st_min_pace_consecutive_frames() {
+        // 30 bytes: first frame with 6, then 7 + 7 + 7 + 3 in consecutive frames
+        let data = payload(30);
+        let mock = MockBus::new();
+        mock.inject(flow_control(0, 2, 10));
+        let mut tester = IsoTp::new(mock.clone(), config());
+        let started = Instant::now();
+        // One block of 2, then the sender waits for flow control that never comes
+        assert!(is_timeout(tester.send(&data)));
+        assert!(started.elapsed() >= Duration::from_millis(10 + 20));
+        let pci: Vec<u8> = mock.take_sent().iter().map(|f| f.data[0]).collect();
+        assert_eq!(pci, [0x10, 0x21, 0x22]);
+
+        mock.inject(flow_control(0, 2, 0xF5));
+        mock.inject(flow_control(0, 2, 0xF5));
+        tester.send(&data).unwrap();
+        let pci: Vec<u8> = mock.take_sent().iter().map(|f| f.data[0]).collect();
+        assert_eq!(pci, [0x10, 0x21, 0x22, 0x23, 0x24]);
+    }
+
+    #[test]
+    fn flow_control_wait_and_overflow() {
+        let mock = MockBus::new();
+        let mut tester = IsoTp::new(mock.clone(), config());
+        mock.inject(flow_control(1, 0, 0));
+        mock.inject(flow_control(1, 0, 0));
+        mock.inject(flow_control(0, 0, 0));
+        tester.send(&payload(20)).unwrap();
+        assert_eq!(mock.take_sent().len(), 3);
+
+        mock.inject(flow_control(2, 0, 0));
+        assert!(matches!(tester.send(&payload(20)), Err(SlcanError::Protocol("isotp: receiver overflow"))));
+        assert_eq!(mock.take_sent().len(), 1);
+
+        for _ in 0..=MAX_WAIT_FRAMES {
+            mock.inject(flow_control(1, 0, 0));
+        }
+        assert!(matches!(tester.send(&payload(20)), Err(SlcanError::Protocol("isotp: too many wait frames"))));
+    }
+
+    #[test]
+    fn receiver_answers_overflow() {
+        let mock = MockBus::new();
+        let mut node = IsoTp::new(mock.clone(), IsoTpConfig { max_len: 100, ..node_config(&config()) });
+        mock.inject(frame(TESTER, &[0x10, 200, 0, 1, 2, 3, 4, 5]));
+        assert!(matches!(node.recv(), Err(SlcanError::Protocol("isotp: message exceeds max_len"))));
+        let sent = mock.take_sent();
+        assert_eq!((sent[0].id, sent[0].data[0]), (NODE, 0x32));
+    }
+
+    #[test]
+    fn wrong_sequence_number_aborts() {
+        let mock = MockBus::new();
+        let mut node = IsoTp::new(mock.clone(), node_config(&config()));
+        mock.inject(frame(TESTER, &[0x10, 20, 0, 1, 2, 3, 4, 5]));
+        mock.inject(frame(TESTER, &[0x21, 6, 7, 8, 9, 10, 11, 12]));
+        mock.inject(frame(TESTER, &[0x23, 13, 14, 15, 16, 17, 18, 19]));
+        assert!(matches!(node.recv(), Err(SlcanError::Protocol("isotp: wrong sequence number"))));
+        assert_eq!(mock.take_sent()[0].data[..3], [0x30, 0, 0]);
+    }
+
+    #[test]
+    fn timeouts() {
+        // N_Bs: no flow control after the first frame
+        let mock = MockBus::new();
+        let started = Instant::now();
+        assert!(is_timeout(IsoTp::new(mock.clone(), config()).send(&payload(10))));
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        assert_eq!(mock.take_sent().len(), 1);
+
+        // N_Cr: the next consecutive frame arrives too late
+        let mock = MockBus::new();
+        mock.inject(frame(TESTER, &[0x10, 10, 0, 1, 2, 3, 4, 5]));
+        mock.inject_after(frame(TESTER, &[0x21, 6, 7, 8, 9, 0xCC, 0xCC, 0xCC]), Duration::from_millis(60));
+        assert!(is_timeout(IsoTp::new(mock, node_config(&config())).recv()));
+
+        // Nothing at all
+        assert!(is_timeout(IsoTp::new(MockBus::new(), config()).recv()));
+    }
+
+    #[test]
+    fn long_messages_use_the_length_escape() {
+        let (frames, received) = round_trip(config(), &payload(4095));
+        assert_eq!(frames[0].data[..2], [0x1F, 0xFF]);
+        assert_eq!(received, payload(4095));
+
+        let (frames, received) = round_trip(config(), &payload(5000));
+        assert_eq!(frames[0].data[..8], [0x10, 0x00, 0x00, 0x00, 0x13, 0x88, 0, 1]);
+        assert_eq!(frames.len(), 1 + (5000 - 2usize).div_ceil(7));
+        assert_eq!(received, payload(5000));
+    }
+
+    #[test]
+    fn single_and_first_frame_boundaries() {
+        let (frames, received) = round_trip(config(), &payload(7));
+        assert_eq!((frames.len(), frames[0].data[0], frames[0].data.len()), (1, 0x07, 8));
+        assert_eq!(received, payload(7));
+        let (frames, received) = round_trip(config(), &payload(8));
+        assert_eq!((frames.len(), &frames[0].data[..2]), (2, &[0x10, 8][..]));
+        assert_eq!(received, payload(8));
+
+        let fd = IsoTpConfig { fd: true, ..config() };
+        // (length, frames, first bytes, first frame length)
+        let cases: [(usize, usize, &[u8], usize); 6] = [
+            (7, 1, &[0x07], 8),
+            (8, 1, &[0x00, 8], 12),
+            (62, 1, &[0x00, 62], 64),
+            (63, 2, &[0x10, 63], 64),
+            (4095, 1 + (4095 - 62usize).div_ceil(63), &[0x1F, 0xFF], 64),
+            (4096, 1 + (4096 - 58usize).div_ceil(63), &[0x10, 0, 0, 0, 0x10, 0x00], 64),
+        ];
+        for (len, count, head, first_len) in cases {
+            let (frames, received) = round_trip(fd.clone(), &payload(len));
+            assert_eq!(frames.len(), count, "{} bytes", len);
+            assert_eq!(&frames[0].data[..head.len()], head, "{} bytes", len);
+            assert_eq!(frames[0].data.len(), first_len, "{} bytes", len);
+            assert!(frames.iter().all(|f| f.fd && crate::dlc_to_len(crate::len_to_dlc(f.data.len())) == f.data.len()));
+            assert_eq!(received, payload(len), "{} bytes", len);
+        }
+
+        // Without padding, FD frames still round up to a valid length
+        let (frames, _) = round_trip(IsoTpConfig { padding: None, ..fd }, &payload(9));
+        assert_eq!(frames[0].data.len(), 12);
+        assert_eq!(frames[0].data[11], 0xCC);
+    }
+}
@@ -1,10 +1,33 @@
+use std::collections::VecDeque;
 use std::io::{Read, Write};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use hex::FromHex;
 use serialport::SerialPort;
 use thiserror::Error;
 
+pub mod bus;
+pub mod canopen;
+pub mod dbc;
+pub mod isotp;
+pub mod mock;
+pub mod motors;
+pub mod params;
+pub mod record;
+pub mod threaded;
+pub mod trajectory;
+pub mod rmd;
+pub mod safety;
+pub mod script;
+pub mod stats;
+pub mod supervised;
+#[cfg(target_os = "linux")]
+pub mod socketcan;
+
+pub use bus::{open_bus, BusStatus, CanBus, CanFilter};
+#[cfg(target_os = "linux")]
+pub use socketcan::SocketCan;
+
 macro_rules! slcandbg {
     ($($arg:tt)*) => {{
         if std::env::var("CAN_DEBUG").is_ok() { eprintln!("[slcan] {}", format!($($arg)*)); }
@@ -37,6 +60,36 @@ pub enum Bitrate {
 }
 
 impl Bitrate {
+    /// Bitrate for a value in bit/s (`500000` → `B500k`); `None` if SLCAN has no preset for it.
+    pub fn from_bps(bps: u32) -> Option<Self> {
+        Some(match bps {
+            10_000 => Bitrate::B10k,
+            20_000 => Bitrate::B20k,
+            50_000 => Bitrate::B50k,
+            100_000 => Bitrate::B100k,
+            125_000 => Bitrate::B125k,
+            250_000 => Bitrate::B250k,
+            500_000 => Bitrate::B500k,
+            800_000 => Bitrate::B800k,
+            1_000_000 => Bitrate::B1M,
+            _ => return None,
+        })
+    }
+
+    pub fn bps(self) -> u32 {
+        match self {
+            Bitrate::B10k => 10_000,
+            Bitrate::B20k => 20_000,
+            Bitrate::B50k => 50_000,
+            Bitrate::B100k => 100_000,
+            Bitrate::B125k => 125_000,
+            Bitrate::B250k => 250_000,
+            Bitrate::B500k => 500_000,
+            Bitrate::B800k => 800_000,
+            Bitrate::B1M => 1_000_000,
+        }
+    }
+
     fn to_slcan_code(self) -> &'static str {
         match self {
             Bitrate::B10k => "S0",
@@ -52,18 +105,167 @@ impl Bitrate {
     }
 }
 
+/// CAN FD data-phase bitrate (SLCAN `Yn`, CANable 2.0 / candleLight FD firmwares).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataBitrate {
+    D1M,
+    D2M,
+    D4M,
+    D5M,
+}
+
+impl DataBitrate {
+    /// Data bitrate for a value in bit/s (`2000000` → `D2M`).
+    pub fn from_bps(bps: u32) -> Option<Self> {
+        Some(match bps {
+            1_000_000 => DataBitrate::D1M,
+            2_000_000 => DataBitrate::D2M,
+            4_000_000 => DataBitrate::D4M,
+            5_000_000 => DataBitrate::D5M,
+            _ => return None,
+        })
+    }
+
+    pub fn bps(self) -> u32 {
+        match self {
+            DataBitrate::D1M => 1_000_000,
+            DataBitrate::D2M => 2_000_000,
+            DataBitrate::D4M => 4_000_000,
+            DataBitrate::D5M => 5_000_000,
+        }
+    }
+
+    fn to_slcan_code(self) -> &'static str {
+        match self {
+            DataBitrate::D1M => "Y1",
+            DataBitrate::D2M => "Y2",
+            DataBitrate::D4M => "Y4",
+            DataBitrate::D5M => "Y5",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CanFrame {
     pub id: u32,
     pub data: Vec<u8>,
     pub extended: bool,
     pub rtr: bool,
+    /// CAN FD frame: up to 64 data bytes, no RTR.
+    pub fd: bool,
+    /// Bit-rate switch: send the FD data phase at the data bitrate.
+    pub brs: bool,
+    /// Receive time since the UNIX epoch: adapter timestamp when available, else host time.
+    /// `None` on frames built for sending.
+    pub timestamp: Option<Duration>,
+}
+
+pub(crate) fn now_since_epoch() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+/// Lawicel `F` status flags.
+pub mod status_flag {
+    pub const RX_FIFO_FULL: u8 = 0x01;
+    pub const TX_FIFO_FULL: u8 = 0x02;
+    pub const ERROR_WARNING: u8 = 0x04;
+    pub const DATA_OVERRUN: u8 = 0x08;
+    pub const ERROR_PASSIVE: u8 = 0x20;
+    pub const ARBITRATION_LOST: u8 = 0x40;
+    pub const BUS_ERROR: u8 = 0x80;
+}
+
+impl BusStatus {
+    /// Decode the flag byte of an SLCAN `Fxx` reply. 0x80 is the SJA1000 bus-error
+    /// interrupt, set by any single bus error, so it becomes `bus_error`. SLCAN has no
+    /// bus-off bit and `bus_off` stays false.
+    ///
+    /// ```
+    /// use can::BusStatus;
+    ///
+    /// let status = BusStatus::from_slcan_flags(0xA0);
+    /// assert!(status.bus_error && status.error_passive && !status.bus_off);
+    /// assert_eq!(status.to_string(), "passive,bus_error");
+    /// ```
+    pub fn from_slcan_flags(flags: u8) -> Self {
+        BusStatus {
+            error_warning: flags & status_flag::ERROR_WARNING != 0,
+            error_passive: flags & status_flag::ERROR_PASSIVE != 0,
+            overrun: flags & (status_flag::RX_FIFO_FULL | status_flag::TX_FIFO_FULL | status_flag::DATA_OVERRUN) != 0,
+            arbitration_lost: flags & status_flag::ARBITRATION_LOST != 0,
+            bus_error: flags & status_flag::BUS_ERROR != 0,
+            bus_off: false,
+            error_frames: 0,
+        }
+    }
+}
+
+/// SLCAN `Z1` timestamps are a millisecond counter that wraps every 60 s; this maps them
+/// onto host time, re-anchoring whenever the two drift apart (e.g. after a silent minute).
+#[derive(Default)]
+struct TimestampClock {
+    anchor: Option<Duration>,
+    last_ms: Option<u16>,
+    wraps: u64,
+}
+
+impl TimestampClock {
+    const WRAP_MS: u64 = 60_000;
+
+    fn convert(&mut self, ms: u16) -> Duration {
+        if self.last_ms.is_some_and(|last| ms < last) {
+            self.wraps += 1;
+        }
+        self.last_ms = Some(ms);
+        let device = Duration::from_millis(self.wraps * Self::WRAP_MS + ms as u64);
+        let host = now_since_epoch();
+        let anchor = *self.anchor.get_or_insert_with(|| host.saturating_sub(device));
+        let t = anchor + device;
+        if t.abs_diff(host) > Duration::from_secs(1) {
+            self.anchor = Some(host.saturating_sub(device));
+            return host;
+        }
+        t
+    }
+}
+
+/// Payload length for DLC code `dlc` (0..=15). Codes 9..=15 map to 12–64 bytes on CAN FD.
+pub fn dlc_to_len(dlc: u8) -> usize {
+    match dlc {
+        0..=8 => dlc as usize,
+        9 => 12,
+        10 => 16,
+        11 => 20,
+        12 => 24,
+        13 => 32,
+        14 => 48,
+        _ => 64,
+    }
+}
+
+/// Smallest DLC code whose payload holds `len` bytes; FD payloads are zero-padded up to it.
+pub fn len_to_dlc(len: usize) -> u8 {
+    (0..=15).find(|&d| dlc_to_len(d) >= len).unwrap_or(15)
 }
 
 pub struct Slcan {
     port: Box<dyn SerialPort>,
+    filters: Vec<CanFilter>,
+    /// `O` or `l` (listen/loopback), re-sent when the channel is reopened for `M`/`m`.
+    open_cmd: &'static [u8],
+    bitrate: Bitrate,
+    data_bitrate: Option<DataBitrate>,
+    /// Frames read while waiting for a command reply, returned by the next `read`.
+    pending: VecDeque<CanFrame>,
+    clock: TimestampClock,
+    /// Acceptance code/mask last programmed with `M`/`m`, re-sent by `restart`; `None` is
+    /// the firmware default, which accepts everything.
+    acceptance: Option<(u32, u32)>,
 }
 
+/// SJA1000 acceptance code/mask that passes every frame.
+const ACCEPT_ALL: (u32, u32) = (0, 0xFFFF_FFFF);
+
 impl Slcan {
     pub fn open(path: &str, bitrate: Bitrate) -> Result<Self, SlcanError> {
         Self::open_with_baud(path, bitrate, 115_200)
@@ -74,7 +276,17 @@ impl Slcan {
     }
 
     pub fn open_with_baud(path: &str, bitrate: Bitrate, serial_baud: u32) -> Result<Self, SlcanError> {
-        slcandbg!("open path={} serial_baud={} bitrate={:?}", path, serial_baud, bitrate);
+        Self::open_inner(path, bitrate, None, serial_baud)
+    }
+
+    /// Open a CAN FD capable adapter: `bitrate` is the arbitration phase, `data_bitrate` the
+    /// data phase used by frames with `brs` set.
+    pub fn open_fd(path: &str, bitrate: Bitrate, data_bitrate: DataBitrate, serial_baud: u32) -> Result<Self, SlcanError> {
+        Self::open_inner(path, bitrate, Some(data_bitrate), serial_baud)
+    }
+
+    fn open_inner(path: &str, bitrate: Bitrate, data_bitrate: Option<DataBitrate>, serial_baud: u32) -> Result<Self, SlcanError> {
+        slcandbg!("open path={} serial_baud={} bitrate={:?} data_bitrate={:?}", path, serial_baud, bitrate, data_bitrate);
         let mut port = serialport::new(path, serial_baud)
             .timeout(Duration::from_millis(50))
             .open()?;
@@ -101,17 +313,59 @@ impl Slcan {
         // Probe version (optional) to sync line settings if the device expects it
         let _ = Self::probe_version(&mut port);
 
+        let open_cmd: &'static [u8] = if std::env::var("CAN_LOOPBACK").is_ok() { b"l" } else { b"O" };
+        Self::init_channel(&mut port, bitrate, data_bitrate, None, open_cmd)?;
+
+        let mut slcan = Slcan {
+            port,
+            filters: Vec::new(),
+            open_cmd,
+            bitrate,
+            data_bitrate,
+            pending: VecDeque::new(),
+            clock: TimestampClock::default(),
+            acceptance: None,
+        };
+        match slcan.status() {
+            Ok(st) => slcandbg!("status after open: {:?}", st),
+            Err(e) => slcandbg!("status not available: {}", e),
+        }
+        Ok(slcan)
+    }
+
+    fn init_channel(
+        port: &mut Box<dyn SerialPort>,
+        bitrate: Bitrate,
+        data_bitrate: Option<DataBitrate>,
+        acceptance: Option<(u32, u32)>,
+        open_cmd: &[u8],
+    ) -> Result<(), SlcanError> {
         // reset, set bitrate, close/open bus (tolerant: ignore early failures)
-        let _ = Self::write_cmd_lenient(&mut port, b"C"); // Close if open
-        let _ = Self::write_cmd_lenient(&mut port, bitrate.to_slcan_code().as_bytes());
-        // Some firmwares do not support Zx/Ey commands; skip timestamp/echo tweaks
+        let _ = Self::write_cmd_lenient(port, b"C"); // Close if open
+        let _ = Self::write_cmd_lenient(port, bitrate.to_slcan_code().as_bytes());
+        if let Some(db) = data_bitrate {
+            Self::write_cmd_lenient(port, db.to_slcan_code().as_bytes())?;
+        }
+        // Millisecond timestamps on received frames; firmwares without `Z` NAK it and we fall
+        // back to host time. Echo (`E`) is left at the firmware default.
+        let _ = Self::write_cmd_lenient(port, b"Z1");
+        if let Some((code, mask)) = acceptance {
+            // `recv` still filters in software, so the bus stays usable if this fails
+            if let Err(e) = Self::write_acceptance(port, code, mask) {
+                slcandbg!("acceptance filter not restored: {}", e);
+            }
+        }
         // Some firmwares need a small pause before opening
         std::thread::sleep(Duration::from_millis(20));
-        let loopback = std::env::var("CAN_LOOPBACK").is_ok();
-        if loopback { Self::write_cmd_lenient(&mut port, b"l")?; } else { Self::write_cmd_lenient(&mut port, b"O")?; }
-        let _ = Self::write_cmd_lenient(&mut port, b"F"); // read status/error register
+        Self::write_cmd_lenient(port, open_cmd)
+    }
 
-        Ok(Slcan { port })
+    /// Close and reopen the CAN channel (`C`, `Sx`, `O`) on the same serial port, e.g. to
+    /// leave bus-off. The acceptance code/mask last set is programmed again.
+    pub fn restart(&mut self) -> Result<(), SlcanError> {
+        self.pending.clear();
+        self.clock = TimestampClock::default();
+        Self::init_channel(&mut self.port, self.bitrate, self.data_bitrate, self.acceptance, self.open_cmd)
     }
 
     fn _write_cmd(port: &mut Box<dyn SerialPort>, cmd: &[u8]) -> Result<(), SlcanError> {
@@ -201,6 +455,9 @@ impl Slcan {
 
     pub fn send(&mut self, frame: &CanFrame) -> Result<(), SlcanError> {
         // slcan format: t{ID3}{LEN}{DATA..}\r (std), T{ID8}{LEN}{DATA..}\r (ext)
+        if frame.fd {
+            return self.send_fd(frame);
+        }
         let mut buf = String::new();
         if frame.rtr {
             if frame.extended { buf.push('R'); } else { buf.push('r'); }
@@ -223,8 +480,106 @@ impl Slcan {
         self.write_line_lenient(&buf)
     }
 
+    fn send_fd(&mut self, frame: &CanFrame) -> Result<(), SlcanError> {
+        // d/D (no BRS) and b/B (BRS) mirror t/T; the DLC is one hex digit (0..F)
+        let mut buf = String::new();
+        buf.push(match (frame.brs, frame.extended) {
+            (false, false) => 'd',
+            (false, true) => 'D',
+            (true, false) => 'b',
+            (true, true) => 'B',
+        });
+        if frame.extended {
+            buf.push_str(&format!("{:08X}", frame.id & 0x1FFF_FFFF));
+        } else {
+            buf.push_str(&format!("{:03X}", frame.id & 0x7FF));
+        }
+        let len = std::cmp::min(frame.data.len(), 64);
+        let dlc = len_to_dlc(len);
+        buf.push_str(&format!("{:X}", dlc));
+        for i in 0..dlc_to_len(dlc) {
+            buf.push_str(&format!("{:02X}", frame.data.get(i).copied().unwrap_or(0)));
+        }
+        slcandbg!("-> {}\\r", buf);
+        self.write_line_lenient(&buf)
+    }
+
     pub fn read(&mut self) -> Result<CanFrame, SlcanError> {
-        // Read until CR or LF and parse (return on timeout to avoid UI stalls)
+        if let Some(frame) = self.pending.pop_front() {
+            return Ok(frame);
+        }
+        let line = self.read_line()?;
+        self.frame_from_line(&line)
+    }
+
+    fn frame_from_line(&mut self, line: &[u8]) -> Result<CanFrame, SlcanError> {
+        let (mut frame, hw_ms) = Self::parse_line(line)?;
+        frame.timestamp = Some(match hw_ms {
+            Some(ms) => self.clock.convert(ms),
+            None => now_since_epoch(),
+        });
+        Ok(frame)
+    }
+
+    /// Query the `F` status register. Frames that arrive before the reply are kept for `read`.
+    pub fn status(&mut self) -> Result<BusStatus, SlcanError> {
+        slcandbg!("-> F\\r");
+        self.port.write_all(b"F\r")?;
+        let deadline = Instant::now() + Duration::from_millis(500);
+        while Instant::now() < deadline {
+            let line = match self.read_line() {
+                Ok(l) => l,
+                Err(SlcanError::Io(e)) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+                Err(e) => return Err(e),
+            };
+            match line.first() {
+                Some(b'F') if line.len() >= 3 => {
+                    let hex = std::str::from_utf8(&line[1..3]).map_err(|e| SlcanError::Parse(e.to_string()))?;
+                    let flags = u8::from_str_radix(hex, 16).map_err(|e| SlcanError::Parse(e.to_string()))?;
+                    return Ok(BusStatus::from_slcan_flags(flags));
+                }
+                Some(0x07) => return Err(SlcanError::Protocol("NAK")),
+                _ => {
+                    if let Ok(frame) = self.frame_from_line(&line) {
+                        self.pending.push_back(frame);
+                    }
+                }
+            }
+        }
+        Err(SlcanError::Protocol("no reply to F"))
+    }
+
+    /// Program the SJA1000-style acceptance code/mask (`M`/`m`, mask bit 1 = don't care).
+    /// The channel is closed and reopened around the change, as the protocol requires.
+    pub fn set_acceptance(&mut self, code: u32, mask: u32) -> Result<(), SlcanError> {
+        Self::write_cmd_lenient(&mut self.port, b"C")?;
+        let result = Self::write_acceptance(&mut self.port, code, mask);
+        // Reopen even if the adapter rejected the filter, so the bus keeps working
+        Self::write_cmd_lenient(&mut self.port, self.open_cmd)?;
+        result?;
+        self.acceptance = Some((code, mask));
+        Ok(())
+    }
+
+    fn write_acceptance(port: &mut Box<dyn SerialPort>, code: u32, mask: u32) -> Result<(), SlcanError> {
+        Self::write_cmd_lenient(port, format!("M{:08X}", code).as_bytes())?;
+        Self::write_cmd_lenient(port, format!("m{:08X}", mask).as_bytes())
+    }
+
+    /// Program the adapter's acceptance filter to pass at least `filters` (an empty slice
+    /// accepts everything). One hardware filter can only approximate several software ones,
+    /// so `recv` still filters exactly in software. Nothing is sent when the code/mask would
+    /// not change, as reprogramming closes the channel for a moment.
+    pub fn set_hardware_filters(&mut self, filters: &[CanFilter]) -> Result<(), SlcanError> {
+        let (code, mask) = acceptance_for(filters);
+        if self.acceptance.unwrap_or(ACCEPT_ALL) == (code, mask) {
+            return Ok(());
+        }
+        self.set_acceptance(code, mask)
+    }
+
+    fn read_line(&mut self) -> Result<Vec<u8>, SlcanError> {
+        // Read until CR or LF (return on timeout to avoid UI stalls)
         let mut line = Vec::with_capacity(64);
         loop {
             let mut b = [0u8; 1];
@@ -242,17 +597,22 @@ impl Slcan {
             }
         }
         slcandbg!("<- line: {}", String::from_utf8_lossy(&line));
-        Self::parse_line(&line)
+        Ok(line)
     }
 
-    fn parse_line(line: &[u8]) -> Result<CanFrame, SlcanError> {
+    /// Parse a frame line; the second value is the `Z1` millisecond timestamp, if present.
+    fn parse_line(line: &[u8]) -> Result<(CanFrame, Option<u16>), SlcanError> {
         if line.is_empty() { return Err(SlcanError::Parse("empty".into())); }
         let kind = line[0] as char;
-        let (extended, rtr) = match kind {
-            't' => (false, false),
-            'T' => (true,  false),
-            'r' => (false, true),
-            'R' => (true,  true),
+        let (extended, rtr, fd, brs) = match kind {
+            't' => (false, false, false, false),
+            'T' => (true,  false, false, false),
+            'r' => (false, true,  false, false),
+            'R' => (true,  true,  false, false),
+            'd' => (false, false, true,  false),
+            'D' => (true,  false, true,  false),
+            'b' => (false, false, true,  true),
+            'B' => (true,  false, true,  true),
             _ => return Err(SlcanError::Parse(format!("unknown frame type: {}", kind))),
         };
         let mut idx = 1usize;
@@ -268,7 +628,9 @@ impl Slcan {
             u32::from_str_radix(id_hex, 16).map_err(|e| SlcanError::Parse(e.to_string()))?
         };
         if line.len() <= idx { return Err(SlcanError::Parse("missing DLC".into())); }
-        let dlc = (line[idx] - b'0') as usize;
+        let dlc = (line[idx] as char).to_digit(16).ok_or_else(|| SlcanError::Parse("bad DLC".into()))? as u8;
+        // Classic DLC 9..15 still means 8 bytes; FD maps it to 12..64
+        let dlc = if fd { dlc_to_len(dlc) } else { std::cmp::min(dlc as usize, 8) };
         idx += 1;
         let mut data = Vec::new();
         if !rtr {
@@ -276,9 +638,62 @@ impl Slcan {
             if line.len() < idx + needed { return Err(SlcanError::Parse("short data".into())); }
             let data_hex = std::str::from_utf8(&line[idx..idx+needed]).map_err(|e| SlcanError::Parse(e.to_string()))?;
             data = Vec::from_hex(data_hex).map_err(|e| SlcanError::Parse(e.to_string()))?;
+            idx += needed;
         }
-        Ok(CanFrame { id, data, extended, rtr })
+        // With `Z1` the adapter appends a 4-digit hex millisecond counter
+        let hw_ms = line
+            .get(idx..idx + 4)
+            .and_then(|t| std::str::from_utf8(t).ok())
+            .and_then(|t| u16::from_str_radix(t, 16).ok());
+        Ok((CanFrame { id, data, extended, rtr, fd, brs, timestamp: None }, hw_ms))
     }
 }
 
+/// Single SJA1000 acceptance code/mask that passes every frame matched by `filters`.
+/// Standard IDs sit in bits 31..21, extended IDs in bits 31..3; mixed formats accept all.
+fn acceptance_for(filters: &[CanFilter]) -> (u32, u32) {
+    let Some(first) = filters.first() else { return ACCEPT_ALL };
+    if filters.iter().any(|f| f.extended != first.extended) {
+        return ACCEPT_ALL;
+    }
+    let (shift, width) = if first.extended { (3, 0x1FFF_FFFF) } else { (21, 0x7FF) };
+    // Bits that every filter cares about and on which all of them agree
+    let mut care = width;
+    for f in filters {
+        care &= f.mask & width;
+        care &= !((f.id ^ first.id) & width);
+    }
+    let code = (first.id & care) << shift;
+    let mask = !(care << shift);
+    (code, mask)
+}
 
+impl CanBus for Slcan {
+    fn send(&mut self, frame: &CanFrame) -> Result<(), SlcanError> {
+        Slcan::send(self, frame)
+    }
+
+    fn recv(&mut self) -> Result<CanFrame, SlcanError> {
+        // Filters are applied in software; keep reading until a frame passes or the port times out
+        loop {
+            let frame = self.read()?;
+            if bus::filters_accept(&self.filters, &frame) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Programs the adapter's `M`/`m` mask (best effort: not every firmware supports it)
+    /// and keeps exact filtering in software.
+    fn set_filters(&mut self, filters: &[CanFilter]) -> Result<(), SlcanError> {
+        if let Err(e) = self.set_hardware_filters(filters) {
+            slcandbg!("hardware filter not applied: {}", e);
+        }
+        self.filters = filters.to_vec();
+        Ok(())
+    }
+
+    fn status(&mut self) -> Result<BusStatus, SlcanError> {
+        Slcan::status(self)
+    }
+}
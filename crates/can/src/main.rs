@@ -1,531 +1,401 @@
-use std::io;
-use std::time::{Duration, Instant};
+//! `can`: one CLI for SLCAN adapters and SocketCAN interfaces.
+//!
+//! Without a subcommand it starts the TUI. Every subcommand takes `-p/--port` (serial port,
+//! SocketCAN interface or `mock:`; detected or prompted for when omitted), `-b/--bitrate` and
+//! `--serial-baud`.
 
-use anyhow::{Context, Result};
-use can::{Bitrate, CanFrame, Slcan, SlcanError};
-use crossterm::event::{self, Event, KeyCode};
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
-use ratatui::{
-    backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Style, Color, Modifier},
-    text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Table, Row as TRow, Cell, Scrollbar, ScrollbarOrientation, ScrollbarState, Gauge, Tabs},
-    widgets::canvas::{Canvas, Line as CLine, Rectangle as CRect},
-    widgets::{Chart, Axis, Dataset, GraphType},
-    symbols,
-    Terminal,
-};
-/*
-RMD‑L CAN Protocol – Quick Capability Summary (for future maintainers)
-
-Bus/IDs
-- CAN (default 1 Mbps). Per motor: TX (host→motor) ID = 0x140 + motor_id, RX (motor→host) ID = 0x240 + motor_id.
-- Commands rarely ACK; some firmwares only reply on explicit reads or when active reply is enabled (0xB6).
-
-Major Commands (single motor)
-- 0xA1 torque closed loop (current/torque)
-- 0xA2 speed closed loop (deg/s ×100, LE i32 in data[4..8])
-- 0xA4 absolute multi‑turn position (deg ×100, LE i32 in data[4..8])
-- 0xA6 single‑turn position; 0xA8 incremental position
-- 0x92 multi‑turn angle; 0x94 single‑turn angle
-- 0x9A status1/error; 0x9C status2; 0x9D/0x9E extended
-- 0x77 brake release; 0x78 brake lock; 0x81 stop; 0x80 shutdown
-- 0x30/0x31/0x32 PID read/RAM/ROM; 0x42/0x43 accel read/write; 0x79 ID; 0xB4 baud; 0xB3 watchdog; 0xB5 model; 0xB1 runtime; 0xB2 version date
-- 0xB6 Active Reply: [0xB6, cmd, enable(0/1), interval_low, interval_high, 0,0,0]. Example: enable periodic angle → B6 92 01 05 00 00 00 00 (50 ms, 10 ms units)
-
-TUI Hotkeys
-- r/k/x/+/‑/0/p for brake/stop/speed/position
-- a (angle 0x92), s (status2 0x9C), A (active reply angle 0x92)
-- Up/Down/Home/End scroll frames; q/Esc quit
-
-Next UI ideas (Ratatui)
-- Tabs: Modes (Torque/Speed/AbsPos/SingleTurn/Incremental), Telemetry, Safety, Frames
-- Live telemetry header + angle/speed sparklines/gauges
-- Controls panel: focused inputs with step keys
-- Safety panel: watchdog, ID/baud, zero encoder
-- Frames table: TS/Dir/ID/Kind/Data + scrollbar
-*/
+use std::path::PathBuf;
+use std::time::Duration;
 
-#[derive(Clone)]
-struct FrameRow { id: u32, data: Vec<u8>, extended: bool, ts: Instant }
-
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum Tab { Telemetry, Frames, Help }
+use anyhow::{Context, Result};
+use can::dbc::Dbc;
+use can::isotp::IsoTpConfig;
+use can::params::Param;
+use can::safety::{Limits, SafetyConfig};
+use can::trajectory::{JointLimits, Profile, Waypoint};
+use can::{Bitrate, DataBitrate};
+use clap::{Args, Parser, Subcommand};
+
+mod commands;
+mod tui;
+
+#[derive(Parser, Debug)]
+#[command(name = "can", version, about = "CAN bus tools for SLCAN adapters and SocketCAN", args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Options for the TUI when no subcommand is given
+    #[command(flatten)]
+    tui: TuiArgs,
+}
 
-fn main() -> Result<()> {
-    // Ensure terminal is restored on panic/Ctrl+C
-    let _guard = scopeguard::guard((), |_| {
-        let _ = disable_raw_mode();
-        let _ = crossterm::execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen);
-    });
-    ctrlc::set_handler(|| {
-        let _ = disable_raw_mode();
-        let _ = crossterm::execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen);
-        std::process::exit(0);
-    }).ok();
-    let mut args = std::env::args().skip(1);
-    let port = if let Some(p) = args.next() {
-        p
-    } else {
-        choose_serial_port_interactive()? // auto-detect or prompt when absent
-    };
-    let bitrate = match args.next().as_deref() {
-        Some("10000") => Bitrate::B10k,
-        Some("20000") => Bitrate::B20k,
-        Some("50000") => Bitrate::B50k,
-        Some("100000") => Bitrate::B100k,
-        Some("125000") => Bitrate::B125k,
-        Some("250000") => Bitrate::B250k,
-        Some("500000") | None => Bitrate::B500k,
-        Some("800000") => Bitrate::B800k,
-        Some("1000000") => Bitrate::B1M,
-        Some(v) => anyhow::bail!("unsupported bitrate: {}", v),
-    };
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Interactive motor console (default)
+    Tui(TuiArgs),
+    /// Print received frames
+    Monitor {
+        #[command(flatten)]
+        port: PortArgs,
+        /// Decode known messages with a DBC file
+        #[arg(long)]
+        dbc: Option<PathBuf>,
+    },
+    /// Send frames in candump notation (`123#11223344`, `12345678#AA`, `123##1AABB` for FD+BRS)
+    Send {
+        #[command(flatten)]
+        port: PortArgs,
+        /// CAN FD data-phase bitrate for SLCAN FD adapters (1000000, 2000000, 4000000, 5000000)
+        #[arg(long, value_parser = parse_data_bitrate)]
+        data_bitrate: Option<DataBitrate>,
+        #[arg(required = true)]
+        frames: Vec<String>,
+    },
+    /// Find RMD motors; without --bitrate, tries 1 Mbit/s then 500 kbit/s
+    Scan {
+        #[command(flatten)]
+        port: PortArgs,
+        /// Motor IDs to probe
+        #[arg(long, default_value = "1-32", value_parser = parse_id_range)]
+        ids: std::ops::RangeInclusive<u32>,
+        /// Keep polling and print online/offline transitions
+        #[arg(long)]
+        watch: bool,
+    },
+    /// Capture traffic to a candump `.log` or Vector `.asc` file until Ctrl+C
+    Record {
+        #[command(flatten)]
+        port: PortArgs,
+        output: PathBuf,
+        /// Stop after this many seconds
+        #[arg(long)]
+        duration: Option<f64>,
+    },
+    /// Send the frames of a `.log`/`.asc` capture
+    Replay {
+        #[command(flatten)]
+        port: PortArgs,
+        input: PathBuf,
+        /// Playback speed relative to the recorded timing
+        #[arg(long, default_value_t = 1.0, conflicts_with = "fast")]
+        speed: f64,
+        /// Send back-to-back, ignoring timestamps
+        #[arg(long)]
+        fast: bool,
+        /// Also send frames recorded as transmitted (Tx)
+        #[arg(long)]
+        tx: bool,
+    },
+    /// Execute a script of sends, waits and expected replies (see `can::script`)
+    Run {
+        #[command(flatten)]
+        port: PortArgs,
+        script: PathBuf,
+    },
+    /// Read (and with --set, write) PID gains, acceleration limits and the encoder zero
+    Params {
+        #[command(flatten)]
+        port: PortArgs,
+        /// RMD motor ID
+        #[arg(short, long, default_value_t = 1)]
+        motor: u32,
+        /// `NAME=VALUE`, e.g. `speed_kp=60`, `speed_accel=20000`, `encoder_zero=0`; repeatable
+        #[arg(long, value_parser = parse_param)]
+        set: Vec<(Param, i64)>,
+        /// Store values in flash; without it PID gains go to RAM and are lost at power-off
+        #[arg(long)]
+        flash: bool,
+        /// Store the current position as the encoder zero (flash)
+        #[arg(long, conflicts_with = "clear_zero")]
+        zero_here: bool,
+        /// Remove the encoder zero offset (flash)
+        #[arg(long)]
+        clear_zero: bool,
+        /// Reset the motor afterwards so a new encoder zero applies
+        #[arg(long)]
+        reset: bool,
+        /// Do not ask before writing to flash
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Move motors through waypoints along a synchronised trajectory and report tracking error
+    Move {
+        #[command(flatten)]
+        port: PortArgs,
+        /// RMD motor IDs, one per joint of each waypoint
+        #[arg(short, long, value_delimiter = ',', default_value = "1")]
+        motors: Vec<u32>,
+        /// Multi-turn angles in degrees, one per motor, with an optional dwell: `90,45@1s`; put `--` before negative ones
+        #[arg(required = true, value_parser = parse_waypoint)]
+        waypoints: Vec<Waypoint>,
+        /// Velocity profile: trapezoidal or cubic
+        #[arg(long, default_value = "trapezoidal")]
+        profile: Profile,
+        /// Velocity limit of every joint in deg/s
+        #[arg(long, default_value_t = 90.0)]
+        max_velocity: f64,
+        /// Acceleration limit of every joint in deg/s²
+        #[arg(long, default_value_t = 360.0)]
+        max_accel: f64,
+        /// Setpoint rate in Hz
+        #[arg(long, default_value_t = 100.0)]
+        rate: f64,
+        /// Keep streaming the last waypoint this long so the motors can settle
+        #[arg(long, default_value = "500ms", value_parser = parse_timeout)]
+        settle: Duration,
+        #[command(flatten)]
+        safety: SafetyArgs,
+    },
+    /// Send one ISO-TP message, pacing it with the receiver's flow control
+    IsotpSend {
+        #[command(flatten)]
+        port: PortArgs,
+        #[command(flatten)]
+        isotp: IsoTpArgs,
+        /// Hex bytes (`1122334455667788`) or `@file`
+        data: String,
+        /// How long to wait for each flow control frame
+        #[arg(long, default_value = "1s", value_parser = parse_timeout)]
+        timeout: Duration,
+    },
+    /// Print received ISO-TP messages; with --out, save the first one and exit
+    IsotpRecv {
+        #[command(flatten)]
+        port: PortArgs,
+        #[command(flatten)]
+        isotp: IsoTpArgs,
+        /// Consecutive frames the sender may send per flow control (0 = no limit)
+        #[arg(long, default_value_t = 0)]
+        block_size: u8,
+        /// Minimum gap between the sender's consecutive frames, e.g. 1ms or 500us
+        #[arg(long, default_value = "0ms", value_parser = parse_st_min)]
+        st_min: Duration,
+        /// Save the first message to this file and exit
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+}
 
-    let serial_baud = args.next().and_then(|s| s.parse::<u32>().ok()).unwrap_or(115_200);
-    let motor_id = args.next().and_then(|s| s.parse::<u32>().ok()).unwrap_or(1);
-    let mut slcan = Slcan::open_with_baud(&port, bitrate, serial_baud)?;
+#[derive(Args, Debug, Clone)]
+struct PortArgs {
+    /// Serial port, SocketCAN interface, or `mock:` for an in-memory loopback bus; detected
+    /// (or prompted for) when omitted
+    #[arg(short, long)]
+    port: Option<String>,
+    /// CAN bitrate in bit/s, e.g. 500000, 500k, 1M [default: 500000]
+    #[arg(short, long, value_parser = parse_bitrate)]
+    bitrate: Option<Bitrate>,
+    /// Serial baud rate of the SLCAN adapter
+    #[arg(long, default_value_t = 115_200)]
+    serial_baud: u32,
+}
 
-    if std::env::var("CAN_NO_TUI").is_ok() {
-        eprintln!("[can] connected port={} bitrate={:?} serial_baud={}", port, bitrate, serial_baud);
-        eprintln!("[can] type any key + Enter to send a test frame 123#11223344 (std)");
-        let stdin = std::io::stdin();
-        let mut line = String::new();
-        loop {
-            match slcan.read() {
-                Ok(f) => {
-                    let id = if f.extended { format!("{:08X}", f.id) } else { format!("{:03X}", f.id) };
-                    let data_str = f.data.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
-                    println!("{}  {}", id, data_str);
-                }
-                Err(e) => {
-                    if std::env::var("CAN_DEBUG").is_ok() { eprintln!("[can] read err: {}", e); }
-                }
-            }
-            // Non-blocking-ish: check for a line on stdin
-            use std::io::Read as _;
-            while let Ok(n) = stdin.read_line(&mut line) { if n == 0 { break; } else { break; } }
-            if !line.is_empty() {
-                let _ = slcan.send(&can::CanFrame { id: 0x123, data: vec![0x11,0x22,0x33,0x44], extended: false, rtr: false });
-                if std::env::var("CAN_EXIT_AFTER_TX").is_ok() { return Ok(()); }
-                line.clear();
-            }
+impl PortArgs {
+    fn port(&self) -> Result<String> {
+        match &self.port {
+            Some(p) => Ok(p.clone()),
+            None => choose_serial_port_interactive(),
         }
     }
 
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    crossterm::execute!(stdout, crossterm::terminal::EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    let app_start = Instant::now();
-    let mut rows: Vec<FrameRow> = Vec::new();
-    let mut target_speed_x100: i32 = 0; // deg/s * 100
-    let mut last_poll = Instant::now();
-    let mut last_read = Instant::now();
-    let mut last_rx = Instant::now();
-    let mut angle_x100: i32 = 0;
-    let mut status2: u8 = 0;
-    let mut scroll_offset: usize = 0; // 0 shows newest
-    let mut sb_state = ScrollbarState::default();
-    let mut tab = Tab::Telemetry;
-    // Telemetry histories for charts (time in seconds since start)
-    let mut angle_hist_pts: Vec<(f64,f64)> = Vec::new();
-    let mut speed_hist_pts: Vec<(f64,f64)> = Vec::new();
-    let mut last_angle_degs_abs: Option<(f64, Instant)> = None; // multi-turn degrees + timestamp
-    let window_secs: f64 = 10.0;
-    // Sparkline history removed; using a dial canvas instead
-
-    loop {
-        // Poll device without blocking UI
-        for _ in 0..64 {
-            match slcan.read() {
-                Ok(f) => {
-                    if f.id == 0x240 + motor_id && !f.data.is_empty() {
-                        match f.data[0] {
-                            0x92 if f.data.len() >= 8 => {
-                                let mut le=[0u8;4];
-                                le.copy_from_slice(&f.data[4..8]);
-                                angle_x100 = i32::from_le_bytes(le);
-                                // update histories
-                                let now = Instant::now();
-                                let t = app_start.elapsed().as_secs_f64();
-                                let angle_abs_deg = (angle_x100 as f64) / 100.0; // multi-turn
-                                angle_hist_pts.push((t, angle_abs_deg));
-                                if let Some((prev_deg, prev_t)) = last_angle_degs_abs {
-                                    let dt = now.duration_since(prev_t).as_secs_f64();
-                                    if dt > 0.0 {
-                                        let speed = (angle_abs_deg - prev_deg) / dt; // deg/s
-                                        speed_hist_pts.push((t, speed));
-                                    }
-                                }
-                                last_angle_degs_abs = Some((angle_abs_deg, now));
-                                // trim window
-                                let cutoff = t - window_secs;
-                                while angle_hist_pts.first().map_or(false, |(x,_)| *x < cutoff) { angle_hist_pts.remove(0); }
-                                while speed_hist_pts.first().map_or(false, |(x,_)| *x < cutoff) { speed_hist_pts.remove(0); }
-                            }
-                            0x9C if f.data.len() >= 2 => { status2 = f.data[1]; }
-                            _ => {}
-                        }
-                    }
-                    rows.push(FrameRow { id: f.id, data: f.data, extended: f.extended, ts: Instant::now() });
-                    if rows.len() > 1000 { rows.drain(0..rows.len()-1000); }
-                    last_read = Instant::now();
-                    last_rx = Instant::now();
-                }
-                Err(SlcanError::Io(_)) => break,
-                Err(_) => continue,
-            }
-        }
-
-        // Periodically request status/angle so the view updates on quiet buses
-        // No periodic polling by default; use hotkeys to request
-
-        terminal.draw(|f| {
-            let size = f.area();
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Length(3), // tabs row
-                    Constraint::Min(1),    // content
-                    Constraint::Length(1), // footer info
-                ]).split(size);
-            let content_area = chunks[1];
-
-            // Official Tabs widget with unstyled titles; highlight_style drives the emphasis
-            // Include numeric hints for quick access
-            let titles: Vec<Line> = [" 1 Telemetry ", " 2 Frames ", " 3 Help "]
-                .iter().map(|t| Line::from(*t)).collect();
-            let selected = match tab { Tab::Telemetry => 0, Tab::Frames => 1, Tab::Help => 2 };
-            let tabs = Tabs::new(titles)
-                .style(Style::default())
-                .highlight_style(Style::default().add_modifier(Modifier::REVERSED).add_modifier(Modifier::BOLD))
-                .padding(" ", " ")
-                .divider(" ")
-                .select(selected);
-            f.render_widget(tabs, chunks[0]);
-
-            match tab {
-                Tab::Telemetry => {
-                    let dash_chunks = Layout::default()
-                        .direction(Direction::Vertical)
-                        .constraints([
-                            Constraint::Min(8), // dial gets space
-                            Constraint::Length(3), // bar gauge
-                            Constraint::Length(10), // charts area
-                        ])
-                        .split(content_area);
-
-                    // Dial rendered with Canvas (aspect-correct to remain circular)
-                    let angle_deg = ((angle_x100 as f64) / 100.0).rem_euclid(360.0);
-                    let dial_area = dash_chunks[0];
-                    // Use HalfBlock marker; compensate for widget aspect so the circle appears round
-                    let w = dial_area.width.max(1) as f64;
-                    let h = dial_area.height.max(1) as f64;
-                    let marker_scale = 2.0; // HalfBlock doubles vertical resolution
-                    let eff_h = h * marker_scale; // effective pixel height
-                    let min_px = w.min(eff_h);
-                    let mut dial_margin: f64 = std::env::var("CAN_DIAL_MARGIN").ok().and_then(|s| s.parse().ok()).unwrap_or(0.92);
-                    if dial_margin <= 0.0 || dial_margin > 1.0 { dial_margin = 0.92; }
-                    // Choose symmetric bounds so 1 unit radius maps to min_px/2 pixels
-                    let sx = (w / min_px);
-                    let sy = (eff_h / min_px);
-                    let x_bounds = [-sx, sx];
-                    let y_bounds = [-sy, sy];
-                    let dial = Canvas::default()
-                        .block(Block::default().borders(Borders::ALL).title("Angle Dial (°)"))
-                        .marker(symbols::Marker::HalfBlock)
-                        .x_bounds(x_bounds)
-                        .y_bounds(y_bounds)
-                        .paint(move |ctx| {
-                            let r = dial_margin; // actual dial radius inside the widget
-                            // Outer circle
-                            for i in 0..360 {
-                                let rad = (i as f64).to_radians();
-                                let x = r * rad.cos();
-                                let y = r * rad.sin();
-                                ctx.draw(&CRect { x, y, width: 0.002, height: 0.002, color: Color::DarkGray });
-                            }
-                            // Tick marks every 30°
-                            for i in (0..360).step_by(30) {
-                                let r1 = r * 0.85;
-                                let r2 = r * 0.98;
-                                let rad = (i as f64).to_radians();
-                                ctx.draw(&CLine { x1: r1*rad.cos(), y1: r1*rad.sin(), x2: r2*rad.cos(), y2: r2*rad.sin(), color: Color::Gray });
-                            }
-                            // Needle pointing at angle_deg
-                            let rad = angle_deg.to_radians();
-                            ctx.draw(&CLine { x1: 0.0, y1: 0.0, x2: (r*0.8)*rad.cos(), y2: (r*0.8)*rad.sin(), color: Color::Yellow });
-                        });
-                    f.render_widget(dial, dash_chunks[0]);
-
-                    // Bar gauge also shows single‑turn ratio
-                    let angle_norm = angle_deg;
-                    let ratio = (angle_norm / 360.0).clamp(0.0, 1.0);
-                    let gauge = Gauge::default()
-                        .block(Block::default().borders(Borders::ALL).title("Angle"))
-                        .ratio(ratio)
-                        .gauge_style(Style::default().fg(Color::LightCyan))
-                        .label(format!("{:.2}° (single)", angle_norm));
-                    f.render_widget(gauge, dash_chunks[1]);
-
-                    // Charts: Angle (multi-turn, °) and Speed (deg/s) using identical sampling
-                    let now_s = app_start.elapsed().as_secs_f64();
-                    let x_bounds = [now_s - window_secs, now_s];
-                    let angle_points: Vec<(f64,f64)> = angle_hist_pts.clone();
-                    let speed_points: Vec<(f64,f64)> = speed_hist_pts.clone();
-
-                    // Autoscale Y bounds for angle
-                    let (amin, amax) = if let Some(((..),)) = angle_points.first().map(|_| ((0,),)) {
-                        let mut mn = angle_points[0].1; let mut mx = mn;
-                        for &(_, v) in &angle_points { if v < mn { mn = v } if v > mx { mx = v } }
-                        (mn, mx)
-                    } else { (0.0, 1.0) };
-                    let apad = ((amax - amin) * 0.1).max(0.5);
-                    let a_bounds = [amin - apad, amax + apad];
-
-                    // Autoscale Y bounds for speed (symmetric around 0)
-                    let (smin, smax) = if let Some(((..),)) = speed_points.first().map(|_| ((0,),)) {
-                        let mut mn = speed_points[0].1; let mut mx = mn;
-                        for &(_, v) in &speed_points { if v < mn { mn = v } if v > mx { mx = v } }
-                        (mn, mx)
-                    } else { (-1.0, 1.0) };
-                    let sab = smax.abs().max(smin.abs()).max(1.0);
-                    let s_bounds = [-sab * 1.1, sab * 1.1];
-
-                    let chart_chunks = Layout::default()
-                        .direction(Direction::Horizontal)
-                        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-                        .split(dash_chunks[2]);
+    fn bitrate(&self) -> Bitrate {
+        self.bitrate.unwrap_or(Bitrate::B500k)
+    }
+}
 
-                    let angle_ds = Dataset::default()
-                        .name("angle")
-                        .marker(symbols::Marker::Braille)
-                        .style(Style::default().fg(Color::Cyan))
-                        .graph_type(GraphType::Line)
-                        .data(&angle_points);
-                    let angle_chart = Chart::new(vec![angle_ds])
-                        .block(Block::default().borders(Borders::ALL).title("Angle (°)"))
-                        .x_axis(Axis::default().bounds(x_bounds).labels(vec![Line::from(format!("-{:.0}s", window_secs)), Line::from("now")] ))
-                        .y_axis(Axis::default().bounds(a_bounds).labels(vec![Line::from(format!("{:.0}", a_bounds[0])), Line::from(format!("{:.0}", a_bounds[1]))]));
-                    f.render_widget(angle_chart, chart_chunks[0]);
+#[derive(Args, Debug, Clone)]
+struct TuiArgs {
+    #[command(flatten)]
+    port: PortArgs,
+    /// RMD motor ID to control
+    #[arg(short, long, default_value_t = 1)]
+    motor: u32,
+    /// Decode known messages in the Frames tab with a DBC file
+    #[arg(long)]
+    dbc: Option<PathBuf>,
+    #[command(flatten)]
+    safety: SafetyArgs,
+}
 
-                    let speed_ds = Dataset::default()
-                        .name("speed")
-                        .marker(symbols::Marker::Braille)
-                        .style(Style::default().fg(Color::Green))
-                        .graph_type(GraphType::Line)
-                        .data(&speed_points);
-                    let speed_chart = Chart::new(vec![speed_ds])
-                        .block(Block::default().borders(Borders::ALL).title("Speed (deg/s)"))
-                        .x_axis(Axis::default().bounds(x_bounds).labels(vec![Line::from(format!("-{:.0}s", window_secs)), Line::from("now")] ))
-                        .y_axis(Axis::default().bounds(s_bounds).labels(vec![Line::from(format!("{:.0}", s_bounds[0])), Line::from("0"), Line::from(format!("{:.0}", s_bounds[1]))]));
-                    f.render_widget(speed_chart, chart_chunks[1]);
-                }
-                Tab::Frames => {
-                    // determine visible rows by area height (minus borders)
-                    let visible = content_area.height.saturating_sub(2) as usize;
-                    let total = rows.len();
-                    let max_skip = total.saturating_sub(visible);
-                    if scroll_offset > max_skip { scroll_offset = max_skip; }
-                    sb_state = sb_state.content_length(total.saturating_sub(visible)).position(scroll_offset);
+/// Addressing and framing of an ISO-TP link (see `can::isotp`).
+#[derive(Args, Debug, Clone)]
+struct IsoTpArgs {
+    /// ID this end transmits on, in hex (e.g. 7E0); flow control goes here when receiving
+    #[arg(value_parser = parse_hex_id)]
+    tx_id: u32,
+    /// ID the peer transmits on, in hex
+    #[arg(value_parser = parse_hex_id)]
+    rx_id: u32,
+    /// Both IDs are 29-bit
+    #[arg(long)]
+    extended: bool,
+    /// Send minimal-length frames instead of padding them to 8 bytes
+    #[arg(long)]
+    no_padding: bool,
+}
 
-                    let table_rows = rows.iter().rev().skip(scroll_offset).take(visible).map(|r| {
-                        let (dir, kind) = classify_row(r.id, motor_id, &r.data);
-                        let id_str = if r.extended { format!("{:08X}", r.id) } else { format!("{:03X}", r.id) };
-                        let ts_ms = r.ts.duration_since(app_start).as_millis();
-                        let data_str = r.data.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
-                        let dir_color = if dir == "TX" { Color::Yellow } else if dir == "RX" { Color::Cyan } else { Color::Gray };
-                        TRow::new(vec![
-                            Cell::from(format!("{:>8}ms", ts_ms)).style(Style::default().fg(Color::DarkGray)),
-                            Cell::from(dir.to_string()).style(Style::default().fg(dir_color).add_modifier(Modifier::BOLD)),
-                            Cell::from(id_str).style(Style::default().fg(Color::Cyan)),
-                            Cell::from(kind).style(Style::default().fg(Color::Green)),
-                            Cell::from(data_str).style(Style::default().fg(Color::White)),
-                        ])
-                    });
+impl IsoTpArgs {
+    fn config(&self) -> IsoTpConfig {
+        let mut config = IsoTpConfig::new(self.tx_id, self.rx_id);
+        config.extended = self.extended;
+        if self.no_padding {
+            config.padding = None;
+        }
+        config
+    }
+}
 
-                    let table = Table::new(table_rows, [
-                            Constraint::Length(10), // ts
-                            Constraint::Length(3),  // dir
-                            Constraint::Length(8),  // id
-                            Constraint::Length(16), // kind
-                            Constraint::Min(10),    // data
-                        ])
-                        .block(Block::default().borders(Borders::ALL).title("Frames (latest first)"));
-                    f.render_widget(table, content_area);
-                    // render scrollbar at right edge of table area
-                    let sb = Scrollbar::default().orientation(ScrollbarOrientation::VerticalRight);
-                    f.render_stateful_widget(sb, content_area, &mut sb_state);
-                }
-                Tab::Help => {
-                    let keys_chunks = Layout::default()
-                        .direction(Direction::Horizontal)
-                        .constraints([
-                            Constraint::Percentage(50),
-                            Constraint::Percentage(50),
-                        ])
-                        .split(content_area);
+/// Soft limits and watchdog applied to every motor (see `can::safety`).
+#[derive(Args, Debug, Clone)]
+struct SafetyArgs {
+    /// Largest commanded speed in deg/s
+    #[arg(long)]
+    max_speed: Option<f64>,
+    /// Largest commanded torque current in A
+    #[arg(long)]
+    max_torque: Option<f64>,
+    /// Allowed multi-turn angle range in degrees, e.g. `-90:90`
+    #[arg(long, value_parser = parse_position_range, allow_hyphen_values = true)]
+    position_range: Option<(f64, f64)>,
+    /// Stop and brake a motor when no command arrives within this time
+    #[arg(long, default_value = "500ms", value_parser = parse_timeout)]
+    command_timeout: Duration,
+}
 
-                    let key_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
-                    let key = |label: &str| Span::styled(format!("[{}]", label), key_style);
+impl SafetyArgs {
+    fn config(&self) -> Result<SafetyConfig> {
+        let unlimited = Limits::default();
+        let limits = Limits {
+            max_speed_dps: self.max_speed.unwrap_or(unlimited.max_speed_dps),
+            position_deg: self.position_range,
+            max_torque_a: self.max_torque.unwrap_or(unlimited.max_torque_a),
+        };
+        let config = SafetyConfig { command_timeout: self.command_timeout, default_limits: limits, ..Default::default() };
+        config.validate()?;
+        Ok(config)
+    }
+}
 
-                    // Left column: Navigation + Frames
-                    let nav_lines = vec![
-                        Line::from(vec![key("Tab"), Span::raw(" Next  "), key("Shift-Tab"), Span::raw(" Prev")]),
-                        Line::from(vec![key("1"), Span::raw(" Dash  "), key("2"), Span::raw(" Frames  "), key("3"), Span::raw(" Safety  "), key("4"), Span::raw(" Keys")]),
-                    ];
-                    let frames_lines = vec![
-                        Line::from(vec![key("Up/Down"), Span::raw(" Scroll  "), key("Home/End"), Span::raw(" Jump")]),
-                    ];
-                    let left = Paragraph::new(nav_lines.into_iter().chain(frames_lines).collect::<Vec<_>>())
-                        .block(Block::default().borders(Borders::ALL).title("Help: Navigation / Frames"));
-                    f.render_widget(left, keys_chunks[0]);
+fn parse_bitrate(s: &str) -> Result<Bitrate, String> {
+    let bps = parse_bps(s)?;
+    Bitrate::from_bps(bps).ok_or_else(|| format!("unsupported bitrate {} (10k, 20k, 50k, 100k, 125k, 250k, 500k, 800k, 1M)", bps))
+}
 
-                    // Right column: Motor + Telemetry + Quit
-                    let motor_lines = vec![
-                        Line::from(vec![key("r"), Span::raw(" Release brake  "), key("k"), Span::raw(" Lock brake")]),
-                        Line::from(vec![key("x"), Span::raw(" Stop  "), key("0"), Span::raw(" Zero speed")]),
-                        Line::from(vec![key("+/-"), Span::raw(" Speed ±5 dps  "), key("p"), Span::raw(" Position 90°")]),
-                    ];
-                    let tele_lines = vec![
-                        Line::from(vec![key("a"), Span::raw(" Read angle (0x92)  "), key("s"), Span::raw(" Read status2 (0x9C)")]),
-                        Line::from(vec![key("A"), Span::raw(" Active reply angle (0xB6→0x92)")]),
-                        Line::from(vec![key("q"), Span::raw(" / "), key("Esc"), Span::raw(" Quit")]),
-                    ];
-                    let right = Paragraph::new(motor_lines.into_iter().chain(tele_lines).collect::<Vec<_>>())
-                        .block(Block::default().borders(Borders::ALL).title("Help: Motor / Telemetry"));
-                    f.render_widget(right, keys_chunks[1]);
-                }
-            }
+fn parse_data_bitrate(s: &str) -> Result<DataBitrate, String> {
+    let bps = parse_bps(s)?;
+    DataBitrate::from_bps(bps).ok_or_else(|| format!("unsupported data bitrate {} (1M, 2M, 4M, 5M)", bps))
+}
 
-            // Footer info (moved header)
-            let rx_age_ms = last_rx.elapsed().as_millis() as u64;
-            let rx_color = if rx_age_ms < 100 { Color::Green } else if rx_age_ms < 500 { Color::Yellow } else { Color::Red };
-            let footer_line = Line::from(vec![
-                Span::styled("port:", Style::default().fg(Color::DarkGray)),
-                Span::styled(format!("{}", port), Style::default().fg(Color::White)),
-                Span::raw("  "),
-                Span::styled("id:", Style::default().fg(Color::DarkGray)),
-                Span::styled(format!("{:03X}", 0x140 + motor_id), Style::default().fg(Color::Cyan)),
-                Span::raw("  "),
-                Span::styled("can:", Style::default().fg(Color::DarkGray)),
-                Span::styled(format!("{:?}", bitrate), Style::default().fg(Color::Magenta)),
-                Span::raw("  "),
-                Span::styled("sbaud:", Style::default().fg(Color::DarkGray)),
-                Span::styled(format!("{}", serial_baud), Style::default().fg(Color::White)),
-                Span::raw("  "),
-                Span::styled("speed:", Style::default().fg(Color::DarkGray)),
-                Span::styled(format!("{:.2} dps", (target_speed_x100 as f32)/100.0), Style::default().fg(Color::Green)),
-                Span::raw("  "),
-                Span::styled("angle:", Style::default().fg(Color::DarkGray)),
-                Span::styled(format!("{:.2}°", (angle_x100 as f32)/100.0), Style::default().fg(Color::Cyan)),
-                Span::raw("  "),
-                Span::styled("frames:", Style::default().fg(Color::DarkGray)),
-                Span::styled(format!("{}", rows.len()), Style::default().fg(Color::White)),
-                Span::raw("  "),
-                Span::styled("last_rx:", Style::default().fg(Color::DarkGray)),
-                Span::styled(format!("{}ms", rx_age_ms), Style::default().fg(rx_color)),
-            ]);
-            let footer = Paragraph::new(footer_line);
-            f.render_widget(footer, chunks[2]);
-        })?;
+/// `500000`, `500k`, `1M`.
+fn parse_bps(s: &str) -> Result<u32, String> {
+    let (num, scale) = match s.strip_suffix(['k', 'K']) {
+        Some(n) => (n, 1_000),
+        None => match s.strip_suffix('M') {
+            Some(n) => (n, 1_000_000),
+            None => (s, 1),
+        },
+    };
+    num.parse::<u32>().ok().and_then(|n| n.checked_mul(scale)).ok_or_else(|| format!("not a bitrate: {}", s))
+}
 
-        // Input handling
-        if event::poll(Duration::from_millis(1))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('c') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => break,
-                    KeyCode::Char('1') => { tab = Tab::Telemetry; }
-                    KeyCode::Char('2') => { tab = Tab::Frames; }
-                    KeyCode::Char('3') => { tab = Tab::Help; }
-                    KeyCode::Tab => { tab = match tab { Tab::Telemetry => Tab::Frames, Tab::Frames => Tab::Help, Tab::Help => Tab::Telemetry }; }
-                    KeyCode::BackTab => { tab = match tab { Tab::Telemetry => Tab::Help, Tab::Help => Tab::Frames, Tab::Frames => Tab::Telemetry }; }
-                    KeyCode::Char('r') => { rows.push(FrameRow { id: 0x140 + motor_id, data: vec![0x77,0,0,0,0,0,0,0], extended: false, ts: Instant::now() }); let _ = send_cmd(&mut slcan, motor_id, [0x77,0,0,0,0,0,0,0]); }
-                    KeyCode::Char('k') => { rows.push(FrameRow { id: 0x140 + motor_id, data: vec![0x78,0,0,0,0,0,0,0], extended: false, ts: Instant::now() }); let _ = send_cmd(&mut slcan, motor_id, [0x78,0,0,0,0,0,0,0]); }
-                    KeyCode::Char('x') => { rows.push(FrameRow { id: 0x140 + motor_id, data: vec![0x81,0,0,0,0,0,0,0], extended: false, ts: Instant::now() }); let _ = send_cmd(&mut slcan, motor_id, [0x81,0,0,0,0,0,0,0]); target_speed_x100 = 0; }
-                    KeyCode::Char('0') => { target_speed_x100 = 0; rows.push(FrameRow { id: 0x140 + motor_id, data: build_speed_bytes(0), extended: false, ts: Instant::now() }); let _ = send_speed(&mut slcan, motor_id, target_speed_x100); }
-                    KeyCode::Char('+') | KeyCode::Char('=') => { target_speed_x100 = target_speed_x100.saturating_add(500); rows.push(FrameRow { id: 0x140 + motor_id, data: build_speed_bytes(target_speed_x100), extended: false, ts: Instant::now() }); let _ = send_speed(&mut slcan, motor_id, target_speed_x100); }
-                    KeyCode::Char('-') | KeyCode::Char('_') => { target_speed_x100 = target_speed_x100.saturating_sub(500); rows.push(FrameRow { id: 0x140 + motor_id, data: build_speed_bytes(target_speed_x100), extended: false, ts: Instant::now() }); let _ = send_speed(&mut slcan, motor_id, target_speed_x100); }
-                    KeyCode::Char('p') => { rows.push(FrameRow { id: 0x140 + motor_id, data: build_pos_bytes(9000), extended: false, ts: Instant::now() }); let _ = send_position(&mut slcan, motor_id, 9000); }
-                    KeyCode::Char('a') => { rows.push(FrameRow { id: 0x140 + motor_id, data: vec![0x92,0,0,0,0,0,0,0], extended: false, ts: Instant::now() }); let _ = send_cmd(&mut slcan, motor_id, [0x92,0,0,0,0,0,0,0]); }
-                    KeyCode::Char('s') => { rows.push(FrameRow { id: 0x140 + motor_id, data: vec![0x9C,0,0,0,0,0,0,0], extended: false, ts: Instant::now() }); let _ = send_cmd(&mut slcan, motor_id, [0x9C,0,0,0,0,0,0,0]); }
-                    KeyCode::Char('A') => { // Enable Active Reply for angle (0x92) @ 50ms interval
-                        let data = [0xB6, 0x92, 0x01, 0x05, 0x00, 0x00, 0x00, 0x00];
-                        rows.push(FrameRow { id: 0x140 + motor_id, data: data.to_vec(), extended: false, ts: Instant::now() });
-                        let _ = send_cmd(&mut slcan, motor_id, data);
-                    }
-                    KeyCode::Up => { if tab == Tab::Frames { scroll_offset = scroll_offset.saturating_add(1); } }
-                    KeyCode::Down => { if tab == Tab::Frames { scroll_offset = scroll_offset.saturating_sub(1); } }
-                    KeyCode::Home => { if tab == Tab::Frames { scroll_offset = 0; } }
-                    KeyCode::End => { if tab == Tab::Frames { scroll_offset = rows.len(); } }
-                    _ => {}
-                }
-            }
-        }
+/// `-90:90`, in degrees.
+fn parse_position_range(s: &str) -> Result<(f64, f64), String> {
+    let (min, max) = s.split_once(':').ok_or_else(|| format!("expected MIN:MAX, got {}", s))?;
+    let parse = |v: &str| v.trim().parse::<f64>().map_err(|_| format!("not an angle: {}", v));
+    let (min, max) = (parse(min)?, parse(max)?);
+    if min > max {
+        return Err(format!("empty range {}:{}", min, max));
     }
+    Ok((min, max))
+}
 
-    disable_raw_mode()?;
-    crossterm::execute!(
-        io::stdout(),
-        crossterm::terminal::LeaveAlternateScreen
-    )?;
-    Ok(())
+fn parse_timeout(s: &str) -> Result<Duration, String> {
+    can::script::parse_duration(s).filter(|d| !d.is_zero()).ok_or_else(|| format!("not a duration: {} (e.g. 500ms, 1s)", s))
 }
 
-fn send_cmd(slcan: &mut Slcan, motor_id: u32, data: [u8;8]) -> Result<()> {
-    let id = 0x140 + motor_id;
-    slcan.send(&CanFrame { id, data: data.to_vec(), extended: false, rtr: false })?;
-    Ok(())
+fn parse_st_min(s: &str) -> Result<Duration, String> {
+    can::script::parse_duration(s).ok_or_else(|| format!("not a duration: {} (e.g. 1ms, 500us)", s))
 }
 
-fn send_speed(slcan: &mut Slcan, motor_id: u32, speed_x100: i32) -> Result<()> {
-    let mut data = [0u8;8];
-    data[0] = 0xA2;
-    data[4..8].copy_from_slice(&speed_x100.to_le_bytes());
-    send_cmd(slcan, motor_id, data)
+/// `7E0` or `0x7E0`.
+fn parse_hex_id(s: &str) -> Result<u32, String> {
+    let hex = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+    u32::from_str_radix(hex, 16).ok().filter(|&id| id <= 0x1FFF_FFFF).ok_or_else(|| format!("not a hex CAN ID: {}", s))
 }
 
-fn send_position(slcan: &mut Slcan, motor_id: u32, angle_x100: i32) -> Result<()> {
-    let mut data = [0u8;8];
-    data[0] = 0xA4;
-    data[4..8].copy_from_slice(&angle_x100.to_le_bytes());
-    send_cmd(slcan, motor_id, data)
+/// `speed_kp=60`.
+fn parse_param(s: &str) -> Result<(Param, i64), String> {
+    let (name, value) = s.split_once('=').ok_or_else(|| format!("expected NAME=VALUE, got {}", s))?;
+    let param = Param::from_name(name.trim()).ok_or_else(|| {
+        let names: Vec<&str> = Param::ALL.iter().map(|p| p.name()).collect();
+        format!("unknown parameter {} ({})", name, names.join(", "))
+    })?;
+    let value: i64 = value.trim().parse().map_err(|_| format!("not a number: {}", value))?;
+    if !param.range().contains(&value) {
+        return Err(format!("{} must be in {}..={}", param.name(), param.range().start(), param.range().end()));
+    }
+    Ok((param, value))
 }
 
-fn build_speed_bytes(speed_x100: i32) -> Vec<u8> {
-    let mut v = vec![0u8;8];
-    v[0] = 0xA2;
-    v[4..8].copy_from_slice(&speed_x100.to_le_bytes());
-    v
+/// `90,45` or `90,45@1s`.
+fn parse_waypoint(s: &str) -> Result<Waypoint, String> {
+    let (angles, dwell) = match s.split_once('@') {
+        Some((a, d)) => (a, can::script::parse_duration(d).ok_or_else(|| format!("not a duration: {} (e.g. 500ms, 1s)", d))?),
+        None => (s, Duration::ZERO),
+    };
+    let angles = angles
+        .split(',')
+        .map(|v| v.trim().parse::<f64>().ok().filter(|a| a.is_finite()).ok_or_else(|| format!("not an angle: {}", v)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Waypoint::new(angles).with_dwell(dwell))
+}
+
+/// `1-32` or a single ID.
+fn parse_id_range(s: &str) -> Result<std::ops::RangeInclusive<u32>, String> {
+    let parse = |v: &str| v.trim().parse::<u32>().map_err(|_| format!("not a motor ID: {}", v));
+    match s.split_once('-') {
+        Some((a, b)) => Ok(parse(a)?..=parse(b)?),
+        None => parse(s).map(|id| id..=id),
+    }
 }
 
-fn build_pos_bytes(angle_x100: i32) -> Vec<u8> {
-    let mut v = vec![0u8;8];
-    v[0] = 0xA4;
-    v[4..8].copy_from_slice(&angle_x100.to_le_bytes());
-    v
+fn load_dbc(path: Option<&PathBuf>) -> Result<Option<Dbc>> {
+    path.map(|p| Dbc::from_file(p).with_context(|| format!("load {}", p.display()))).transpose()
 }
 
-fn classify_row(id: u32, motor_id: u32, data: &[u8]) -> (&'static str, String) {
-    let dir = if id == 0x140 + motor_id { "TX" } else if id == 0x240 + motor_id { "RX" } else { "--" };
-    let kind = if let Some(cmd) = data.get(0) {
-        match *cmd {
-            0x92 => "angle(0x92)",
-            0x9A => "status1(0x9A)",
-            0x9C => "status2(0x9C)",
-            0xA2 => "speed(0xA2)",
-            0xA4 => "pos(0xA4)",
-            0x77 => "brake_release(0x77)",
-            0x78 => "brake_lock(0x78)",
-            0x81 => "stop(0x81)",
-            _ => "other",
-        }.to_string()
-    } else { String::from("") };
-    (dir, kind)
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    match cli.command.unwrap_or(Command::Tui(cli.tui)) {
+        Command::Tui(args) => {
+            let dbc = load_dbc(args.dbc.as_ref())?;
+            tui::run(args.port.port()?, args.port.bitrate(), args.port.serial_baud, args.motor, dbc, args.safety.config()?)
+        }
+        Command::Monitor { port, dbc } => commands::monitor(&port, load_dbc(dbc.as_ref())?),
+        Command::Send { port, data_bitrate, frames } => commands::send(&port, data_bitrate, &frames),
+        Command::Scan { port, ids, watch } => commands::scan(&port, ids, watch),
+        Command::Record { port, output, duration } => commands::record(&port, &output, duration.map(Duration::from_secs_f64)),
+        Command::Replay { port, input, speed, fast, tx } => commands::replay(&port, &input, speed, fast, tx),
+        Command::Run { port, script } => commands::run_script(&port, &script),
+        Command::Params { port, motor, set, flash, zero_here, clear_zero, reset, yes } => {
+            commands::params(&port, motor, &set, flash, zero_here, clear_zero, reset, yes)
+        }
+        Command::Move { port, motors, mut waypoints, profile, max_velocity, max_accel, rate, settle, safety } => {
+            if let Some(last) = waypoints.last_mut() {
+                last.dwell += settle;
+            }
+            let limits = JointLimits { max_velocity_dps: max_velocity, max_accel_dps2: max_accel };
+            commands::move_motors(&port, &motors, &waypoints, profile, limits, rate, safety.config()?)
+        }
+        Command::IsotpSend { port, isotp, data, timeout } => {
+            let config = IsoTpConfig { timeout, ..isotp.config() };
+            commands::isotp_send(&port, config, &data)
+        }
+        Command::IsotpRecv { port, isotp, block_size, st_min, out } => {
+            let config = IsoTpConfig { block_size, st_min, ..isotp.config() };
+            commands::isotp_recv(&port, config, out.as_deref())
+        }
+    }
 }
 
 fn choose_serial_port_interactive() -> Result<String> {
@@ -566,6 +436,13 @@ fn choose_serial_port_interactive() -> Result<String> {
                 .collect();
         }
         candidates = linux_candidates;
+        // Native SocketCAN interfaces (can0, vcan0) are offered alongside serial adapters
+        if let Ok(entries) = std::fs::read_dir("/sys/class/net") {
+            for e in entries.flatten() {
+                let name = e.file_name().to_string_lossy().to_string();
+                if name.starts_with("can") || name.starts_with("vcan") { candidates.push(name); }
+            }
+        }
     }
 
     // Unique and stable ordering
@@ -608,6 +485,3 @@ fn choose_serial_port_interactive() -> Result<String> {
         }
     }
 }
-
-
-
@@ -0,0 +1,354 @@
+//! In-memory CAN transport for tests and demos without hardware.
+//!
+//! `MockBus` implements [`CanBus`] with:
+//! - loopback: every sent frame is also received,
+//! - scripted rules: "on 0x141 [0x92..] reply 0x241 [0x92, …]", optionally after a delay,
+//! - injected frames, errors and bus status.
+//!
+//! Clones share state, so a test can hand one clone to the code under test and keep
+//! another to inject traffic and inspect what was sent.
+//!
+//! ```
+//! use can::mock::{MockBus, Rule};
+//! use can::rmd::{RmdCommand, RmdReply};
+//! use can::CanBus;
+//!
+//! let mut bus = MockBus::new();
+//! bus.add_rule(Rule::on(0x141, &[0x92]).reply_frame(RmdReply::MultiTurnAngle { angle_x100: 9000 }.to_frame(1)));
+//! bus.send(&RmdCommand::ReadMultiTurnAngle.to_frame(1)).unwrap();
+//! let reply = bus.recv().unwrap();
+//! assert_eq!(RmdReply::from_frame(&reply), Some((1, RmdReply::MultiTurnAngle { angle_x100: 9000 })));
+//! ```
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::bus::{filters_accept, BusStatus, CanBus, CanFilter};
+use crate::{CanFrame, SlcanError};
+
+/// Request→reply rule: when a sent frame has `id` and its data starts with `prefix`,
+/// queue `replies` for reception after `delay`.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    id: u32,
+    prefix: Vec<u8>,
+    replies: Vec<CanFrame>,
+    delay: Duration,
+    remaining: Option<usize>,
+}
+
+impl Rule {
+    pub fn on(id: u32, prefix: &[u8]) -> Self {
+        Rule { id, prefix: prefix.to_vec(), replies: Vec::new(), delay: Duration::ZERO, remaining: None }
+    }
+
+    /// Reply with a standard frame.
+    pub fn reply(self, id: u32, data: &[u8]) -> Self {
+        self.reply_frame(CanFrame { id, data: data.to_vec(), extended: false, rtr: false, fd: false, brs: false, timestamp: None })
+    }
+
+    pub fn reply_frame(mut self, frame: CanFrame) -> Self {
+        self.replies.push(frame);
+        self
+    }
+
+    /// Deliver replies `delay` after the triggering send.
+    pub fn after(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Only fire for the first `n` matching sends.
+    pub fn times(mut self, n: usize) -> Self {
+        self.remaining = Some(n);
+        self
+    }
+
+    fn matches(&self, frame: &CanFrame) -> bool {
+        frame.id == self.id && frame.data.starts_with(&self.prefix)
+    }
+}
+
+enum Pending {
+    Frame(CanFrame),
+    Error(SlcanError),
+}
+
+#[derive(Default)]
+struct Inner {
+    loopback: bool,
+    rules: Vec<Rule>,
+    rx: VecDeque<(Instant, Pending)>,
+    sent: Vec<CanFrame>,
+    send_errors: VecDeque<SlcanError>,
+    filters: Vec<CanFilter>,
+    status: BusStatus,
+    read_timeout: Duration,
+}
+
+impl Inner {
+    fn schedule(&mut self, due: Instant, item: Pending) {
+        // Keep the queue ordered by due time, FIFO among equal times
+        let pos = self.rx.iter().position(|(t, _)| *t > due).unwrap_or(self.rx.len());
+        self.rx.insert(pos, (due, item));
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MockBus {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl MockBus {
+    /// Silent bus: sends are recorded, nothing is received unless scripted or injected.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bus that echoes every sent frame back to `recv`.
+    pub fn loopback() -> Self {
+        let bus = Self::new();
+        bus.inner.lock().unwrap().loopback = true;
+        bus
+    }
+
+    /// How long `recv` waits for a delayed frame before reporting a timeout (default: 0).
+    pub fn set_read_timeout(&self, timeout: Duration) {
+        self.inner.lock().unwrap().read_timeout = timeout;
+    }
+
+    pub fn add_rule(&self, rule: Rule) {
+        self.inner.lock().unwrap().rules.push(rule);
+    }
+
+    pub fn clear_rules(&self) {
+        self.inner.lock().unwrap().rules.clear();
+    }
+
+    /// Queue a frame for reception now.
+    pub fn inject(&self, frame: CanFrame) {
+        self.inject_after(frame, Duration::ZERO);
+    }
+
+    /// Queue a frame for reception after `delay`.
+    pub fn inject_after(&self, frame: CanFrame, delay: Duration) {
+        self.inner.lock().unwrap().schedule(Instant::now() + delay, Pending::Frame(frame));
+    }
+
+    /// Make a future `recv` return `err` (in order with queued frames).
+    pub fn inject_recv_error(&self, err: SlcanError) {
+        self.inner.lock().unwrap().schedule(Instant::now(), Pending::Error(err));
+    }
+
+    /// Make the next `send` fail with `err` instead of transmitting.
+    pub fn fail_next_send(&self, err: SlcanError) {
+        self.inner.lock().unwrap().send_errors.push_back(err);
+    }
+
+    pub fn set_status(&self, status: BusStatus) {
+        self.inner.lock().unwrap().status = status;
+    }
+
+    /// Every frame successfully sent so far.
+    pub fn sent(&self) -> Vec<CanFrame> {
+        self.inner.lock().unwrap().sent.clone()
+    }
+
+    /// Drain and return the sent-frame log.
+    pub fn take_sent(&self) -> Vec<CanFrame> {
+        std::mem::take(&mut self.inner.lock().unwrap().sent)
+    }
+
+    /// Number of frames (and errors) still waiting to be received.
+    pub fn pending(&self) -> usize {
+        self.inner.lock().unwrap().rx.len()
+    }
+}
+
+fn timed_out() -> SlcanError {
+    SlcanError::Io(io::Error::new(io::ErrorKind::TimedOut, "mock read timeout"))
+}
+
+impl CanBus for MockBus {
+    fn send(&mut self, frame: &CanFrame) -> Result<(), SlcanError> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(err) = inner.send_errors.pop_front() {
+            return Err(err);
+        }
+        inner.sent.push(frame.clone());
+        let now = Instant::now();
+        if inner.loopback {
+            inner.schedule(now, Pending::Frame(frame.clone()));
+        }
+        let mut fired = Vec::new();
+        for rule in inner.rules.iter_mut() {
+            if rule.remaining == Some(0) || !rule.matches(frame) {
+                continue;
+            }
+            if let Some(n) = rule.remaining.as_mut() {
+                *n -= 1;
+            }
+            for reply in &rule.replies {
+                fired.push((now + rule.delay, reply.clone()));
+            }
+        }
+        for (due, reply) in fired {
+            inner.schedule(due, Pending::Frame(reply));
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Result<CanFrame, SlcanError> {
+        let deadline = Instant::now() + self.inner.lock().unwrap().read_timeout;
+        loop {
+            let wait = {
+                let mut inner = self.inner.lock().unwrap();
+                let now = Instant::now();
+                match inner.rx.front() {
+                    Some((due, _)) if *due <= now => {
+                        let (_, item) = inner.rx.pop_front().unwrap();
+                        match item {
+                            Pending::Frame(mut f) if filters_accept(&inner.filters, &f) => {
+                                f.timestamp.get_or_insert_with(crate::now_since_epoch);
+                                return Ok(f);
+                            }
+                            Pending::Frame(_) => continue,
+                            Pending::Error(e) => return Err(e),
+                        }
+                    }
+                    Some((due, _)) if *due <= deadline => *due - now,
+                    _ => {
+                        if deadline > now {
+                            std::thread::sleep(deadline - now);
+                        }
+                        return Err(timed_out());
+                    }
+                }
+            };
+            std::thread::sleep(wait);
+        }
+    }
+
+    fn set_filters(&mut self, filters: &[CanFilter]) -> Result<(), SlcanError> {
+        self.inner.lock().unwrap().filters = filters.to_vec();
+        Ok(())
+    }
+
+    fn status(&mut self) -> Result<BusStatus, SlcanError> {
+        Ok(self.inner.lock().unwrap().status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::motors::{MotorBus, MotorEvent};
+    use crate::rmd::{self, RmdCommand, RmdReply};
+
+    fn angle(motor_id: u32, angle_x100: i32) -> CanFrame {
+        RmdReply::MultiTurnAngle { angle_x100 }.to_frame(motor_id)
+    }
+
+    #[test]
+    fn loopback_and_rules() {
+        let mut bus = MockBus::loopback();
+        bus.add_rule(Rule::on(0x141, &[0x92]).reply(0x241, &[0x92, 0, 0, 0, 1, 0, 0, 0]).times(1));
+        let cmd = RmdCommand::ReadMultiTurnAngle.to_frame(1);
+        bus.send(&cmd).unwrap();
+        bus.send(&cmd).unwrap();
+        let received: Vec<u32> = std::iter::from_fn(|| bus.recv().ok()).map(|f| f.id).collect();
+        assert_eq!(received, vec![0x141, 0x241, 0x141]);
+        assert_eq!(bus.take_sent().len(), 2);
+        assert!(bus.sent().is_empty());
+    }
+
+    #[test]
+    fn filters_errors_and_status() {
+        let mut bus = MockBus::new();
+        bus.set_filters(&[CanFilter::standard(0x241)]).unwrap();
+        bus.inject(angle(2, 0));
+        bus.inject(angle(1, 100));
+        bus.inject_recv_error(SlcanError::Protocol("boom"));
+        assert_eq!(bus.recv().unwrap().id, 0x241);
+        assert!(matches!(bus.recv(), Err(SlcanError::Protocol("boom"))));
+        assert!(matches!(bus.recv(), Err(SlcanError::Io(e)) if e.kind() == io::ErrorKind::TimedOut));
+
+        bus.fail_next_send(SlcanError::Protocol("tx"));
+        assert!(bus.send(&angle(1, 0)).is_err());
+        assert!(bus.send(&angle(1, 0)).is_ok());
+
+        let status = BusStatus { bus_off: true, ..BusStatus::default() };
+        bus.set_status(status);
+        assert_eq!(bus.status().unwrap(), status);
+    }
+
+    #[test]
+    fn delayed_replies_wait_for_read_timeout() {
+        let mut bus = MockBus::new();
+        bus.inject_after(angle(1, 0), Duration::from_millis(20));
+        assert!(bus.recv().is_err());
+        assert_eq!(bus.pending(), 1);
+        bus.set_read_timeout(Duration::from_secs(1));
+        assert_eq!(bus.recv().unwrap().id, 0x241);
+    }
+
+    #[test]
+    fn discover_probes_every_id() {
+        let mock = MockBus::new();
+        // Motors answer different probes; a reply from outside the range is left out of the
+        // result but still tracked (Online(9) below)
+        mock.add_rule(Rule::on(rmd::tx_id(1), &[rmd::code::READ_MULTI_TURN_ANGLE]).reply_frame(angle(1, -9000)));
+        mock.add_rule(
+            Rule::on(rmd::tx_id(3), &[rmd::code::READ_STATUS1])
+                .reply_frame(RmdReply::Status1 { temperature_c: 30, brake_released: false, voltage_x10: 240, error_flags: 0 }.to_frame(3)),
+        );
+        mock.add_rule(Rule::on(rmd::tx_id(4), &[rmd::code::READ_STATUS2]).reply_frame(angle(9, 0)));
+        let mut motors = MotorBus::new(mock.clone());
+
+        assert_eq!(motors.discover(1..=4, Duration::ZERO).unwrap(), vec![1, 3]);
+        let sent: Vec<(u32, u8)> = mock.sent().iter().map(|f| (f.id, f.data[0])).collect();
+        let expected: Vec<(u32, u8)> = [rmd::code::READ_MULTI_TURN_ANGLE, rmd::code::READ_STATUS1, rmd::code::READ_STATUS2]
+            .into_iter()
+            .flat_map(|code| (1..=4).map(move |id| (rmd::tx_id(id), code)))
+            .collect();
+        assert_eq!(sent, expected);
+
+        assert_eq!(motors.take_events(), vec![MotorEvent::Online(1), MotorEvent::Online(3), MotorEvent::Online(9)]);
+        assert_eq!(motors.motor(1).unwrap().angle_deg, Some(-90.0));
+        assert_eq!(motors.motor(3).unwrap().voltage_v, Some(24.0));
+    }
+
+    #[test]
+    fn discover_waits_for_late_replies() {
+        let mock = MockBus::new();
+        mock.add_rule(Rule::on(rmd::tx_id(2), &[rmd::code::READ_MULTI_TURN_ANGLE]).reply_frame(angle(2, 0)).after(Duration::from_millis(50)));
+        assert_eq!(MotorBus::new(mock.clone()).discover(1..=2, Duration::ZERO).unwrap(), Vec::<u32>::new());
+
+        mock.set_read_timeout(Duration::from_millis(10));
+        assert_eq!(MotorBus::new(mock).discover(1..=2, Duration::from_millis(200)).unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn discover_skips_garbled_input() {
+        let mock = MockBus::new();
+        mock.add_rule(Rule::on(rmd::tx_id(1), &[rmd::code::READ_MULTI_TURN_ANGLE]).reply_frame(angle(1, 0)));
+        mock.inject_recv_error(SlcanError::Parse("bad line".into()));
+        mock.inject_recv_error(SlcanError::Protocol("NAK"));
+        let mut motors = MotorBus::new(mock.clone());
+        assert_eq!(motors.discover(1..=1, Duration::from_millis(5)).unwrap(), vec![1]);
+
+        mock.inject_recv_error(SlcanError::Parse("bad line".into()));
+        mock.inject(angle(1, 100));
+        assert_eq!(motors.poll().unwrap(), 1);
+        assert_eq!(motors.motor(1).unwrap().angle_deg, Some(1.0));
+    }
+
+    #[test]
+    fn discover_reports_transport_errors() {
+        let mock = MockBus::new();
+        mock.fail_next_send(SlcanError::Protocol("adapter gone"));
+        assert!(matches!(MotorBus::new(mock).discover(1..=1, Duration::ZERO), Err(SlcanError::Protocol("adapter gone"))));
+    }
+}
@@ -0,0 +1,305 @@
+//! Multi-motor manager for RMD actuators sharing one bus.
+//!
+//! [`MotorBus`] discovers motors in an ID range (probing with the same angle/status1/status2
+//! reads `can scan` uses), keeps the last known state of each one, and reports
+//! online/offline transitions as [`MotorEvent`]s. Commands are addressed by motor ID, so a
+//! whole leg can be driven through one handle.
+//!
+//! ```
+//! use std::time::Duration;
+//! use can::mock::{MockBus, Rule};
+//! use can::motors::{MotorBus, MotorEvent};
+//! use can::rmd::{self, RmdReply};
+//!
+//! let mock = MockBus::new();
+//! mock.add_rule(Rule::on(rmd::tx_id(2), &[0x92]).reply_frame(RmdReply::MultiTurnAngle { angle_x100: 4500 }.to_frame(2)));
+//! let mut motors = MotorBus::new(mock);
+//! assert_eq!(motors.discover(1..=4, Duration::ZERO).unwrap(), vec![2]);
+//! assert_eq!(motors.take_events(), vec![MotorEvent::Online(2)]);
+//! assert_eq!(motors.motor(2).unwrap().angle_deg, Some(45.0));
+//! ```
+
+use std::collections::BTreeMap;
+use std::io;
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+use crate::bus::CanBus;
+use crate::rmd::{RmdCommand, RmdReply};
+use crate::{CanFrame, SlcanError};
+
+/// Reads sent to every candidate ID during discovery.
+const PROBES: [RmdCommand; 3] = [RmdCommand::ReadMultiTurnAngle, RmdCommand::ReadStatus1, RmdCommand::ReadStatus2];
+
+/// Upper bound on frames handled per [`MotorBus::poll`], so a chatty bus cannot stall the caller.
+const MAX_FRAMES_PER_POLL: usize = 256;
+
+/// Last known state of one motor. Fields are `None` until the matching reply is seen.
+#[derive(Debug, Clone)]
+pub struct MotorState {
+    pub id: u32,
+    pub online: bool,
+    pub last_seen: Instant,
+    /// Multi-turn output angle in degrees.
+    pub angle_deg: Option<f64>,
+    pub speed_dps: Option<i16>,
+    pub iq_x100: Option<i16>,
+    pub temperature_c: Option<i8>,
+    pub voltage_v: Option<f32>,
+    pub brake_released: Option<bool>,
+    /// Status1 error bits, see [`crate::rmd::error_names`].
+    pub error_flags: u16,
+    pub last_reply: Option<RmdReply>,
+}
+
+impl MotorState {
+    fn new(id: u32, now: Instant) -> Self {
+        MotorState {
+            id,
+            online: true,
+            last_seen: now,
+            angle_deg: None,
+            speed_dps: None,
+            iq_x100: None,
+            temperature_c: None,
+            voltage_v: None,
+            brake_released: None,
+            error_flags: 0,
+            last_reply: None,
+        }
+    }
+
+    fn apply(&mut self, reply: RmdReply) {
+        if let Some(t) = reply.temperature_c() {
+            self.temperature_c = Some(t);
+        }
+        match reply {
+            RmdReply::MultiTurnAngle { angle_x100 } => self.angle_deg = Some(angle_x100 as f64 / 100.0),
+            RmdReply::Status1 { brake_released, voltage_x10, error_flags, .. } => {
+                self.brake_released = Some(brake_released);
+                self.voltage_v = Some(voltage_x10 as f32 / 10.0);
+                self.error_flags = error_flags;
+            }
+            RmdReply::Status2(fb) | RmdReply::Torque(fb) | RmdReply::Speed(fb) | RmdReply::Position(fb) => {
+                self.speed_dps = Some(fb.speed_dps);
+                self.iq_x100 = Some(fb.iq_x100);
+            }
+            RmdReply::BrakeRelease => self.brake_released = Some(true),
+            RmdReply::BrakeLock => self.brake_released = Some(false),
+            _ => {}
+        }
+        self.last_reply = Some(reply);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotorEvent {
+    /// First reply from a motor, or a reply after it was marked offline.
+    Online(u32),
+    /// No reply for longer than the offline timeout.
+    Offline(u32),
+}
+
+pub struct MotorBus<B: CanBus = Box<dyn CanBus>> {
+    bus: B,
+    motors: BTreeMap<u32, MotorState>,
+    events: Vec<MotorEvent>,
+    offline_after: Duration,
+}
+
+impl<B: CanBus> MotorBus<B> {
+    pub fn new(bus: B) -> Self {
+        MotorBus { bus, motors: BTreeMap::new(), events: Vec::new(), offline_after: Duration::from_secs(1) }
+    }
+
+    /// How long a motor may stay silent before it is reported offline (default 1 s).
+    /// Motors only answer requests, so keep polling with [`MotorBus::request_state`] or active reply.
+    pub fn set_offline_after(&mut self, timeout: Duration) {
+        self.offline_after = timeout;
+    }
+
+    /// Probe every ID in `ids`, then keep listening for `timeout`. Returns the IDs that answered.
+    /// Garbled input is skipped; only a failed send or a serial/I/O error aborts the scan.
+    pub fn discover(&mut self, ids: RangeInclusive<u32>, timeout: Duration) -> Result<Vec<u32>, SlcanError> {
+        let started = Instant::now();
+        let mut found = Vec::new();
+        for probe in PROBES {
+            for id in ids.clone() {
+                self.bus.send(&probe.to_frame(id))?;
+                // One read per probe paces the adapter and catches early replies
+                match self.recv_one()? {
+                    Some(m) if ids.contains(&m) && !found.contains(&m) => found.push(m),
+                    _ => {}
+                }
+            }
+        }
+        loop {
+            let read_started = Instant::now();
+            for m in self.drain()? {
+                if ids.contains(&m) && !found.contains(&m) {
+                    found.push(m);
+                }
+            }
+            if started.elapsed() >= timeout {
+                break;
+            }
+            // Transports whose reads do not block (e.g. MockBus) would otherwise spin
+            if read_started.elapsed() < Duration::from_millis(1) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+        found.sort_unstable();
+        Ok(found)
+    }
+
+    /// Handle pending replies and mark silent motors offline. Returns the number of RMD
+    /// replies processed; blocks for at most one read timeout of the underlying bus.
+    pub fn poll(&mut self) -> Result<usize, SlcanError> {
+        let n = self.drain()?.len();
+        self.check_timeouts(Instant::now());
+        Ok(n)
+    }
+
+    /// Update state from a frame received elsewhere (e.g. a [`crate::threaded::BusThread`]
+    /// subscription). Returns the motor ID if the frame was an RMD reply.
+    pub fn handle_frame(&mut self, frame: &CanFrame) -> Option<u32> {
+        let (id, reply) = RmdReply::from_frame(frame)?;
+        let now = Instant::now();
+        let state = self.motors.entry(id).or_insert_with(|| {
+            self.events.push(MotorEvent::Online(id));
+            MotorState::new(id, now)
+        });
+        if !state.online {
+            state.online = true;
+            self.events.push(MotorEvent::Online(id));
+        }
+        state.last_seen = now;
+        state.apply(reply);
+        Some(id)
+    }
+
+    /// Mark motors not heard from since `now - offline_after` as offline.
+    pub fn check_timeouts(&mut self, now: Instant) {
+        for state in self.motors.values_mut() {
+            if state.online && now.saturating_duration_since(state.last_seen) > self.offline_after {
+                state.online = false;
+                self.events.push(MotorEvent::Offline(state.id));
+            }
+        }
+    }
+
+    /// Drain the online/offline transitions since the last call.
+    pub fn take_events(&mut self) -> Vec<MotorEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn motor(&self, id: u32) -> Option<&MotorState> {
+        self.motors.get(&id)
+    }
+
+    /// Every motor seen so far, online or not, ordered by ID.
+    pub fn motors(&self) -> impl Iterator<Item = &MotorState> {
+        self.motors.values()
+    }
+
+    pub fn online_ids(&self) -> Vec<u32> {
+        self.motors.values().filter(|m| m.online).map(|m| m.id).collect()
+    }
+
+    /// Forget a motor (e.g. after re-addressing it).
+    pub fn remove(&mut self, id: u32) -> Option<MotorState> {
+        self.motors.remove(&id)
+    }
+
+    pub fn send(&mut self, motor_id: u32, cmd: RmdCommand) -> Result<(), SlcanError> {
+        self.bus.send(&cmd.to_frame(motor_id))
+    }
+
+    /// Send the same command to each of `ids`, stopping at the first transport error.
+    pub fn send_many(&mut self, ids: &[u32], cmd: RmdCommand) -> Result<(), SlcanError> {
+        for &id in ids {
+            self.send(id, cmd)?;
+        }
+        Ok(())
+    }
+
+    /// Send `cmd` to every motor currently online.
+    pub fn send_all(&mut self, cmd: RmdCommand) -> Result<(), SlcanError> {
+        let ids = self.online_ids();
+        self.send_many(&ids, cmd)
+    }
+
+    /// Ask each known motor for angle and status2; replies arrive through [`MotorBus::poll`].
+    pub fn request_state(&mut self) -> Result<(), SlcanError> {
+        let ids: Vec<u32> = self.motors.keys().copied().collect();
+        self.send_many(&ids, RmdCommand::ReadMultiTurnAngle)?;
+        self.send_many(&ids, RmdCommand::ReadStatus2)
+    }
+
+    pub fn set_speed(&mut self, motor_id: u32, dps: f64) -> Result<(), SlcanError> {
+        self.send(motor_id, RmdCommand::Speed { speed_x100: (dps * 100.0).round() as i32 })
+    }
+
+    /// Absolute multi-turn position in degrees; `max_speed_dps == 0` uses the motor's default.
+    pub fn set_position(&mut self, motor_id: u32, deg: f64, max_speed_dps: u16) -> Result<(), SlcanError> {
+        self.send(motor_id, RmdCommand::Position { max_speed_dps, angle_x100: (deg * 100.0).round() as i32 })
+    }
+
+    pub fn set_torque(&mut self, motor_id: u32, iq_amps: f64) -> Result<(), SlcanError> {
+        self.send(motor_id, RmdCommand::Torque { iq_x100: (iq_amps * 100.0).round() as i16 })
+    }
+
+    pub fn stop(&mut self, motor_id: u32) -> Result<(), SlcanError> {
+        self.send(motor_id, RmdCommand::Stop)
+    }
+
+    /// Stop every known motor, online or not; tries all of them before reporting an error.
+    pub fn stop_all(&mut self) -> Result<(), SlcanError> {
+        let ids: Vec<u32> = self.motors.keys().copied().collect();
+        let mut result = Ok(());
+        for id in ids {
+            if let Err(e) = self.stop(id) {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    pub fn bus_mut(&mut self) -> &mut B {
+        &mut self.bus
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bus
+    }
+
+    /// One `recv`; `Ok(None)` on timeout, garbled input or non-RMD frames.
+    fn recv_one(&mut self) -> Result<Option<u32>, SlcanError> {
+        match self.bus.recv() {
+            Ok(frame) => Ok(self.handle_frame(&frame)),
+            Err(SlcanError::Io(e)) if e.kind() == io::ErrorKind::TimedOut => Ok(None),
+            Err(e) if is_frame_error(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Receive until the bus is quiet (or the per-poll cap); returns the motor of each reply.
+    fn drain(&mut self) -> Result<Vec<u32>, SlcanError> {
+        let mut seen = Vec::new();
+        for _ in 0..MAX_FRAMES_PER_POLL {
+            match self.bus.recv() {
+                Ok(frame) => seen.extend(self.handle_frame(&frame)),
+                Err(SlcanError::Io(e)) if e.kind() == io::ErrorKind::TimedOut => break,
+                Err(e) if is_frame_error(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(seen)
+    }
+}
+
+/// Errors that spoil one line or frame (an unparsable SLCAN line, an adapter NAK) rather
+/// than the transport, as the TUI treats them.
+fn is_frame_error(e: &SlcanError) -> bool {
+    matches!(e, SlcanError::Parse(_) | SlcanError::Protocol(_))
+}
@@ -0,0 +1,362 @@
+//! RMD tuning parameters: PID gains, acceleration limits and the multi-turn encoder zero.
+//!
+//! [`MotorParams`] collects what a motor reported; it is filled either by the blocking
+//! [`ParamClient`] or by feeding replies from an existing receive loop to
+//! [`MotorParams::apply`] (as the TUI and PAD do).
+//!
+//! PID gains can be written to RAM, which is lost at power-off, for tuning. Everything that
+//! persists goes to flash, which wears out and, for the encoder zero, changes where the
+//! motor thinks 0° is after the next reset. Such writes are built as a [`FlashWrite`],
+//! whose [`Display`](std::fmt::Display) text is meant for a confirmation prompt, and are
+//! only sent by [`ParamClient::write_flash`] or by the UI once the user agreed.
+//!
+//! ```
+//! use can::mock::{MockBus, Rule};
+//! use can::params::{Param, ParamClient};
+//! use can::rmd::{code, PidGains, RmdReply};
+//!
+//! let gains = PidGains { current_kp: 100, current_ki: 100, speed_kp: 50, speed_ki: 40, position_kp: 50, position_ki: 50 };
+//! let bus = MockBus::new();
+//! bus.add_rule(Rule::on(0x141, &[code::READ_PID]).reply_frame(RmdReply::Pid { gains, code: code::READ_PID }.to_frame(1)));
+//! bus.add_rule(Rule::on(0x141, &[code::WRITE_PID_ROM]).reply(0x241, &[code::WRITE_PID_ROM, 0, 100, 100, 60, 40, 50, 50]));
+//!
+//! let mut bus = bus.clone();
+//! let mut client = ParamClient::new(&mut bus, 1);
+//! assert_eq!(client.read_pid().unwrap().speed_kp, 50);
+//!
+//! let write = client.params().flash_write(Param::SpeedKp, 60).unwrap();
+//! assert_eq!(write.to_string(), "store PID gains in flash (speed Kp 50 → 60)");
+//! client.write_flash(&write).unwrap();
+//! assert_eq!(client.params().pid.unwrap().speed_kp, 60);
+//! ```
+
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::bus::CanBus;
+use crate::rmd::{self, AccelKind, PidGains, RmdCommand, RmdReply};
+use crate::SlcanError;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Range the firmware accepts for acceleration limits, in dps/s.
+pub const ACCELERATION_RANGE: std::ops::RangeInclusive<i64> = 100..=60_000;
+
+/// Last reported parameters of one motor; `None` until read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MotorParams {
+    pub pid: Option<PidGains>,
+    /// Indexed by [`AccelKind::index`], dps/s.
+    pub acceleration: [Option<u32>; 4],
+    /// Position with the zero offset applied, in encoder counts.
+    pub encoder: Option<i32>,
+    pub encoder_raw: Option<i32>,
+    pub encoder_zero: Option<i32>,
+}
+
+impl MotorParams {
+    /// Update from a reply. Returns true if it carried a parameter.
+    pub fn apply(&mut self, reply: &RmdReply) -> bool {
+        match *reply {
+            RmdReply::Pid { gains, .. } => self.pid = Some(gains),
+            RmdReply::Acceleration { kind, dps_per_s, .. } => self.acceleration[kind.index() as usize] = Some(dps_per_s),
+            RmdReply::Encoder { counts } => self.encoder = Some(counts),
+            RmdReply::EncoderRaw { counts } => self.encoder_raw = Some(counts),
+            RmdReply::EncoderZero { offset } | RmdReply::ZeroWritten { offset, .. } => self.encoder_zero = Some(offset),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Commands that read every parameter.
+    pub fn read_commands() -> Vec<RmdCommand> {
+        let mut cmds = vec![RmdCommand::ReadPid];
+        cmds.extend(AccelKind::ALL.map(RmdCommand::ReadAcceleration));
+        cmds.extend([RmdCommand::ReadEncoder, RmdCommand::ReadEncoderRaw, RmdCommand::ReadEncoderZero]);
+        cmds
+    }
+
+    /// RAM write setting `param` to `value`. Only PID gains have one, and all six are sent,
+    /// so the gains must have been read first.
+    pub fn ram_write(&self, param: Param, value: i64) -> Option<RmdCommand> {
+        Some(RmdCommand::WritePidRam(param.with_gain(self.pid?, value)?))
+    }
+
+    /// Flash write setting `param` to `value`, or `None` when the PID gains are still unknown
+    /// or `value` is out of range.
+    pub fn flash_write(&self, param: Param, value: i64) -> Option<FlashWrite> {
+        if !param.range().contains(&value) {
+            return None;
+        }
+        let command = match param {
+            Param::Acceleration(kind) => RmdCommand::WriteAcceleration { kind, dps_per_s: value as u32 },
+            Param::EncoderZero => RmdCommand::WriteEncoderZero { offset: value as i32 },
+            gain => RmdCommand::WritePidRom(gain.with_gain(self.pid?, value)?),
+        };
+        let change = match param.get(self) {
+            Some(old) => format!(" ({} {} → {})", param.label(), old, value),
+            None => format!(" ({} → {})", param.label(), value),
+        };
+        let what = match param {
+            Param::Acceleration(_) => "store acceleration limit in flash",
+            Param::EncoderZero => "store encoder zero in flash, applies after reset",
+            _ => "store PID gains in flash",
+        };
+        Some(FlashWrite { command, description: format!("{}{}", what, change) })
+    }
+}
+
+/// A parameter shown and edited in the UIs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    CurrentKp,
+    CurrentKi,
+    SpeedKp,
+    SpeedKi,
+    PositionKp,
+    PositionKi,
+    Acceleration(AccelKind),
+    EncoderZero,
+}
+
+impl Param {
+    pub const ALL: [Param; 11] = [
+        Param::CurrentKp,
+        Param::CurrentKi,
+        Param::SpeedKp,
+        Param::SpeedKi,
+        Param::PositionKp,
+        Param::PositionKi,
+        Param::Acceleration(AccelKind::PositionAccel),
+        Param::Acceleration(AccelKind::PositionDecel),
+        Param::Acceleration(AccelKind::SpeedAccel),
+        Param::Acceleration(AccelKind::SpeedDecel),
+        Param::EncoderZero,
+    ];
+
+    /// Identifier used on the command line (`speed_kp`, `position_accel`, `encoder_zero`).
+    pub fn name(self) -> &'static str {
+        match self {
+            Param::CurrentKp => "current_kp",
+            Param::CurrentKi => "current_ki",
+            Param::SpeedKp => "speed_kp",
+            Param::SpeedKi => "speed_ki",
+            Param::PositionKp => "position_kp",
+            Param::PositionKi => "position_ki",
+            Param::Acceleration(AccelKind::PositionAccel) => "position_accel",
+            Param::Acceleration(AccelKind::PositionDecel) => "position_decel",
+            Param::Acceleration(AccelKind::SpeedAccel) => "speed_accel",
+            Param::Acceleration(AccelKind::SpeedDecel) => "speed_decel",
+            Param::EncoderZero => "encoder_zero",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Param::CurrentKp => "current Kp",
+            Param::CurrentKi => "current Ki",
+            Param::SpeedKp => "speed Kp",
+            Param::SpeedKi => "speed Ki",
+            Param::PositionKp => "position Kp",
+            Param::PositionKi => "position Ki",
+            Param::Acceleration(AccelKind::PositionAccel) => "position accel",
+            Param::Acceleration(AccelKind::PositionDecel) => "position decel",
+            Param::Acceleration(AccelKind::SpeedAccel) => "speed accel",
+            Param::Acceleration(AccelKind::SpeedDecel) => "speed decel",
+            Param::EncoderZero => "encoder zero",
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            Param::Acceleration(_) => "dps/s",
+            Param::EncoderZero => "counts",
+            _ => "",
+        }
+    }
+
+    pub fn range(self) -> std::ops::RangeInclusive<i64> {
+        match self {
+            Param::Acceleration(_) => ACCELERATION_RANGE,
+            Param::EncoderZero => i32::MIN as i64..=i32::MAX as i64,
+            _ => 0..=255,
+        }
+    }
+
+    /// Increment for one key press or drag step.
+    pub fn step(self) -> i64 {
+        match self {
+            Param::Acceleration(_) => 100,
+            _ => 1,
+        }
+    }
+
+    /// True for PID gains, which can also be written to RAM.
+    pub fn is_gain(self) -> bool {
+        !matches!(self, Param::Acceleration(_) | Param::EncoderZero)
+    }
+
+    pub fn get(self, params: &MotorParams) -> Option<i64> {
+        match self {
+            Param::Acceleration(kind) => params.acceleration[kind.index() as usize].map(i64::from),
+            Param::EncoderZero => params.encoder_zero.map(i64::from),
+            gain => gain.gain_mut(&mut params.pid?).map(|g| *g as i64),
+        }
+    }
+
+    fn gain_mut(self, gains: &mut PidGains) -> Option<&mut u8> {
+        Some(match self {
+            Param::CurrentKp => &mut gains.current_kp,
+            Param::CurrentKi => &mut gains.current_ki,
+            Param::SpeedKp => &mut gains.speed_kp,
+            Param::SpeedKi => &mut gains.speed_ki,
+            Param::PositionKp => &mut gains.position_kp,
+            Param::PositionKi => &mut gains.position_ki,
+            _ => return None,
+        })
+    }
+
+    fn with_gain(self, mut gains: PidGains, value: i64) -> Option<PidGains> {
+        *self.gain_mut(&mut gains)? = u8::try_from(value).ok()?;
+        Some(gains)
+    }
+}
+
+/// A write that persists in the motor's flash. Show it (`Display`) and get a confirmation
+/// before sending.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlashWrite {
+    command: RmdCommand,
+    description: String,
+}
+
+impl FlashWrite {
+    /// Store the current multi-turn position as the encoder zero.
+    pub fn zero_at_current_position() -> Self {
+        FlashWrite { command: RmdCommand::WriteCurrentAsZero, description: "store current position as encoder zero, applies after reset".into() }
+    }
+
+    /// Remove the encoder zero offset.
+    pub fn clear_zero() -> Self {
+        FlashWrite { command: RmdCommand::WriteEncoderZero { offset: 0 }, description: "clear encoder zero, applies after reset".into() }
+    }
+
+    pub fn command(&self) -> RmdCommand {
+        self.command
+    }
+
+    /// True if the motor must be reset ([`RmdCommand::SystemReset`]) before the value applies.
+    pub fn needs_reset(&self) -> bool {
+        matches!(self.command, RmdCommand::WriteEncoderZero { .. } | RmdCommand::WriteCurrentAsZero)
+    }
+}
+
+impl fmt::Display for FlashWrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.description)
+    }
+}
+
+/// Blocking request/reply access to one motor's parameters.
+pub struct ParamClient<'a> {
+    bus: &'a mut dyn CanBus,
+    motor_id: u32,
+    timeout: Duration,
+    params: MotorParams,
+}
+
+impl<'a> ParamClient<'a> {
+    pub fn new(bus: &'a mut dyn CanBus, motor_id: u32) -> Self {
+        ParamClient { bus, motor_id, timeout: DEFAULT_TIMEOUT, params: MotorParams::default() }
+    }
+
+    /// How long to wait for each reply (default 200 ms).
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Everything read or written so far.
+    pub fn params(&self) -> &MotorParams {
+        &self.params
+    }
+
+    /// Send `cmd` and wait for the reply with the same code, skipping other traffic.
+    pub fn request(&mut self, cmd: RmdCommand) -> Result<RmdReply, SlcanError> {
+        self.bus.send(&cmd.to_frame(self.motor_id))?;
+        let start = Instant::now();
+        loop {
+            match self.bus.recv() {
+                Ok(frame) => {
+                    if let Some((id, reply)) = RmdReply::from_frame(&frame) {
+                        if id == self.motor_id && reply.code() == cmd.code() {
+                            self.params.apply(&reply);
+                            return Ok(reply);
+                        }
+                    }
+                }
+                Err(SlcanError::Io(e)) if e.kind() == io::ErrorKind::TimedOut => {}
+                Err(e) => return Err(e),
+            }
+            if start.elapsed() >= self.timeout {
+                return Err(SlcanError::Io(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("motor {}: no {} reply within {:?}", self.motor_id, rmd::command_name(cmd.code()), self.timeout),
+                )));
+            }
+        }
+    }
+
+    pub fn read_pid(&mut self) -> Result<PidGains, SlcanError> {
+        match self.request(RmdCommand::ReadPid)? {
+            RmdReply::Pid { gains, .. } => Ok(gains),
+            _ => Err(SlcanError::Protocol("unexpected PID reply")),
+        }
+    }
+
+    /// Set gains until power-off; returns what the motor echoed.
+    pub fn write_pid_ram(&mut self, gains: PidGains) -> Result<PidGains, SlcanError> {
+        match self.request(RmdCommand::WritePidRam(gains))? {
+            RmdReply::Pid { gains, .. } => Ok(gains),
+            _ => Err(SlcanError::Protocol("unexpected PID reply")),
+        }
+    }
+
+    pub fn read_acceleration(&mut self, kind: AccelKind) -> Result<u32, SlcanError> {
+        match self.request(RmdCommand::ReadAcceleration(kind))? {
+            RmdReply::Acceleration { dps_per_s, .. } => Ok(dps_per_s),
+            _ => Err(SlcanError::Protocol("unexpected acceleration reply")),
+        }
+    }
+
+    /// `(position, raw position, zero offset)` in encoder counts.
+    pub fn read_encoder(&mut self) -> Result<(i32, i32, i32), SlcanError> {
+        self.request(RmdCommand::ReadEncoder)?;
+        self.request(RmdCommand::ReadEncoderRaw)?;
+        self.request(RmdCommand::ReadEncoderZero)?;
+        let p = &self.params;
+        Ok((p.encoder.unwrap_or_default(), p.encoder_raw.unwrap_or_default(), p.encoder_zero.unwrap_or_default()))
+    }
+
+    /// Read every parameter.
+    pub fn read_all(&mut self) -> Result<MotorParams, SlcanError> {
+        for cmd in MotorParams::read_commands() {
+            self.request(cmd)?;
+        }
+        Ok(self.params)
+    }
+
+    /// Send a confirmed flash write and wait for the motor's echo.
+    pub fn write_flash(&mut self, write: &FlashWrite) -> Result<RmdReply, SlcanError> {
+        self.request(write.command)
+    }
+
+    /// Reboot the motor so a new encoder zero takes effect. There is no reply.
+    pub fn reset(&mut self) -> Result<(), SlcanError> {
+        self.bus.send(&RmdCommand::SystemReset.to_frame(self.motor_id))
+    }
+}
@@ -0,0 +1,391 @@
+//! Record and replay CAN traffic.
+//!
+//! Two text formats are supported:
+//! - candump `-L` (`(1436509052.249713) can0 123#11223344 R`), the SocketCAN/can-utils log format
+//! - Vector ASC (`0.001000 1  123  Rx   d 4 11 22 33 44`), readable by CANalyzer/CANoe and most viewers
+//!
+//! CAN FD frames use candump's `123##1<data>` form and ASC `CANFD` lines.
+//!
+//! `Recorder` writes timestamped frames as they arrive; `read_log` loads either format back
+//! and `replay` sends a log onto any [`CanBus`], at original timing or as fast as possible.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::bus::CanBus;
+use crate::{now_since_epoch, CanFrame, SlcanError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Candump,
+    Asc,
+}
+
+impl LogFormat {
+    /// `.asc` files are Vector ASC, everything else candump `-L`.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("asc") => LogFormat::Asc,
+            _ => LogFormat::Candump,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Rx,
+    Tx,
+}
+
+/// One logged frame. `timestamp` is wall-clock time since the UNIX epoch.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub timestamp: Duration,
+    pub channel: String,
+    pub direction: Direction,
+    pub frame: CanFrame,
+}
+
+fn id_hex(frame: &CanFrame) -> String {
+    if frame.extended { format!("{:08X}", frame.id) } else { format!("{:03X}", frame.id) }
+}
+
+/// Streams frames to a log as they happen.
+pub struct Recorder<W: Write> {
+    out: W,
+    format: LogFormat,
+    channel: String,
+    start: Option<Duration>,
+    frames: usize,
+    finished: bool,
+}
+
+impl Recorder<BufWriter<File>> {
+    /// Create `path`, picking the format from its extension.
+    pub fn create(path: impl AsRef<Path>, channel: &str) -> io::Result<Self> {
+        let path = path.as_ref();
+        let out = BufWriter::new(File::create(path)?);
+        Recorder::new(out, LogFormat::from_path(path), channel)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(out: W, format: LogFormat, channel: &str) -> io::Result<Self> {
+        Ok(Recorder { out, format, channel: channel.to_string(), start: None, frames: 0, finished: false })
+    }
+
+    pub fn format(&self) -> LogFormat {
+        self.format
+    }
+
+    /// Number of frames written so far.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Record `frame` at its receive timestamp, or the current host time if it has none.
+    pub fn record(&mut self, frame: &CanFrame, direction: Direction) -> io::Result<()> {
+        self.record_at(frame.timestamp.unwrap_or_else(now_since_epoch), frame, direction)
+    }
+
+    pub fn record_at(&mut self, timestamp: Duration, frame: &CanFrame, direction: Direction) -> io::Result<()> {
+        let start = match self.start {
+            Some(s) => s,
+            None => {
+                self.start = Some(timestamp);
+                if self.format == LogFormat::Asc {
+                    self.write_asc_header(timestamp)?;
+                }
+                timestamp
+            }
+        };
+        match self.format {
+            LogFormat::Candump => {
+                // FD frames use `##<flags>` where flags bit 0 is BRS
+                let payload = if frame.fd {
+                    format!("#{:X}{}", frame.brs as u8, hex::encode_upper(&frame.data))
+                } else if frame.rtr {
+                    "R".to_string()
+                } else {
+                    hex::encode_upper(&frame.data)
+                };
+                // Trailing T/R is the `candump -x` direction marker; canplayer ignores it
+                let dir = match direction { Direction::Rx => "R", Direction::Tx => "T" };
+                writeln!(self.out, "({}.{:06}) {} {}#{} {}", timestamp.as_secs(), timestamp.subsec_micros(), self.channel, id_hex(frame), payload, dir)?;
+            }
+            LogFormat::Asc => {
+                let rel = timestamp.saturating_sub(start);
+                let id = if frame.extended { format!("{:X}x", frame.id) } else { format!("{:X}", frame.id) };
+                let dir = match direction { Direction::Rx => "Rx", Direction::Tx => "Tx" };
+                if frame.fd {
+                    // CANFD <ch> <dir> <id> <brs> <esi> <dlc> <len> <data>
+                    let bytes = frame.data.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+                    let dlc = crate::len_to_dlc(frame.data.len());
+                    writeln!(self.out, "{:>11.6} CANFD   1 {} {:>8} {} 0 {:x} {:>2} {}", rel.as_secs_f64(), dir, id, frame.brs as u8, dlc, frame.data.len(), bytes)?;
+                } else if frame.rtr {
+                    writeln!(self.out, "{:>11.6} 1  {:<15} {}   r", rel.as_secs_f64(), id, dir)?;
+                } else {
+                    let bytes = frame.data.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+                    writeln!(self.out, "{:>11.6} 1  {:<15} {}   d {} {}", rel.as_secs_f64(), id, dir, frame.data.len(), bytes)?;
+                }
+            }
+        }
+        self.frames += 1;
+        // Flush per frame: captures must survive a crash or a yanked cable
+        self.out.flush()
+    }
+
+    fn write_asc_header(&mut self, start: Duration) -> io::Result<()> {
+        let date = asc_date(start);
+        writeln!(self.out, "date {}", date)?;
+        writeln!(self.out, "base hex  timestamps absolute")?;
+        writeln!(self.out, "internal events logged")?;
+        writeln!(self.out, "Begin Triggerblock {}", date)?;
+        writeln!(self.out, "{:>11.6} Start of measurement", 0.0)
+    }
+
+    /// Write the format trailer (ASC `End TriggerBlock`) and flush. Called on drop if omitted.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        if self.format == LogFormat::Asc && self.start.is_some() {
+            writeln!(self.out, "End TriggerBlock")?;
+        }
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Drop for Recorder<W> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+/// `Mon Oct 18 10:00:00.000 am 2026` (UTC) for the ASC header.
+fn asc_date(t: Duration) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let secs = t.as_secs();
+    let days = (secs / 86_400) as i64;
+    let sod = secs % 86_400;
+    // Civil-from-days (H. Hinnant), days since 1970-01-01
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    let (h, m, s) = (sod / 3600, (sod / 60) % 60, sod % 60);
+    let (h12, ampm) = match h { 0 => (12, "am"), 1..=11 => (h, "am"), 12 => (12, "pm"), _ => (h - 12, "pm") };
+    format!(
+        "{} {} {:02} {:02}:{:02}:{:02}.{:03} {} {}",
+        DAYS[days.rem_euclid(7) as usize],
+        MONTHS[(month - 1) as usize],
+        day,
+        h12,
+        m,
+        s,
+        t.subsec_millis(),
+        ampm,
+        year
+    )
+}
+
+fn parse_err(line_no: usize, msg: &str) -> SlcanError {
+    SlcanError::Parse(format!("line {}: {}", line_no, msg))
+}
+
+fn parse_candump_line(line: &str, line_no: usize) -> Result<LogEntry, SlcanError> {
+    // (1436509052.249713) can0 123#11223344
+    let mut parts = line.split_whitespace();
+    let ts = parts.next().ok_or_else(|| parse_err(line_no, "missing timestamp"))?;
+    let ts = ts.trim_start_matches('(').trim_end_matches(')');
+    let (sec, frac) = ts.split_once('.').unwrap_or((ts, "0"));
+    let sec: u64 = sec.parse().map_err(|_| parse_err(line_no, "bad timestamp"))?;
+    let micros: u32 = format!("{:0<6}", frac.get(..6).unwrap_or(frac)).parse().map_err(|_| parse_err(line_no, "bad timestamp"))?;
+    let channel = parts.next().ok_or_else(|| parse_err(line_no, "missing channel"))?.to_string();
+    let body = parts.next().ok_or_else(|| parse_err(line_no, "missing frame"))?;
+    let mut frame = parse_frame_body(body).map_err(|e| parse_err(line_no, e))?;
+    frame.timestamp = Some(Duration::new(sec, micros * 1_000));
+    // Trailing T/R marks direction (`candump -x`); plain `-L` logs are all Rx
+    let direction = match parts.next() { Some("T") => Direction::Tx, _ => Direction::Rx };
+    Ok(LogEntry { timestamp: Duration::new(sec, micros * 1_000), channel, direction, frame })
+}
+
+/// Parse a frame in candump notation: `123#11223344` (standard), `12345678#…` (extended),
+/// `123#R` (remote), `123##1AABB` (CAN FD, flags digit: 1 = bit-rate switch).
+///
+/// ```
+/// use can::record::parse_frame;
+///
+/// assert!(parse_frame("123##1AABB").unwrap().brs);
+/// assert!(parse_frame("123##é").is_err());
+/// ```
+pub fn parse_frame(s: &str) -> Result<CanFrame, SlcanError> {
+    parse_frame_body(s).map_err(|e| SlcanError::Parse(format!("{}: {}", s, e)))
+}
+
+fn parse_frame_body(s: &str) -> Result<CanFrame, &'static str> {
+    let (id_str, payload) = s.split_once('#').ok_or("missing '#'")?;
+    let id = u32::from_str_radix(id_str, 16).map_err(|_| "bad id")?;
+    let extended = id_str.len() > 3;
+    let (fd, brs, payload) = match payload.strip_prefix('#') {
+        Some(p) if !p.is_empty() => {
+            let flags = p.get(..1).and_then(|f| f.parse::<u8>().ok()).ok_or("bad FD flags")?;
+            (true, flags & 1 != 0, &p[1..])
+        }
+        Some(_) => return Err("missing FD flags"),
+        None => (false, false, payload),
+    };
+    let rtr = !fd && payload.starts_with('R');
+    let data = if rtr { Vec::new() } else { hex::decode(payload).map_err(|_| "bad data")? };
+    Ok(CanFrame { id, data, extended, rtr, fd, brs, timestamp: None })
+}
+
+fn parse_asc_line(line: &str, line_no: usize) -> Result<Option<LogEntry>, SlcanError> {
+    // 0.001000 1  123  Rx   d 4 11 22 33 44
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 5 {
+        return Ok(None);
+    }
+    let Ok(ts) = parts[0].parse::<f64>() else { return Ok(None) };
+    if parts[1] == "CANFD" {
+        return parse_asc_fd(&parts, ts, line_no);
+    }
+    if parts[1].parse::<u32>().is_err() {
+        return Ok(None); // events such as "Start of measurement" or error frames
+    }
+    let (id_str, extended) = match parts[2].strip_suffix('x') {
+        Some(s) => (s, true),
+        None => (parts[2], false),
+    };
+    let Ok(id) = u32::from_str_radix(id_str, 16) else { return Ok(None) };
+    let direction = match parts[3] { "Tx" => Direction::Tx, "Rx" => Direction::Rx, _ => return Ok(None) };
+    let (rtr, data) = match parts[4] {
+        "r" => (true, Vec::new()),
+        "d" => {
+            let dlc: usize = parts.get(5).and_then(|s| s.parse().ok()).ok_or_else(|| parse_err(line_no, "bad DLC"))?;
+            if parts.len() < 6 + dlc {
+                return Err(parse_err(line_no, "short data"));
+            }
+            let data = parts[6..6 + dlc]
+                .iter()
+                .map(|b| u8::from_str_radix(b, 16))
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|_| parse_err(line_no, "bad data"))?;
+            (false, data)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(LogEntry {
+        timestamp: asc_timestamp(ts, line_no)?,
+        channel: parts[1].to_string(),
+        direction,
+        frame: CanFrame { id, data, extended, rtr, fd: false, brs: false, timestamp: None },
+    }))
+}
+
+fn parse_asc_fd(parts: &[&str], ts: f64, line_no: usize) -> Result<Option<LogEntry>, SlcanError> {
+    // 0.001000 CANFD   1 Rx      123 [name] 1 0 9 12 11 22 ...  (trailing timing fields ignored)
+    if parts.len() < 9 {
+        return Ok(None);
+    }
+    let (id_str, extended) = match parts[4].strip_suffix('x') {
+        Some(s) => (s, true),
+        None => (parts[4], false),
+    };
+    let Ok(id) = u32::from_str_radix(id_str, 16) else { return Ok(None) };
+    let direction = match parts[3] { "Tx" => Direction::Tx, "Rx" => Direction::Rx, _ => return Ok(None) };
+    // Optional symbolic message name before the BRS flag
+    let i = if matches!(parts[5], "0" | "1") { 5 } else { 6 };
+    let brs = parts.get(i) == Some(&"1");
+    let len: usize = parts.get(i + 3).and_then(|s| s.parse().ok()).ok_or_else(|| parse_err(line_no, "bad FD length"))?;
+    let start = i + 4;
+    if parts.len() < start + len {
+        return Err(parse_err(line_no, "short data"));
+    }
+    let data = parts[start..start + len]
+        .iter()
+        .map(|b| u8::from_str_radix(b, 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| parse_err(line_no, "bad data"))?;
+    Ok(Some(LogEntry {
+        timestamp: asc_timestamp(ts, line_no)?,
+        channel: parts[2].to_string(),
+        direction,
+        frame: CanFrame { id, data, extended, rtr: false, fd: true, brs, timestamp: None },
+    }))
+}
+
+fn asc_timestamp(ts: f64, line_no: usize) -> Result<Duration, SlcanError> {
+    Duration::try_from_secs_f64(ts.max(0.0)).map_err(|_| parse_err(line_no, "bad timestamp"))
+}
+
+/// Load a candump `-L` or ASC log, format chosen by extension.
+pub fn read_log(path: impl AsRef<Path>) -> Result<Vec<LogEntry>, SlcanError> {
+    let path = path.as_ref();
+    let reader = BufReader::new(File::open(path)?);
+    parse_log(reader, LogFormat::from_path(path))
+}
+
+pub fn parse_log(reader: impl BufRead, format: LogFormat) -> Result<Vec<LogEntry>, SlcanError> {
+    let mut entries = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        match format {
+            LogFormat::Candump => {
+                if trimmed.starts_with('(') {
+                    entries.push(parse_candump_line(trimmed, i + 1)?);
+                }
+            }
+            LogFormat::Asc => {
+                if let Some(e) = parse_asc_line(trimmed, i + 1)? {
+                    entries.push(e);
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timing {
+    /// Reproduce the recorded inter-frame gaps (scaled: 2.0 plays twice as fast).
+    Original { speed: f64 },
+    /// Send back to back.
+    AsFastAsPossible,
+}
+
+/// Send `entries` onto `bus`. Only `Rx` entries (traffic seen on the bus) are replayed
+/// unless `include_tx` is set. Returns the number of frames sent.
+pub fn replay(bus: &mut dyn CanBus, entries: &[LogEntry], timing: Timing, include_tx: bool) -> Result<usize, SlcanError> {
+    let started = Instant::now();
+    let first = entries.first().map(|e| e.timestamp).unwrap_or_default();
+    let mut sent = 0usize;
+    for entry in entries {
+        if entry.direction == Direction::Tx && !include_tx {
+            continue;
+        }
+        if let Timing::Original { speed } = timing {
+            let offset = entry.timestamp.saturating_sub(first).div_f64(speed.max(1e-6));
+            let elapsed = started.elapsed();
+            if offset > elapsed {
+                std::thread::sleep(offset - elapsed);
+            }
+        }
+        bus.send(&entry.frame)?;
+        sent += 1;
+    }
+    Ok(sent)
+}
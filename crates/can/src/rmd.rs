@@ -0,0 +1,667 @@
+//! MyActuator RMD-L motor protocol.
+//!
+//! Per motor: host→motor frames go to `0x140 + id`, replies come back on `0x240 + id`.
+//! Every command and reply is a single 8-byte standard frame whose first byte is the
+//! command code. Multi-byte fields are little-endian.
+//!
+//! Units follow the protocol: angles in 0.01°, speed commands in 0.01 dps,
+//! reported speed in 1 dps, currents in 0.01 A, voltage in 0.1 V.
+//!
+//! Parameter commands (PID gains, acceleration, encoder zero) are typed here too; see
+//! [`crate::params`] for reading them back and for the writes that go to flash.
+
+use crate::CanFrame;
+
+pub const TX_BASE: u32 = 0x140;
+pub const RX_BASE: u32 = 0x240;
+
+/// Host→motor CAN ID for `motor_id`.
+pub fn tx_id(motor_id: u32) -> u32 {
+    TX_BASE + motor_id
+}
+
+/// Motor→host CAN ID for `motor_id`.
+pub fn rx_id(motor_id: u32) -> u32 {
+    RX_BASE + motor_id
+}
+
+/// Motor ID if `id` is an RMD command frame (0x141..=0x160).
+pub fn motor_id_from_tx(id: u32) -> Option<u32> {
+    (TX_BASE + 1..=TX_BASE + 32).contains(&id).then(|| id - TX_BASE)
+}
+
+/// Motor ID if `id` is an RMD reply frame (0x241..=0x260).
+pub fn motor_id_from_rx(id: u32) -> Option<u32> {
+    (RX_BASE + 1..=RX_BASE + 32).contains(&id).then(|| id - RX_BASE)
+}
+
+pub mod code {
+    pub const READ_MULTI_TURN_ANGLE: u8 = 0x92;
+    pub const READ_STATUS1: u8 = 0x9A;
+    pub const READ_STATUS2: u8 = 0x9C;
+    pub const READ_STATUS3: u8 = 0x9D;
+    pub const TORQUE_CONTROL: u8 = 0xA1;
+    pub const SPEED_CONTROL: u8 = 0xA2;
+    pub const POSITION_CONTROL: u8 = 0xA4;
+    pub const BRAKE_RELEASE: u8 = 0x77;
+    pub const BRAKE_LOCK: u8 = 0x78;
+    pub const SHUTDOWN: u8 = 0x80;
+    pub const STOP: u8 = 0x81;
+    pub const CAN_TIMEOUT: u8 = 0xB3;
+    pub const ACTIVE_REPLY: u8 = 0xB6;
+    pub const READ_PID: u8 = 0x30;
+    pub const WRITE_PID_RAM: u8 = 0x31;
+    pub const WRITE_PID_ROM: u8 = 0x32;
+    pub const READ_ACCELERATION: u8 = 0x42;
+    pub const WRITE_ACCELERATION: u8 = 0x43;
+    pub const READ_ENCODER: u8 = 0x60;
+    pub const READ_ENCODER_RAW: u8 = 0x61;
+    pub const READ_ENCODER_ZERO: u8 = 0x62;
+    pub const WRITE_ENCODER_ZERO: u8 = 0x63;
+    pub const WRITE_CURRENT_AS_ZERO: u8 = 0x64;
+    pub const SYSTEM_RESET: u8 = 0x76;
+}
+
+/// Short label for a command code, used by the frame tables ("other" if unknown).
+pub fn command_name(cmd: u8) -> &'static str {
+    match cmd {
+        code::READ_MULTI_TURN_ANGLE => "angle",
+        code::READ_STATUS1 => "status1",
+        code::READ_STATUS2 => "status2",
+        code::READ_STATUS3 => "status3",
+        code::TORQUE_CONTROL => "torque",
+        code::SPEED_CONTROL => "speed",
+        code::POSITION_CONTROL => "position",
+        code::BRAKE_RELEASE => "brake_rel",
+        code::BRAKE_LOCK => "brake_lock",
+        code::SHUTDOWN => "shutdown",
+        code::STOP => "stop",
+        code::CAN_TIMEOUT => "can_timeout",
+        code::ACTIVE_REPLY => "active_reply",
+        code::READ_PID => "read_pid",
+        code::WRITE_PID_RAM => "pid_ram",
+        code::WRITE_PID_ROM => "pid_rom",
+        code::READ_ACCELERATION => "read_accel",
+        code::WRITE_ACCELERATION => "accel_rom",
+        code::READ_ENCODER => "encoder",
+        code::READ_ENCODER_RAW => "encoder_raw",
+        code::READ_ENCODER_ZERO => "encoder_zero",
+        code::WRITE_ENCODER_ZERO => "zero_rom",
+        code::WRITE_CURRENT_AS_ZERO => "zero_here_rom",
+        code::SYSTEM_RESET => "reset",
+        _ => "other",
+    }
+}
+
+/// Position/speed/current loop gains. Each is a raw 0–255 value whose scale is set by the firmware.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PidGains {
+    pub current_kp: u8,
+    pub current_ki: u8,
+    pub speed_kp: u8,
+    pub speed_ki: u8,
+    pub position_kp: u8,
+    pub position_ki: u8,
+}
+
+impl PidGains {
+    fn decode(data: &[u8]) -> Self {
+        PidGains {
+            current_kp: data[2],
+            current_ki: data[3],
+            speed_kp: data[4],
+            speed_ki: data[5],
+            position_kp: data[6],
+            position_ki: data[7],
+        }
+    }
+
+    fn encode_into(&self, d: &mut [u8; 8]) {
+        d[2..8].copy_from_slice(&[self.current_kp, self.current_ki, self.speed_kp, self.speed_ki, self.position_kp, self.position_ki]);
+    }
+}
+
+/// Which acceleration limit of the motion planner a 0x42/0x43 command addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AccelKind {
+    PositionAccel,
+    PositionDecel,
+    SpeedAccel,
+    SpeedDecel,
+}
+
+impl AccelKind {
+    pub const ALL: [AccelKind; 4] = [AccelKind::PositionAccel, AccelKind::PositionDecel, AccelKind::SpeedAccel, AccelKind::SpeedDecel];
+
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+}
+
+/// Host→motor command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RmdCommand {
+    ReadMultiTurnAngle,
+    ReadStatus1,
+    ReadStatus2,
+    ReadStatus3,
+    /// Torque closed loop, q-axis current in 0.01 A.
+    Torque { iq_x100: i16 },
+    /// Speed closed loop in 0.01 dps.
+    Speed { speed_x100: i32 },
+    /// Absolute multi-turn position in 0.01°, `max_speed_dps == 0` leaves the motor's default.
+    Position { max_speed_dps: u16, angle_x100: i32 },
+    BrakeRelease,
+    BrakeLock,
+    Shutdown,
+    Stop,
+    /// Stop the motor and lock the brake when no command arrives for `timeout_ms` (0 disables).
+    /// Runs on the motor, so it also covers a host that crashed or lost the bus. Stored in flash.
+    CanTimeout { timeout_ms: u32 },
+    /// Periodically send the reply of `cmd` every `interval_10ms` × 10 ms.
+    ActiveReply { cmd: u8, enable: bool, interval_10ms: u16 },
+    ReadPid,
+    /// Set gains until power-off.
+    WritePidRam(PidGains),
+    /// Set gains and store them in flash.
+    WritePidRom(PidGains),
+    ReadAcceleration(AccelKind),
+    /// Store an acceleration limit in dps/s (100–60000) in flash; it also applies immediately.
+    WriteAcceleration { kind: AccelKind, dps_per_s: u32 },
+    /// Multi-turn encoder position minus the zero offset, in encoder counts.
+    ReadEncoder,
+    /// Multi-turn encoder position without the zero offset.
+    ReadEncoderRaw,
+    ReadEncoderZero,
+    /// Store `offset` (raw counts) as the encoder zero in flash; 0 clears it. Applies after [`RmdCommand::SystemReset`].
+    WriteEncoderZero { offset: i32 },
+    /// Store the current raw position as the encoder zero in flash. Applies after [`RmdCommand::SystemReset`].
+    WriteCurrentAsZero,
+    /// Reboot the motor controller (no reply).
+    SystemReset,
+}
+
+impl RmdCommand {
+    pub fn code(&self) -> u8 {
+        match self {
+            RmdCommand::ReadMultiTurnAngle => code::READ_MULTI_TURN_ANGLE,
+            RmdCommand::ReadStatus1 => code::READ_STATUS1,
+            RmdCommand::ReadStatus2 => code::READ_STATUS2,
+            RmdCommand::ReadStatus3 => code::READ_STATUS3,
+            RmdCommand::Torque { .. } => code::TORQUE_CONTROL,
+            RmdCommand::Speed { .. } => code::SPEED_CONTROL,
+            RmdCommand::Position { .. } => code::POSITION_CONTROL,
+            RmdCommand::BrakeRelease => code::BRAKE_RELEASE,
+            RmdCommand::BrakeLock => code::BRAKE_LOCK,
+            RmdCommand::Shutdown => code::SHUTDOWN,
+            RmdCommand::Stop => code::STOP,
+            RmdCommand::CanTimeout { .. } => code::CAN_TIMEOUT,
+            RmdCommand::ActiveReply { .. } => code::ACTIVE_REPLY,
+            RmdCommand::ReadPid => code::READ_PID,
+            RmdCommand::WritePidRam(_) => code::WRITE_PID_RAM,
+            RmdCommand::WritePidRom(_) => code::WRITE_PID_ROM,
+            RmdCommand::ReadAcceleration(_) => code::READ_ACCELERATION,
+            RmdCommand::WriteAcceleration { .. } => code::WRITE_ACCELERATION,
+            RmdCommand::ReadEncoder => code::READ_ENCODER,
+            RmdCommand::ReadEncoderRaw => code::READ_ENCODER_RAW,
+            RmdCommand::ReadEncoderZero => code::READ_ENCODER_ZERO,
+            RmdCommand::WriteEncoderZero { .. } => code::WRITE_ENCODER_ZERO,
+            RmdCommand::WriteCurrentAsZero => code::WRITE_CURRENT_AS_ZERO,
+            RmdCommand::SystemReset => code::SYSTEM_RESET,
+        }
+    }
+
+    /// True for commands that store values in the motor's flash (limited write cycles).
+    pub fn writes_flash(&self) -> bool {
+        matches!(
+            self,
+            RmdCommand::WritePidRom(_)
+                | RmdCommand::WriteAcceleration { .. }
+                | RmdCommand::WriteEncoderZero { .. }
+                | RmdCommand::WriteCurrentAsZero
+                | RmdCommand::CanTimeout { .. }
+        )
+    }
+
+    pub fn encode(&self) -> [u8; 8] {
+        let mut d = [0u8; 8];
+        d[0] = self.code();
+        match *self {
+            RmdCommand::Torque { iq_x100 } => d[4..6].copy_from_slice(&iq_x100.to_le_bytes()),
+            RmdCommand::Speed { speed_x100 } => d[4..8].copy_from_slice(&speed_x100.to_le_bytes()),
+            RmdCommand::Position { max_speed_dps, angle_x100 } => {
+                d[2..4].copy_from_slice(&max_speed_dps.to_le_bytes());
+                d[4..8].copy_from_slice(&angle_x100.to_le_bytes());
+            }
+            RmdCommand::CanTimeout { timeout_ms } => d[4..8].copy_from_slice(&timeout_ms.to_le_bytes()),
+            RmdCommand::ActiveReply { cmd, enable, interval_10ms } => {
+                d[1] = cmd;
+                d[2] = enable as u8;
+                d[3..5].copy_from_slice(&interval_10ms.to_le_bytes());
+            }
+            RmdCommand::WritePidRam(gains) | RmdCommand::WritePidRom(gains) => gains.encode_into(&mut d),
+            RmdCommand::ReadAcceleration(kind) => d[1] = kind.index(),
+            RmdCommand::WriteAcceleration { kind, dps_per_s } => {
+                d[1] = kind.index();
+                d[4..8].copy_from_slice(&dps_per_s.to_le_bytes());
+            }
+            RmdCommand::WriteEncoderZero { offset } => d[4..8].copy_from_slice(&offset.to_le_bytes()),
+            _ => {}
+        }
+        d
+    }
+
+    /// Parse a host→motor payload (e.g. to label frames captured on the bus).
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+        let cmd = match data[0] {
+            code::READ_MULTI_TURN_ANGLE => RmdCommand::ReadMultiTurnAngle,
+            code::READ_STATUS1 => RmdCommand::ReadStatus1,
+            code::READ_STATUS2 => RmdCommand::ReadStatus2,
+            code::READ_STATUS3 => RmdCommand::ReadStatus3,
+            code::TORQUE_CONTROL => RmdCommand::Torque { iq_x100: le_i16(&data[4..6]) },
+            code::SPEED_CONTROL => RmdCommand::Speed { speed_x100: le_i32(&data[4..8]) },
+            code::POSITION_CONTROL => RmdCommand::Position { max_speed_dps: le_u16(&data[2..4]), angle_x100: le_i32(&data[4..8]) },
+            code::BRAKE_RELEASE => RmdCommand::BrakeRelease,
+            code::BRAKE_LOCK => RmdCommand::BrakeLock,
+            code::SHUTDOWN => RmdCommand::Shutdown,
+            code::STOP => RmdCommand::Stop,
+            code::CAN_TIMEOUT => RmdCommand::CanTimeout { timeout_ms: le_u32(&data[4..8]) },
+            code::ACTIVE_REPLY => RmdCommand::ActiveReply { cmd: data[1], enable: data[2] != 0, interval_10ms: le_u16(&data[3..5]) },
+            code::READ_PID => RmdCommand::ReadPid,
+            code::WRITE_PID_RAM => RmdCommand::WritePidRam(PidGains::decode(data)),
+            code::WRITE_PID_ROM => RmdCommand::WritePidRom(PidGains::decode(data)),
+            code::READ_ACCELERATION => RmdCommand::ReadAcceleration(AccelKind::from_index(data[1])?),
+            code::WRITE_ACCELERATION => RmdCommand::WriteAcceleration { kind: AccelKind::from_index(data[1])?, dps_per_s: le_u32(&data[4..8]) },
+            code::READ_ENCODER => RmdCommand::ReadEncoder,
+            code::READ_ENCODER_RAW => RmdCommand::ReadEncoderRaw,
+            code::READ_ENCODER_ZERO => RmdCommand::ReadEncoderZero,
+            code::WRITE_ENCODER_ZERO => RmdCommand::WriteEncoderZero { offset: le_i32(&data[4..8]) },
+            code::WRITE_CURRENT_AS_ZERO => RmdCommand::WriteCurrentAsZero,
+            code::SYSTEM_RESET => RmdCommand::SystemReset,
+            _ => return None,
+        };
+        Some(cmd)
+    }
+
+    /// Frame addressed to `motor_id`.
+    pub fn to_frame(&self, motor_id: u32) -> CanFrame {
+        CanFrame { id: tx_id(motor_id), data: self.encode().to_vec(), extended: false, rtr: false, fd: false, brs: false, timestamp: None }
+    }
+}
+
+/// Temperature/current/speed/encoder block shared by status2 and the closed-loop replies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MotorFeedback {
+    pub temperature_c: i8,
+    pub iq_x100: i16,
+    pub speed_dps: i16,
+    pub degree: i16,
+}
+
+impl MotorFeedback {
+    fn decode(data: &[u8]) -> Self {
+        MotorFeedback {
+            temperature_c: data[1] as i8,
+            iq_x100: le_i16(&data[2..4]),
+            speed_dps: le_i16(&data[4..6]),
+            degree: le_i16(&data[6..8]),
+        }
+    }
+
+    fn encode_into(&self, d: &mut [u8; 8]) {
+        d[1] = self.temperature_c as u8;
+        d[2..4].copy_from_slice(&self.iq_x100.to_le_bytes());
+        d[4..6].copy_from_slice(&self.speed_dps.to_le_bytes());
+        d[6..8].copy_from_slice(&self.degree.to_le_bytes());
+    }
+}
+
+/// Status1 error bits.
+pub mod error_flag {
+    pub const STALL: u16 = 0x0002;
+    pub const LOW_VOLTAGE: u16 = 0x0004;
+    pub const OVER_VOLTAGE: u16 = 0x0008;
+    pub const OVER_CURRENT: u16 = 0x0010;
+    pub const POWER_OVERRUN: u16 = 0x0040;
+    pub const SPEEDING: u16 = 0x0100;
+    pub const OVER_TEMPERATURE: u16 = 0x1000;
+    pub const ENCODER_CALIBRATION: u16 = 0x2000;
+}
+
+/// Human-readable names of the error bits set in `flags`.
+pub fn error_names(flags: u16) -> Vec<&'static str> {
+    [
+        (error_flag::STALL, "stall"),
+        (error_flag::LOW_VOLTAGE, "low_voltage"),
+        (error_flag::OVER_VOLTAGE, "over_voltage"),
+        (error_flag::OVER_CURRENT, "over_current"),
+        (error_flag::POWER_OVERRUN, "power_overrun"),
+        (error_flag::SPEEDING, "speeding"),
+        (error_flag::OVER_TEMPERATURE, "over_temperature"),
+        (error_flag::ENCODER_CALIBRATION, "encoder_calibration"),
+    ]
+    .iter()
+    .filter(|(bit, _)| flags & bit != 0)
+    .map(|(_, name)| *name)
+    .collect()
+}
+
+/// Motor→host reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RmdReply {
+    MultiTurnAngle { angle_x100: i32 },
+    Status1 { temperature_c: i8, brake_released: bool, voltage_x10: u16, error_flags: u16 },
+    Status2(MotorFeedback),
+    Status3 { temperature_c: i8, phase_current_x100: [i16; 3] },
+    Torque(MotorFeedback),
+    Speed(MotorFeedback),
+    Position(MotorFeedback),
+    BrakeRelease,
+    BrakeLock,
+    Shutdown,
+    Stop,
+    /// Echo of a 0xB3 timeout write. Some firmware echoes zeros instead of the value.
+    CanTimeout { timeout_ms: u32 },
+    ActiveReply { cmd: u8, enabled: bool },
+    /// Gains after a read (0x30) or write (0x31 to RAM, 0x32 to flash).
+    Pid { gains: PidGains, code: u8 },
+    /// Acceleration limit after a read (0x42) or write (0x43).
+    Acceleration { kind: AccelKind, dps_per_s: u32, code: u8 },
+    Encoder { counts: i32 },
+    EncoderRaw { counts: i32 },
+    EncoderZero { offset: i32 },
+    /// Zero offset stored in flash by 0x63 or 0x64.
+    ZeroWritten { offset: i32, code: u8 },
+}
+
+impl RmdReply {
+    pub fn code(&self) -> u8 {
+        match self {
+            RmdReply::MultiTurnAngle { .. } => code::READ_MULTI_TURN_ANGLE,
+            RmdReply::Status1 { .. } => code::READ_STATUS1,
+            RmdReply::Status2(_) => code::READ_STATUS2,
+            RmdReply::Status3 { .. } => code::READ_STATUS3,
+            RmdReply::Torque(_) => code::TORQUE_CONTROL,
+            RmdReply::Speed(_) => code::SPEED_CONTROL,
+            RmdReply::Position(_) => code::POSITION_CONTROL,
+            RmdReply::BrakeRelease => code::BRAKE_RELEASE,
+            RmdReply::BrakeLock => code::BRAKE_LOCK,
+            RmdReply::Shutdown => code::SHUTDOWN,
+            RmdReply::Stop => code::STOP,
+            RmdReply::CanTimeout { .. } => code::CAN_TIMEOUT,
+            RmdReply::ActiveReply { .. } => code::ACTIVE_REPLY,
+            RmdReply::Pid { code: c, .. } | RmdReply::Acceleration { code: c, .. } | RmdReply::ZeroWritten { code: c, .. } => *c,
+            RmdReply::Encoder { .. } => code::READ_ENCODER,
+            RmdReply::EncoderRaw { .. } => code::READ_ENCODER_RAW,
+            RmdReply::EncoderZero { .. } => code::READ_ENCODER_ZERO,
+        }
+    }
+
+    /// Decode a reply payload. Returns `None` for unknown codes or short frames.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+        let reply = match data[0] {
+            code::READ_MULTI_TURN_ANGLE => RmdReply::MultiTurnAngle { angle_x100: le_i32(&data[4..8]) },
+            code::READ_STATUS1 => RmdReply::Status1 {
+                temperature_c: data[1] as i8,
+                brake_released: data[3] != 0,
+                voltage_x10: le_u16(&data[4..6]),
+                error_flags: le_u16(&data[6..8]),
+            },
+            code::READ_STATUS2 => RmdReply::Status2(MotorFeedback::decode(data)),
+            code::READ_STATUS3 => RmdReply::Status3 {
+                temperature_c: data[1] as i8,
+                phase_current_x100: [le_i16(&data[2..4]), le_i16(&data[4..6]), le_i16(&data[6..8])],
+            },
+            code::TORQUE_CONTROL => RmdReply::Torque(MotorFeedback::decode(data)),
+            code::SPEED_CONTROL => RmdReply::Speed(MotorFeedback::decode(data)),
+            code::POSITION_CONTROL => RmdReply::Position(MotorFeedback::decode(data)),
+            code::BRAKE_RELEASE => RmdReply::BrakeRelease,
+            code::BRAKE_LOCK => RmdReply::BrakeLock,
+            code::SHUTDOWN => RmdReply::Shutdown,
+            code::STOP => RmdReply::Stop,
+            code::CAN_TIMEOUT => RmdReply::CanTimeout { timeout_ms: le_u32(&data[4..8]) },
+            code::ACTIVE_REPLY => RmdReply::ActiveReply { cmd: data[1], enabled: data[2] != 0 },
+            c @ (code::READ_PID | code::WRITE_PID_RAM | code::WRITE_PID_ROM) => RmdReply::Pid { gains: PidGains::decode(data), code: c },
+            c @ (code::READ_ACCELERATION | code::WRITE_ACCELERATION) => {
+                RmdReply::Acceleration { kind: AccelKind::from_index(data[1])?, dps_per_s: le_u32(&data[4..8]), code: c }
+            }
+            code::READ_ENCODER => RmdReply::Encoder { counts: le_i32(&data[4..8]) },
+            code::READ_ENCODER_RAW => RmdReply::EncoderRaw { counts: le_i32(&data[4..8]) },
+            code::READ_ENCODER_ZERO => RmdReply::EncoderZero { offset: le_i32(&data[4..8]) },
+            c @ (code::WRITE_ENCODER_ZERO | code::WRITE_CURRENT_AS_ZERO) => RmdReply::ZeroWritten { offset: le_i32(&data[4..8]), code: c },
+            _ => return None,
+        };
+        Some(reply)
+    }
+
+    /// Decode `frame` if it is a reply from an RMD motor; returns `(motor_id, reply)`.
+    pub fn from_frame(frame: &CanFrame) -> Option<(u32, Self)> {
+        if frame.extended || frame.rtr {
+            return None;
+        }
+        let motor_id = motor_id_from_rx(frame.id)?;
+        Some((motor_id, Self::decode(&frame.data)?))
+    }
+
+    pub fn encode(&self) -> [u8; 8] {
+        let mut d = [0u8; 8];
+        d[0] = self.code();
+        match *self {
+            RmdReply::MultiTurnAngle { angle_x100 } => d[4..8].copy_from_slice(&angle_x100.to_le_bytes()),
+            RmdReply::Status1 { temperature_c, brake_released, voltage_x10, error_flags } => {
+                d[1] = temperature_c as u8;
+                d[3] = brake_released as u8;
+                d[4..6].copy_from_slice(&voltage_x10.to_le_bytes());
+                d[6..8].copy_from_slice(&error_flags.to_le_bytes());
+            }
+            RmdReply::Status2(fb) | RmdReply::Torque(fb) | RmdReply::Speed(fb) | RmdReply::Position(fb) => fb.encode_into(&mut d),
+            RmdReply::Status3 { temperature_c, phase_current_x100 } => {
+                d[1] = temperature_c as u8;
+                for (i, c) in phase_current_x100.iter().enumerate() {
+                    d[2 + i * 2..4 + i * 2].copy_from_slice(&c.to_le_bytes());
+                }
+            }
+            RmdReply::CanTimeout { timeout_ms } => d[4..8].copy_from_slice(&timeout_ms.to_le_bytes()),
+            RmdReply::ActiveReply { cmd, enabled } => {
+                d[1] = cmd;
+                d[2] = enabled as u8;
+            }
+            RmdReply::Pid { gains, .. } => gains.encode_into(&mut d),
+            RmdReply::Acceleration { kind, dps_per_s, .. } => {
+                d[1] = kind.index();
+                d[4..8].copy_from_slice(&dps_per_s.to_le_bytes());
+            }
+            RmdReply::Encoder { counts } | RmdReply::EncoderRaw { counts } => d[4..8].copy_from_slice(&counts.to_le_bytes()),
+            RmdReply::EncoderZero { offset } | RmdReply::ZeroWritten { offset, .. } => d[4..8].copy_from_slice(&offset.to_le_bytes()),
+            _ => {}
+        }
+        d
+    }
+
+    /// Frame as the motor `motor_id` would send it.
+    pub fn to_frame(&self, motor_id: u32) -> CanFrame {
+        CanFrame { id: rx_id(motor_id), data: self.encode().to_vec(), extended: false, rtr: false, fd: false, brs: false, timestamp: None }
+    }
+
+    /// Temperature carried by this reply, if any.
+    pub fn temperature_c(&self) -> Option<i8> {
+        match *self {
+            RmdReply::Status1 { temperature_c, .. } | RmdReply::Status3 { temperature_c, .. } => Some(temperature_c),
+            RmdReply::Status2(fb) | RmdReply::Torque(fb) | RmdReply::Speed(fb) | RmdReply::Position(fb) => Some(fb.temperature_c),
+            _ => None,
+        }
+    }
+}
+
+fn le_i16(b: &[u8]) -> i16 {
+    i16::from_le_bytes([b[0], b[1]])
+}
+
+fn le_u16(b: &[u8]) -> u16 {
+    u16::from_le_bytes([b[0], b[1]])
+}
+
+fn le_u32(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn le_i32(b: &[u8]) -> i32 {
+    i32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAINS: PidGains = PidGains { current_kp: 0, current_ki: 255, speed_kp: 1, speed_ki: 128, position_kp: 127, position_ki: 64 };
+    const FEEDBACK: MotorFeedback = MotorFeedback { temperature_c: -40, iq_x100: i16::MIN, speed_dps: -1, degree: i16::MAX };
+
+    fn commands() -> Vec<RmdCommand> {
+        let mut all = vec![
+            RmdCommand::ReadMultiTurnAngle,
+            RmdCommand::ReadStatus1,
+            RmdCommand::ReadStatus2,
+            RmdCommand::ReadStatus3,
+            RmdCommand::Torque { iq_x100: -200 },
+            RmdCommand::Torque { iq_x100: i16::MIN },
+            RmdCommand::Torque { iq_x100: i16::MAX },
+            RmdCommand::Speed { speed_x100: i32::MIN },
+            RmdCommand::Speed { speed_x100: -1 },
+            RmdCommand::Speed { speed_x100: i32::MAX },
+            RmdCommand::Position { max_speed_dps: 0, angle_x100: i32::MIN },
+            RmdCommand::Position { max_speed_dps: u16::MAX, angle_x100: i32::MAX },
+            RmdCommand::Position { max_speed_dps: 360, angle_x100: -9000 },
+            RmdCommand::BrakeRelease,
+            RmdCommand::BrakeLock,
+            RmdCommand::Shutdown,
+            RmdCommand::Stop,
+            RmdCommand::CanTimeout { timeout_ms: 0 },
+            RmdCommand::CanTimeout { timeout_ms: u32::MAX },
+            RmdCommand::ActiveReply { cmd: code::READ_STATUS2, enable: true, interval_10ms: u16::MAX },
+            RmdCommand::ActiveReply { cmd: 0xFF, enable: false, interval_10ms: 0 },
+            RmdCommand::ReadPid,
+            RmdCommand::WritePidRam(GAINS),
+            RmdCommand::WritePidRom(PidGains::default()),
+            RmdCommand::WriteEncoderZero { offset: i32::MIN },
+            RmdCommand::WriteEncoderZero { offset: 0 },
+            RmdCommand::ReadEncoder,
+            RmdCommand::ReadEncoderRaw,
+            RmdCommand::ReadEncoderZero,
+            RmdCommand::WriteCurrentAsZero,
+            RmdCommand::SystemReset,
+        ];
+        for kind in AccelKind::ALL {
+            all.push(RmdCommand::ReadAcceleration(kind));
+            all.push(RmdCommand::WriteAcceleration { kind, dps_per_s: u32::MAX });
+            all.push(RmdCommand::WriteAcceleration { kind, dps_per_s: 100 });
+        }
+        all
+    }
+
+    fn replies() -> Vec<RmdReply> {
+        let mut all = vec![
+            RmdReply::MultiTurnAngle { angle_x100: i32::MIN },
+            RmdReply::MultiTurnAngle { angle_x100: i32::MAX },
+            RmdReply::Status1 { temperature_c: i8::MIN, brake_released: true, voltage_x10: u16::MAX, error_flags: 0xFFFF },
+            RmdReply::Status1 { temperature_c: 45, brake_released: false, voltage_x10: 480, error_flags: 0 },
+            RmdReply::Status2(FEEDBACK),
+            RmdReply::Status3 { temperature_c: -1, phase_current_x100: [i16::MIN, -150, i16::MAX] },
+            RmdReply::Torque(FEEDBACK),
+            RmdReply::Speed(MotorFeedback { temperature_c: i8::MAX, iq_x100: -1, speed_dps: i16::MIN, degree: 0 }),
+            RmdReply::Position(MotorFeedback::default()),
+            RmdReply::BrakeRelease,
+            RmdReply::BrakeLock,
+            RmdReply::Shutdown,
+            RmdReply::Stop,
+            RmdReply::CanTimeout { timeout_ms: 500 },
+            RmdReply::ActiveReply { cmd: code::READ_MULTI_TURN_ANGLE, enabled: true },
+            RmdReply::ActiveReply { cmd: 0, enabled: false },
+            RmdReply::Encoder { counts: i32::MIN },
+            RmdReply::EncoderRaw { counts: i32::MAX },
+            RmdReply::EncoderZero { offset: -1 },
+        ];
+        for code in [code::READ_PID, code::WRITE_PID_RAM, code::WRITE_PID_ROM] {
+            all.push(RmdReply::Pid { gains: GAINS, code });
+        }
+        for code in [code::READ_ACCELERATION, code::WRITE_ACCELERATION] {
+            for kind in AccelKind::ALL {
+                all.push(RmdReply::Acceleration { kind, dps_per_s: u32::MAX, code });
+            }
+        }
+        for code in [code::WRITE_ENCODER_ZERO, code::WRITE_CURRENT_AS_ZERO] {
+            all.push(RmdReply::ZeroWritten { offset: i32::MIN, code });
+        }
+        all
+    }
+
+    #[test]
+    fn command_round_trip() {
+        for cmd in commands() {
+            let data = cmd.encode();
+            assert_eq!(data[0], cmd.code(), "{:?}", cmd);
+            assert_eq!(RmdCommand::decode(&data), Some(cmd));
+
+            let frame = cmd.to_frame(32);
+            assert_eq!(frame.id, 0x160);
+            assert_eq!(motor_id_from_tx(frame.id), Some(32));
+            assert_eq!(RmdCommand::decode(&frame.data), Some(cmd));
+        }
+    }
+
+    #[test]
+    fn reply_round_trip() {
+        for reply in replies() {
+            let data = reply.encode();
+            assert_eq!(data[0], reply.code(), "{:?}", reply);
+            assert_eq!(RmdReply::decode(&data), Some(reply));
+            assert_eq!(RmdReply::from_frame(&reply.to_frame(1)), Some((1, reply)));
+        }
+    }
+
+    #[test]
+    fn every_code_is_covered() {
+        let mut codes: Vec<u8> = commands().iter().map(RmdCommand::code).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        let named: Vec<u8> = (0..=u8::MAX).filter(|&c| command_name(c) != "other").collect();
+        assert_eq!(codes, named);
+        for c in named.iter().filter(|&&c| c != code::SYSTEM_RESET) {
+            assert!(replies().iter().any(|r| r.code() == *c), "no reply for {:#04x}", c);
+        }
+    }
+
+    #[test]
+    fn wire_layout() {
+        assert_eq!(RmdCommand::Torque { iq_x100: -2 }.encode(), [0xA1, 0, 0, 0, 0xFE, 0xFF, 0, 0]);
+        assert_eq!(RmdCommand::Position { max_speed_dps: 500, angle_x100: i32::MIN }.encode(), [0xA4, 0, 0xF4, 0x01, 0, 0, 0, 0x80]);
+        assert_eq!(RmdCommand::CanTimeout { timeout_ms: 1000 }.encode(), [0xB3, 0, 0, 0, 0xE8, 0x03, 0, 0]);
+        let fb = RmdReply::decode(&[0xA1, 0xF6, 0x9C, 0xFF, 0x10, 0x00, 0x00, 0x80]).unwrap();
+        assert_eq!(fb, RmdReply::Torque(MotorFeedback { temperature_c: -10, iq_x100: -100, speed_dps: 16, degree: i16::MIN }));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert_eq!(RmdCommand::decode(&[code::STOP; 7]), None);
+        assert_eq!(RmdReply::decode(&[code::STOP; 7]), None);
+        assert_eq!(RmdCommand::decode(&[0x00; 8]), None);
+        assert_eq!(RmdReply::decode(&[0x00; 8]), None);
+        assert_eq!(RmdCommand::decode(&[code::READ_ACCELERATION, 4, 0, 0, 0, 0, 0, 0]), None);
+        assert_eq!(RmdReply::decode(&[code::WRITE_ACCELERATION, 4, 0, 0, 0, 0, 0, 0]), None);
+
+        let mut frame = RmdReply::Stop.to_frame(1);
+        frame.extended = true;
+        assert_eq!(RmdReply::from_frame(&frame), None);
+        assert_eq!(RmdReply::from_frame(&RmdCommand::Stop.to_frame(1)), None);
+        assert_eq!(RmdReply::from_frame(&RmdReply::Stop.to_frame(33)), None);
+    }
+}
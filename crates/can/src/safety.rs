@@ -0,0 +1,486 @@
+//! Safety layer for RMD motor commands.
+//!
+//! [`SafeBus`] wraps any [`CanBus`] and inspects the RMD commands sent through it:
+//! - torque, speed and position commands are clamped to per-motor [`Limits`]; a torque or
+//!   speed command that would drive a motor further past its position range is zeroed;
+//! - every motion command arms a watchdog. A motor that is not commanded again within
+//!   [`SafetyConfig::command_timeout`] gets stop (0x81) followed by brake lock (0x78);
+//! - the first time a motor is armed, its own CAN communication timeout (0xB3) is set to the
+//!   same value, so it also stops when this process cannot. The motor keeps it in flash after
+//!   this process exits; it is written once per motor and `SafeBus`, and again only if the
+//!   timeout changes;
+//! - [`SafetyHandle::estop`] sends stop and brake lock to every motor seen on the bus (commanded or
+//!   replying) and rejects motion commands and brake release until [`SafetyHandle::release`].
+//!
+//! The watchdog runs inside `send` and `recv`, so the bus has to be polled. Wrap the
+//! transport before handing it to a [`BusThread`](crate::threaded::BusThread), which reads
+//! continuously, and keep the [`SafetyHandle`] to trigger the e-stop from any thread.
+//!
+//! The watchdog only runs while the process does, and `Drop` only on an orderly shutdown. A
+//! hang, a kill, an abort or a host power loss is caught by the motor-side timeout instead.
+//! Firmware that does not echo 0xB3 gets [`SafetyEvent::MotorTimeoutUnset`]: such a motor
+//! keeps its last command if the host dies.
+//!
+//! ```
+//! use std::time::Duration;
+//! use can::mock::MockBus;
+//! use can::rmd::{code, RmdCommand};
+//! use can::safety::{Limits, SafeBus, SafetyConfig};
+//! use can::CanBus;
+//!
+//! let mock = MockBus::new();
+//! let mut config = SafetyConfig { command_timeout: Duration::from_millis(20), ..Default::default() };
+//! config.limits.insert(1, Limits { max_speed_dps: 90.0, ..Default::default() });
+//! let mut bus = SafeBus::new(mock.clone(), config);
+//!
+//! bus.send(&RmdCommand::Speed { speed_x100: 50_000 }.to_frame(1)).unwrap();
+//! let sent: Vec<RmdCommand> = mock.take_sent().iter().filter_map(|f| RmdCommand::decode(&f.data)).collect();
+//! assert_eq!(sent, [RmdCommand::CanTimeout { timeout_ms: 20 }, RmdCommand::Speed { speed_x100: 9_000 }]);
+//!
+//! // No refresh: the next poll after the timeout stops the motor and locks the brake
+//! std::thread::sleep(Duration::from_millis(30));
+//! let _ = bus.recv();
+//! let codes: Vec<u8> = mock.take_sent().iter().map(|f| f.data[0]).collect();
+//! assert_eq!(codes, [code::STOP, code::BRAKE_LOCK]);
+//!
+//! let handle = bus.handle();
+//! handle.estop();
+//! assert!(bus.send(&RmdCommand::Speed { speed_x100: 100 }.to_frame(1)).is_err());
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::bus::{BusStatus, CanBus, CanFilter};
+use crate::rmd::{self, RmdCommand, RmdReply};
+use crate::{CanFrame, SlcanError};
+
+const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_millis(500);
+/// How long a motor has to echo its 0xB3 timeout before [`SafetyEvent::MotorTimeoutUnset`].
+const MOTOR_TIMEOUT_CONFIRM: Duration = Duration::from_secs(1);
+
+/// Soft limits for one motor. The default limits nothing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// Largest |speed| in deg/s, for speed commands and as the max speed of position moves.
+    pub max_speed_dps: f64,
+    /// Allowed multi-turn angle range in degrees.
+    pub position_deg: Option<(f64, f64)>,
+    /// Largest |q-axis current| in A for torque commands.
+    pub max_torque_a: f64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits { max_speed_dps: f64::INFINITY, position_deg: None, max_torque_a: f64::INFINITY }
+    }
+}
+
+impl Limits {
+    /// Errors if `position_deg` is reversed, not finite, or holds no 0.01° step a position
+    /// command could target.
+    ///
+    /// ```
+    /// use can::rmd::RmdCommand;
+    /// use can::safety::Limits;
+    ///
+    /// let narrow = Limits { position_deg: Some((0.001, 0.002)), ..Default::default() };
+    /// assert!(narrow.validate().is_err());
+    /// assert!(Limits { position_deg: Some((90.0, -90.0)), ..Default::default() }.validate().is_err());
+    /// assert!(Limits { position_deg: Some((-90.0, 90.0)), ..Default::default() }.validate().is_ok());
+    ///
+    /// // Clamping with an invalid range still does not panic
+    /// let cmd = RmdCommand::Position { max_speed_dps: 0, angle_x100: 500 };
+    /// assert_eq!(narrow.clamp(cmd, None), RmdCommand::Position { max_speed_dps: 0, angle_x100: 1 });
+    /// ```
+    pub fn validate(&self) -> Result<(), SlcanError> {
+        if let Some((min, max)) = self.position_deg {
+            if !(min.is_finite() && max.is_finite()) || (min * 100.0).ceil() > (max * 100.0).floor() {
+                return Err(SlcanError::Parse(format!("position range {}:{} holds no 0.01° step", min, max)));
+            }
+        }
+        Ok(())
+    }
+
+    /// `cmd` brought within these limits. `angle_deg` is the last known motor angle; when it
+    /// is outside the position range, torque and speed commands pushing further out become zero.
+    pub fn clamp(&self, cmd: RmdCommand, angle_deg: Option<f64>) -> RmdCommand {
+        // Direction of travel that is still allowed: -1 below min, +1 above max
+        let blocked = match (self.position_deg, angle_deg) {
+            (Some((min, _)), Some(a)) if a <= min => -1,
+            (Some((_, max)), Some(a)) if a >= max => 1,
+            _ => 0,
+        };
+        match cmd {
+            RmdCommand::Torque { iq_x100 } => {
+                let max = clamp_x100(self.max_torque_a, i16::MAX as f64) as i16;
+                let iq_x100 = iq_x100.clamp(-max, max);
+                let iq_x100 = if blocked != 0 && iq_x100.signum() as i32 == blocked { 0 } else { iq_x100 };
+                RmdCommand::Torque { iq_x100 }
+            }
+            RmdCommand::Speed { speed_x100 } => {
+                let max = clamp_x100(self.max_speed_dps, i32::MAX as f64) as i32;
+                let speed_x100 = speed_x100.clamp(-max, max);
+                let speed_x100 = if blocked != 0 && speed_x100.signum() == blocked { 0 } else { speed_x100 };
+                RmdCommand::Speed { speed_x100 }
+            }
+            RmdCommand::Position { max_speed_dps, angle_x100 } => {
+                let angle_x100 = match self.position_deg {
+                    Some((min, max)) => {
+                        // Ordered even for ranges `validate` rejects, which `clamp` would panic on
+                        let (lo, hi) = ((min * 100.0).ceil() as i32, (max * 100.0).floor() as i32);
+                        angle_x100.clamp(lo.min(hi), lo.max(hi))
+                    }
+                    None => angle_x100,
+                };
+                // 0 means "motor default", which may be faster than the limit
+                let limit = self.max_speed_dps.clamp(0.0, u16::MAX as f64) as u16;
+                let max_speed_dps = if self.max_speed_dps.is_finite() && (max_speed_dps == 0 || max_speed_dps > limit) {
+                    limit.max(1)
+                } else {
+                    max_speed_dps
+                };
+                RmdCommand::Position { max_speed_dps, angle_x100 }
+            }
+            other => other,
+        }
+    }
+}
+
+fn clamp_x100(limit: f64, type_max: f64) -> f64 {
+    (limit * 100.0).clamp(0.0, type_max)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SafetyConfig {
+    /// How long a motion command stays valid without being sent again.
+    pub command_timeout: Duration,
+    /// Limits for motors without an entry in `limits`.
+    pub default_limits: Limits,
+    pub limits: BTreeMap<u32, Limits>,
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        SafetyConfig { command_timeout: DEFAULT_COMMAND_TIMEOUT, default_limits: Limits::default(), limits: BTreeMap::new() }
+    }
+}
+
+impl SafetyConfig {
+    pub fn limits_for(&self, motor_id: u32) -> Limits {
+        self.limits.get(&motor_id).copied().unwrap_or(self.default_limits)
+    }
+
+    /// Check the default and every per-motor [`Limits`].
+    pub fn validate(&self) -> Result<(), SlcanError> {
+        self.default_limits.validate()?;
+        self.limits.values().try_for_each(Limits::validate)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SafetyEvent {
+    /// A command was changed to respect the motor's limits.
+    Clamped { motor_id: u32, requested: RmdCommand, sent: RmdCommand },
+    /// No command within the timeout; stop and brake lock were sent.
+    WatchdogTripped { motor_id: u32 },
+    /// Emergency stop engaged; `motors` were stopped and braked.
+    EmergencyStop { motors: Vec<u32> },
+    /// Emergency stop cleared; commands are accepted again.
+    Released,
+    /// A command was dropped because the emergency stop is engaged.
+    Rejected { motor_id: u32, command: RmdCommand },
+    /// The motor did not confirm its CAN communication timeout (0xB3), so it will not stop
+    /// by itself if this process dies.
+    MotorTimeoutUnset { motor_id: u32 },
+}
+
+impl fmt::Display for SafetyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SafetyEvent::Clamped { motor_id, requested, sent } => write!(f, "motor {}: {:?} limited to {:?}", motor_id, requested, sent),
+            SafetyEvent::WatchdogTripped { motor_id } => write!(f, "motor {}: no command in time, stopped and braked", motor_id),
+            SafetyEvent::EmergencyStop { motors } => write!(f, "emergency stop ({} motor(s))", motors.len()),
+            SafetyEvent::Released => write!(f, "emergency stop released"),
+            SafetyEvent::Rejected { motor_id, command } => write!(f, "motor {}: {:?} rejected, e-stop active", motor_id, command),
+            SafetyEvent::MotorTimeoutUnset { motor_id } => {
+                write!(f, "motor {}: CAN timeout (0xB3) not confirmed, a crash would leave it running", motor_id)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct MotorSafety {
+    /// When the last motion command was sent; `None` once stopped (watchdog disarmed).
+    armed_at: Option<Instant>,
+    angle_deg: Option<f64>,
+    /// Motor-side timeout (ms) last sent with 0xB3.
+    motor_timeout_ms: Option<u32>,
+    /// When that 0xB3 was sent, until the motor echoes it or the confirmation times out.
+    motor_timeout_sent: Option<Instant>,
+}
+
+/// 0xB3 value matching `timeout`, rounded up to whole milliseconds.
+fn motor_timeout_ms(timeout: Duration) -> u32 {
+    timeout.as_micros().div_ceil(1000).clamp(1, u32::MAX as u128) as u32
+}
+
+#[derive(Default)]
+struct State {
+    config: SafetyConfig,
+    motors: BTreeMap<u32, MotorSafety>,
+    estopped: bool,
+    /// E-stop requested through a handle; the frames go out on the next `send`/`recv`.
+    estop_pending: bool,
+    listeners: Vec<Sender<SafetyEvent>>,
+}
+
+impl State {
+    fn emit(&mut self, event: SafetyEvent) {
+        self.listeners.retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+/// Cloneable control over a [`SafeBus`], usable from other threads.
+#[derive(Clone)]
+pub struct SafetyHandle(Arc<Mutex<State>>);
+
+impl SafetyHandle {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.0.lock().unwrap()
+    }
+
+    /// Stop and brake every known motor on the bus's next poll, and reject motion until
+    /// [`release`](Self::release).
+    pub fn estop(&self) {
+        let mut state = self.lock();
+        state.estopped = true;
+        state.estop_pending = true;
+    }
+
+    pub fn release(&self) {
+        let mut state = self.lock();
+        if state.estopped {
+            state.estopped = false;
+            state.estop_pending = false;
+            state.emit(SafetyEvent::Released);
+        }
+    }
+
+    pub fn is_estopped(&self) -> bool {
+        self.lock().estopped
+    }
+
+    /// Replace the limits of `motor_id`; invalid limits are refused and the old ones kept.
+    pub fn set_limits(&self, motor_id: u32, limits: Limits) -> Result<(), SlcanError> {
+        limits.validate()?;
+        self.lock().config.limits.insert(motor_id, limits);
+        Ok(())
+    }
+
+    pub fn set_default_limits(&self, limits: Limits) -> Result<(), SlcanError> {
+        limits.validate()?;
+        self.lock().config.default_limits = limits;
+        Ok(())
+    }
+
+    pub fn set_command_timeout(&self, timeout: Duration) {
+        self.lock().config.command_timeout = timeout;
+    }
+
+    pub fn config(&self) -> SafetyConfig {
+        self.lock().config.clone()
+    }
+
+    /// Motors seen on the bus so far, commanded or replying.
+    pub fn known_motors(&self) -> Vec<u32> {
+        self.lock().motors.keys().copied().collect()
+    }
+
+    /// New event receiver.
+    pub fn events(&self) -> Receiver<SafetyEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.lock().listeners.push(tx);
+        rx
+    }
+}
+
+/// [`CanBus`] wrapper enforcing limits, the command watchdog and the e-stop (see the module docs).
+pub struct SafeBus<B: CanBus> {
+    inner: B,
+    state: Arc<Mutex<State>>,
+}
+
+impl<B: CanBus> SafeBus<B> {
+    /// Wrap `inner`. Check a hand-built `config` with [`SafetyConfig::validate`] first; an
+    /// invalid position range is not rejected here, only clamped to the nearest 0.01° step.
+    pub fn new(inner: B, config: SafetyConfig) -> Self {
+        SafeBus { inner, state: Arc::new(Mutex::new(State { config, ..Default::default() })) }
+    }
+
+    pub fn handle(&self) -> SafetyHandle {
+        SafetyHandle(self.state.clone())
+    }
+
+    /// Engage the e-stop and send stop and brake lock to every known motor right away.
+    pub fn estop(&mut self) -> Result<(), SlcanError> {
+        self.handle().estop();
+        self.service()
+    }
+
+    pub fn inner_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    fn halt(inner: &mut B, motor_id: u32) -> Result<(), SlcanError> {
+        inner.send(&RmdCommand::Stop.to_frame(motor_id))?;
+        inner.send(&RmdCommand::BrakeLock.to_frame(motor_id))
+    }
+
+    /// Carry out a pending e-stop and trip expired watchdogs.
+    fn service(&mut self) -> Result<(), SlcanError> {
+        let mut state = self.state.lock().unwrap();
+        if state.estop_pending {
+            state.estop_pending = false;
+            let motors: Vec<u32> = state.motors.keys().copied().collect();
+            for m in state.motors.values_mut() {
+                m.armed_at = None;
+            }
+            state.emit(SafetyEvent::EmergencyStop { motors: motors.clone() });
+            for id in motors {
+                Self::halt(&mut self.inner, id)?;
+            }
+            return Ok(());
+        }
+        let timeout = state.config.command_timeout;
+        let expired: Vec<u32> = state
+            .motors
+            .iter()
+            .filter(|(_, m)| m.armed_at.is_some_and(|t| t.elapsed() >= timeout))
+            .map(|(id, _)| *id)
+            .collect();
+        let unconfirmed: Vec<u32> = state
+            .motors
+            .iter()
+            .filter(|(_, m)| m.motor_timeout_sent.is_some_and(|t| t.elapsed() >= MOTOR_TIMEOUT_CONFIRM))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in unconfirmed {
+            if let Some(m) = state.motors.get_mut(&id) {
+                m.motor_timeout_sent = None;
+            }
+            state.emit(SafetyEvent::MotorTimeoutUnset { motor_id: id });
+        }
+        for id in expired {
+            if let Some(m) = state.motors.get_mut(&id) {
+                m.armed_at = None;
+            }
+            state.emit(SafetyEvent::WatchdogTripped { motor_id: id });
+            Self::halt(&mut self.inner, id)?;
+        }
+        Ok(())
+    }
+
+    /// Program the motor-side timeout of `motor_id` if it does not match `timeout_ms` yet.
+    fn ensure_motor_timeout(&mut self, motor_id: u32, timeout_ms: u32) {
+        let mut state = self.state.lock().unwrap();
+        let motor = state.motors.entry(motor_id).or_default();
+        if motor.motor_timeout_ms == Some(timeout_ms) {
+            return;
+        }
+        motor.motor_timeout_ms = Some(timeout_ms);
+        motor.motor_timeout_sent = Some(Instant::now());
+        drop(state);
+        if self.inner.send(&RmdCommand::CanTimeout { timeout_ms }.to_frame(motor_id)).is_err() {
+            // Retried on the next motion command
+            let mut state = self.state.lock().unwrap();
+            if let Some(m) = state.motors.get_mut(&motor_id) {
+                m.motor_timeout_ms = None;
+                m.motor_timeout_sent = None;
+            }
+            state.emit(SafetyEvent::MotorTimeoutUnset { motor_id });
+        }
+    }
+}
+
+impl<B: CanBus> CanBus for SafeBus<B> {
+    fn send(&mut self, frame: &CanFrame) -> Result<(), SlcanError> {
+        self.service()?;
+        let command = match rmd::motor_id_from_tx(frame.id) {
+            Some(id) if !frame.extended => RmdCommand::decode(&frame.data).map(|cmd| (id, cmd)),
+            _ => None,
+        };
+        let Some((motor_id, cmd)) = command else {
+            return self.inner.send(frame);
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let estopped = state.estopped;
+        let limits = state.config.limits_for(motor_id);
+        let timeout_ms = motor_timeout_ms(state.config.command_timeout);
+        let motor = state.motors.entry(motor_id).or_default();
+        match cmd {
+            RmdCommand::Torque { .. } | RmdCommand::Speed { .. } | RmdCommand::Position { .. } | RmdCommand::BrakeRelease if estopped => {
+                state.emit(SafetyEvent::Rejected { motor_id, command: cmd });
+                Err(SlcanError::Protocol("emergency stop active"))
+            }
+            RmdCommand::Torque { .. } | RmdCommand::Speed { .. } | RmdCommand::Position { .. } => {
+                let sent = limits.clamp(cmd, motor.angle_deg);
+                motor.armed_at = Some(Instant::now());
+                if sent != cmd {
+                    state.emit(SafetyEvent::Clamped { motor_id, requested: cmd, sent });
+                }
+                drop(state);
+                self.ensure_motor_timeout(motor_id, timeout_ms);
+                self.inner.send(&sent.to_frame(motor_id))
+            }
+            RmdCommand::Stop | RmdCommand::BrakeLock | RmdCommand::Shutdown => {
+                motor.armed_at = None;
+                drop(state);
+                self.inner.send(frame)
+            }
+            _ => {
+                drop(state);
+                self.inner.send(frame)
+            }
+        }
+    }
+
+    fn recv(&mut self) -> Result<CanFrame, SlcanError> {
+        self.service()?;
+        let frame = self.inner.recv()?;
+        if let Some((motor_id, reply)) = RmdReply::from_frame(&frame) {
+            let mut state = self.state.lock().unwrap();
+            let motor = state.motors.entry(motor_id).or_default();
+            match reply {
+                RmdReply::MultiTurnAngle { angle_x100 } => motor.angle_deg = Some(angle_x100 as f64 / 100.0),
+                RmdReply::CanTimeout { .. } => motor.motor_timeout_sent = None,
+                _ => {}
+            }
+        }
+        Ok(frame)
+    }
+
+    fn set_filters(&mut self, filters: &[CanFilter]) -> Result<(), SlcanError> {
+        self.inner.set_filters(filters)
+    }
+
+    fn status(&mut self) -> Result<BusStatus, SlcanError> {
+        self.inner.status()
+    }
+}
+
+impl<B: CanBus> Drop for SafeBus<B> {
+    /// Motors still running under a live command are stopped and braked. This covers an
+    /// orderly shutdown only; a crash needs the motor-side timeout (see the module docs).
+    fn drop(&mut self) {
+        let armed: Vec<u32> = self.state.lock().unwrap().motors.iter().filter(|(_, m)| m.armed_at.is_some()).map(|(id, _)| *id).collect();
+        for id in armed {
+            let _ = Self::halt(&mut self.inner, id);
+        }
+    }
+}
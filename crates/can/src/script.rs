@@ -0,0 +1,245 @@
+//! Scripted command sequences for reproducible bench bring-up (`can run <script>`).
+//!
+//! One command per line; `#` at the start of a line or after a space starts a comment. Frames use candump notation
+//! (`141#9200000000000000`, see [`crate::record::parse_frame`]), durations take a
+//! `us`/`ms`/`s` suffix (`250ms`, `1.5s`).
+//!
+//! ```text
+//! print releasing brake on motor 1
+//! send 141#7700000000000000
+//! wait 50ms
+//! send 141#9200000000000000
+//! expect 241#92 within 200ms         # data is a prefix; `..` matches any byte
+//! send 141#A200000010270000 every 10ms for 2s
+//! send 141#8100000000000000
+//! ```
+//!
+//! `expect` reads the bus until a matching frame arrives, skipping everything else, and
+//! fails the run with a `TimedOut` I/O error after `within` (default 1 s). Frames that arrive
+//! during a `wait` stay queued, so a reply to an earlier `send` is still seen.
+//!
+//! ```
+//! use can::mock::{MockBus, Rule};
+//! use can::script::Script;
+//!
+//! let bus = MockBus::new();
+//! bus.add_rule(Rule::on(0x141, &[0x92]).reply(0x241, &[0x92, 0, 0, 0, 0x28, 0x23, 0, 0]));
+//! let script = Script::parse("send 141#9200000000000000\nexpect 241#92......2823 within 100ms\n").unwrap();
+//! assert_eq!(script.run(&mut bus.clone(), |_| {}).unwrap().matched, 1);
+//!
+//! let silent = Script::parse("expect 242#92 within 10ms").unwrap();
+//! assert!(silent.run(&mut bus.clone(), |_| {}).is_err());
+//! ```
+
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::bus::CanBus;
+use crate::record::parse_frame;
+use crate::{CanFrame, SlcanError};
+
+const DEFAULT_EXPECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Send `frame` once, or every `interval` until `total` has passed.
+    Send { frame: CanFrame, repeat: Option<(Duration, Duration)> },
+    Wait(Duration),
+    /// Wait up to `within` for a frame with `id` whose data starts with `data` (`None` = any byte).
+    Expect { id: u32, extended: bool, data: Vec<Option<u8>>, within: Duration },
+    Print(String),
+}
+
+/// Progress reported while a script runs; `line` is 1-based.
+#[derive(Debug, Clone)]
+pub enum ScriptEvent {
+    Sent { line: usize, frame: CanFrame, count: usize },
+    Waiting { line: usize, duration: Duration },
+    Matched { line: usize, frame: CanFrame, after: Duration },
+    Print { line: usize, text: String },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunSummary {
+    pub sent: usize,
+    pub matched: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    /// Steps with their source line numbers.
+    pub steps: Vec<(usize, Step)>,
+}
+
+fn perr(line_no: usize, msg: impl std::fmt::Display) -> SlcanError {
+    SlcanError::Parse(format!("script line {}: {}", line_no, msg))
+}
+
+/// `250ms`, `1.5s`, `500us`; `None` for anything else, including negative or out-of-range values.
+///
+/// ```
+/// use can::script::parse_duration;
+/// use std::time::Duration;
+///
+/// assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+/// assert_eq!(parse_duration("1e400s"), None);
+/// assert_eq!(parse_duration("infms"), None);
+/// ```
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let (num, scale) = if let Some(n) = s.strip_suffix("us") {
+        (n, 1e-6)
+    } else if let Some(n) = s.strip_suffix("ms") {
+        (n, 1e-3)
+    } else if let Some(n) = s.strip_suffix('s') {
+        (n, 1.0)
+    } else {
+        return None;
+    };
+    let v: f64 = num.parse().ok()?;
+    Duration::try_from_secs_f64(v * scale).ok()
+}
+
+fn parse_pattern(s: &str, line_no: usize) -> Result<(u32, bool, Vec<Option<u8>>), SlcanError> {
+    let (id_str, data) = s.split_once('#').ok_or_else(|| perr(line_no, "expected ID#DATA"))?;
+    let id = u32::from_str_radix(id_str, 16).map_err(|_| perr(line_no, "bad id"))?;
+    if !data.len().is_multiple_of(2) {
+        return Err(perr(line_no, "odd number of data digits"));
+    }
+    let bytes = data
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            b".." => Ok(None),
+            hex => u8::from_str_radix(std::str::from_utf8(hex).unwrap_or(""), 16).map(Some).map_err(|_| perr(line_no, "bad data")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((id, id_str.len() > 3, bytes))
+}
+
+fn parse_step(line: &str, line_no: usize) -> Result<Step, SlcanError> {
+    let (cmd, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    let dur = |s: &str| parse_duration(s).ok_or_else(|| perr(line_no, format!("bad duration '{}'", s)));
+    match cmd {
+        "send" => {
+            let frame = parse_frame(args.first().ok_or_else(|| perr(line_no, "send needs a frame"))?).map_err(|e| perr(line_no, e))?;
+            let repeat = match args[1..] {
+                [] => None,
+                ["every", interval, "for", total] => Some((dur(interval)?, dur(total)?)),
+                _ => return Err(perr(line_no, "expected 'send FRAME [every DUR for DUR]'")),
+            };
+            if matches!(repeat, Some((interval, _)) if interval.is_zero()) {
+                return Err(perr(line_no, "interval must be > 0"));
+            }
+            Ok(Step::Send { frame, repeat })
+        }
+        "wait" => match args[..] {
+            [d] => Ok(Step::Wait(dur(d)?)),
+            _ => Err(perr(line_no, "expected 'wait DUR'")),
+        },
+        "expect" => {
+            let (pattern, within) = match args[..] {
+                [p] => (p, DEFAULT_EXPECT_TIMEOUT),
+                [p, "within", d] => (p, dur(d)?),
+                _ => return Err(perr(line_no, "expected 'expect ID#DATA [within DUR]'")),
+            };
+            let (id, extended, data) = parse_pattern(pattern, line_no)?;
+            Ok(Step::Expect { id, extended, data, within })
+        }
+        "print" => Ok(Step::Print(rest.trim().to_string())),
+        other => Err(perr(line_no, format!("unknown command '{}'", other))),
+    }
+}
+
+fn matches(frame: &CanFrame, id: u32, extended: bool, data: &[Option<u8>]) -> bool {
+    frame.id == id
+        && frame.extended == extended
+        && frame.data.len() >= data.len()
+        && data.iter().zip(&frame.data).all(|(want, got)| want.is_none_or(|w| w == *got))
+}
+
+impl Script {
+    pub fn parse(text: &str) -> Result<Self, SlcanError> {
+        let mut steps = Vec::new();
+        for (i, raw) in text.lines().enumerate() {
+            // `#` inside a frame is part of it, so only comments that start a word are cut
+            let line = if raw.trim_start().starts_with('#') { "" } else { strip_comment(raw).trim() };
+            if !line.is_empty() {
+                steps.push((i + 1, parse_step(line, i + 1)?));
+            }
+        }
+        Ok(Script { steps })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SlcanError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Execute every step in order on `bus`, reporting progress to `on_event`.
+    /// Stops at the first failed `expect` or transport error.
+    pub fn run(&self, bus: &mut dyn CanBus, mut on_event: impl FnMut(&ScriptEvent)) -> Result<RunSummary, SlcanError> {
+        let mut summary = RunSummary::default();
+        for (line, step) in &self.steps {
+            let line = *line;
+            match step {
+                Step::Send { frame, repeat: None } => {
+                    bus.send(frame)?;
+                    summary.sent += 1;
+                    on_event(&ScriptEvent::Sent { line, frame: frame.clone(), count: 1 });
+                }
+                Step::Send { frame, repeat: Some((interval, total)) } => {
+                    let start = Instant::now();
+                    let mut count = 0;
+                    let mut next = start;
+                    while next.duration_since(start) < *total {
+                        let now = Instant::now();
+                        if next > now {
+                            std::thread::sleep(next - now);
+                        }
+                        bus.send(frame)?;
+                        count += 1;
+                        next += *interval;
+                    }
+                    summary.sent += count;
+                    on_event(&ScriptEvent::Sent { line, frame: frame.clone(), count });
+                }
+                Step::Wait(d) => {
+                    on_event(&ScriptEvent::Waiting { line, duration: *d });
+                    std::thread::sleep(*d);
+                }
+                Step::Expect { id, extended, data, within } => {
+                    let start = Instant::now();
+                    loop {
+                        match bus.recv() {
+                            Ok(f) if matches(&f, *id, *extended, data) => {
+                                summary.matched += 1;
+                                on_event(&ScriptEvent::Matched { line, frame: f, after: start.elapsed() });
+                                break;
+                            }
+                            Ok(_) => {}
+                            Err(SlcanError::Io(e)) if e.kind() == io::ErrorKind::TimedOut => {}
+                            Err(e) => return Err(e),
+                        }
+                        if start.elapsed() >= *within {
+                            return Err(SlcanError::Io(io::Error::new(
+                                io::ErrorKind::TimedOut,
+                                format!("script line {}: no {:X} reply within {:?}", line, id, within),
+                            )));
+                        }
+                    }
+                }
+                Step::Print(text) => on_event(&ScriptEvent::Print { line, text: text.clone() }),
+            }
+        }
+        Ok(summary)
+    }
+}
+
+/// Cut a trailing `# comment` (a `#` preceded by whitespace).
+fn strip_comment(line: &str) -> &str {
+    match line.find(" #").or_else(|| line.find("\t#")) {
+        Some(i) => &line[..i],
+        None => line,
+    }
+}
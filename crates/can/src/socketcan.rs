@@ -0,0 +1,278 @@
+//! Native Linux SocketCAN backend (`can0`, `vcan0`, Jetson's built-in controller).
+//!
+//! Bring the interface up before opening it, e.g.
+//! `sudo ip link set can0 type can bitrate 1000000 && sudo ip link set can0 up`
+//! or, for tests, `sudo ip link add dev vcan0 type vcan && sudo ip link set vcan0 up`.
+
+use std::ffi::CString;
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::Duration;
+
+use crate::bus::{BusStatus, CanBus, CanFilter};
+use crate::{CanFrame, SlcanError};
+
+// Error frame classes / controller bits from <linux/can/error.h>
+const CAN_ERR_LOSTARB: u32 = 0x0000_0002;
+const CAN_ERR_CRTL: u32 = 0x0000_0004;
+const CAN_ERR_PROT: u32 = 0x0000_0008;
+const CAN_ERR_TRX: u32 = 0x0000_0010;
+const CAN_ERR_ACK: u32 = 0x0000_0020;
+const CAN_ERR_BUSOFF: u32 = 0x0000_0040;
+const CAN_ERR_BUSERROR: u32 = 0x0000_0080;
+const CAN_ERR_RESTARTED: u32 = 0x0000_0100;
+/// Classes that report a bus error (protocol violation, missing ACK, transceiver fault).
+const CAN_ERR_BUS_ERRORS: u32 = CAN_ERR_PROT | CAN_ERR_TRX | CAN_ERR_ACK | CAN_ERR_BUSERROR;
+const CAN_ERR_CRTL_RX_OVERFLOW: u8 = 0x01;
+const CAN_ERR_CRTL_TX_OVERFLOW: u8 = 0x02;
+const CAN_ERR_CRTL_RX_WARNING: u8 = 0x04;
+const CAN_ERR_CRTL_TX_WARNING: u8 = 0x08;
+const CAN_ERR_CRTL_RX_PASSIVE: u8 = 0x10;
+const CAN_ERR_CRTL_TX_PASSIVE: u8 = 0x20;
+const CAN_ERR_CRTL_ACTIVE: u8 = 0x40;
+
+pub struct SocketCan {
+    fd: OwnedFd,
+    iface: String,
+    status: BusStatus,
+    fd_frames: bool,
+}
+
+impl SocketCan {
+    pub fn open(iface: &str) -> Result<Self, SlcanError> {
+        Self::open_with_timeout(iface, Duration::from_millis(50))
+    }
+
+    /// Open `iface` with a custom receive timeout (the `recv` poll granularity).
+    pub fn open_with_timeout(iface: &str, read_timeout: Duration) -> Result<Self, SlcanError> {
+        let c_iface = CString::new(iface).map_err(|_| SlcanError::Parse(format!("bad interface name: {}", iface)))?;
+        let ifindex = unsafe { libc::if_nametoindex(c_iface.as_ptr()) };
+        if ifindex == 0 {
+            return Err(SlcanError::Io(io::Error::last_os_error()));
+        }
+
+        let raw = unsafe { libc::socket(libc::PF_CAN, libc::SOCK_RAW | libc::SOCK_CLOEXEC, libc::CAN_RAW) };
+        if raw < 0 {
+            return Err(SlcanError::Io(io::Error::last_os_error()));
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+
+        let mut addr: libc::sockaddr_can = unsafe { mem::zeroed() };
+        addr.can_family = libc::AF_CAN as libc::sa_family_t;
+        addr.can_ifindex = ifindex as libc::c_int;
+        let rc = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_can as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_can>() as libc::socklen_t,
+            )
+        };
+        if rc < 0 {
+            return Err(SlcanError::Io(io::Error::last_os_error()));
+        }
+
+        let tv = libc::timeval {
+            tv_sec: read_timeout.as_secs() as libc::time_t,
+            tv_usec: read_timeout.subsec_micros() as libc::suseconds_t,
+        };
+        setsockopt(&fd, libc::SOL_SOCKET, libc::SO_RCVTIMEO, &tv)?;
+        // Deliver controller, bus-off, arbitration and bus error frames so status() has something
+        // to report. Bus errors also need `berr-reporting on` on some controllers.
+        let err_mask: libc::can_err_mask_t = CAN_ERR_LOSTARB | CAN_ERR_CRTL | CAN_ERR_BUSOFF | CAN_ERR_RESTARTED | CAN_ERR_BUS_ERRORS;
+        setsockopt(&fd, libc::SOL_CAN_RAW, libc::CAN_RAW_ERR_FILTER, &err_mask)?;
+        // Accept CAN FD frames when the kernel supports it; classic-only interfaces still work
+        let enable: libc::c_int = 1;
+        let fd_frames = setsockopt(&fd, libc::SOL_CAN_RAW, libc::CAN_RAW_FD_FRAMES, &enable).is_ok();
+
+        Ok(SocketCan { fd, iface: iface.to_string(), status: BusStatus::default(), fd_frames })
+    }
+
+    pub fn interface(&self) -> &str {
+        &self.iface
+    }
+
+    fn apply_error_frame(&mut self, id: u32, data: &[u8]) {
+        self.status.error_frames += 1;
+        if id & CAN_ERR_RESTARTED != 0 {
+            self.status = BusStatus { error_frames: self.status.error_frames, ..BusStatus::default() };
+        }
+        if id & CAN_ERR_BUSOFF != 0 {
+            self.status.bus_off = true;
+        }
+        if id & CAN_ERR_LOSTARB != 0 {
+            self.status.arbitration_lost = true;
+        }
+        if id & CAN_ERR_BUS_ERRORS != 0 {
+            self.status.bus_error = true;
+        }
+        if id & CAN_ERR_CRTL != 0 {
+            let c = data[1];
+            if c & CAN_ERR_CRTL_ACTIVE != 0 {
+                self.status.error_warning = false;
+                self.status.error_passive = false;
+            }
+            if c & (CAN_ERR_CRTL_RX_WARNING | CAN_ERR_CRTL_TX_WARNING) != 0 {
+                self.status.error_warning = true;
+            }
+            if c & (CAN_ERR_CRTL_RX_PASSIVE | CAN_ERR_CRTL_TX_PASSIVE) != 0 {
+                self.status.error_passive = true;
+            }
+            if c & (CAN_ERR_CRTL_RX_OVERFLOW | CAN_ERR_CRTL_TX_OVERFLOW) != 0 {
+                self.status.overrun = true;
+            }
+        }
+    }
+
+    fn send_fd(&mut self, frame: &CanFrame) -> Result<(), SlcanError> {
+        if !self.fd_frames {
+            return Err(SlcanError::Protocol("kernel does not support CAN FD frames"));
+        }
+        let mut raw: libc::canfd_frame = unsafe { mem::zeroed() };
+        raw.can_id = to_raw_id(frame.id, frame.extended, false);
+        let len = std::cmp::min(frame.data.len(), 64);
+        // The kernel rejects lengths that are not a valid FD DLC; pad with zeros
+        raw.len = crate::dlc_to_len(crate::len_to_dlc(len)) as u8;
+        raw.data[..len].copy_from_slice(&frame.data[..len]);
+        if frame.brs {
+            raw.flags = libc::CANFD_BRS as u8;
+        }
+        let n = unsafe {
+            libc::write(self.fd.as_raw_fd(), &raw as *const libc::canfd_frame as *const libc::c_void, libc::CANFD_MTU)
+        };
+        if n < 0 {
+            return Err(SlcanError::Io(io::Error::last_os_error()));
+        }
+        if n as usize != libc::CANFD_MTU {
+            return Err(SlcanError::Protocol("short SocketCAN write"));
+        }
+        Ok(())
+    }
+}
+
+fn setsockopt<T>(fd: &OwnedFd, level: libc::c_int, name: libc::c_int, value: &T) -> Result<(), SlcanError> {
+    let rc = unsafe {
+        libc::setsockopt(
+            fd.as_raw_fd(),
+            level,
+            name,
+            value as *const T as *const libc::c_void,
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if rc < 0 {
+        return Err(SlcanError::Io(io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+fn to_raw_id(id: u32, extended: bool, rtr: bool) -> libc::canid_t {
+    let mut raw = if extended { (id & libc::CAN_EFF_MASK) | libc::CAN_EFF_FLAG } else { id & libc::CAN_SFF_MASK };
+    if rtr {
+        raw |= libc::CAN_RTR_FLAG;
+    }
+    raw
+}
+
+impl CanBus for SocketCan {
+    fn send(&mut self, frame: &CanFrame) -> Result<(), SlcanError> {
+        if frame.fd {
+            return self.send_fd(frame);
+        }
+        let mut raw: libc::can_frame = unsafe { mem::zeroed() };
+        raw.can_id = to_raw_id(frame.id, frame.extended, frame.rtr);
+        let dlc = std::cmp::min(frame.data.len(), 8);
+        raw.can_dlc = dlc as u8;
+        if !frame.rtr {
+            raw.data[..dlc].copy_from_slice(&frame.data[..dlc]);
+        }
+        let n = unsafe {
+            libc::write(
+                self.fd.as_raw_fd(),
+                &raw as *const libc::can_frame as *const libc::c_void,
+                mem::size_of::<libc::can_frame>(),
+            )
+        };
+        if n < 0 {
+            return Err(SlcanError::Io(io::Error::last_os_error()));
+        }
+        if n as usize != mem::size_of::<libc::can_frame>() {
+            return Err(SlcanError::Protocol("short SocketCAN write"));
+        }
+        Ok(())
+    }
+
+    fn recv(&mut self) -> Result<CanFrame, SlcanError> {
+        loop {
+            // Classic frames fill the first CAN_MTU bytes; the header layout is shared with FD
+            let mut raw: libc::canfd_frame = unsafe { mem::zeroed() };
+            let n = unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    &mut raw as *mut libc::canfd_frame as *mut libc::c_void,
+                    libc::CANFD_MTU,
+                )
+            };
+            if n < 0 {
+                let e = io::Error::last_os_error();
+                // SO_RCVTIMEO expiry surfaces as EAGAIN; report it like a serial timeout
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    return Err(SlcanError::Io(io::Error::new(io::ErrorKind::TimedOut, "SocketCAN read timeout")));
+                }
+                return Err(SlcanError::Io(e));
+            }
+            let fd = match n as usize {
+                libc::CAN_MTU => false,
+                libc::CANFD_MTU => true,
+                _ => return Err(SlcanError::Parse("short SocketCAN frame".into())),
+            };
+            if raw.can_id & libc::CAN_ERR_FLAG != 0 {
+                self.apply_error_frame(raw.can_id & libc::CAN_ERR_MASK, &raw.data[..8]);
+                continue;
+            }
+            let extended = raw.can_id & libc::CAN_EFF_FLAG != 0;
+            let rtr = !fd && raw.can_id & libc::CAN_RTR_FLAG != 0;
+            let id = if extended { raw.can_id & libc::CAN_EFF_MASK } else { raw.can_id & libc::CAN_SFF_MASK };
+            let len = std::cmp::min(raw.len as usize, if fd { 64 } else { 8 });
+            let data = if rtr { Vec::new() } else { raw.data[..len].to_vec() };
+            let brs = fd && raw.flags as libc::c_int & libc::CANFD_BRS != 0;
+            return Ok(CanFrame { id, data, extended, rtr, fd, brs, timestamp: Some(crate::now_since_epoch()) });
+        }
+    }
+
+    fn set_filters(&mut self, filters: &[CanFilter]) -> Result<(), SlcanError> {
+        let raw: Vec<libc::can_filter> = if filters.is_empty() {
+            vec![libc::can_filter { can_id: 0, can_mask: 0 }]
+        } else {
+            filters
+                .iter()
+                .map(|f| libc::can_filter {
+                    can_id: to_raw_id(f.id, f.extended, false),
+                    can_mask: (if f.extended { f.mask & libc::CAN_EFF_MASK } else { f.mask & libc::CAN_SFF_MASK }) | libc::CAN_EFF_FLAG,
+                })
+                .collect()
+        };
+        let rc = unsafe {
+            libc::setsockopt(
+                self.fd.as_raw_fd(),
+                libc::SOL_CAN_RAW,
+                libc::CAN_RAW_FILTER,
+                raw.as_ptr() as *const libc::c_void,
+                (raw.len() * mem::size_of::<libc::can_filter>()) as libc::socklen_t,
+            )
+        };
+        if rc < 0 {
+            return Err(SlcanError::Io(io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    /// Error state from the error frames received so far. Like SLCAN's `F`, reading clears
+    /// the one-off events (bus error, arbitration lost); the controller state flags persist.
+    fn status(&mut self) -> Result<BusStatus, SlcanError> {
+        let status = self.status;
+        self.status.bus_error = false;
+        self.status.arbitration_lost = false;
+        Ok(status)
+    }
+}
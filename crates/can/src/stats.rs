@@ -0,0 +1,408 @@
+//! Rolling bus statistics from the frame stream: load, per-ID rates, jitter, gaps, errors.
+//!
+//! [`BusStats`] is fed every frame seen on the bus (received and sent) plus, when available,
+//! the adapter's [`BusStatus`]. It keeps a sliding window (1 s by default) and reports:
+//! - bus load: time on the wire over the window. Classic frames are counted bit-exactly,
+//!   including stuff bits (computed over the real CRC) and the 3-bit interframe space;
+//!   CAN FD frames use a worst-case stuffing estimate and the data bitrate for BRS frames;
+//! - per ID: frames/s in the window, interval mean and jitter (standard deviation);
+//! - missing periodic messages: an ID is periodic once its intervals are regular (or after
+//!   [`BusStats::expect_period`]); a gap of more than 1.5 periods counts the skipped frames,
+//!   and an ID silent for more than 2 periods is overdue;
+//! - error counts: rising edges of the warning/passive/overrun/arbitration-lost/bus-error/
+//!   bus-off flags seen by status polls, plus the error frames SocketCAN delivers
+//!   ([`BusStatus::error_frames`]). SLCAN adapters only report the flags.
+//!
+//! Times are durations since the UNIX epoch, like [`CanFrame::timestamp`], so adapter
+//! timestamps give jitter without host scheduling noise.
+//!
+//! ```
+//! use std::time::Duration;
+//! use can::stats::{frame_bits, BusStats};
+//! use can::{Bitrate, CanFrame};
+//!
+//! // 8 data bytes: 111 bits including the interframe space, plus stuff bits
+//! let frame = CanFrame { id: 0x141, data: vec![0x55; 8], extended: false, rtr: false, fd: false, brs: false, timestamp: None };
+//! assert_eq!(frame_bits(&frame).nominal, 111 + frame_bits(&frame).stuff);
+//! let zeros = CanFrame { id: 0, data: vec![], ..frame.clone() };
+//! assert_eq!(frame_bits(&zeros), can::stats::FrameBits { nominal: 34 + 6 + 13, data: 0, stuff: 6 });
+//!
+//! let mut stats = BusStats::new(Bitrate::B1M);
+//! for i in 0..100u64 {
+//!     if i == 50 { continue; } // one frame lost
+//!     stats.record_at(&frame, Duration::from_millis(10 * i));
+//! }
+//! let snap = stats.snapshot(Duration::from_millis(995));
+//! let id = &snap.ids[0];
+//! assert_eq!(id.period, Some(Duration::from_millis(10)));
+//! assert_eq!(id.missing, 1);
+//! assert!((id.rate_hz - 99.0).abs() < 1.5);
+//! assert!(snap.load > 0.01 && snap.load < 0.02); // ~111 µs every 10 ms
+//! assert!(stats.snapshot(Duration::from_millis(1100)).ids[0].overdue);
+//!
+//! // Error frames are counted as they arrive, flags once per episode
+//! stats.record_status(&can::BusStatus { error_frames: 3, ..Default::default() });
+//! stats.record_status(&can::BusStatus { error_frames: 5, error_warning: true, ..Default::default() });
+//! assert_eq!((stats.errors().frames, stats.errors().warning), (5, 1));
+//! // Reopened transport: its counter starts again from 0
+//! stats.record_status(&can::BusStatus { error_frames: 2, ..Default::default() });
+//! assert_eq!(stats.errors().frames, 7);
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+use crate::bus::BusStatus;
+use crate::{Bitrate, CanFrame, DataBitrate};
+
+/// Intervals kept per ID for the period and jitter estimates.
+const INTERVALS: usize = 64;
+/// Intervals needed before an ID can be classed as periodic.
+const MIN_INTERVALS: usize = 8;
+/// Largest jitter/period ratio of a periodic ID.
+const MAX_JITTER_RATIO: f64 = 0.25;
+
+/// Bits of one frame on the wire: `nominal` at the arbitration bitrate, `data` at the FD
+/// data bitrate (BRS frames only). `stuff` is the part of `nominal + data` that is stuffing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameBits {
+    pub nominal: u32,
+    pub data: u32,
+    pub stuff: u32,
+}
+
+fn push_bits(bits: &mut Vec<bool>, value: u32, n: u32) {
+    bits.extend((0..n).rev().map(|i| (value >> i) & 1 == 1));
+}
+
+fn crc15(bits: &[bool]) -> u32 {
+    bits.iter().fold(0u32, |crc, &bit| {
+        let next = bit ^ ((crc >> 14) & 1 == 1);
+        let crc = (crc << 1) & 0x7FFF;
+        if next { crc ^ 0x4599 } else { crc }
+    })
+}
+
+/// Stuff bits inserted into `bits`: one after every run of five equal bits.
+fn stuff_count(bits: &[bool]) -> u32 {
+    let (mut count, mut run, mut last) = (0, 0, None);
+    for &bit in bits {
+        if Some(bit) == last {
+            run += 1;
+        } else {
+            (run, last) = (1, Some(bit));
+        }
+        if run == 5 {
+            // The stuff bit is the opposite level and starts the next run
+            count += 1;
+            (run, last) = (1, Some(!bit));
+        }
+    }
+    count
+}
+
+/// Bits `frame` occupies on the bus, including the 3-bit interframe space.
+pub fn frame_bits(frame: &CanFrame) -> FrameBits {
+    // CRC delimiter, ACK slot, ACK delimiter, end of frame, interframe space
+    const TRAILER: u32 = 1 + 1 + 1 + 7 + 3;
+    if frame.fd {
+        let len = crate::dlc_to_len(crate::len_to_dlc(frame.data.len())) as u32;
+        // SOF, ID, (SRR, IDE, ID ext,) RRS, IDE/FDF, res, BRS
+        let arbitration = if frame.extended { 1 + 11 + 1 + 1 + 18 + 1 + 1 + 1 + 1 } else { 1 + 11 + 1 + 1 + 1 + 1 + 1 };
+        let crc = if len > 16 { 21 } else { 17 };
+        // ESI, DLC, data, stuff count, CRC with its fixed stuff bits, CRC delimiter
+        let payload = 1 + 4 + 8 * len + 4 + crc + crc / 4 + 1;
+        // Worst case dynamic stuffing, split at the bit-rate switch
+        let (arb_stuff, data_stuff) = ((arbitration - 1) / 4, (1 + 4 + 8 * len) / 4);
+        let stuff = arb_stuff + data_stuff;
+        // The CRC delimiter is part of `payload`
+        let trailer = TRAILER - 1;
+        return if frame.brs {
+            FrameBits { nominal: arbitration + arb_stuff + trailer, data: payload + data_stuff, stuff }
+        } else {
+            FrameBits { nominal: arbitration + payload + stuff + trailer, data: 0, stuff }
+        };
+    }
+
+    let len = if frame.rtr { 0 } else { frame.data.len().min(8) };
+    let mut bits = Vec::with_capacity(128);
+    bits.push(false); // SOF
+    if frame.extended {
+        push_bits(&mut bits, frame.id >> 18, 11);
+        bits.extend([true, true]); // SRR, IDE
+        push_bits(&mut bits, frame.id & 0x3FFFF, 18);
+        bits.extend([frame.rtr, false, false]); // RTR, r1, r0
+    } else {
+        push_bits(&mut bits, frame.id, 11);
+        bits.extend([frame.rtr, false, false]); // RTR, IDE, r0
+    }
+    push_bits(&mut bits, frame.data.len().min(8) as u32, 4);
+    for &byte in &frame.data[..len] {
+        push_bits(&mut bits, byte as u32, 8);
+    }
+    let crc = crc15(&bits);
+    push_bits(&mut bits, crc, 15);
+    let stuff = stuff_count(&bits);
+    FrameBits { nominal: bits.len() as u32 + stuff + TRAILER, data: 0, stuff }
+}
+
+/// Error flag episodes: how often each [`BusStatus`] flag went from clear to set, and the
+/// error frames received.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ErrorCounts {
+    pub warning: u64,
+    pub passive: u64,
+    pub overrun: u64,
+    pub arbitration_lost: u64,
+    pub bus_error: u64,
+    pub bus_off: u64,
+    /// Error frames (SocketCAN only).
+    pub frames: u64,
+}
+
+impl ErrorCounts {
+    pub fn total(&self) -> u64 {
+        self.warning + self.passive + self.overrun + self.arbitration_lost + self.bus_error + self.bus_off + self.frames
+    }
+}
+
+impl std::fmt::Display for ErrorCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "warning {}  passive {}  overrun {}  arb_lost {}  bus_error {}  bus_off {}  frames {}",
+            self.warning, self.passive, self.overrun, self.arbitration_lost, self.bus_error, self.bus_off, self.frames
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct IdState {
+    count: u64,
+    /// Frame times within the window.
+    recent: VecDeque<Duration>,
+    intervals: VecDeque<f64>,
+    last: Option<Duration>,
+    expected: Option<Duration>,
+    missing: u64,
+}
+
+impl IdState {
+    /// Declared period, else the median interval once the intervals are regular.
+    fn period(&self) -> Option<f64> {
+        if let Some(p) = self.expected {
+            return Some(p.as_secs_f64());
+        }
+        if self.intervals.len() < MIN_INTERVALS {
+            return None;
+        }
+        let mut sorted: Vec<f64> = self.intervals.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let median = sorted[sorted.len() / 2];
+        // Spread of the intervals around the median, ignoring the gaps counted as missing
+        let regular: Vec<f64> = sorted.iter().copied().filter(|i| *i <= 1.5 * median).collect();
+        let mad = regular.iter().map(|i| (i - median).abs()).sum::<f64>() / regular.len() as f64;
+        (median > 0.0 && mad <= MAX_JITTER_RATIO * median).then_some(median)
+    }
+
+    fn jitter(&self) -> Option<f64> {
+        if self.intervals.len() < 2 {
+            return None;
+        }
+        let n = self.intervals.len() as f64;
+        let mean = self.intervals.iter().sum::<f64>() / n;
+        Some((self.intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / n).sqrt())
+    }
+}
+
+/// Statistics of one CAN ID at the time of a [`BusStats::snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdStats {
+    pub id: u32,
+    pub extended: bool,
+    /// Frames since the statistics were started or reset.
+    pub count: u64,
+    /// Frames per second over the window.
+    pub rate_hz: f64,
+    pub mean_interval: Option<Duration>,
+    /// Standard deviation of the recent intervals.
+    pub jitter: Option<Duration>,
+    /// Declared or detected period; `None` for irregular (e.g. request/response) traffic.
+    pub period: Option<Duration>,
+    /// Frames skipped by periodic IDs, judged from the gaps between arrivals.
+    pub missing: u64,
+    /// Periodic ID silent for more than two periods.
+    pub overdue: bool,
+    pub since_last: Duration,
+}
+
+/// Bus-wide statistics at one instant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// Fraction of the window the bus was busy (0.0–1.0, can exceed 1.0 if frames were
+    /// recorded faster than the configured bitrate allows).
+    pub load: f64,
+    pub frames_per_sec: f64,
+    pub total_frames: u64,
+    pub errors: ErrorCounts,
+    /// Sorted by ID.
+    pub ids: Vec<IdStats>,
+}
+
+pub struct BusStats {
+    bitrate_bps: f64,
+    data_bitrate_bps: f64,
+    window: Duration,
+    started: Option<Duration>,
+    /// (time, seconds on the wire) of every frame within the window.
+    frames: VecDeque<(Duration, f64)>,
+    total: u64,
+    ids: BTreeMap<(u32, bool), IdState>,
+    errors: ErrorCounts,
+    status: BusStatus,
+}
+
+impl BusStats {
+    pub fn new(bitrate: Bitrate) -> Self {
+        let bps = bitrate.bps() as f64;
+        BusStats {
+            bitrate_bps: bps,
+            data_bitrate_bps: bps,
+            window: Duration::from_secs(1),
+            started: None,
+            frames: VecDeque::new(),
+            total: 0,
+            ids: BTreeMap::new(),
+            errors: ErrorCounts::default(),
+            status: BusStatus::default(),
+        }
+    }
+
+    /// Data-phase bitrate of CAN FD frames with BRS.
+    pub fn with_data_bitrate(mut self, data_bitrate: DataBitrate) -> Self {
+        self.data_bitrate_bps = data_bitrate.bps() as f64;
+        self
+    }
+
+    /// Length of the sliding window for load and rates (default 1 s).
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window.max(Duration::from_millis(10));
+    }
+
+    /// Declare `id` as sent every `period`, instead of waiting for it to look periodic.
+    pub fn expect_period(&mut self, id: u32, extended: bool, period: Duration) {
+        self.ids.entry((id, extended)).or_default().expected = Some(period);
+    }
+
+    /// Forget everything except bitrates, window and declared periods.
+    pub fn reset(&mut self) {
+        self.started = None;
+        self.frames.clear();
+        self.total = 0;
+        self.errors = ErrorCounts::default();
+        self.status = BusStatus::default();
+        for state in self.ids.values_mut() {
+            *state = IdState { expected: state.expected, ..Default::default() };
+        }
+        self.ids.retain(|_, s| s.expected.is_some());
+    }
+
+    /// Record a frame at its timestamp, or now for frames without one (e.g. sent frames).
+    pub fn record(&mut self, frame: &CanFrame) {
+        self.record_at(frame, frame.timestamp.unwrap_or_else(crate::now_since_epoch));
+    }
+
+    pub fn record_at(&mut self, frame: &CanFrame, at: Duration) {
+        let bits = frame_bits(frame);
+        let wire = bits.nominal as f64 / self.bitrate_bps + bits.data as f64 / self.data_bitrate_bps;
+        self.started.get_or_insert(at);
+        self.frames.push_back((at, wire));
+        self.total += 1;
+
+        let state = self.ids.entry((frame.id, frame.extended)).or_default();
+        state.count += 1;
+        state.recent.push_back(at);
+        if let Some(interval) = state.last.and_then(|last| at.checked_sub(last)) {
+            let interval = interval.as_secs_f64();
+            if let Some(period) = state.period() {
+                if interval > 1.5 * period {
+                    state.missing += ((interval / period).round() as u64).saturating_sub(1);
+                }
+            }
+            state.intervals.push_back(interval);
+            if state.intervals.len() > INTERVALS {
+                state.intervals.pop_front();
+            }
+        }
+        state.last = Some(state.last.map_or(at, |last| last.max(at)));
+        self.expire(at);
+    }
+
+    /// Count error flags that were clear at the previous status and are set now, and the
+    /// error frames since the previous status. A count lower than before means the transport
+    /// was reopened and started again from 0, so all of it is new.
+    pub fn record_status(&mut self, status: &BusStatus) {
+        let prev = self.status;
+        let rising = |now: bool, before: bool| u64::from(now && !before);
+        self.errors.warning += rising(status.error_warning, prev.error_warning);
+        self.errors.passive += rising(status.error_passive, prev.error_passive);
+        self.errors.overrun += rising(status.overrun, prev.overrun);
+        self.errors.arbitration_lost += rising(status.arbitration_lost, prev.arbitration_lost);
+        self.errors.bus_error += rising(status.bus_error, prev.bus_error);
+        self.errors.bus_off += rising(status.bus_off, prev.bus_off);
+        self.errors.frames += status.error_frames.checked_sub(prev.error_frames).unwrap_or(status.error_frames);
+        self.status = *status;
+    }
+
+    pub fn errors(&self) -> ErrorCounts {
+        self.errors
+    }
+
+    fn expire(&mut self, now: Duration) {
+        let cutoff = now.saturating_sub(self.window);
+        while self.frames.front().is_some_and(|(t, _)| *t < cutoff) {
+            self.frames.pop_front();
+        }
+        for state in self.ids.values_mut() {
+            while state.recent.front().is_some_and(|t| *t < cutoff) {
+                state.recent.pop_front();
+            }
+        }
+    }
+
+    /// Statistics over the window ending at `now` (a time on the same clock as the frames).
+    pub fn snapshot(&self, now: Duration) -> Snapshot {
+        let cutoff = now.saturating_sub(self.window);
+        // Shorter than the window right after the first frame
+        let span = self.started.map_or(0.0, |s| now.saturating_sub(s).min(self.window).as_secs_f64()).max(1e-3);
+        let (busy, count) = self.frames.iter().filter(|(t, _)| *t >= cutoff).fold((0.0, 0), |(b, n), (_, w)| (b + w, n + 1));
+        let ids = self
+            .ids
+            .iter()
+            .filter(|(_, s)| s.count > 0)
+            .map(|(&(id, extended), s)| {
+                let period = s.period();
+                let since_last = s.last.map_or(Duration::ZERO, |l| now.saturating_sub(l));
+                let mean = (!s.intervals.is_empty()).then(|| s.intervals.iter().sum::<f64>() / s.intervals.len() as f64);
+                IdStats {
+                    id,
+                    extended,
+                    count: s.count,
+                    rate_hz: s.recent.iter().filter(|t| **t >= cutoff).count() as f64 / span,
+                    mean_interval: mean.map(Duration::from_secs_f64),
+                    jitter: s.jitter().map(Duration::from_secs_f64),
+                    period: period.map(Duration::from_secs_f64),
+                    missing: s.missing,
+                    overdue: period.is_some_and(|p| since_last.as_secs_f64() > 2.0 * p),
+                    since_last,
+                }
+            })
+            .collect();
+        Snapshot { load: busy / span, frames_per_sec: count as f64 / span, total_frames: self.total, errors: self.errors, ids }
+    }
+
+    /// [`BusStats::snapshot`] at the current host time.
+    pub fn snapshot_now(&self) -> Snapshot {
+        self.snapshot(crate::now_since_epoch())
+    }
+}
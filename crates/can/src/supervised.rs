@@ -0,0 +1,344 @@
+//! Self-healing SLCAN connection.
+//!
+//! [`SupervisedSlcan`] wraps an [`Slcan`] and keeps it alive:
+//! - a serial error (adapter unplugged, hub reset) drops the port and retries with backoff,
+//!   finding the adapter again by USB VID/PID/serial number, since `/dev/ttyACM0` may come
+//!   back as `/dev/ttyACM1`;
+//! - bus-off restarts the channel (`C`, `Sx`, `M`/`m`, `O`) with the same acceptance filter.
+//!   SLCAN has no bus-off flag, so it is assumed when the periodic `F` poll reports error
+//!   passive and the adapter refused a transmit, on two polls in a row. Repeated restarts
+//!   back off from 2 s to 30 s until a poll finds the bus out of error passive.
+//!
+//! Every transition is published as a [`ConnectionEvent`] to the receivers returned by
+//! [`SupervisedSlcan::events`], so UIs can show the link state instead of stale data.
+//! While disconnected, `recv` reports timeouts (a quiet bus) and `send` fails.
+
+use std::fmt;
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serialport::SerialPortType;
+
+use crate::bus::{BusStatus, CanBus, CanFilter};
+use crate::{Bitrate, CanFrame, DataBitrate, Slcan, SlcanError};
+
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+const BACKOFF_MIN: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(5);
+/// Consecutive status polls that must look like bus-off before the channel is restarted.
+const BUS_OFF_POLLS: u32 = 2;
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// Port opened and channel initialised (initially and after every reconnect).
+    Connected { port: String },
+    /// Serial I/O failed; the port was closed and reconnection begins.
+    Disconnected { reason: String },
+    /// A reconnect attempt failed; another follows after `retry_in`.
+    ReconnectFailed { attempt: u32, error: String, retry_in: Duration },
+    /// The controller reported bus-off; the channel is being restarted.
+    BusOff,
+    /// The channel was restarted after bus-off.
+    BusRecovered,
+}
+
+impl fmt::Display for ConnectionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionEvent::Connected { port } => write!(f, "connected to {}", port),
+            ConnectionEvent::Disconnected { reason } => write!(f, "disconnected: {}", reason),
+            ConnectionEvent::ReconnectFailed { attempt, error, retry_in } => {
+                write!(f, "reconnect #{} failed: {} (retry in {:.1}s)", attempt, error, retry_in.as_secs_f32())
+            }
+            ConnectionEvent::BusOff => write!(f, "bus-off, restarting channel"),
+            ConnectionEvent::BusRecovered => write!(f, "bus recovered"),
+        }
+    }
+}
+
+/// USB identity of an adapter, used to find it again after re-enumeration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdapterId {
+    pub vid: u16,
+    pub pid: u16,
+    pub serial_number: Option<String>,
+}
+
+impl AdapterId {
+    /// Identity of the USB device behind `port`, if it is one.
+    pub fn of_port(port: &str) -> Option<Self> {
+        serialport::available_ports().ok()?.into_iter().find(|p| p.port_name == port).and_then(|p| match p.port_type {
+            SerialPortType::UsbPort(info) => Some(AdapterId { vid: info.vid, pid: info.pid, serial_number: info.serial_number }),
+            _ => None,
+        })
+    }
+
+    /// Current port name of this adapter. Without a serial number, the first port with the
+    /// same VID/PID wins.
+    pub fn find_port(&self) -> Option<String> {
+        serialport::available_ports().ok()?.into_iter().find_map(|p| match p.port_type {
+            SerialPortType::UsbPort(info)
+                if info.vid == self.vid
+                    && info.pid == self.pid
+                    && (self.serial_number.is_none() || info.serial_number == self.serial_number) =>
+            {
+                Some(p.port_name)
+            }
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkState {
+    Connected,
+    Reconnecting,
+}
+
+#[derive(Clone, Default)]
+struct Listeners(Arc<Mutex<Vec<Sender<ConnectionEvent>>>>);
+
+impl Listeners {
+    fn emit(&self, event: ConnectionEvent) {
+        self.0.lock().unwrap().retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+pub struct SupervisedSlcan {
+    inner: Option<Slcan>,
+    port: String,
+    adapter: Option<AdapterId>,
+    bitrate: Bitrate,
+    data_bitrate: Option<DataBitrate>,
+    serial_baud: u32,
+    filters: Vec<CanFilter>,
+    listeners: Listeners,
+    attempt: u32,
+    next_attempt: Instant,
+    next_status: Instant,
+    /// A send was refused by the adapter since the last status poll.
+    tx_failed: bool,
+    /// Consecutive status polls that looked like bus-off.
+    bus_off_polls: u32,
+    /// Channel restarts since the bus was last seen healthy, and when the next may happen.
+    restarts: u32,
+    next_restart: Instant,
+}
+
+impl SupervisedSlcan {
+    /// Open `port` like [`Slcan::open_with_baud`]; fails if the first open fails.
+    pub fn open(port: &str, bitrate: Bitrate, serial_baud: u32) -> Result<Self, SlcanError> {
+        Self::open_inner(port, bitrate, None, serial_baud)
+    }
+
+    /// CAN FD variant, see [`Slcan::open_fd`].
+    pub fn open_fd(port: &str, bitrate: Bitrate, data_bitrate: DataBitrate, serial_baud: u32) -> Result<Self, SlcanError> {
+        Self::open_inner(port, bitrate, Some(data_bitrate), serial_baud)
+    }
+
+    fn open_inner(port: &str, bitrate: Bitrate, data_bitrate: Option<DataBitrate>, serial_baud: u32) -> Result<Self, SlcanError> {
+        let inner = Self::connect(port, bitrate, data_bitrate, serial_baud)?;
+        let now = Instant::now();
+        Ok(SupervisedSlcan {
+            inner: Some(inner),
+            port: port.to_string(),
+            adapter: AdapterId::of_port(port),
+            bitrate,
+            data_bitrate,
+            serial_baud,
+            filters: Vec::new(),
+            listeners: Listeners::default(),
+            attempt: 0,
+            next_attempt: now,
+            next_status: now + STATUS_INTERVAL,
+            tx_failed: false,
+            bus_off_polls: 0,
+            restarts: 0,
+            next_restart: now,
+        })
+    }
+
+    fn connect(port: &str, bitrate: Bitrate, data_bitrate: Option<DataBitrate>, serial_baud: u32) -> Result<Slcan, SlcanError> {
+        match data_bitrate {
+            Some(db) => Slcan::open_fd(port, bitrate, db, serial_baud),
+            None => Slcan::open_with_baud(port, bitrate, serial_baud),
+        }
+    }
+
+    /// New event receiver. It starts with the current state (`Connected` when the port is up).
+    pub fn events(&self) -> Receiver<ConnectionEvent> {
+        let (tx, rx) = mpsc::channel();
+        if self.inner.is_some() {
+            let _ = tx.send(ConnectionEvent::Connected { port: self.port.clone() });
+        }
+        self.listeners.0.lock().unwrap().push(tx);
+        rx
+    }
+
+    pub fn state(&self) -> LinkState {
+        if self.inner.is_some() { LinkState::Connected } else { LinkState::Reconnecting }
+    }
+
+    /// Port currently (or last) in use; changes if the adapter re-enumerates under a new name.
+    pub fn port(&self) -> &str {
+        &self.port
+    }
+
+    pub fn adapter(&self) -> Option<&AdapterId> {
+        self.adapter.as_ref()
+    }
+
+    fn disconnect(&mut self, reason: String) {
+        if self.inner.take().is_some() {
+            self.attempt = 0;
+            self.next_attempt = Instant::now();
+            self.listeners.emit(ConnectionEvent::Disconnected { reason });
+        }
+    }
+
+    /// Try to reopen the adapter if a retry is due. Returns true when connected.
+    fn ensure_connected(&mut self) -> bool {
+        if self.inner.is_some() {
+            return true;
+        }
+        if Instant::now() < self.next_attempt {
+            return false;
+        }
+        self.attempt += 1;
+        let port = self.adapter.as_ref().and_then(|a| a.find_port()).unwrap_or_else(|| self.port.clone());
+        match Self::connect(&port, self.bitrate, self.data_bitrate, self.serial_baud) {
+            Ok(mut slcan) => {
+                if !self.filters.is_empty() {
+                    let _ = CanBus::set_filters(&mut slcan, &self.filters);
+                }
+                self.inner = Some(slcan);
+                self.port = port.clone();
+                self.attempt = 0;
+                self.next_status = Instant::now() + STATUS_INTERVAL;
+                self.listeners.emit(ConnectionEvent::Connected { port });
+                true
+            }
+            Err(e) => {
+                let retry_in = BACKOFF_MIN.saturating_mul(1 << self.attempt.min(4)).min(BACKOFF_MAX);
+                self.next_attempt = Instant::now() + retry_in;
+                self.listeners.emit(ConnectionEvent::ReconnectFailed { attempt: self.attempt, error: e.to_string(), retry_in });
+                false
+            }
+        }
+    }
+
+    /// Poll `F` once per interval and restart the channel on bus-off (see the module docs).
+    fn check_bus(&mut self) {
+        let now = Instant::now();
+        if now < self.next_status {
+            return;
+        }
+        self.next_status = now + STATUS_INTERVAL;
+        let Some(slcan) = self.inner.as_mut() else { return };
+        let st = match slcan.status() {
+            Ok(st) => st,
+            Err(e) if is_link_error(&e) => return self.disconnect(e.to_string()),
+            Err(_) => return,
+        };
+        let tx_failed = std::mem::take(&mut self.tx_failed);
+        if !(st.bus_off || (st.error_passive && tx_failed)) {
+            self.bus_off_polls = 0;
+            if !st.error_passive {
+                self.restarts = 0;
+            }
+            return;
+        }
+        self.bus_off_polls += 1;
+        if (!st.bus_off && self.bus_off_polls < BUS_OFF_POLLS) || now < self.next_restart {
+            return;
+        }
+        self.bus_off_polls = 0;
+        self.restarts += 1;
+        self.next_restart = now + STATUS_INTERVAL.saturating_mul(1 << self.restarts.min(5)).min(RESTART_BACKOFF_MAX);
+        self.listeners.emit(ConnectionEvent::BusOff);
+        let Some(slcan) = self.inner.as_mut() else { return };
+        match slcan.restart() {
+            Ok(()) => self.listeners.emit(ConnectionEvent::BusRecovered),
+            Err(e) if is_link_error(&e) => self.disconnect(e.to_string()),
+            Err(_) => {}
+        }
+    }
+}
+
+/// Errors that mean the serial link itself is gone (as opposed to a quiet bus or a bad line).
+fn is_link_error(e: &SlcanError) -> bool {
+    match e {
+        SlcanError::Io(io) => io.kind() != io::ErrorKind::TimedOut,
+        SlcanError::Serial(_) => true,
+        _ => false,
+    }
+}
+
+fn timed_out() -> SlcanError {
+    SlcanError::Io(io::Error::new(io::ErrorKind::TimedOut, "adapter reconnecting"))
+}
+
+impl CanBus for SupervisedSlcan {
+    fn send(&mut self, frame: &CanFrame) -> Result<(), SlcanError> {
+        if !self.ensure_connected() {
+            return Err(SlcanError::Protocol("adapter disconnected"));
+        }
+        let result = self.inner.as_mut().map_or(Ok(()), |s| s.send(frame));
+        match &result {
+            Err(e) if is_link_error(e) => self.disconnect(e.to_string()),
+            Err(_) => self.tx_failed = true,
+            Ok(()) => {}
+        }
+        result
+    }
+
+    fn recv(&mut self) -> Result<CanFrame, SlcanError> {
+        if !self.ensure_connected() {
+            // Keep the `recv` pacing of a connected port so callers do not spin
+            std::thread::sleep(Duration::from_millis(50));
+            return Err(timed_out());
+        }
+        self.check_bus();
+        let Some(slcan) = self.inner.as_mut() else { return Err(timed_out()) };
+        match CanBus::recv(slcan) {
+            Err(e) if is_link_error(&e) => {
+                self.disconnect(e.to_string());
+                Err(e)
+            }
+            other => other,
+        }
+    }
+
+    fn set_filters(&mut self, filters: &[CanFilter]) -> Result<(), SlcanError> {
+        self.filters = filters.to_vec();
+        match self.inner.as_mut() {
+            Some(slcan) => CanBus::set_filters(slcan, filters),
+            None => Ok(()), // applied on reconnect
+        }
+    }
+
+    fn status(&mut self) -> Result<BusStatus, SlcanError> {
+        match self.inner.as_mut() {
+            Some(slcan) => slcan.status(),
+            None => Err(SlcanError::Protocol("adapter disconnected")),
+        }
+    }
+}
+
+/// Like [`crate::open_bus`], but SLCAN ports are wrapped in a [`SupervisedSlcan`]. The
+/// receiver yields connection events; for other backends it only reports the initial
+/// `Connected`.
+pub fn open_bus(name: &str, bitrate: Bitrate, serial_baud: u32) -> Result<(Box<dyn CanBus>, Receiver<ConnectionEvent>), SlcanError> {
+    if crate::bus::is_mock(name) || crate::bus::is_socketcan_interface(name) {
+        let bus = crate::open_bus(name, bitrate, serial_baud)?;
+        let (tx, rx) = mpsc::channel();
+        let _ = tx.send(ConnectionEvent::Connected { port: name.to_string() });
+        return Ok((bus, rx));
+    }
+    let bus = SupervisedSlcan::open(name, bitrate, serial_baud)?;
+    let events = bus.events();
+    Ok((Box::new(bus), events))
+}
@@ -0,0 +1,281 @@
+//! Background-thread bus owner with multi-consumer frame broadcast.
+//!
+//! `BusThread` takes ownership of any [`CanBus`] (usually an `Slcan`) and runs the
+//! blocking read loop on its own thread. Received frames, and frames sent through the
+//! thread, are fanned out to every subscriber, so the UI, a logger and a bridge can all
+//! watch the same bus without sharing a lock. Sends are queued and written between reads,
+//! so on a quiet bus they go out within one read timeout (50 ms for `Slcan`).
+//!
+//! Subscribers come in two flavours:
+//! - [`BusThread::subscribe`]: a bounded `std::sync::mpsc` receiver (sync code, Bevy systems)
+//! - [`BusThread::subscribe_async`]: a `tokio::sync::broadcast` receiver (feature `tokio`)
+//!
+//! [`BusThread::client`] wraps a subscription in a [`CanBus`] implementation so existing
+//! code written against `CanBus` keeps working unchanged.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::bus::{filters_accept, BusStatus, CanBus, CanFilter};
+use crate::record::Direction;
+use crate::{CanFrame, SlcanError};
+
+/// Per-subscriber queue depth; frames are dropped for subscribers that fall this far behind.
+const SUBSCRIBER_DEPTH: usize = 4096;
+
+/// A frame seen on the bus, tagged with whether it was received or sent by us.
+#[derive(Debug, Clone)]
+pub struct Traffic {
+    pub direction: Direction,
+    pub frame: CanFrame,
+}
+
+enum Command {
+    Send(CanFrame),
+    SetFilters(Vec<CanFilter>),
+    Status(Sender<Result<BusStatus, SlcanError>>),
+}
+
+#[derive(Default)]
+struct Shared {
+    subscribers: Mutex<Vec<SyncSender<Traffic>>>,
+    #[cfg(feature = "tokio")]
+    broadcast: Mutex<Option<tokio::sync::broadcast::Sender<Traffic>>>,
+    stop: AtomicBool,
+    dropped: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Shared {
+    fn publish(&self, traffic: Traffic) {
+        #[cfg(feature = "tokio")]
+        if let Some(tx) = self.broadcast.lock().unwrap().as_ref() {
+            let _ = tx.send(traffic.clone());
+        }
+        let mut subs = self.subscribers.lock().unwrap();
+        subs.retain(|s| match s.try_send(traffic.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+pub struct BusThread {
+    commands: Sender<Command>,
+    shared: Arc<Shared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl BusThread {
+    /// Move `bus` onto a background thread and start reading.
+    pub fn spawn(bus: Box<dyn CanBus>) -> Self {
+        let (commands, rx) = mpsc::channel();
+        let shared = Arc::new(Shared::default());
+        let thread_shared = shared.clone();
+        let handle = std::thread::Builder::new()
+            .name("can-bus".into())
+            .spawn(move || run(bus, rx, thread_shared))
+            .expect("spawn can-bus thread");
+        BusThread { commands, shared, handle: Some(handle) }
+    }
+
+    /// Open `name` like [`crate::open_bus`] and move it onto a background thread.
+    pub fn open(name: &str, bitrate: crate::Bitrate, serial_baud: u32) -> Result<Self, SlcanError> {
+        Ok(Self::spawn(crate::open_bus(name, bitrate, serial_baud)?))
+    }
+
+    /// New sync subscriber; sees every frame from now on.
+    pub fn subscribe(&self) -> Receiver<Traffic> {
+        let (tx, rx) = mpsc::sync_channel(SUBSCRIBER_DEPTH);
+        self.shared.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// New tokio subscriber; lagging receivers get `RecvError::Lagged` as usual.
+    #[cfg(feature = "tokio")]
+    pub fn subscribe_async(&self) -> tokio::sync::broadcast::Receiver<Traffic> {
+        let mut guard = self.shared.broadcast.lock().unwrap();
+        match guard.as_ref() {
+            Some(tx) => tx.subscribe(),
+            None => {
+                let (tx, rx) = tokio::sync::broadcast::channel(SUBSCRIBER_DEPTH);
+                *guard = Some(tx);
+                rx
+            }
+        }
+    }
+
+    /// Queue `frame` for transmission. Fails only if the bus thread has exited.
+    pub fn send(&self, frame: CanFrame) -> Result<(), SlcanError> {
+        self.commands.send(Command::Send(frame)).map_err(|_| SlcanError::Protocol("bus thread stopped"))
+    }
+
+    /// Program acceptance filters on the underlying bus (affects every subscriber).
+    pub fn set_hardware_filters(&self, filters: &[CanFilter]) -> Result<(), SlcanError> {
+        self.commands.send(Command::SetFilters(filters.to_vec())).map_err(|_| SlcanError::Protocol("bus thread stopped"))
+    }
+
+    pub fn status(&self) -> Result<BusStatus, SlcanError> {
+        let (tx, rx) = mpsc::channel();
+        self.commands.send(Command::Status(tx)).map_err(|_| SlcanError::Protocol("bus thread stopped"))?;
+        rx.recv_timeout(Duration::from_secs(2)).map_err(|_| SlcanError::Protocol("bus thread did not answer"))?
+    }
+
+    /// A `CanBus` view of this thread: sends are queued, `recv` reads from a private subscription.
+    pub fn client(&self) -> BusClient {
+        BusClient {
+            commands: self.commands.clone(),
+            rx: self.subscribe(),
+            filters: Vec::new(),
+            read_timeout: Duration::from_millis(50),
+            include_tx: false,
+        }
+    }
+
+    /// Frames dropped because a subscriber was not keeping up.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Non-timeout transport errors seen by the read loop.
+    pub fn errors(&self) -> u64 {
+        self.shared.errors.load(Ordering::Relaxed)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.handle.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
+    /// Stop the thread and wait for it to release the bus.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        if let Some(h) = self.handle.take() {
+            let _ = h.join();
+        }
+    }
+}
+
+impl Drop for BusThread {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn run(mut bus: Box<dyn CanBus>, commands: Receiver<Command>, shared: Arc<Shared>) {
+    while !shared.stop.load(Ordering::Relaxed) {
+        // Flush queued commands before blocking on the next read
+        loop {
+            match commands.try_recv() {
+                Ok(Command::Send(frame)) => match bus.send(&frame) {
+                    Ok(()) => shared.publish(Traffic { direction: Direction::Tx, frame }),
+                    Err(_) => {
+                        shared.errors.fetch_add(1, Ordering::Relaxed);
+                    }
+                },
+                Ok(Command::SetFilters(filters)) => {
+                    let _ = bus.set_filters(&filters);
+                }
+                Ok(Command::Status(reply)) => {
+                    let _ = reply.send(bus.status());
+                }
+                Err(_) => break,
+            }
+        }
+        let started = Instant::now();
+        match bus.recv() {
+            Ok(frame) => shared.publish(Traffic { direction: Direction::Rx, frame }),
+            Err(SlcanError::Io(e)) if e.kind() == io::ErrorKind::TimedOut => {
+                // Transports whose reads do not block (e.g. MockBus) would otherwise spin
+                if started.elapsed() < Duration::from_millis(1) {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            }
+            Err(_) => {
+                shared.errors.fetch_add(1, Ordering::Relaxed);
+                // Avoid spinning on a persistently failing transport
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+}
+
+/// `CanBus` handle onto a [`BusThread`]. Cheap to create; each client has its own queue.
+pub struct BusClient {
+    commands: Sender<Command>,
+    rx: Receiver<Traffic>,
+    filters: Vec<CanFilter>,
+    read_timeout: Duration,
+    include_tx: bool,
+}
+
+impl BusClient {
+    /// How long `recv` waits for a frame (default 50 ms, like `Slcan`). Zero never blocks.
+    pub fn set_read_timeout(&mut self, timeout: Duration) {
+        self.read_timeout = timeout;
+    }
+
+    /// Also return frames sent through the bus thread (by any client) from `recv`.
+    pub fn set_include_tx(&mut self, on: bool) {
+        self.include_tx = on;
+    }
+
+    /// Next traffic item without blocking, including its direction.
+    pub fn try_recv_traffic(&mut self) -> Option<Traffic> {
+        self.rx.try_recv().ok()
+    }
+}
+
+impl CanBus for BusClient {
+    fn send(&mut self, frame: &CanFrame) -> Result<(), SlcanError> {
+        self.commands.send(Command::Send(frame.clone())).map_err(|_| SlcanError::Protocol("bus thread stopped"))
+    }
+
+    fn recv(&mut self) -> Result<CanFrame, SlcanError> {
+        loop {
+            let traffic = if self.read_timeout.is_zero() {
+                self.rx.try_recv().map_err(|e| match e {
+                    mpsc::TryRecvError::Empty => RecvTimeoutError::Timeout,
+                    mpsc::TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+                })
+            } else {
+                self.rx.recv_timeout(self.read_timeout)
+            };
+            match traffic {
+                Ok(t) => {
+                    if (t.direction == Direction::Rx || self.include_tx) && filters_accept(&self.filters, &t.frame) {
+                        return Ok(t.frame);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(SlcanError::Io(io::Error::new(io::ErrorKind::TimedOut, "no frame")));
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(SlcanError::Protocol("bus thread stopped")),
+            }
+        }
+    }
+
+    /// Filters are per client and applied in software; use
+    /// [`BusThread::set_hardware_filters`] to filter on the adapter itself.
+    fn set_filters(&mut self, filters: &[CanFilter]) -> Result<(), SlcanError> {
+        self.filters = filters.to_vec();
+        Ok(())
+    }
+
+    fn status(&mut self) -> Result<BusStatus, SlcanError> {
+        let (tx, rx) = mpsc::channel();
+        self.commands.send(Command::Status(tx)).map_err(|_| SlcanError::Protocol("bus thread stopped"))?;
+        rx.recv_timeout(Duration::from_secs(2)).map_err(|_| SlcanError::Protocol("bus thread did not answer"))?
+    }
+}
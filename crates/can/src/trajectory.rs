@@ -0,0 +1,415 @@
+//! Multi-joint position trajectories streamed to RMD motors.
+//!
+//! [`Trajectory::plan`] turns waypoints into a time-parameterised profile under per-joint
+//! velocity and acceleration limits. Each segment runs rest to rest: every joint starts and
+//! ends it at zero velocity, and all joints take the time the slowest one needs, so they
+//! arrive together. Two shapes are available:
+//! - [`Profile::Trapezoidal`]: constant acceleration, cruise, constant deceleration
+//!   (a triangle when the segment is too short to reach the velocity limit);
+//! - [`Profile::Cubic`]: `3s² − 2s³` blend, with continuous velocity and no cruise phase.
+//!
+//! [`Streamer`] samples a trajectory at a fixed rate and emits one position command (0xA4)
+//! per motor and tick, followed by a multi-turn angle read (0x92). Feeding the replies back
+//! through [`Streamer::handle_frame`] yields the tracking error of each motor. `tick` and
+//! `handle_frame` never block, so UIs call them from their own loops; [`Streamer::run`]
+//! drives a bus until the trajectory ends.
+//!
+//! ```
+//! use std::time::Duration;
+//! use can::trajectory::{JointLimits, Profile, Trajectory, Waypoint};
+//!
+//! let limits = [JointLimits { max_velocity_dps: 90.0, max_accel_dps2: 180.0 }; 2];
+//! let waypoints = [Waypoint::new(vec![90.0, 45.0]), Waypoint::new(vec![0.0, 0.0]).with_dwell(Duration::from_millis(500))];
+//! let traj = Trajectory::plan(Profile::Trapezoidal, &[0.0, 0.0], &waypoints, &limits).unwrap();
+//!
+//! // 90° at 90 dps with 0.5 s ramps: 1.5 s out, 1.5 s back, 0.5 s dwell
+//! assert!((traj.duration().as_secs_f64() - 3.5).abs() < 1e-9);
+//! let mid = traj.sample(Duration::from_millis(750));
+//! assert!((mid.position_deg[0] - 45.0).abs() < 1e-9);
+//! assert!((mid.position_deg[1] - 22.5).abs() < 1e-9); // synchronised: half way as well
+//! assert_eq!(traj.sample(Duration::from_secs(10)).position_deg, vec![0.0, 0.0]);
+//! ```
+//!
+//! Streaming to a motor that never moves reports the whole profile as tracking error:
+//!
+//! ```
+//! use can::mock::{MockBus, Rule};
+//! use can::rmd::{RmdCommand, RmdReply};
+//! use can::trajectory::{JointLimits, Profile, Streamer, Trajectory, Waypoint};
+//!
+//! let bus = MockBus::new();
+//! bus.add_rule(Rule::on(0x141, &[0x92]).reply_frame(RmdReply::MultiTurnAngle { angle_x100: 0 }.to_frame(1)));
+//! let limits = [JointLimits { max_velocity_dps: 200.0, max_accel_dps2: 4000.0 }];
+//! let traj = Trajectory::plan(Profile::Cubic, &[0.0], &[Waypoint::new(vec![10.0])], &limits).unwrap();
+//! let mut streamer = Streamer::new(traj, vec![1], 200.0).unwrap();
+//!
+//! let tracking = streamer.run(&mut bus.clone(), |_, _| {}).unwrap();
+//! assert!(tracking[0].samples > 0);
+//! assert!(tracking[0].max_abs_deg > 9.0 && tracking[0].max_abs_deg <= 10.0);
+//! let last = bus.take_sent().iter().filter_map(|f| RmdCommand::decode(&f.data)).filter(|c| matches!(c, RmdCommand::Position { .. })).last();
+//! assert_eq!(last, Some(RmdCommand::Position { max_speed_dps: 200, angle_x100: 1000 }));
+//! ```
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::bus::CanBus;
+use crate::rmd::{RmdCommand, RmdReply};
+use crate::{CanFrame, SlcanError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    Trapezoidal,
+    Cubic,
+}
+
+impl std::str::FromStr for Profile {
+    type Err = SlcanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trapezoidal" | "trap" => Ok(Profile::Trapezoidal),
+            "cubic" => Ok(Profile::Cubic),
+            other => Err(SlcanError::Parse(format!("unknown profile '{}' (trapezoidal, cubic)", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JointLimits {
+    pub max_velocity_dps: f64,
+    pub max_accel_dps2: f64,
+}
+
+/// Target multi-turn angles, one per joint, and how long to hold them once reached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waypoint {
+    pub position_deg: Vec<f64>,
+    pub dwell: Duration,
+}
+
+impl Waypoint {
+    pub fn new(position_deg: Vec<f64>) -> Self {
+        Waypoint { position_deg, dwell: Duration::ZERO }
+    }
+
+    pub fn with_dwell(mut self, dwell: Duration) -> Self {
+        self.dwell = dwell;
+        self
+    }
+}
+
+/// Desired state of every joint at one instant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setpoint {
+    pub position_deg: Vec<f64>,
+    pub velocity_dps: Vec<f64>,
+}
+
+/// One joint's motion over a segment, relative to its start position.
+#[derive(Debug, Clone, Copy)]
+enum Shape {
+    Hold,
+    /// `peak` velocity (signed), reached after `t_acc` at acceleration `accel` (signed).
+    Trapezoid { peak: f64, accel: f64, t_acc: f64 },
+    Cubic { distance: f64 },
+}
+
+impl Shape {
+    /// Offset from the segment start and velocity at `t` seconds into a segment of `total` seconds.
+    fn eval(&self, t: f64, total: f64) -> (f64, f64) {
+        match *self {
+            Shape::Hold => (0.0, 0.0),
+            Shape::Trapezoid { peak, accel, t_acc } => {
+                let distance = peak * (total - t_acc);
+                if t < t_acc {
+                    (0.5 * accel * t * t, accel * t)
+                } else if t < total - t_acc {
+                    (0.5 * accel * t_acc * t_acc + peak * (t - t_acc), peak)
+                } else {
+                    let left = total - t;
+                    (distance - 0.5 * accel * left * left, accel * left)
+                }
+            }
+            Shape::Cubic { distance } => {
+                let s = t / total;
+                (distance * (3.0 * s * s - 2.0 * s * s * s), distance * 6.0 * s * (1.0 - s) / total)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Segment {
+    /// Seconds from the trajectory start.
+    start: f64,
+    duration: f64,
+    from: Vec<f64>,
+    shapes: Vec<Shape>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Trajectory {
+    profile: Profile,
+    limits: Vec<JointLimits>,
+    segments: Vec<Segment>,
+    end: Vec<f64>,
+    duration: f64,
+}
+
+/// Shortest rest-to-rest time for `distance` (≥ 0) under `limits`.
+fn min_time(profile: Profile, distance: f64, limits: &JointLimits) -> f64 {
+    let (v, a) = (limits.max_velocity_dps, limits.max_accel_dps2);
+    match profile {
+        Profile::Trapezoidal if distance >= v * v / a => distance / v + v / a,
+        Profile::Trapezoidal => 2.0 * (distance / a).sqrt(),
+        Profile::Cubic => (1.5 * distance / v).max((6.0 * distance / a).sqrt()),
+    }
+}
+
+/// Shape covering `delta` in exactly `total` seconds (≥ the joint's minimum time).
+fn shape_for(profile: Profile, delta: f64, total: f64, limits: &JointLimits) -> Shape {
+    if delta == 0.0 || total <= 0.0 {
+        return Shape::Hold;
+    }
+    match profile {
+        Profile::Cubic => Shape::Cubic { distance: delta },
+        Profile::Trapezoidal => {
+            // Peak velocity of the slowed-down profile: d = v·(T − v/a)
+            let (d, a) = (delta.abs(), limits.max_accel_dps2);
+            let disc = (a * a * total * total - 4.0 * a * d).max(0.0);
+            let peak = ((a * total - disc.sqrt()) / 2.0).min(limits.max_velocity_dps);
+            let t_acc = (peak / a).min(total / 2.0);
+            Shape::Trapezoid { peak: peak * delta.signum(), accel: a * delta.signum(), t_acc }
+        }
+    }
+}
+
+impl Trajectory {
+    /// Plan from `start` through `waypoints`. Every waypoint and `limits` need one entry per
+    /// joint of `start`; limits must be positive.
+    pub fn plan(profile: Profile, start: &[f64], waypoints: &[Waypoint], limits: &[JointLimits]) -> Result<Self, SlcanError> {
+        let joints = start.len();
+        if joints == 0 || limits.len() != joints || waypoints.iter().any(|w| w.position_deg.len() != joints) {
+            return Err(SlcanError::Protocol("trajectory: waypoints and limits need one entry per joint"));
+        }
+        let positive = |x: f64| x.is_finite() && x > 0.0;
+        if !limits.iter().all(|l| positive(l.max_velocity_dps) && positive(l.max_accel_dps2)) {
+            return Err(SlcanError::Protocol("trajectory: velocity and acceleration limits must be positive"));
+        }
+        if !start.iter().chain(waypoints.iter().flat_map(|w| &w.position_deg)).all(|p| p.is_finite()) {
+            return Err(SlcanError::Protocol("trajectory: positions must be finite"));
+        }
+
+        let mut segments = Vec::new();
+        let mut t = 0.0;
+        let mut from = start.to_vec();
+        for wp in waypoints {
+            let duration = (0..joints)
+                .map(|j| min_time(profile, (wp.position_deg[j] - from[j]).abs(), &limits[j]))
+                .fold(0.0, f64::max);
+            if duration > 0.0 {
+                let shapes = (0..joints).map(|j| shape_for(profile, wp.position_deg[j] - from[j], duration, &limits[j])).collect();
+                segments.push(Segment { start: t, duration, from: from.clone(), shapes });
+                t += duration;
+            }
+            from = wp.position_deg.clone();
+            if !wp.dwell.is_zero() {
+                let duration = wp.dwell.as_secs_f64();
+                segments.push(Segment { start: t, duration, from: from.clone(), shapes: vec![Shape::Hold; joints] });
+                t += duration;
+            }
+        }
+        Ok(Trajectory { profile, limits: limits.to_vec(), segments, end: from, duration: t })
+    }
+
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    pub fn joints(&self) -> usize {
+        self.end.len()
+    }
+
+    pub fn limits(&self) -> &[JointLimits] {
+        &self.limits
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.duration)
+    }
+
+    /// State at `t` after the start; the final waypoint (at rest) once `t` is past the end.
+    pub fn sample(&self, t: Duration) -> Setpoint {
+        let t = t.as_secs_f64();
+        let segment = self.segments.iter().rev().find(|s| s.start <= t).filter(|s| t < s.start + s.duration);
+        match segment {
+            Some(seg) => {
+                let (position_deg, velocity_dps) = seg
+                    .shapes
+                    .iter()
+                    .zip(&seg.from)
+                    .map(|(shape, from)| {
+                        let (offset, v) = shape.eval(t - seg.start, seg.duration);
+                        (from + offset, v)
+                    })
+                    .unzip();
+                Setpoint { position_deg, velocity_dps }
+            }
+            None => Setpoint { position_deg: self.end.clone(), velocity_dps: vec![0.0; self.end.len()] },
+        }
+    }
+}
+
+/// Tracking error of one motor: commanded minus measured angle, in degrees.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TrackingError {
+    pub motor_id: u32,
+    pub last_deg: Option<f64>,
+    pub max_abs_deg: f64,
+    sum_sq: f64,
+    pub samples: usize,
+}
+
+impl TrackingError {
+    pub fn rms_deg(&self) -> f64 {
+        if self.samples == 0 { 0.0 } else { (self.sum_sq / self.samples as f64).sqrt() }
+    }
+
+    fn record(&mut self, error: f64) {
+        self.last_deg = Some(error);
+        self.max_abs_deg = self.max_abs_deg.max(error.abs());
+        self.sum_sq += error * error;
+        self.samples += 1;
+    }
+}
+
+/// Streams a [`Trajectory`] to motors at a fixed rate; joint `i` drives `motor_ids[i]`.
+pub struct Streamer {
+    trajectory: Trajectory,
+    motor_ids: Vec<u32>,
+    period: Duration,
+    started: Option<Instant>,
+    next_tick: Instant,
+    finished: bool,
+    read_angles: bool,
+    tracking: Vec<TrackingError>,
+}
+
+impl Streamer {
+    pub fn new(trajectory: Trajectory, motor_ids: Vec<u32>, rate_hz: f64) -> Result<Self, SlcanError> {
+        if motor_ids.len() != trajectory.joints() {
+            return Err(SlcanError::Protocol("trajectory: one motor ID per joint"));
+        }
+        if !(rate_hz.is_finite() && rate_hz > 0.0) {
+            return Err(SlcanError::Protocol("trajectory: rate must be positive"));
+        }
+        let tracking = motor_ids.iter().map(|&motor_id| TrackingError { motor_id, ..Default::default() }).collect();
+        Ok(Streamer {
+            trajectory,
+            motor_ids,
+            period: Duration::from_secs_f64(1.0 / rate_hz),
+            started: None,
+            next_tick: Instant::now(),
+            finished: false,
+            read_angles: true,
+            tracking,
+        })
+    }
+
+    /// Skip the angle reads after each setpoint (no tracking error then).
+    pub fn set_read_angles(&mut self, on: bool) {
+        self.read_angles = on;
+    }
+
+    pub fn trajectory(&self) -> &Trajectory {
+        &self.trajectory
+    }
+
+    pub fn motor_ids(&self) -> &[u32] {
+        &self.motor_ids
+    }
+
+    /// Time since the first tick.
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        self.started.map_or(Duration::ZERO, |s| now.saturating_duration_since(s))
+    }
+
+    /// True once the final setpoint went out.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn tracking(&self) -> &[TrackingError] {
+        &self.tracking
+    }
+
+    /// Frames due at `now`: every motor's setpoint, then its angle read. Empty between ticks
+    /// and after the end. The first call starts the clock.
+    pub fn tick(&mut self, now: Instant) -> Vec<CanFrame> {
+        if self.finished {
+            return Vec::new();
+        }
+        let start = *self.started.get_or_insert(now);
+        if now < self.next_tick && now != start {
+            return Vec::new();
+        }
+        // Catch up after a stall instead of bursting the missed ticks
+        self.next_tick = (self.next_tick + self.period).max(now);
+        let elapsed = now - start;
+        if elapsed >= self.trajectory.duration() {
+            self.finished = true;
+        }
+        let setpoint = self.trajectory.sample(elapsed);
+        let mut frames = Vec::with_capacity(self.motor_ids.len() * 2);
+        for (j, &id) in self.motor_ids.iter().enumerate() {
+            // The motor's own speed cap only bounds catch-up; the profile already respects the limit
+            let max_speed_dps = self.trajectory.limits()[j].max_velocity_dps.ceil().clamp(1.0, u16::MAX as f64) as u16;
+            let angle_x100 = (setpoint.position_deg[j] * 100.0).round() as i32;
+            frames.push(RmdCommand::Position { max_speed_dps, angle_x100 }.to_frame(id));
+        }
+        if self.read_angles {
+            frames.extend(self.motor_ids.iter().map(|&id| RmdCommand::ReadMultiTurnAngle.to_frame(id)));
+        }
+        frames
+    }
+
+    /// Account an angle reply against the setpoint at `now`. Returns true if it was one of ours.
+    pub fn handle_frame(&mut self, frame: &CanFrame, now: Instant) -> bool {
+        let Some(start) = self.started else { return false };
+        let Some((id, RmdReply::MultiTurnAngle { angle_x100 })) = RmdReply::from_frame(frame) else { return false };
+        let Some(j) = self.motor_ids.iter().position(|&m| m == id) else { return false };
+        let target = self.trajectory.sample(now.saturating_duration_since(start)).position_deg[j];
+        self.tracking[j].record(target - angle_x100 as f64 / 100.0);
+        true
+    }
+
+    /// Stream the whole trajectory on `bus`, calling `on_tick` with the time into it after
+    /// each tick. Returns the tracking error per motor.
+    pub fn run(&mut self, bus: &mut dyn CanBus, mut on_tick: impl FnMut(Duration, &[TrackingError])) -> Result<Vec<TrackingError>, SlcanError> {
+        while !self.finished {
+            let now = Instant::now();
+            let frames = self.tick(now);
+            for frame in &frames {
+                bus.send(frame)?;
+            }
+            if !frames.is_empty() {
+                on_tick(self.elapsed(now), &self.tracking);
+            }
+            // Read replies until the next tick is due
+            while Instant::now() < self.next_tick {
+                match bus.recv() {
+                    Ok(frame) => {
+                        self.handle_frame(&frame, Instant::now());
+                    }
+                    Err(SlcanError::Io(e)) if e.kind() == io::ErrorKind::TimedOut => {
+                        let left = self.next_tick.saturating_duration_since(Instant::now());
+                        std::thread::sleep(left.min(Duration::from_millis(1)));
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(self.tracking.clone())
+    }
+}
@@ -0,0 +1,954 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use can::dbc::Dbc;
+use can::record::{self, Recorder};
+use can::params::{FlashWrite, MotorParams, Param};
+use can::rmd::{self, RmdCommand, RmdReply};
+use can::safety::{SafeBus, SafetyConfig, SafetyEvent};
+use can::stats::BusStats;
+use can::supervised::ConnectionEvent;
+use can::threaded::BusThread;
+use can::trajectory::{JointLimits, Profile, Streamer, TrackingError, Trajectory, Waypoint};
+use can::{Bitrate, CanBus, SlcanError};
+use crossterm::event::{self, Event, KeyCode};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Style, Color, Modifier},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Table, Row as TRow, Cell, Scrollbar, ScrollbarOrientation, ScrollbarState, Gauge, Tabs},
+    widgets::canvas::{Canvas, Line as CLine, Rectangle as CRect},
+    widgets::{Chart, Axis, Dataset, GraphType},
+    symbols,
+    Terminal,
+};
+/*
+RMD‑L CAN Protocol – Quick Capability Summary (for future maintainers)
+
+Bus/IDs
+- CAN (default 1 Mbps). Per motor: TX (host→motor) ID = 0x140 + motor_id, RX (motor→host) ID = 0x240 + motor_id.
+- Commands rarely ACK; some firmwares only reply on explicit reads or when active reply is enabled (0xB6).
+
+Major Commands (single motor)
+- 0xA1 torque closed loop (current/torque)
+- 0xA2 speed closed loop (deg/s ×100, LE i32 in data[4..8])
+- 0xA4 absolute multi‑turn position (deg ×100, LE i32 in data[4..8])
+- 0xA6 single‑turn position; 0xA8 incremental position
+- 0x92 multi‑turn angle; 0x94 single‑turn angle
+- 0x9A status1/error; 0x9C status2; 0x9D/0x9E extended
+- 0x77 brake release; 0x78 brake lock; 0x81 stop; 0x80 shutdown
+- 0x30/0x31/0x32 PID read/RAM/ROM; 0x42/0x43 accel read/write; 0x79 ID; 0xB4 baud; 0xB3 watchdog; 0xB5 model; 0xB1 runtime; 0xB2 version date
+- 0xB6 Active Reply: [0xB6, cmd, enable(0/1), interval_low, interval_high, 0,0,0]. Example: enable periodic angle → B6 92 01 05 00 00 00 00 (50 ms, 10 ms units)
+
+TUI Hotkeys
+- r/k/x/+/‑/0/p for brake/stop/speed/position
+- Space/E e-stop every motor (stop + brake lock), R release
+- a (angle 0x92), s (status2 0x9C), A (active reply angle 0x92)
+- Up/Down/Home/End scroll frames; q/Esc quit
+- Params tab: g read, Up/Down select, Left/Right edit, Enter RAM write, W/Z/C flash (y to confirm), B reset
+
+Next UI ideas (Ratatui)
+- Tabs: Modes (Torque/Speed/AbsPos/SingleTurn/Incremental), Telemetry, Safety, Frames
+- Live telemetry header + angle/speed sparklines/gauges
+- Controls panel: focused inputs with step keys
+- Safety panel: watchdog, ID/baud, zero encoder
+- Frames table: TS/Dir/ID/Kind/Data + scrollbar
+*/
+
+#[derive(Clone)]
+struct FrameRow { id: u32, data: Vec<u8>, extended: bool, fd: bool, brs: bool, tx: bool, ts: Instant }
+
+/// Frames tab buffer plus the optional capture file it is mirrored into.
+struct FrameLog {
+    rows: Vec<FrameRow>,
+    recorder: Option<(Recorder<std::io::BufWriter<std::fs::File>>, String)>,
+    channel: String,
+    stats: BusStats,
+    /// Why the last capture failed to start, write or finish; shown in the footer.
+    error: Option<String>,
+}
+
+impl FrameLog {
+    /// Account `frame` in the bus statistics and add it to the table.
+    fn push_frame(&mut self, frame: &can::CanFrame, tx: bool) {
+        self.stats.record(frame);
+        self.push(FrameRow { id: frame.id, data: frame.data.clone(), extended: frame.extended, fd: frame.fd, brs: frame.brs, tx, ts: Instant::now() });
+    }
+
+    fn push(&mut self, row: FrameRow) {
+        if let Some((rec, path)) = self.recorder.as_mut() {
+            if let Err(e) = rec.record(&row_frame(&row), if row.tx { record::Direction::Tx } else { record::Direction::Rx }) {
+                // Stop rather than leave a capture with holes in it
+                self.error = Some(format!("write {}: {}", path, e));
+                self.recorder = None;
+            }
+        }
+        self.rows.push(row);
+        if self.rows.len() > 1000 { self.rows.drain(0..self.rows.len()-1000); }
+    }
+
+    /// Start recording (seeded with the frames already buffered) or stop the current capture,
+    /// keeping any error for the footer.
+    fn toggle_recording(&mut self) {
+        if let Err(e) = self.try_toggle_recording() {
+            self.error = Some(format!("{:#}", e));
+        }
+    }
+
+    fn try_toggle_recording(&mut self) -> Result<()> {
+        if let Some((mut rec, path)) = self.recorder.take() {
+            rec.finish().with_context(|| format!("finish {}", path))?;
+            return Ok(());
+        }
+        self.error = None;
+        let ext = if std::env::var("CAN_LOG_FORMAT").is_ok_and(|f| f.eq_ignore_ascii_case("asc")) { "asc" } else { "log" };
+        let epoch = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+        let path = format!("can-{}.{}", epoch.as_secs(), ext);
+        let mut rec = Recorder::create(&path, &self.channel).with_context(|| format!("create {}", path))?;
+        let now = Instant::now();
+        for row in &self.rows {
+            let ts = epoch.saturating_sub(now.duration_since(row.ts));
+            rec.record_at(ts, &row_frame(row), if row.tx { record::Direction::Tx } else { record::Direction::Rx }).with_context(|| format!("write {}", path))?;
+        }
+        self.recorder = Some((rec, path));
+        Ok(())
+    }
+}
+
+fn row_frame(row: &FrameRow) -> can::CanFrame {
+    can::CanFrame { id: row.id, data: row.data.clone(), extended: row.extended, rtr: false, fd: row.fd, brs: row.brs, timestamp: None }
+}
+
+/// Resend interval of the held motion command, well inside the default 500 ms watchdog.
+const KEEPALIVE: Duration = Duration::from_millis(200);
+
+/// Limits and setpoint rate of the `p`/`o` moves.
+const MOVE_LIMITS: JointLimits = JointLimits { max_velocity_dps: 90.0, max_accel_dps2: 180.0 };
+const MOVE_RATE_HZ: f64 = 50.0;
+
+/// Width of the angle/speed charts.
+const WINDOW_SECS: f64 = 10.0;
+
+/// Angle and speed history of the selected motor for the Telemetry tab.
+struct Telemetry {
+    motor_id: u32,
+    angle_x100: i32,
+    /// (seconds since start, multi-turn degrees)
+    angle_hist: Vec<(f64, f64)>,
+    /// (seconds since start, deg/s) estimated from consecutive angle replies
+    speed_hist: Vec<(f64, f64)>,
+    /// Last multi-turn angle in degrees and when it arrived
+    last_angle: Option<(f64, Instant)>,
+}
+
+impl Telemetry {
+    fn new(motor_id: u32) -> Self {
+        Telemetry { motor_id, angle_x100: 0, angle_hist: Vec::new(), speed_hist: Vec::new(), last_angle: None }
+    }
+
+    /// Record `frame` if it is a reply from our motor, received `t` seconds after start at `now`.
+    /// Returns the decoded reply.
+    fn handle_frame(&mut self, frame: &can::CanFrame, t: f64, now: Instant) -> Option<RmdReply> {
+        let (id, reply) = RmdReply::from_frame(frame)?;
+        if id != self.motor_id {
+            return None;
+        }
+        if let RmdReply::MultiTurnAngle { angle_x100 } = reply {
+            self.angle_x100 = angle_x100;
+            let angle_deg = angle_x100 as f64 / 100.0;
+            self.angle_hist.push((t, angle_deg));
+            if let Some((prev_deg, prev_t)) = self.last_angle {
+                let dt = now.duration_since(prev_t).as_secs_f64();
+                if dt > 0.0 {
+                    self.speed_hist.push((t, (angle_deg - prev_deg) / dt));
+                }
+            }
+            self.last_angle = Some((angle_deg, now));
+            let cutoff = t - WINDOW_SECS;
+            self.angle_hist.retain(|(x, _)| *x >= cutoff);
+            self.speed_hist.retain(|(x, _)| *x >= cutoff);
+        }
+        Some(reply)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tab { Telemetry, Frames, Bus, Params, Help }
+
+/// Streamer moving `motor_id` from `from_deg` to `to_deg`.
+fn plan_move(motor_id: u32, from_deg: f64, to_deg: f64, profile: Profile) -> Result<Streamer, SlcanError> {
+    let trajectory = Trajectory::plan(profile, &[from_deg], &[Waypoint::new(vec![to_deg])], &[MOVE_LIMITS])?;
+    Streamer::new(trajectory, vec![motor_id], MOVE_RATE_HZ)
+}
+
+/// Run the TUI on `port` until `q`/`Esc` (or Ctrl+C).
+/// Motion commands go through a [`SafeBus`] with `safety`; the held command is resent every
+/// [`KEEPALIVE`] so the watchdog only trips when the TUI stops running.
+pub fn run(port: String, bitrate: Bitrate, serial_baud: u32, motor_id: u32, dbc: Option<Dbc>, safety: SafetyConfig) -> Result<()> {
+    // Ensure terminal is restored on panic/Ctrl+C
+    let _guard = scopeguard::guard((), |_| {
+        let _ = disable_raw_mode();
+        let _ = crossterm::execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen);
+    });
+    ctrlc::set_handler(|| {
+        let _ = disable_raw_mode();
+        let _ = crossterm::execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen);
+        std::process::exit(0);
+    }).ok();
+    // SLCAN adapters are supervised: unplug/replug and bus-off recover on their own
+    let (mut bus, link_events) = can::supervised::open_bus(&port, bitrate, serial_baud)?;
+
+    if std::env::var("CAN_NO_TUI").is_ok() {
+        eprintln!("[can] connected port={} bitrate={:?} serial_baud={}", port, bitrate, serial_baud);
+        eprintln!("[can] type any key + Enter to send a test frame 123#11223344 (std)");
+        let stdin = std::io::stdin();
+        let mut line = String::new();
+        loop {
+            match bus.recv() {
+                Ok(f) => {
+                    let id = if f.extended { format!("{:08X}", f.id) } else { format!("{:03X}", f.id) };
+                    let data_str = f.data.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+                    println!("{}  {}", id, data_str);
+                }
+                Err(e) => {
+                    if std::env::var("CAN_DEBUG").is_ok() { eprintln!("[can] read err: {}", e); }
+                }
+            }
+            // Non-blocking-ish: check for a line on stdin
+            let _ = stdin.read_line(&mut line);
+            if !line.is_empty() {
+                let _ = bus.send(&can::CanFrame { id: 0x123, data: vec![0x11,0x22,0x33,0x44], extended: false, rtr: false, fd: false, brs: false, timestamp: None });
+                if std::env::var("CAN_EXIT_AFTER_TX").is_ok() { return Ok(()); }
+                line.clear();
+            }
+        }
+    }
+
+    // Own the port on a background thread so a quiet bus never blocks drawing or input
+    let bus = SafeBus::new(bus, safety);
+    let safety = bus.handle();
+    let safety_events = safety.events();
+    let bus_thread = BusThread::spawn(Box::new(bus));
+    let mut client = bus_thread.client();
+    client.set_read_timeout(Duration::ZERO);
+    let mut bus: Box<dyn CanBus> = Box::new(client);
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    crossterm::execute!(stdout, crossterm::terminal::EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let app_start = Instant::now();
+    let channel = std::path::Path::new(&port).file_name().map_or_else(|| port.clone(), |n| n.to_string_lossy().to_string());
+    let mut log = FrameLog { rows: Vec::new(), recorder: None, channel, stats: BusStats::new(bitrate), error: None };
+    let mut target_speed_x100: i32 = 0; // deg/s * 100
+    // Last motion command, resent every KEEPALIVE until stop/brake/e-stop
+    let mut held: Option<RmdCommand> = None;
+    let mut last_keepalive = Instant::now();
+    let mut safety_note: Option<SafetyEvent> = None;
+    // Trajectory move in progress (p/o), its profile, and the tracking error of the last one
+    let mut streamer: Option<Streamer> = None;
+    let mut move_profile = Profile::Trapezoidal;
+    let mut move_tracking: Option<TrackingError> = None;
+    let mut move_note: Option<String> = None;
+    // Parameters tab: selected row, edited value, command awaiting y/n, last result
+    let mut params = MotorParams::default();
+    let mut param_sel: usize = 0;
+    let mut param_draft: Option<i64> = None;
+    let mut param_confirm: Option<(String, RmdCommand)> = None;
+    let mut param_note: Option<String> = None;
+    let mut last_rx = Instant::now();
+    let mut bus_status = can::BusStatus::default();
+    let mut link: Option<ConnectionEvent> = None;
+    let mut last_status_poll = Instant::now();
+    let mut telemetry = Telemetry::new(motor_id);
+    let mut scroll_offset: usize = 0; // 0 shows newest
+    let mut sb_state = ScrollbarState::default();
+    let mut tab = Tab::Telemetry;
+    // Sparkline history removed; using a dial canvas instead
+
+    loop {
+        // Poll device without blocking UI
+        for _ in 0..64 {
+            match bus.recv() {
+                Ok(f) => {
+                    if let Some(s) = streamer.as_mut() {
+                        s.handle_frame(&f, Instant::now());
+                        move_tracking = s.tracking().first().copied();
+                    }
+                    if let Some(reply) = telemetry.handle_frame(&f, app_start.elapsed().as_secs_f64(), Instant::now()) {
+                        params.apply(&reply);
+                    }
+                    log.push_frame(&f, false);
+                    last_rx = Instant::now();
+                }
+                Err(SlcanError::Io(_)) => break,
+                Err(_) => continue,
+            }
+        }
+
+        while let Ok(ev) = link_events.try_recv() {
+            link = Some(ev);
+        }
+        while let Ok(ev) = safety_events.try_recv() {
+            if matches!(ev, SafetyEvent::WatchdogTripped { .. } | SafetyEvent::EmergencyStop { .. }) { held = None; streamer = None; }
+            safety_note = Some(ev);
+        }
+        // Stream the move; once done, hold its final setpoint like any other motion command
+        if let Some(s) = streamer.as_mut() {
+            for frame in s.tick(Instant::now()) {
+                log.push_frame(&frame, true);
+                let _ = bus.send(&frame);
+            }
+            if s.is_finished() {
+                let end = s.trajectory().sample(s.trajectory().duration()).position_deg[0];
+                held = Some(RmdCommand::Position { max_speed_dps: MOVE_LIMITS.max_velocity_dps as u16, angle_x100: (end * 100.0).round() as i32 });
+                last_keepalive = Instant::now();
+                streamer = None;
+            }
+        }
+        if let Some(cmd) = held {
+            if last_keepalive.elapsed() >= KEEPALIVE && !safety.is_estopped() {
+                let _ = bus.send(&cmd.to_frame(motor_id));
+                last_keepalive = Instant::now();
+            }
+        }
+
+        // Adapter error flags, so a silent motor can be told apart from a dead bus
+        if last_status_poll.elapsed() >= Duration::from_secs(1) {
+            if let Ok(st) = bus.status() { bus_status = st; log.stats.record_status(&st); }
+            last_status_poll = Instant::now();
+        }
+
+        // Periodically request status/angle so the view updates on quiet buses
+        // No periodic polling by default; use hotkeys to request
+
+        terminal.draw(|f| {
+            let size = f.area();
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(3), // tabs row
+                    Constraint::Min(1),    // content
+                    Constraint::Length(1), // footer info
+                ]).split(size);
+            let content_area = chunks[1];
+
+            // Official Tabs widget with unstyled titles; highlight_style drives the emphasis
+            // Include numeric hints for quick access
+            let titles: Vec<Line> = [" 1 Telemetry ", " 2 Frames ", " 3 Bus ", " 4 Params ", " 5 Help "]
+                .iter().map(|t| Line::from(*t)).collect();
+            let selected = match tab { Tab::Telemetry => 0, Tab::Frames => 1, Tab::Bus => 2, Tab::Params => 3, Tab::Help => 4 };
+            let tabs = Tabs::new(titles)
+                .style(Style::default())
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED).add_modifier(Modifier::BOLD))
+                .padding(" ", " ")
+                .divider(" ")
+                .select(selected);
+            f.render_widget(tabs, chunks[0]);
+
+            match tab {
+                Tab::Telemetry => {
+                    let dash_chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([
+                            Constraint::Min(8), // dial gets space
+                            Constraint::Length(3), // bar gauge
+                            Constraint::Length(10), // charts area
+                        ])
+                        .split(content_area);
+
+                    // Dial rendered with Canvas (aspect-correct to remain circular)
+                    let angle_deg = ((telemetry.angle_x100 as f64) / 100.0).rem_euclid(360.0);
+                    let dial_area = dash_chunks[0];
+                    // Use HalfBlock marker; compensate for widget aspect so the circle appears round
+                    let w = dial_area.width.max(1) as f64;
+                    let h = dial_area.height.max(1) as f64;
+                    let marker_scale = 2.0; // HalfBlock doubles vertical resolution
+                    let eff_h = h * marker_scale; // effective pixel height
+                    let min_px = w.min(eff_h);
+                    let mut dial_margin: f64 = std::env::var("CAN_DIAL_MARGIN").ok().and_then(|s| s.parse().ok()).unwrap_or(0.92);
+                    if dial_margin <= 0.0 || dial_margin > 1.0 { dial_margin = 0.92; }
+                    // Choose symmetric bounds so 1 unit radius maps to min_px/2 pixels
+                    let sx = w / min_px;
+                    let sy = eff_h / min_px;
+                    let x_bounds = [-sx, sx];
+                    let y_bounds = [-sy, sy];
+                    let dial = Canvas::default()
+                        .block(Block::default().borders(Borders::ALL).title("Angle Dial (°)"))
+                        .marker(symbols::Marker::HalfBlock)
+                        .x_bounds(x_bounds)
+                        .y_bounds(y_bounds)
+                        .paint(move |ctx| {
+                            let r = dial_margin; // actual dial radius inside the widget
+                            // Outer circle
+                            for i in 0..360 {
+                                let rad = (i as f64).to_radians();
+                                let x = r * rad.cos();
+                                let y = r * rad.sin();
+                                ctx.draw(&CRect { x, y, width: 0.002, height: 0.002, color: Color::DarkGray });
+                            }
+                            // Tick marks every 30°
+                            for i in (0..360).step_by(30) {
+                                let r1 = r * 0.85;
+                                let r2 = r * 0.98;
+                                let rad = (i as f64).to_radians();
+                                ctx.draw(&CLine { x1: r1*rad.cos(), y1: r1*rad.sin(), x2: r2*rad.cos(), y2: r2*rad.sin(), color: Color::Gray });
+                            }
+                            // Needle pointing at angle_deg
+                            let rad = angle_deg.to_radians();
+                            ctx.draw(&CLine { x1: 0.0, y1: 0.0, x2: (r*0.8)*rad.cos(), y2: (r*0.8)*rad.sin(), color: Color::Yellow });
+                        });
+                    f.render_widget(dial, dash_chunks[0]);
+
+                    // Bar gauge also shows single‑turn ratio
+                    let angle_norm = angle_deg;
+                    let ratio = (angle_norm / 360.0).clamp(0.0, 1.0);
+                    let gauge = Gauge::default()
+                        .block(Block::default().borders(Borders::ALL).title("Angle"))
+                        .ratio(ratio)
+                        .gauge_style(Style::default().fg(Color::LightCyan))
+                        .label(format!("{:.2}° (single)", angle_norm));
+                    f.render_widget(gauge, dash_chunks[1]);
+
+                    // Charts: Angle (multi-turn, °) and Speed (deg/s) using identical sampling
+                    let now_s = app_start.elapsed().as_secs_f64();
+                    let x_bounds = [now_s - WINDOW_SECS, now_s];
+                    let angle_points: Vec<(f64,f64)> = telemetry.angle_hist.clone();
+                    let speed_points: Vec<(f64,f64)> = telemetry.speed_hist.clone();
+
+                    // Autoscale Y bounds for angle
+                    let (amin, amax) = if let Some(((..),)) = angle_points.first().map(|_| ((0,),)) {
+                        let mut mn = angle_points[0].1; let mut mx = mn;
+                        for &(_, v) in &angle_points { mn = mn.min(v); mx = mx.max(v); }
+                        (mn, mx)
+                    } else { (0.0, 1.0) };
+                    let apad = ((amax - amin) * 0.1).max(0.5);
+                    let a_bounds = [amin - apad, amax + apad];
+
+                    // Autoscale Y bounds for speed (symmetric around 0)
+                    let (smin, smax) = if let Some(((..),)) = speed_points.first().map(|_| ((0,),)) {
+                        let mut mn = speed_points[0].1; let mut mx = mn;
+                        for &(_, v) in &speed_points { mn = mn.min(v); mx = mx.max(v); }
+                        (mn, mx)
+                    } else { (-1.0, 1.0) };
+                    let sab = smax.abs().max(smin.abs()).max(1.0);
+                    let s_bounds = [-sab * 1.1, sab * 1.1];
+
+                    let chart_chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                        .split(dash_chunks[2]);
+
+                    let angle_ds = Dataset::default()
+                        .name("angle")
+                        .marker(symbols::Marker::Braille)
+                        .style(Style::default().fg(Color::Cyan))
+                        .graph_type(GraphType::Line)
+                        .data(&angle_points);
+                    let angle_chart = Chart::new(vec![angle_ds])
+                        .block(Block::default().borders(Borders::ALL).title("Angle (°)"))
+                        .x_axis(Axis::default().bounds(x_bounds).labels(vec![Line::from(format!("-{:.0}s", WINDOW_SECS)), Line::from("now")] ))
+                        .y_axis(Axis::default().bounds(a_bounds).labels(vec![Line::from(format!("{:.0}", a_bounds[0])), Line::from(format!("{:.0}", a_bounds[1]))]));
+                    f.render_widget(angle_chart, chart_chunks[0]);
+
+                    let speed_ds = Dataset::default()
+                        .name("speed")
+                        .marker(symbols::Marker::Braille)
+                        .style(Style::default().fg(Color::Green))
+                        .graph_type(GraphType::Line)
+                        .data(&speed_points);
+                    let speed_chart = Chart::new(vec![speed_ds])
+                        .block(Block::default().borders(Borders::ALL).title("Speed (deg/s)"))
+                        .x_axis(Axis::default().bounds(x_bounds).labels(vec![Line::from(format!("-{:.0}s", WINDOW_SECS)), Line::from("now")] ))
+                        .y_axis(Axis::default().bounds(s_bounds).labels(vec![Line::from(format!("{:.0}", s_bounds[0])), Line::from("0"), Line::from(format!("{:.0}", s_bounds[1]))]));
+                    f.render_widget(speed_chart, chart_chunks[1]);
+                }
+                Tab::Frames => {
+                    // determine visible rows by area height (minus borders)
+                    let visible = content_area.height.saturating_sub(2) as usize;
+                    let total = log.rows.len();
+                    let max_skip = total.saturating_sub(visible);
+                    if scroll_offset > max_skip { scroll_offset = max_skip; }
+                    sb_state = sb_state.content_length(total.saturating_sub(visible)).position(scroll_offset);
+
+                    let table_rows = log.rows.iter().rev().skip(scroll_offset).take(visible).map(|r| {
+                        let (dir, mut kind) = classify_row(r.id, motor_id, &r.data);
+                        let id_str = if r.extended { format!("{:08X}", r.id) } else { format!("{:03X}", r.id) };
+                        let ts_ms = r.ts.duration_since(app_start).as_millis();
+                        let mut data_str = r.data.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+                        if let Some(decoded) = dbc.as_ref().and_then(|d| d.decode(&row_frame(r))) {
+                            kind = decoded.message.name.clone();
+                            data_str = format!("{:<24} {}", data_str, decoded.signals_string());
+                        }
+                        let dir_color = if dir == "TX" { Color::Yellow } else if dir == "RX" { Color::Cyan } else { Color::Gray };
+                        TRow::new(vec![
+                            Cell::from(format!("{:>8}ms", ts_ms)).style(Style::default().fg(Color::DarkGray)),
+                            Cell::from(dir.to_string()).style(Style::default().fg(dir_color).add_modifier(Modifier::BOLD)),
+                            Cell::from(id_str).style(Style::default().fg(Color::Cyan)),
+                            Cell::from(kind).style(Style::default().fg(Color::Green)),
+                            Cell::from(data_str).style(Style::default().fg(Color::White)),
+                        ])
+                    });
+
+                    let table = Table::new(table_rows, [
+                            Constraint::Length(10), // ts
+                            Constraint::Length(3),  // dir
+                            Constraint::Length(8),  // id
+                            Constraint::Length(16), // kind
+                            Constraint::Min(10),    // data
+                        ])
+                        .block(Block::default().borders(Borders::ALL).title("Frames (latest first)"));
+                    f.render_widget(table, content_area);
+                    // render scrollbar at right edge of table area
+                    let sb = Scrollbar::default().orientation(ScrollbarOrientation::VerticalRight);
+                    f.render_stateful_widget(sb, content_area, &mut sb_state);
+                }
+                Tab::Bus => {
+                    let snap = log.stats.snapshot_now();
+                    let bus_chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Min(4)])
+                        .split(content_area);
+                    let load_pct = snap.load * 100.0;
+                    let load_color = if load_pct < 50.0 { Color::Green } else if load_pct < 80.0 { Color::Yellow } else { Color::Red };
+                    let gauge = Gauge::default()
+                        .block(Block::default().borders(Borders::ALL).title(format!("Bus load at {:?} (1 s window, incl. stuffing)", bitrate)))
+                        .gauge_style(Style::default().fg(load_color))
+                        .ratio(snap.load.clamp(0.0, 1.0))
+                        .label(format!("{:.1}%", load_pct));
+                    f.render_widget(gauge, bus_chunks[0]);
+
+                    let errors_color = if snap.errors.total() == 0 { Color::Green } else { Color::Red };
+                    let summary = Paragraph::new(Line::from(vec![
+                        Span::styled("frames/s:", Style::default().fg(Color::DarkGray)),
+                        Span::styled(format!("{:.0}", snap.frames_per_sec), Style::default().fg(Color::White)),
+                        Span::raw("  "),
+                        Span::styled("total:", Style::default().fg(Color::DarkGray)),
+                        Span::styled(snap.total_frames.to_string(), Style::default().fg(Color::White)),
+                        Span::raw("  "),
+                        Span::styled("errors:", Style::default().fg(Color::DarkGray)),
+                        Span::styled(snap.errors.to_string(), Style::default().fg(errors_color)),
+                    ])).block(Block::default().borders(Borders::ALL).title("Totals"));
+                    f.render_widget(summary, bus_chunks[1]);
+
+                    let ms = |d: Option<Duration>| d.map_or("-".to_string(), |d| format!("{:.2}", d.as_secs_f64() * 1000.0));
+                    let rows = snap.ids.iter().map(|s| {
+                        let id_str = if s.extended { format!("{:08X}", s.id) } else { format!("{:03X}", s.id) };
+                        let name = dbc.as_ref().and_then(|d| d.message(s.id, s.extended)).map(|m| m.name.clone()).or_else(|| {
+                            rmd::motor_id_from_tx(s.id).map(|m| format!("RMD {} cmd", m)).or_else(|| rmd::motor_id_from_rx(s.id).map(|m| format!("RMD {} reply", m)))
+                        }).unwrap_or_default();
+                        let (state, color) = if s.overdue { ("overdue", Color::Red) } else if s.missing > 0 { ("gaps", Color::Yellow) } else if s.period.is_some() { ("periodic", Color::Green) } else { ("", Color::Gray) };
+                        TRow::new(vec![
+                            Cell::from(id_str).style(Style::default().fg(Color::Cyan)),
+                            Cell::from(name).style(Style::default().fg(Color::Green)),
+                            Cell::from(s.count.to_string()),
+                            Cell::from(format!("{:.1}", s.rate_hz)),
+                            Cell::from(ms(s.period)),
+                            Cell::from(ms(s.jitter)),
+                            Cell::from(s.missing.to_string()).style(Style::default().fg(if s.missing > 0 { Color::Yellow } else { Color::White })),
+                            Cell::from(format!("{:.0}", s.since_last.as_secs_f64() * 1000.0)).style(Style::default().fg(Color::DarkGray)),
+                            Cell::from(state).style(Style::default().fg(color)),
+                        ])
+                    });
+                    let header = TRow::new(["ID", "Name", "Count", "Rate Hz", "Period ms", "Jitter ms", "Missing", "Last ms", ""])
+                        .style(Style::default().fg(Color::DarkGray).add_modifier(Modifier::BOLD));
+                    let table = Table::new(rows, [
+                            Constraint::Length(8),
+                            Constraint::Length(16),
+                            Constraint::Length(8),
+                            Constraint::Length(8),
+                            Constraint::Length(10),
+                            Constraint::Length(10),
+                            Constraint::Length(8),
+                            Constraint::Length(8),
+                            Constraint::Min(8),
+                        ])
+                        .header(header)
+                        .block(Block::default().borders(Borders::ALL).title("Per ID (z resets)"));
+                    f.render_widget(table, bus_chunks[2]);
+                }
+                Tab::Params => {
+                    let param_chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Min(6), Constraint::Length(5)])
+                        .split(content_area);
+                    let rows = Param::ALL.iter().enumerate().map(|(i, p)| {
+                        let current = p.get(&params).map_or("-".to_string(), |v| v.to_string());
+                        let edit = if i == param_sel { param_draft.map_or(String::new(), |v| format!("→ {}", v)) } else { String::new() };
+                        let storage = if p.is_gain() { "RAM/flash" } else { "flash" };
+                        let style = if i == param_sel { Style::default().add_modifier(Modifier::REVERSED) } else { Style::default() };
+                        TRow::new(vec![
+                            Cell::from(p.label()),
+                            Cell::from(current).style(Style::default().fg(Color::Cyan)),
+                            Cell::from(edit).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                            Cell::from(p.unit()).style(Style::default().fg(Color::DarkGray)),
+                            Cell::from(storage).style(Style::default().fg(Color::DarkGray)),
+                        ]).style(style)
+                    });
+                    let counts = |v: Option<i32>| v.map_or("-".to_string(), |v| v.to_string());
+                    let table = Table::new(rows, [
+                            Constraint::Length(16),
+                            Constraint::Length(12),
+                            Constraint::Length(12),
+                            Constraint::Length(8),
+                            Constraint::Min(9),
+                        ])
+                        .block(Block::default().borders(Borders::ALL).title(format!(
+                            "Parameters motor {}  encoder {} (raw {})", motor_id, counts(params.encoder), counts(params.encoder_raw))));
+                    f.render_widget(table, param_chunks[0]);
+
+                    let mut lines = vec![Line::from(Span::styled(
+                        "g read  ↑/↓ select  ←/→ edit  Enter write RAM (gains)  W write flash  Z zero here  C clear zero  B reset motor",
+                        Style::default().fg(Color::DarkGray),
+                    ))];
+                    if let Some((what, _)) = param_confirm.as_ref() {
+                        lines.push(Line::from(Span::styled(format!("{}? y to confirm, any other key cancels", what), Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD))));
+                    } else if let Some(note) = param_note.as_ref() {
+                        lines.push(Line::from(Span::raw(note.clone())));
+                    }
+                    let info = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Edit"));
+                    f.render_widget(info, param_chunks[1]);
+                }
+                Tab::Help => {
+                    let keys_chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([
+                            Constraint::Percentage(50),
+                            Constraint::Percentage(50),
+                        ])
+                        .split(content_area);
+
+                    let key_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+                    let key = |label: &str| Span::styled(format!("[{}]", label), key_style);
+
+                    // Left column: Navigation + Frames
+                    let nav_lines = vec![
+                        Line::from(vec![key("Tab"), Span::raw(" Next  "), key("Shift-Tab"), Span::raw(" Prev")]),
+                        Line::from(vec![key("1"), Span::raw(" Dash  "), key("2"), Span::raw(" Frames  "), key("3"), Span::raw(" Bus  "), key("4"), Span::raw(" Params  "), key("5"), Span::raw(" Keys")]),
+                    ];
+                    let frames_lines = vec![
+                        Line::from(vec![key("Up/Down"), Span::raw(" Scroll  "), key("Home/End"), Span::raw(" Jump")]),
+                        Line::from(vec![key("L"), Span::raw(" Start/stop recording (candump .log, CAN_LOG_FORMAT=asc for .asc)")]),
+                    ];
+                    let bus_lines = vec![
+                        Line::from(vec![key("z"), Span::raw(" Reset bus statistics (load, rates, jitter, missing, errors)")]),
+                    ];
+                    let params_lines = vec![
+                        Line::from(vec![key("g"), Span::raw(" Read params  "), key("←/→"), Span::raw(" Edit  "), key("Enter"), Span::raw(" Gains to RAM")]),
+                        Line::from(vec![key("W"), Span::raw(" Flash  "), key("Z"), Span::raw(" Zero here  "), key("C"), Span::raw(" Clear zero  "), key("B"), Span::raw(" Reset")]),
+                        Line::from(Span::raw("Flash writes and reset ask for y; a new zero applies after reset")),
+                    ];
+                    let left = Paragraph::new(nav_lines.into_iter().chain(frames_lines).chain(bus_lines).chain(params_lines).collect::<Vec<_>>())
+                        .block(Block::default().borders(Borders::ALL).title("Help: Navigation / Frames"));
+                    f.render_widget(left, keys_chunks[0]);
+
+                    // Right column: Motor + Telemetry + Quit
+                    let motor_lines = vec![
+                        Line::from(vec![key("r"), Span::raw(" Release brake  "), key("k"), Span::raw(" Lock brake")]),
+                        Line::from(vec![key("x"), Span::raw(" Stop  "), key("0"), Span::raw(" Zero speed")]),
+                        Line::from(vec![key("+/-"), Span::raw(" Speed ±5 dps")]),
+                        Line::from(vec![key("p"), Span::raw(" Move to 90°  "), key("o"), Span::raw(" Move to 0°  "), key("P"), Span::raw(" Trapezoidal/cubic")]),
+                        Line::from(vec![key("Space/E"), Span::raw(" E-stop all motors  "), key("R"), Span::raw(" Release e-stop")]),
+                        Line::from(Span::raw("Moves stream setpoints at 50 Hz (90 dps, 180 dps²), then hold; speed/position are resent every 200 ms")),
+                    ];
+                    let tele_lines = vec![
+                        Line::from(vec![key("a"), Span::raw(" Read angle (0x92)  "), key("s"), Span::raw(" Read status2 (0x9C)")]),
+                        Line::from(vec![key("A"), Span::raw(" Active reply angle (0xB6→0x92)")]),
+                        Line::from(vec![key("q"), Span::raw(" / "), key("Esc"), Span::raw(" Quit")]),
+                    ];
+                    let right = Paragraph::new(motor_lines.into_iter().chain(tele_lines).collect::<Vec<_>>())
+                        .block(Block::default().borders(Borders::ALL).title("Help: Motor / Telemetry"));
+                    f.render_widget(right, keys_chunks[1]);
+                }
+            }
+
+            // Footer info (moved header)
+            let rx_age_ms = last_rx.elapsed().as_millis() as u64;
+            let rx_color = if rx_age_ms < 100 { Color::Green } else if rx_age_ms < 500 { Color::Yellow } else { Color::Red };
+            let mut footer_line = Line::from(vec![
+                Span::styled("port:", Style::default().fg(Color::DarkGray)),
+                Span::styled(port.to_string(), Style::default().fg(Color::White)),
+                Span::raw("  "),
+                Span::styled("id:", Style::default().fg(Color::DarkGray)),
+                Span::styled(format!("{:03X}", rmd::tx_id(motor_id)), Style::default().fg(Color::Cyan)),
+                Span::raw("  "),
+                Span::styled("can:", Style::default().fg(Color::DarkGray)),
+                Span::styled(format!("{:?}", bitrate), Style::default().fg(Color::Magenta)),
+                Span::raw("  "),
+                Span::styled("sbaud:", Style::default().fg(Color::DarkGray)),
+                Span::styled(format!("{}", serial_baud), Style::default().fg(Color::White)),
+                Span::raw("  "),
+                Span::styled("speed:", Style::default().fg(Color::DarkGray)),
+                Span::styled(format!("{:.2} dps", (target_speed_x100 as f32)/100.0), Style::default().fg(Color::Green)),
+                Span::raw("  "),
+                Span::styled("angle:", Style::default().fg(Color::DarkGray)),
+                Span::styled(format!("{:.2}°", (telemetry.angle_x100 as f32)/100.0), Style::default().fg(Color::Cyan)),
+                Span::raw("  "),
+                Span::styled("frames:", Style::default().fg(Color::DarkGray)),
+                Span::styled(format!("{}", log.rows.len()), Style::default().fg(Color::White)),
+                Span::raw("  "),
+                Span::styled("last_rx:", Style::default().fg(Color::DarkGray)),
+                Span::styled(format!("{}ms", rx_age_ms), Style::default().fg(rx_color)),
+                Span::raw("  "),
+                Span::styled("bus:", Style::default().fg(Color::DarkGray)),
+                Span::styled(bus_status.to_string(), Style::default().fg(if bus_status.is_ok() { Color::Green } else { Color::Red })),
+            ]);
+            if let Some(ev) = link.as_ref() {
+                let (text, color) = match ev {
+                    ConnectionEvent::Connected { .. } | ConnectionEvent::BusRecovered => ("link:up".to_string(), Color::Green),
+                    ConnectionEvent::Disconnected { .. } => ("link:down".to_string(), Color::Red),
+                    ConnectionEvent::ReconnectFailed { attempt, .. } => (format!("link:retry #{}", attempt), Color::Yellow),
+                    ConnectionEvent::BusOff => ("link:bus-off".to_string(), Color::Red),
+                };
+                footer_line.spans.push(Span::raw("  "));
+                footer_line.spans.push(Span::styled(text, Style::default().fg(color)));
+            }
+            let (text, color) = if safety.is_estopped() {
+                ("safety:E-STOP".to_string(), Color::Red)
+            } else {
+                match safety_note.as_ref() {
+                    Some(SafetyEvent::WatchdogTripped { .. }) => ("safety:watchdog".to_string(), Color::Yellow),
+                    Some(SafetyEvent::Clamped { .. }) => ("safety:limited".to_string(), Color::Yellow),
+                    Some(SafetyEvent::MotorTimeoutUnset { .. }) => ("safety:no-0xB3".to_string(), Color::Red),
+                    _ => ("safety:ok".to_string(), Color::Green),
+                }
+            };
+            footer_line.spans.push(Span::raw("  "));
+            footer_line.spans.push(Span::styled(text, Style::default().fg(color).add_modifier(if safety.is_estopped() { Modifier::BOLD } else { Modifier::empty() })));
+            let move_text = match (&streamer, &move_tracking) {
+                (Some(s), t) => Some(format!("move:{:.1}/{:.1}s err {}", s.elapsed(Instant::now()).as_secs_f64(), s.trajectory().duration().as_secs_f64(),
+                    t.and_then(|t| t.last_deg).map_or("-".into(), |e| format!("{:+.2}°", e)))),
+                (None, Some(t)) => Some(format!("move:done max {:.2}° rms {:.2}°", t.max_abs_deg, t.rms_deg())),
+                (None, None) => move_note.clone(),
+            };
+            if let Some(text) = move_text {
+                footer_line.spans.push(Span::raw("  "));
+                footer_line.spans.push(Span::styled(text, Style::default().fg(Color::Cyan)));
+            }
+            if let Some((rec, path)) = log.recorder.as_ref() {
+                footer_line.spans.push(Span::raw("  "));
+                footer_line.spans.push(Span::styled(format!("● REC {} ({})", path, rec.frames()), Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)));
+            } else if let Some(e) = log.error.as_ref() {
+                footer_line.spans.push(Span::raw("  "));
+                footer_line.spans.push(Span::styled(format!("rec error: {}", e), Style::default().fg(Color::Red)));
+            }
+            let footer = Paragraph::new(footer_line);
+            f.render_widget(footer, chunks[2]);
+        })?;
+
+        // Input handling
+        if event::poll(Duration::from_millis(1))? {
+            if let Event::Key(key) = event::read()? {
+                // A pending flash write or reset takes the next key as its answer
+                if let Some((what, cmd)) = param_confirm.take() {
+                    if key.code == KeyCode::Char('y') {
+                        send_rmd(bus.as_mut(), &mut log, motor_id, cmd);
+                        param_note = Some(if matches!(cmd, RmdCommand::WriteEncoderZero { .. } | RmdCommand::WriteCurrentAsZero) {
+                            format!("sent: {} (B resets the motor)", what)
+                        } else {
+                            format!("sent: {}", what)
+                        });
+                        param_draft = None;
+                    } else {
+                        param_note = Some("cancelled".into());
+                    }
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc | KeyCode::Char('c') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => break,
+                    KeyCode::Char('1') => { tab = Tab::Telemetry; }
+                    KeyCode::Char('2') => { tab = Tab::Frames; }
+                    KeyCode::Char('3') => { tab = Tab::Bus; }
+                    KeyCode::Char('4') => { tab = Tab::Params; }
+                    KeyCode::Char('5') => { tab = Tab::Help; }
+                    KeyCode::Tab => { tab = match tab { Tab::Telemetry => Tab::Frames, Tab::Frames => Tab::Bus, Tab::Bus => Tab::Params, Tab::Params => Tab::Help, Tab::Help => Tab::Telemetry }; }
+                    KeyCode::BackTab => { tab = match tab { Tab::Telemetry => Tab::Help, Tab::Help => Tab::Params, Tab::Params => Tab::Bus, Tab::Bus => Tab::Frames, Tab::Frames => Tab::Telemetry }; }
+                    KeyCode::Char('z') if tab == Tab::Bus => { log.stats.reset(); }
+                    KeyCode::Char('g') if tab == Tab::Params => {
+                        for cmd in MotorParams::read_commands() { send_rmd(bus.as_mut(), &mut log, motor_id, cmd); }
+                        param_note = Some("reading parameters".into());
+                    }
+                    KeyCode::Up if tab == Tab::Params => { param_sel = param_sel.saturating_sub(1); param_draft = None; }
+                    KeyCode::Down if tab == Tab::Params => { param_sel = (param_sel + 1).min(Param::ALL.len() - 1); param_draft = None; }
+                    KeyCode::Left | KeyCode::Right if tab == Tab::Params => {
+                        let p = Param::ALL[param_sel];
+                        let step = if key.code == KeyCode::Left { -p.step() } else { p.step() };
+                        if let Some(v) = param_draft.or(p.get(&params)) {
+                            param_draft = Some((v + step).clamp(*p.range().start(), *p.range().end()));
+                        } else {
+                            param_note = Some("read the parameters first (g)".into());
+                        }
+                    }
+                    KeyCode::Enter if tab == Tab::Params => {
+                        let p = Param::ALL[param_sel];
+                        match param_draft.and_then(|v| params.ram_write(p, v)) {
+                            Some(cmd) => { send_rmd(bus.as_mut(), &mut log, motor_id, cmd); param_note = Some(format!("{} → RAM", p.label())); param_draft = None; }
+                            None if !p.is_gain() => param_note = Some(format!("{} is only stored in flash (W)", p.label())),
+                            None => param_note = Some("nothing to write; edit with ←/→ after reading (g)".into()),
+                        }
+                    }
+                    KeyCode::Char('W') if tab == Tab::Params => {
+                        let p = Param::ALL[param_sel];
+                        match param_draft.or(p.get(&params)).and_then(|v| params.flash_write(p, v)) {
+                            Some(write) => param_confirm = Some((write.to_string(), write.command())),
+                            None => param_note = Some("nothing to write; read the parameters first (g)".into()),
+                        }
+                    }
+                    KeyCode::Char('Z') if tab == Tab::Params => { let w = FlashWrite::zero_at_current_position(); param_confirm = Some((w.to_string(), w.command())); }
+                    KeyCode::Char('C') if tab == Tab::Params => { let w = FlashWrite::clear_zero(); param_confirm = Some((w.to_string(), w.command())); }
+                    KeyCode::Char('B') if tab == Tab::Params => { param_confirm = Some(("reset the motor controller".into(), RmdCommand::SystemReset)); }
+                    KeyCode::Char('r') => { send_rmd(bus.as_mut(), &mut log, motor_id, RmdCommand::BrakeRelease); }
+                    KeyCode::Char('k') => { held = None; streamer = None; send_rmd(bus.as_mut(), &mut log, motor_id, RmdCommand::BrakeLock); }
+                    KeyCode::Char('x') => { held = None; streamer = None; send_rmd(bus.as_mut(), &mut log, motor_id, RmdCommand::Stop); target_speed_x100 = 0; }
+                    KeyCode::Char('0') => { streamer = None; target_speed_x100 = 0; held = Some(RmdCommand::Speed { speed_x100: target_speed_x100 }); }
+                    KeyCode::Char('+') | KeyCode::Char('=') => { streamer = None; target_speed_x100 = target_speed_x100.saturating_add(500); held = Some(RmdCommand::Speed { speed_x100: target_speed_x100 }); }
+                    KeyCode::Char('-') | KeyCode::Char('_') => { streamer = None; target_speed_x100 = target_speed_x100.saturating_sub(500); held = Some(RmdCommand::Speed { speed_x100: target_speed_x100 }); }
+                    KeyCode::Char('p') | KeyCode::Char('o') => {
+                        let to_deg = if key.code == KeyCode::Char('p') { 90.0 } else { 0.0 };
+                        // Start from the measured angle (or the held position) so the first setpoint does not jump
+                        let from = telemetry.last_angle.filter(|(_, t)| t.elapsed() < Duration::from_secs(1)).map(|(a, _)| a).or(match held {
+                            Some(RmdCommand::Position { angle_x100, .. }) => Some(angle_x100 as f64 / 100.0),
+                            _ => None,
+                        });
+                        match from {
+                            Some(from_deg) => match plan_move(motor_id, from_deg, to_deg, move_profile) {
+                                Ok(s) => { held = None; streamer = Some(s); move_tracking = None; move_note = None; }
+                                Err(e) => move_note = Some(format!("move: {}", e)),
+                            },
+                            None => {
+                                send_rmd(bus.as_mut(), &mut log, motor_id, RmdCommand::ReadMultiTurnAngle);
+                                move_note = Some("move: angle unknown, read it and press again".into());
+                            }
+                        }
+                    }
+                    KeyCode::Char('P') => {
+                        move_profile = if move_profile == Profile::Trapezoidal { Profile::Cubic } else { Profile::Trapezoidal };
+                        move_tracking = None;
+                        move_note = Some(format!("move:{:?}", move_profile).to_lowercase());
+                    }
+                    KeyCode::Char(' ') | KeyCode::Char('E') => { held = None; streamer = None; target_speed_x100 = 0; safety.estop(); }
+                    KeyCode::Char('R') => { safety.release(); }
+                    KeyCode::Char('a') => { send_rmd(bus.as_mut(), &mut log, motor_id, RmdCommand::ReadMultiTurnAngle); }
+                    KeyCode::Char('s') => { send_rmd(bus.as_mut(), &mut log, motor_id, RmdCommand::ReadStatus2); }
+                    KeyCode::Char('A') => { // Enable Active Reply for angle (0x92) @ 50ms interval
+                        send_rmd(bus.as_mut(), &mut log, motor_id, RmdCommand::ActiveReply { cmd: rmd::code::READ_MULTI_TURN_ANGLE, enable: true, interval_10ms: 5 });
+                    }
+                    KeyCode::Char('L') => log.toggle_recording(),
+                    KeyCode::Up if tab == Tab::Frames => { scroll_offset = scroll_offset.saturating_add(1); }
+                    KeyCode::Down if tab == Tab::Frames => { scroll_offset = scroll_offset.saturating_sub(1); }
+                    KeyCode::Home if tab == Tab::Frames => { scroll_offset = 0; }
+                    KeyCode::End if tab == Tab::Frames => { scroll_offset = log.rows.len(); }
+                    _ => {}
+                }
+                // A new motion command goes out now; the keepalive repeats it
+                if let (Some(cmd), KeyCode::Char('0' | '+' | '=' | '-' | '_')) = (held, key.code) {
+                    send_rmd(bus.as_mut(), &mut log, motor_id, cmd);
+                    last_keepalive = Instant::now();
+                }
+            }
+        }
+    }
+
+    disable_raw_mode()?;
+    crossterm::execute!(
+        io::stdout(),
+        crossterm::terminal::LeaveAlternateScreen
+    )?;
+    Ok(())
+}
+
+/// Send an RMD command to `motor_id` and log it in the Frames table.
+fn send_rmd(bus: &mut dyn CanBus, log: &mut FrameLog, motor_id: u32, cmd: RmdCommand) {
+    let frame = cmd.to_frame(motor_id);
+    log.push_frame(&frame, true);
+    let _ = bus.send(&frame);
+}
+
+fn classify_row(id: u32, motor_id: u32, data: &[u8]) -> (&'static str, String) {
+    let dir = if id == rmd::tx_id(motor_id) { "TX" } else if id == rmd::rx_id(motor_id) { "RX" } else { "--" };
+    let kind = if let Some(cmd) = data.first() {
+        match rmd::command_name(*cmd) {
+            "other" => "other".to_string(),
+            name => format!("{}(0x{:02X})", name, cmd),
+        }
+    } else { String::from("") };
+    (dir, kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use can::mock::{MockBus, Rule};
+    use can::rmd::MotorFeedback;
+
+    fn angle_reply(motor_id: u32, deg: f64) -> can::CanFrame {
+        RmdReply::MultiTurnAngle { angle_x100: (deg * 100.0).round() as i32 }.to_frame(motor_id)
+    }
+
+    #[test]
+    fn telemetry_from_mock_replies() {
+        let mut bus = MockBus::new();
+        bus.add_rule(Rule::on(rmd::tx_id(1), &[rmd::code::READ_MULTI_TURN_ANGLE]).reply_frame(angle_reply(1, -45.5)));
+        bus.add_rule(Rule::on(rmd::tx_id(1), &[rmd::code::READ_ENCODER]).reply_frame(RmdReply::Encoder { counts: -1234 }.to_frame(1)));
+        // Another motor on the same bus must not show up
+        bus.inject(angle_reply(2, 90.0));
+        bus.send(&RmdCommand::ReadMultiTurnAngle.to_frame(1)).unwrap();
+        bus.send(&RmdCommand::ReadEncoder.to_frame(1)).unwrap();
+
+        let mut telemetry = Telemetry::new(1);
+        let mut params = MotorParams::default();
+        let start = Instant::now();
+        while let Ok(frame) = bus.recv() {
+            if let Some(reply) = telemetry.handle_frame(&frame, 0.0, start) {
+                params.apply(&reply);
+            }
+        }
+        assert_eq!(telemetry.angle_x100, -4550);
+        assert_eq!(telemetry.angle_hist, vec![(0.0, -45.5)]);
+        assert!(telemetry.speed_hist.is_empty());
+        assert_eq!(params.encoder, Some(-1234));
+    }
+
+    #[test]
+    fn speed_from_consecutive_angles() {
+        let mut telemetry = Telemetry::new(3);
+        let start = Instant::now();
+        assert!(telemetry.handle_frame(&angle_reply(3, 10.0), 1.0, start).is_some());
+        assert!(telemetry.handle_frame(&angle_reply(3, 30.0), 1.5, start + Duration::from_millis(500)).is_some());
+        assert_eq!(telemetry.speed_hist, vec![(1.5, 40.0)]);
+        // Same arrival time: no speed estimate
+        telemetry.handle_frame(&angle_reply(3, 40.0), 1.5, start + Duration::from_millis(500));
+        assert_eq!(telemetry.speed_hist.len(), 1);
+        assert_eq!(telemetry.last_angle.map(|(deg, _)| deg), Some(40.0));
+    }
+
+    #[test]
+    fn history_keeps_the_chart_window() {
+        let mut telemetry = Telemetry::new(1);
+        let start = Instant::now();
+        for i in 0..=20 {
+            let t = i as f64;
+            telemetry.handle_frame(&angle_reply(1, t), t, start + Duration::from_secs(i));
+        }
+        assert_eq!(telemetry.angle_hist.first(), Some(&(10.0, 10.0)));
+        assert_eq!(telemetry.angle_hist.len(), 11);
+        assert!(telemetry.speed_hist.iter().all(|&(t, speed)| t >= 10.0 && speed == 1.0));
+    }
+
+    #[test]
+    fn ignores_other_traffic() {
+        let mut telemetry = Telemetry::new(1);
+        let now = Instant::now();
+        assert_eq!(telemetry.handle_frame(&RmdCommand::ReadMultiTurnAngle.to_frame(1), 0.0, now), None);
+        assert_eq!(telemetry.handle_frame(&angle_reply(5, 1.0), 0.0, now), None);
+        let status2 = RmdReply::Status2(MotorFeedback { temperature_c: 40, iq_x100: -50, speed_dps: 12, degree: 0 });
+        assert_eq!(telemetry.handle_frame(&status2.to_frame(1), 0.0, now), Some(status2));
+        assert!(telemetry.angle_hist.is_empty());
+        assert_eq!(telemetry.angle_x100, 0);
+    }
+}
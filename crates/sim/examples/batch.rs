@@ -0,0 +1,44 @@
+//! Measures batched stepping throughput on this machine.
+//!
+//! ```bash
+//! cargo run -p sim --release --example batch -- [worlds] [steps] [threads] [urdf]
+//! ```
+
+use sim::batch::BatchSim;
+use sim::sensors::SensorRng;
+use sim::SimConfig;
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let worlds: usize = args.first().map_or(Ok(16), |a| a.parse())?;
+    let steps: usize = args.get(1).map_or(Ok(1000), |a| a.parse())?;
+
+    let config = SimConfig { spawn_jitter: 0.2, ..Default::default() };
+    let mut batch = BatchSim::new(config, worlds)?;
+    if let Some(threads) = args.get(2) {
+        batch.set_threads(threads.parse()?);
+    }
+    if let Some(path) = args.get(3) {
+        batch.load_urdf(&sim::urdf::Urdf::from_file(path)?)?;
+    }
+    batch.reset();
+
+    let action_dim = batch.worlds()[0].action_dim();
+    let mut rng = SensorRng::new(7);
+    for _ in 0..steps {
+        let actions: Vec<Vec<f32>> =
+            (0..worlds).map(|_| (0..action_dim).map(|_| rng.uniform() * 2.0 - 1.0).collect()).collect();
+        batch.step(&actions);
+    }
+
+    let stats = batch.stats();
+    println!(
+        "{worlds} worlds on {} threads: {:.0} steps/s ({:.1}× realtime), {} episodes in {:.2} s",
+        batch.threads(),
+        stats.steps_per_second(),
+        stats.realtime_factor(),
+        stats.episodes,
+        stats.elapsed.as_secs_f64(),
+    );
+    Ok(())
+}
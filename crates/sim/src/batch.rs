@@ -0,0 +1,166 @@
+//! Batched rollouts over many independent worlds
+//! -----------------------------------------------------------------------------
+//! [`BatchSim`] owns N [`SimHandle`]s.  Each is a separate Bevy app with its
+//! own Rapier world, robot and RNG seed (`SimConfig::seed + i`), so nothing
+//! one world does can leak into another.
+//!
+//! [`BatchSim::step`] takes one action per world and returns one [`SimStep`]
+//! per world, in order.  Worlds are split across scoped threads
+//! ([`BatchSim::set_threads`]).  A world whose step reports `done` is reset
+//! right away: its `SimStep` still describes the terminal step, and the first
+//! observation of the new episode is in [`BatchSim::observations`].
+//!
+//! [`BatchSim::stats`] counts steps and wall time, to size training runs:
+//!
+//! ```bash
+//! cargo run -p sim --release --example batch -- 64
+//! ```
+//! -----------------------------------------------------------------------------
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::{init_headless_with, reset, step, urdf, SimConfig, SimHandle, SimStep};
+
+/// Throughput of a [`BatchSim`] since it was built or [`BatchSim::reset_stats`] was called.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BatchStats {
+    /// Steps taken, summed over worlds.
+    pub steps: u64,
+    /// Calls to [`BatchSim::step`].
+    pub batches: u64,
+    /// Episodes that ended and were reset.
+    pub episodes: u64,
+    /// Wall time spent in [`BatchSim::step`].
+    pub elapsed: Duration,
+    /// Physics step of every world (s).
+    pub dt: f32,
+}
+
+impl BatchStats {
+    /// World steps per wall-clock second.
+    pub fn steps_per_second(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.steps as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Simulated seconds per wall-clock second, over all worlds.
+    pub fn realtime_factor(&self) -> f64 {
+        self.steps_per_second() * self.dt as f64
+    }
+}
+
+pub struct BatchSim {
+    worlds: Vec<SimHandle>,
+    threads: usize,
+    stats: BatchStats,
+}
+
+impl BatchSim {
+    /// Build `worlds` headless worlds from `config`; world `i` gets seed `config.seed + i`.
+    pub fn new(config: SimConfig, worlds: usize) -> Result<Self> {
+        anyhow::ensure!(worlds > 0, "sim: a batch needs at least one world");
+        let worlds = (0..worlds)
+            .map(|i| init_headless_with(SimConfig { seed: config.seed.wrapping_add(i as u64), ..config.clone() }))
+            .collect::<Result<Vec<_>>>()?;
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get()).min(worlds.len());
+        let stats = BatchStats { dt: config.dt, ..BatchStats::default() };
+        Ok(Self { worlds, threads, stats })
+    }
+
+    pub fn len(&self) -> usize {
+        self.worlds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.worlds.is_empty()
+    }
+
+    pub fn worlds(&self) -> &[SimHandle] {
+        &self.worlds
+    }
+
+    pub fn worlds_mut(&mut self) -> &mut [SimHandle] {
+        &mut self.worlds
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Worker threads per [`step`](Self::step); 1 steps every world on the calling thread.
+    /// Defaults to the available parallelism.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.clamp(1, self.worlds.len());
+    }
+
+    /// Load the same robot into every world and reset them.
+    pub fn load_urdf(&mut self, description: &urdf::Urdf) -> Result<()> {
+        for world in &mut self.worlds {
+            world.load_urdf(description.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Reset every world and return their first observations.
+    pub fn reset(&mut self) -> Vec<Vec<f32>> {
+        self.worlds.iter_mut().map(reset).collect()
+    }
+
+    /// Current observation of every world.
+    pub fn observations(&mut self) -> Vec<Vec<f32>> {
+        self.worlds.iter_mut().map(SimHandle::observe).collect()
+    }
+
+    /// Step world `i` with `actions[i]`; worlds without an action get an empty one.  Worlds
+    /// that finish an episode are reset before this returns.
+    pub fn step(&mut self, actions: &[Vec<f32>]) -> Vec<SimStep> {
+        let started = Instant::now();
+        let chunk = self.worlds.len().div_ceil(self.threads);
+        let out: Vec<SimStep> = if self.threads <= 1 {
+            step_worlds(&mut self.worlds, actions)
+        } else {
+            std::thread::scope(|scope| {
+                let workers: Vec<_> = self
+                    .worlds
+                    .chunks_mut(chunk)
+                    .zip(actions.chunks(chunk).chain(std::iter::repeat(&[][..])))
+                    .map(|(worlds, actions)| scope.spawn(move || step_worlds(worlds, actions)))
+                    .collect();
+                workers.into_iter().flat_map(|w| w.join().expect("sim: batch worker panicked")).collect()
+            })
+        };
+        self.stats.steps += out.len() as u64;
+        self.stats.batches += 1;
+        self.stats.episodes += out.iter().filter(|s| s.done).count() as u64;
+        self.stats.elapsed += started.elapsed();
+        out
+    }
+
+    pub fn stats(&self) -> BatchStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = BatchStats { dt: self.stats.dt, ..BatchStats::default() };
+    }
+}
+
+fn step_worlds(worlds: &mut [SimHandle], actions: &[Vec<f32>]) -> Vec<SimStep> {
+    worlds
+        .iter_mut()
+        .enumerate()
+        .map(|(i, world)| {
+            let out = step(world, actions.get(i).map_or(&[][..], Vec::as_slice));
+            if out.done {
+                reset(world);
+            }
+            out
+        })
+        .collect()
+}
@@ -0,0 +1,284 @@
+//! Sim clock: how fast simulated time runs against the wall clock
+//! -----------------------------------------------------------------------------
+//! [`SimClock`] is a resource in the sim's Bevy world.  Runners ask it how long
+//! to wait before the next physics step ([`SimClock::until_next_step`]) and
+//! tell it when they took one ([`SimClock::advance`]).
+//!
+//! • **Realtime** – one second of sim time per wall-clock second.
+//! • **Scaled** – N sim seconds per wall-clock second (N < 1 for slow motion).
+//! • **Free** – as fast as the CPU allows, for training and CI.
+//! • **Lockstep** – step *k + 1* only once every registered controller has
+//!   acknowledged step *k*, so a slow controller never misses a step.
+//!
+//! Runners falling behind in realtime or scaled mode don't try to catch up:
+//! the schedule restarts from the late step.
+//!
+//! Over the bus, the sim publishes [`TIME_TOPIC`] after every step and
+//! controllers talk to the clock on `/sim/clock/*`:
+//!
+//! * `/sim/time` – `f64` sim time (s), then `u64` step, little-endian
+//! * `/sim/clock/register`, `/sim/clock/unregister` – controller name (UTF-8)
+//! * `/sim/clock/ack` – `u64` step the controller is done with, then its name
+//! * `/sim/clock/mode` – `realtime`, `scaled:<N>`, `free` or `lockstep`
+//! -----------------------------------------------------------------------------
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail};
+use bevy::prelude::Resource;
+
+use crate::bus_types::Envelope;
+
+/// Sim time and step count, published after every step.
+pub const TIME_TOPIC: &str = "/sim/time";
+pub const REGISTER_TOPIC: &str = "/sim/clock/register";
+pub const UNREGISTER_TOPIC: &str = "/sim/clock/unregister";
+pub const ACK_TOPIC: &str = "/sim/clock/ack";
+pub const MODE_TOPIC: &str = "/sim/clock/mode";
+
+/// Longest a runner sleeps before handling messages again.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ClockMode {
+    #[default]
+    Realtime,
+    /// Sim seconds per wall-clock second.
+    Scaled(f32),
+    Free,
+    Lockstep,
+}
+
+impl FromStr for ClockMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "realtime" => Ok(ClockMode::Realtime),
+            "free" => Ok(ClockMode::Free),
+            "lockstep" => Ok(ClockMode::Lockstep),
+            other => {
+                let scale: f32 = other
+                    .strip_prefix("scaled:")
+                    .and_then(|n| n.trim_end_matches('x').parse().ok())
+                    .ok_or_else(|| anyhow!("unknown clock mode '{s}' (realtime, scaled:<N>, free, lockstep)"))?;
+                if !(scale.is_finite() && scale > 0.0) {
+                    bail!("clock scale must be positive, got {scale}");
+                }
+                Ok(ClockMode::Scaled(scale))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ClockMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClockMode::Realtime => write!(f, "realtime"),
+            ClockMode::Scaled(scale) => write!(f, "scaled:{scale}"),
+            ClockMode::Free => write!(f, "free"),
+            ClockMode::Lockstep => write!(f, "lockstep"),
+        }
+    }
+}
+
+/// Simulated time of one world, and the controllers it waits for in lockstep.
+///
+/// Unlike [`SimHandle::time`](crate::SimHandle::time) it never goes back on reset.
+#[derive(Resource, Debug, Clone)]
+pub struct SimClock {
+    mode: ClockMode,
+    dt: f32,
+    time: f64,
+    step: u64,
+    /// Wall-clock time the next step is due, in realtime and scaled mode.
+    next: Option<Instant>,
+    /// Last step each registered controller acknowledged.
+    controllers: HashMap<String, u64>,
+}
+
+impl SimClock {
+    pub fn new(mode: ClockMode, dt: f32) -> Self {
+        Self { mode, dt, time: 0.0, step: 0, next: None, controllers: HashMap::new() }
+    }
+
+    pub fn mode(&self) -> ClockMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: ClockMode) {
+        self.mode = mode;
+        self.next = None;
+    }
+
+    /// Sim time (s).
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Steps taken so far.
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    /// Wall-clock time per step, if the mode paces steps.
+    fn period(&self) -> Option<Duration> {
+        match self.mode {
+            ClockMode::Realtime => Some(Duration::from_secs_f32(self.dt)),
+            ClockMode::Scaled(scale) => Some(Duration::from_secs_f32(self.dt / scale)),
+            ClockMode::Free | ClockMode::Lockstep => None,
+        }
+    }
+
+    /// Add a controller the clock waits for in lockstep.  It counts as done with the current
+    /// step, so its first wait is for the next one.
+    pub fn register(&mut self, controller: &str) {
+        self.controllers.entry(controller.to_string()).or_insert(self.step);
+    }
+
+    pub fn unregister(&mut self, controller: &str) {
+        self.controllers.remove(controller);
+    }
+
+    pub fn controllers(&self) -> impl Iterator<Item = &str> {
+        self.controllers.keys().map(String::as_str)
+    }
+
+    /// Record that `controller` is done with `step`; unknown controllers are ignored.
+    pub fn ack(&mut self, controller: &str, step: u64) {
+        if let Some(acked) = self.controllers.get_mut(controller) {
+            *acked = (*acked).max(step);
+        }
+    }
+
+    /// How long to wait before the next step, or `None` while lockstep waits for acks.
+    pub fn until_next_step(&self) -> Option<Duration> {
+        match self.mode {
+            ClockMode::Lockstep => self.controllers.values().all(|&acked| acked >= self.step).then_some(Duration::ZERO),
+            ClockMode::Free => Some(Duration::ZERO),
+            ClockMode::Realtime | ClockMode::Scaled(_) => {
+                Some(self.next.map_or(Duration::ZERO, |next| next.saturating_duration_since(Instant::now())))
+            }
+        }
+    }
+
+    /// Sleep until the next step is due, but at most `max` so the caller can keep handling
+    /// messages; returns whether a step is due now.
+    pub fn wait(&self, max: Duration) -> bool {
+        match self.until_next_step() {
+            Some(wait) if wait <= max => {
+                if !wait.is_zero() {
+                    std::thread::sleep(wait);
+                }
+                true
+            }
+            _ => {
+                std::thread::sleep(max);
+                false
+            }
+        }
+    }
+
+    /// Count one step of `dt` and schedule the next.
+    pub fn advance(&mut self) {
+        self.step += 1;
+        self.time += self.dt as f64;
+        let now = Instant::now();
+        self.next = self.period().map(|period| match self.next {
+            Some(next) if next + period > now => next + period,
+            // First step, or behind schedule
+            _ => now + period,
+        });
+    }
+
+    /// Apply a `/sim/clock/*` message; returns whether it was one.
+    pub fn handle_envelope(&mut self, env: &Envelope) -> anyhow::Result<bool> {
+        let text = |data: &[u8]| String::from_utf8_lossy(data).trim().to_string();
+        match env.topic.as_str() {
+            REGISTER_TOPIC => self.register(&text(&env.data)),
+            UNREGISTER_TOPIC => self.unregister(&text(&env.data)),
+            ACK_TOPIC => {
+                let step = env.data.get(..8).ok_or_else(|| anyhow!("sim: clock ack without a step"))?;
+                self.ack(&text(&env.data[8..]), u64::from_le_bytes(step.try_into().unwrap()));
+            }
+            MODE_TOPIC => self.set_mode(text(&env.data).parse()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// The [`TIME_TOPIC`] message for the current step.
+    pub fn time_envelope(&self) -> Envelope {
+        let data = self.time.to_le_bytes().into_iter().chain(self.step.to_le_bytes()).collect();
+        Envelope { topic: TIME_TOPIC.into(), data }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mode_from_str() {
+        assert_eq!("realtime".parse::<ClockMode>().unwrap(), ClockMode::Realtime);
+        assert_eq!(" Lockstep ".parse::<ClockMode>().unwrap(), ClockMode::Lockstep);
+        assert_eq!("free".parse::<ClockMode>().unwrap(), ClockMode::Free);
+        assert_eq!("scaled:0.5".parse::<ClockMode>().unwrap(), ClockMode::Scaled(0.5));
+        assert_eq!("scaled:4x".parse::<ClockMode>().unwrap(), ClockMode::Scaled(4.0));
+        for bad in ["scaled:0", "scaled:-1", "scaled:inf", "scaled:", "fast"] {
+            assert!(bad.parse::<ClockMode>().is_err(), "{bad}");
+        }
+        // Display round-trips
+        let mode = ClockMode::Scaled(2.5);
+        assert_eq!(mode.to_string().parse::<ClockMode>().unwrap(), mode);
+    }
+
+    #[test]
+    fn lockstep_waits_for_every_controller() {
+        let mut clock = SimClock::new(ClockMode::Lockstep, 0.01);
+        // Nobody to wait for
+        assert_eq!(clock.until_next_step(), Some(Duration::ZERO));
+        clock.register("act");
+        clock.register("plan");
+        // Registering counts as done with the current step
+        assert_eq!(clock.until_next_step(), Some(Duration::ZERO));
+
+        clock.advance();
+        assert_eq!(clock.step(), 1);
+        assert_eq!(clock.until_next_step(), None);
+        clock.ack("act", 1);
+        clock.ack("stranger", 1);
+        assert_eq!(clock.until_next_step(), None);
+        clock.ack("plan", 1);
+        assert_eq!(clock.until_next_step(), Some(Duration::ZERO));
+
+        // A controller that goes away no longer holds the clock up
+        clock.advance();
+        clock.ack("act", 2);
+        assert_eq!(clock.until_next_step(), None);
+        clock.unregister("plan");
+        assert_eq!(clock.until_next_step(), Some(Duration::ZERO));
+        // Old acks don't go back
+        clock.ack("act", 1);
+        assert_eq!(clock.until_next_step(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn handles_clock_envelopes() {
+        let mut clock = SimClock::new(ClockMode::Realtime, 0.01);
+        let env = |topic: &str, data: &[u8]| Envelope { topic: topic.into(), data: data.to_vec() };
+        assert!(clock.handle_envelope(&env(MODE_TOPIC, b"lockstep")).unwrap());
+        assert!(clock.handle_envelope(&env(REGISTER_TOPIC, b"act")).unwrap());
+        clock.advance();
+        assert_eq!(clock.until_next_step(), None);
+        let ack: Vec<u8> = 1u64.to_le_bytes().into_iter().chain(*b"act").collect();
+        assert!(clock.handle_envelope(&env(ACK_TOPIC, &ack)).unwrap());
+        assert_eq!(clock.until_next_step(), Some(Duration::ZERO));
+        assert!(clock.handle_envelope(&env(ACK_TOPIC, b"act")).is_err());
+        assert!(!clock.handle_envelope(&env(TIME_TOPIC, &[])).unwrap());
+        assert_eq!(clock.mode(), ClockMode::Lockstep);
+    }
+}
@@ -5,29 +5,62 @@
 //! application doesn't depend on Bevy types:
 //!
 //! ```rust
-//! use sim::{init_headless, reset, step};
+//! use sim::{init_headless, reset, step, SimStep};
 //! let mut sim = init_headless()?;
 //! let obs0 = reset(&mut sim);              // initial sensor vector
-//! loop {
-//!     let SimStep { sensors, reward, .. } = step(&mut sim, &[0.0, 1.0]);
+//! for _ in 0..100 {
+//!     let SimStep { sensors, reward, done, .. } = step(&mut sim, &[0.0, 1.0]);
+//!     if done {
+//!         reset(&mut sim);
+//!     }
 //! }
+//! # Ok::<(), anyhow::Error>(())
 //! ```
 //!
+//! Each `step` applies the action, advances physics by one fixed `dt`
+//! ([`SimConfig::dt`], 1/120 s by default) and returns the robot state: position
+//! (3), orientation quaternion w,x,y,z (4), linear velocity (3) and angular
+//! velocity (3), [`OBS_DIM`] floats in world frame.  The action is a body-frame
+//! force and torque in `[-1, 1]` ([`ACTION_DIM`] floats, scaled by
+//! [`SimConfig::max_force`]/[`SimConfig::max_torque`]); missing entries are 0.
+//! With a URDF robot loaded ([`SimHandle::load_urdf`]) the observation is the
+//! root link's state followed by every actuated joint's position, then velocity,
+//! and the action is one position target per joint, `[-1, 1]` mapped onto its
+//! limits.
+//! The reward is forward progress along +X minus a control cost, and `done` is
+//! set when the robot leaves the ground plane or the episode reaches
+//! [`SimConfig::max_steps`].
+//!
 //! Key design points
 //! -----------------
 //! • **Cross-platform** – pure Rust; Bevy 0.13 + Rapier 0.26 run on Apple-silicon
 //!   and Linux/Jetson.  No PhysX / CUDA dependency.
 //!
-//! • **Embodiment abstraction** – the current example spawns a dynamic cube so
-//!   we can wire the plumbing end-to-end quickly.  The plan is to replace the
-//!   cube with a URDF-driven articulated body exported from our CAD pipeline.
-//!   When that happens we'll keep the public API (`reset`, `step`) unchanged –
-//!   only the internal `setup()` will load a skinned mesh + joints rather than
-//!   a Rapier `Collider::cuboid`.
+//! • **Embodiment abstraction** – without a description the sim drives a
+//!   dynamic cube.  [`SimHandle::load_urdf`] (or a URDF on `/description/urdf`,
+//!   see [`SimHandle::handle_envelope`]) replaces it with an articulated body:
+//!   one Rapier rigid body per link, multibody joints with position motors (see
+//!   [`urdf`]).  The public API (`reset`, `step`) stays the same.
+//!
+//! • **Water** – [`water::WaterPlugin`] adds buoyancy, drag and added mass
+//!   inside [`water::WaterVolume`]s ([`SimHandle::add_water`]), for swimming
+//!   gaits and shore transitions.
+//!
+//! • **Sensors** – [`sensors`] simulates an IMU, joint encoders, a depth camera
+//!   and a lidar at their own rates with noise and bias; due readings come back
+//!   in [`SimStep::readings`].
+//!
+//! • **Batch rollouts** – [`batch::BatchSim`] steps N isolated worlds, each with
+//!   its own seed, with one action per world and auto-reset, and reports
+//!   steps per second.
+//!
+//! • **Time control** – [`clock::SimClock`] runs the background sims in real
+//!   time, N× scaled, as fast as possible or in lockstep with their controllers
+//!   ([`SimConfig::clock`]), and they publish sim time on `/sim/time`.
 //!
-//! • **Bus integration** – if you enable the `bus` feature and pass a `Sender<
-//!   Envelope>` the sim task will publish sensor values on `/sensor/*` topics
-//!   and listen for `/actuator/*` commands, so `mind::act` can drive it without
+//! • **Bus integration** – with the `bus` feature, [`spawn_bus_sim`] publishes
+//!   those readings on the same `/sensor/*` topics as the hardware bridges and
+//!   applies `/actuator/motor/*` commands, so `mind::act` can drive it without
 //!   modification.
 //!
 //! Running the interactive viewer
@@ -40,9 +73,12 @@
 //! plane.  Close the window to quit.
 //! -----------------------------------------------------------------------------
 
-use anyhow::Result;
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "viz")]
 use bevy::prelude::Mesh;
@@ -56,8 +92,6 @@ use quick_xml::Reader;
 use std::collections::HashSet;
 #[cfg(feature = "viz")]
 use crate::bus_types::Envelope as BusEnvelope;
-#[cfg(feature = "viz")]
-use std::collections::HashMap;
 
 #[cfg(feature = "viz")]
 use rerun::{RecordingStream, archetypes::Boxes3D};
@@ -67,14 +101,343 @@ use rerun::{RecordingStream, archetypes::Boxes3D};
 #[allow(dead_code)]
 struct RecRes(RecordingStream);
 
+/// Robot state returned by [`reset`] and [`step`]: position, orientation, linear and angular velocity.
+pub const OBS_DIM: usize = 13;
+/// Body-frame force (3) and torque (3), each in `[-1, 1]`.
+pub const ACTION_DIM: usize = 6;
+
+/// Topic `mind::morphology` publishes the live robot description on.
+pub const URDF_TOPIC: &str = "/description/urdf";
+
+/// Parameters of the headless environment.
+#[derive(Debug, Clone)]
+pub struct SimConfig {
+    /// Physics step per [`step`] call (s).
+    pub dt: f32,
+    /// Rapier substeps per step.
+    pub substeps: usize,
+    /// Episode length; `done` is set on the last step.
+    pub max_steps: u64,
+    /// Force for an action of 1.0 (N).
+    pub max_force: f32,
+    /// Torque for an action of 1.0 (N·m).
+    pub max_torque: f32,
+    /// Reward penalty per unit of squared action.
+    pub ctrl_cost: f32,
+    /// Height of the robot's origin at reset (m).
+    pub spawn_height: f32,
+    /// Random offset of the pose at each reset: up to this far along X and Z (m) and this much
+    /// yaw (rad), drawn from [`SimConfig::seed`].
+    pub spawn_jitter: f32,
+    /// Half size of the ground plane; leaving it ends the episode (m).
+    pub arena_half_extent: f32,
+    pub sensors: sensors::SensorConfig,
+    /// Seed of the sensor noise and spawn jitter.
+    pub seed: u64,
+    /// Pacing of the background runners; [`step`] itself never waits.
+    pub clock: clock::ClockMode,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            dt: 1.0 / 120.0,
+            substeps: 1,
+            max_steps: 1000,
+            max_force: 20.0,
+            max_torque: 5.0,
+            ctrl_cost: 0.01,
+            spawn_height: 1.0,
+            spawn_jitter: 0.0,
+            arena_half_extent: 10.0,
+            sensors: sensors::SensorConfig::default(),
+            seed: 0,
+            clock: clock::ClockMode::Realtime,
+        }
+    }
+}
+
+/// Name that refers to the robot in [`SimHandle::teleport`].
+pub const ROBOT_BODY: &str = "robot";
+
+/// Collision shape of a prop added with [`SimHandle::spawn_object`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ObjectShape {
+    Box { half_extents: [f32; 3] },
+    Sphere { radius: f32 },
+    Cylinder { radius: f32, half_height: f32 },
+}
+
+impl ObjectShape {
+    fn collider(self) -> Collider {
+        match self {
+            ObjectShape::Box { half_extents: [x, y, z] } => Collider::cuboid(x, y, z),
+            ObjectShape::Sphere { radius } => Collider::ball(radius),
+            ObjectShape::Cylinder { radius, half_height } => Collider::cylinder(half_height, radius),
+        }
+    }
+}
+
+/// Marker for the body that actions drive and sensors observe.
+#[derive(Component)]
+pub struct Robot;
+
+enum Body {
+    Cube(Entity),
+    Urdf(urdf::UrdfRobot),
+}
+
+impl Body {
+    fn root(&self) -> Entity {
+        match self {
+            Body::Cube(e) => *e,
+            Body::Urdf(r) => r.root,
+        }
+    }
+
+    fn sensor_target(&self) -> sensors::SensorTarget<'_> {
+        match self {
+            Body::Cube(e) => sensors::SensorTarget { root: *e, bodies: vec![*e], joints: &[] },
+            Body::Urdf(r) => sensors::SensorTarget { root: r.root, bodies: r.links.values().copied().collect(), joints: &r.joints },
+        }
+    }
+}
+
+/// A headless Bevy + Rapier world that only advances when [`step`] is called.
 pub struct SimHandle {
-    // entity IDs etc.
+    app: App,
+    config: SimConfig,
+    description: Option<(urdf::Urdf, urdf::UrdfOptions)>,
+    robot: Option<Body>,
+    sensors: sensors::SensorSuite,
+    rng: sensors::SensorRng,
+    /// Props by name; they stay in place across [`reset`].
+    objects: HashMap<String, Entity>,
+    steps: u64,
+}
+
+impl SimHandle {
+    pub fn config(&self) -> &SimConfig {
+        &self.config
+    }
+
+    /// Steps taken since the last [`reset`].
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Simulated time since the last [`reset`] (s).
+    pub fn time(&self) -> f64 {
+        self.steps as f64 * self.config.dt as f64
+    }
+
+    pub fn clock(&self) -> &clock::SimClock {
+        self.app.world.resource::<clock::SimClock>()
+    }
+
+    pub fn clock_mut(&mut self) -> Mut<'_, clock::SimClock> {
+        self.app.world.resource_mut::<clock::SimClock>()
+    }
+
+    /// The underlying Bevy app, e.g. to add plugins or inspect the world.
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    /// Replace the cube with an articulated robot built from `urdf` and reset.
+    pub fn load_urdf(&mut self, urdf: urdf::Urdf) -> Result<()> {
+        self.load_urdf_with(urdf, urdf::UrdfOptions::default())
+    }
+
+    pub fn load_urdf_with(&mut self, urdf: urdf::Urdf, options: urdf::UrdfOptions) -> Result<()> {
+        // Spawn once up front so a bad description fails here rather than in `reset`
+        let pose = self.spawn_pose();
+        let robot = urdf::spawn(&mut self.app.world, &urdf, pose, &options)?;
+        robot.despawn(&mut self.app.world);
+        self.description = Some((urdf, options));
+        reset(self);
+        Ok(())
+    }
+
+    pub fn load_urdf_file(&mut self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        self.load_urdf(urdf::Urdf::from_file(path)?)
+    }
+
+    /// Apply a bus message meant for the sim; returns whether the message was used.
+    ///
+    /// * [`URDF_TOPIC`] – a description that differs from the loaded one replaces the robot
+    /// * `/sim/clock/*` – controller registration, acks and mode changes (see [`clock`])
+    /// * `/actuator/motor/<id>/{position,speed}` – `f32` degrees or deg/s (mm, mm/s for
+    ///   prismatic joints), as the CAN bridge takes them; `/actuator/motor/<id>/stop` holds still
+    ///
+    /// Motor commands stay in effect until the next non-empty action passed to [`step`].
+    pub fn handle_envelope(&mut self, env: &bus_types::Envelope) -> Result<bool> {
+        if let Some((id, command)) = env.topic.strip_prefix("/actuator/motor/").and_then(|t| t.split_once('/')) {
+            let value = env.data.get(..4).map(|b| f32::from_le_bytes(b.try_into().unwrap()));
+            let id: Option<u32> = id.parse().ok();
+            let Some(joint) = self.robot().and_then(|r| r.joints.iter().find(|j| Some(j.motor_id) == id)) else {
+                return Ok(false);
+            };
+            let scale = if joint.kind == urdf::JointKind::Prismatic { 1e-3 } else { std::f32::consts::PI / 180.0 };
+            let joint = joint.clone();
+            let world = &mut self.app.world;
+            match (command, value) {
+                ("position", Some(v)) if v.is_finite() => joint.set_target(world, v * scale),
+                ("speed", Some(v)) if v.is_finite() => joint.set_velocity(world, v * scale),
+                ("stop", _) => joint.set_velocity(world, 0.0),
+                _ => return Ok(false),
+            }
+            return Ok(true);
+        }
+        if env.topic.starts_with("/sim/clock/") {
+            return self.clock_mut().handle_envelope(env);
+        }
+        if env.topic != URDF_TOPIC {
+            return Ok(false);
+        }
+        let xml = std::str::from_utf8(&env.data).map_err(|_| anyhow::anyhow!("sim: URDF is not UTF-8"))?;
+        let urdf = urdf::Urdf::parse(xml)?;
+        if self.description.as_ref().is_some_and(|(current, _)| *current == urdf) {
+            return Ok(false);
+        }
+        // The current robot stays loaded if the new description fails to spawn
+        let options = self.description.as_ref().map(|(_, o)| o.clone()).unwrap_or_default();
+        self.load_urdf_with(urdf, options)?;
+        Ok(true)
+    }
+
+    /// The articulated robot, if a URDF is loaded.
+    pub fn robot(&self) -> Option<&urdf::UrdfRobot> {
+        match &self.robot {
+            Some(Body::Urdf(r)) => Some(r),
+            _ => None,
+        }
+    }
+
+    pub fn action_dim(&self) -> usize {
+        self.robot().map_or(ACTION_DIM, |r| r.joints.len())
+    }
+
+    pub fn observation_dim(&self) -> usize {
+        OBS_DIM + 2 * self.robot().map_or(0, |r| r.joints.len())
+    }
+
+    /// Add a body of water; the robot's links float and feel drag inside it.
+    pub fn add_water(&mut self, volume: water::WaterVolume) -> Entity {
+        self.app.world.spawn(volume).id()
+    }
+
+    /// Add a named prop: a dynamic body that floats and can be pushed, or a fixed obstacle.
+    pub fn spawn_object(&mut self, name: &str, shape: ObjectShape, pose: Transform, dynamic: bool) -> Result<Entity> {
+        if name == ROBOT_BODY || self.objects.contains_key(name) {
+            bail!("sim: body {name} already exists");
+        }
+        let mut entity = self.app.world.spawn((Name::new(name.to_string()), TransformBundle::from(pose), shape.collider()));
+        if dynamic {
+            entity.insert((RigidBody::Dynamic, Velocity::zero(), Sleeping::disabled(), water::Hydrodynamics::default()));
+        } else {
+            entity.insert(RigidBody::Fixed);
+        }
+        let id = entity.id();
+        self.objects.insert(name.to_string(), id);
+        Ok(id)
+    }
+
+    pub fn remove_object(&mut self, name: &str) -> Result<()> {
+        let entity = self.objects.remove(name).ok_or_else(|| anyhow!("sim: no object {name}"))?;
+        self.app.world.entity_mut(entity).despawn_recursive();
+        Ok(())
+    }
+
+    pub fn objects(&self) -> impl Iterator<Item = (&str, Entity)> {
+        self.objects.iter().map(|(name, &e)| (name.as_str(), e))
+    }
+
+    /// Move the robot ([`ROBOT_BODY`]) or a prop to `pose` and bring it to rest.  A URDF robot
+    /// moves rigidly, keeping its joint angles.
+    pub fn teleport(&mut self, body: &str, pose: Transform) -> Result<()> {
+        let entities: Vec<Entity> = match (body, &self.robot) {
+            (ROBOT_BODY, Some(Body::Cube(e))) => vec![*e],
+            (ROBOT_BODY, Some(Body::Urdf(r))) => r.links.values().copied().collect(),
+            _ => vec![*self.objects.get(body).ok_or_else(|| anyhow!("sim: no body {body}"))?],
+        };
+        let root = match (body, &self.robot) {
+            (ROBOT_BODY, Some(robot)) => robot.root(),
+            _ => entities[0],
+        };
+        let current = self.app.world.get::<Transform>(root).copied().unwrap_or_default();
+        // Rigid motion taking the root to `pose`, applied to every link
+        let delta = Transform::from_matrix(pose.compute_matrix() * current.compute_matrix().inverse());
+        for entity in entities {
+            let world = &mut self.app.world;
+            if let Some(mut transform) = world.get_mut::<Transform>(entity) {
+                *transform = delta * *transform;
+            }
+            if let Some(mut velocity) = world.get_mut::<Velocity>(entity) {
+                *velocity = Velocity::zero();
+            }
+        }
+        Ok(())
+    }
+
+    /// Position target (rad, or m for prismatic joints) for the named joint of a URDF robot.
+    pub fn set_joint_target(&mut self, joint: &str, target: f32) -> Result<()> {
+        anyhow::ensure!(target.is_finite(), "sim: joint target must be finite");
+        let robot = match &self.robot {
+            Some(Body::Urdf(r)) => r,
+            _ => bail!("sim: no articulated robot loaded"),
+        };
+        let joint = robot.joints.iter().find(|j| j.name == joint).ok_or_else(|| anyhow!("sim: no joint {joint}"))?;
+        joint.set_target(&mut self.app.world, target);
+        Ok(())
+    }
+
+    fn spawn_pose(&self) -> Transform {
+        Transform::from_xyz(0.0, self.config.spawn_height, 0.0)
+    }
+
+    /// [`spawn_pose`](Self::spawn_pose) with the configured jitter.
+    fn reset_pose(&mut self) -> Transform {
+        let jitter = self.config.spawn_jitter;
+        let mut pose = self.spawn_pose();
+        if jitter > 0.0 {
+            let mut draw = || (self.rng.uniform() * 2.0 - 1.0) * jitter;
+            pose.translation += Vec3::new(draw(), 0.0, draw());
+            pose.rotation = Quat::from_rotation_y(draw());
+        }
+        pose
+    }
+
+    /// Current observation without stepping.
+    pub fn observe(&mut self) -> Vec<f32> {
+        let mut obs = vec![0.0; self.observation_dim()];
+        let Some(robot) = &self.robot else { return obs };
+        let world = &self.app.world;
+        if let Some(transform) = world.get::<Transform>(robot.root()) {
+            let velocity = world.get::<Velocity>(robot.root()).copied().unwrap_or_default();
+            let (t, r) = (transform.translation, transform.rotation);
+            let (v, w) = (velocity.linvel, velocity.angvel);
+            obs[..OBS_DIM].copy_from_slice(&[t.x, t.y, t.z, r.w, r.x, r.y, r.z, v.x, v.y, v.z, w.x, w.y, w.z]);
+        }
+        if let Body::Urdf(r) = robot {
+            let n = r.joints.len();
+            for (i, joint) in r.joints.iter().enumerate() {
+                let (position, velocity) = joint.state(world);
+                obs[OBS_DIM + i] = position;
+                obs[OBS_DIM + n + i] = velocity;
+            }
+        }
+        obs
+    }
 }
 
 pub struct SimStep {
     pub sensors: Vec<f32>,
     pub reward: f32,
     pub done: bool,
+    /// Simulated sensor readings that came due this step.
+    pub readings: Vec<sensors::SensorReading>,
 }
 
 /// Minimal re-export of Pond bus `Envelope` so that the sim crate can compile standalone.
@@ -95,34 +458,223 @@ use mesh_store::MeshStore;
 #[cfg(feature = "server")]
 pub mod server;
 
+pub mod batch;
+pub mod clock;
+pub mod sensors;
+pub mod urdf;
+pub mod water;
+
+/// Build the headless world with the default [`SimConfig`] and spawn the robot.
 pub fn init_headless() -> Result<SimHandle> {
-    App::new()
-        .add_plugins(MinimalPlugins)
+    init_headless_with(SimConfig::default())
+}
+
+pub fn init_headless_with(config: SimConfig) -> Result<SimHandle> {
+    anyhow::ensure!(config.dt > 0.0 && config.dt.is_finite(), "sim: dt must be positive");
+    let mut app = App::new();
+    app.insert_resource(Assets::<Mesh>::default());
+    app.insert_resource(bevy::scene::SceneSpawner::default());
+    app.add_plugins(MinimalPlugins)
+        .add_plugins((TransformPlugin, HierarchyPlugin))
         .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
-        .add_systems(Startup, setup)
-        .run();
-    Ok(SimHandle {})
+        .add_plugins(water::WaterPlugin)
+        .add_systems(Startup, setup);
+    {
+        // One physics step of exactly `dt` per update, independent of wall-clock time
+        let mut rapier = app.world.resource_mut::<RapierConfiguration>();
+        rapier.timestep_mode = TimestepMode::Fixed { dt: config.dt, substeps: config.substeps.max(1) };
+    }
+    app.insert_resource(clock::SimClock::new(config.clock, config.dt));
+    // `App::run` would hand the app to the schedule runner and never return; finish the
+    // plugins ourselves and drive it with `update` from `step` instead.
+    app.finish();
+    app.cleanup();
+
+    let sensors = sensors::SensorSuite::new(config.sensors.clone(), config.seed);
+    // A separate stream from the sensor noise
+    let rng = sensors::SensorRng::new(!config.seed);
+    let mut sim = SimHandle { app, config, description: None, robot: None, sensors, rng, objects: HashMap::new(), steps: 0 };
+    reset(&mut sim);
+    Ok(sim)
 }
 
 fn setup(mut commands: Commands) {
-    // ground plane
-    commands.spawn(Collider::cuboid(10.0, 0.1, 10.0));
+    // ground plane, top surface at y = 0
+    commands.spawn(TransformBundle::from(Transform::from_xyz(0.0, -0.1, 0.0)))
+        .insert(Collider::cuboid(10.0, 0.1, 10.0));
+}
+
+/// Put the robot back at its spawn pose at rest and return the first observation.
+///
+/// If the loaded URDF fails to spawn, the error is logged and the description dropped, so the
+/// episode continues with the default cube.
+pub fn reset(sim: &mut SimHandle) -> Vec<f32> {
+    match sim.robot.take() {
+        Some(Body::Cube(e)) => {
+            sim.app.world.despawn(e);
+        }
+        Some(Body::Urdf(r)) => r.despawn(&mut sim.app.world),
+        None => {}
+    }
+    let pose = sim.reset_pose();
+    let articulated = match sim.description.as_ref().map(|(u, o)| urdf::spawn(&mut sim.app.world, u, pose, o)) {
+        Some(Ok(robot)) => Some(robot),
+        // `load_urdf` already spawned this description once, so this is not expected
+        Some(Err(e)) => {
+            eprintln!("[sim] respawning URDF failed, falling back to the cube: {e:#}");
+            sim.description = None;
+            None
+        }
+        None => None,
+    };
+    sim.robot = Some(match articulated {
+        Some(robot) => {
+            sim.app.world.entity_mut(robot.root).insert(Robot);
+            for &link in robot.links.values() {
+                sim.app.world.entity_mut(link).insert(water::Hydrodynamics::default());
+            }
+            Body::Urdf(robot)
+        }
+        None => Body::Cube(
+            sim.app
+                .world
+                .spawn((
+                    Robot,
+                    TransformBundle::from(pose),
+                    RigidBody::Dynamic,
+                    Collider::cuboid(0.5, 0.5, 0.5),
+                    Velocity::zero(),
+                    ExternalForce::default(),
+                    Sleeping::disabled(),
+                    water::Hydrodynamics::default(),
+                ))
+                .id(),
+        ),
+    });
+    sim.steps = 0;
+    sim.sensors.reset();
+    sim.app.world.resource_mut::<water::WaterClock>().0 = 0.0;
+    sim.observe()
 }
 
-pub fn reset(_sim: &mut SimHandle) -> Vec<f32> { vec![0.0] }
+/// Apply `action`, advance physics by one `dt` and report the new state.
+///
+/// Joints without an action entry keep their last target, so an empty action leaves a URDF
+/// robot to the motor commands from [`SimHandle::handle_envelope`].
+pub fn step(sim: &mut SimHandle, action: &[f32]) -> SimStep {
+    let mut a = vec![0.0f32; sim.action_dim()];
+    for (slot, v) in a.iter_mut().zip(action) {
+        *slot = if v.is_finite() { v.clamp(-1.0, 1.0) } else { 0.0 };
+    }
+    let before = sim.observe();
+    match &sim.robot {
+        Some(Body::Cube(robot)) => {
+            let robot = *robot;
+            let rotation = sim.app.world.get::<Transform>(robot).map(|t| t.rotation).unwrap_or_default();
+            if let Some(mut external) = sim.app.world.get_mut::<ExternalForce>(robot) {
+                external.force = rotation * Vec3::new(a[0], a[1], a[2]) * sim.config.max_force;
+                external.torque = rotation * Vec3::new(a[3], a[4], a[5]) * sim.config.max_torque;
+            }
+        }
+        Some(Body::Urdf(robot)) => {
+            for (joint, v) in robot.joints.iter().zip(&a).take(action.len()) {
+                let (lo, hi) = joint.range();
+                joint.set_target(&mut sim.app.world, lo + (v + 1.0) * 0.5 * (hi - lo));
+            }
+        }
+        None => {}
+    }
+
+    sim.app.update();
+    sim.steps += 1;
+    sim.clock_mut().advance();
 
-pub fn step(_sim: &mut SimHandle, _action: &[f32]) -> SimStep {
-    SimStep { sensors: vec![0.0], reward: 0.0, done: false }
+    let sensors = sim.observe();
+    let effort: f32 = a.iter().map(|v| v * v).sum();
+    let reward = (sensors[0] - before[0]) - sim.config.ctrl_cost * effort;
+    let limit = sim.config.arena_half_extent;
+    let off_ground = sensors[0].abs() > limit || sensors[2].abs() > limit || sensors[1] < -1.0;
+    let diverged = sensors.iter().any(|v| !v.is_finite());
+    let done = off_ground || diverged || sim.steps >= sim.config.max_steps;
+    let time = sim.time();
+    let readings = match &sim.robot {
+        Some(robot) => sim.sensors.sample(&sim.app.world, &robot.sensor_target(), time),
+        None => Vec::new(),
+    };
+    SimStep { sensors, reward, done, readings }
+}
+
+/// Runs a headless sim on its own thread, standing in for the hardware bridges.
+///
+/// Envelopes from `bus_rx` go through [`SimHandle::handle_envelope`] (robot description,
+/// motor commands and clock control); every step's [`SimStep::readings`] are published on
+/// `/sensor/*` (see [`sensors::SensorReading::to_envelopes`]) followed by
+/// [`clock::TIME_TOPIC`], and each joint's motor is reported on `/sensor/motor/<id>/online`
+/// like the CAN bridge does.  Steps are paced by [`SimConfig::clock`]; episodes reset when
+/// `done`.
+#[cfg(feature = "bus")]
+pub fn spawn_bus_sim(
+    config: SimConfig,
+    tx: tokio::sync::broadcast::Sender<bus_types::Envelope>,
+    mut bus_rx: tokio::sync::broadcast::Receiver<bus_types::Envelope>,
+) -> std::thread::JoinHandle<Result<()>> {
+    use bus_types::Envelope;
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    std::thread::spawn(move || {
+        let mut sim = init_headless_with(config)?;
+        let mut online: Vec<u32> = Vec::new();
+        loop {
+            loop {
+                match bus_rx.try_recv() {
+                    Ok(env) => {
+                        if let Err(e) = sim.handle_envelope(&env) {
+                            let _ = tx.send(Envelope { topic: "/log/sim".into(), data: format!("{e:#}").into_bytes() });
+                        }
+                    }
+                    Err(TryRecvError::Lagged(_)) => {}
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Closed) => return Ok(()),
+                }
+            }
+
+            let ids: Vec<u32> = sim.robot().map_or(Vec::new(), |r| r.joints.iter().map(|j| j.motor_id).collect());
+            if ids != online {
+                let gone = online.iter().filter(|id| !ids.contains(id)).map(|id| (id, 0));
+                for (id, state) in gone.chain(ids.iter().map(|id| (id, 1))) {
+                    let _ = tx.send(Envelope { topic: format!("/sensor/motor/{id}/online"), data: vec![state] });
+                }
+                online = ids;
+            }
+
+            if !sim.clock().wait(clock::POLL_INTERVAL) {
+                continue;
+            }
+            let out = step(&mut sim, &[]);
+            for env in out.readings.iter().flat_map(|r| r.to_envelopes()) {
+                let _ = tx.send(env);
+            }
+            let _ = tx.send(sim.clock().time_envelope());
+            if out.done {
+                reset(&mut sim);
+            }
+        }
+    })
 }
 
 /// Spawns a background Bevy app that steps physics and logs to Rerun.
+///
+/// Steps are paced by a realtime [`clock::SimClock`] (switchable on `/sim/clock/mode`) and
+/// sim time is published on [`clock::TIME_TOPIC`] after each one.
 #[cfg(feature = "viz")]
 pub fn spawn_sim(rec: RecordingStream, mut bus_rx: tokio::sync::broadcast::Receiver<BusEnvelope>, tx: tokio::sync::broadcast::Sender<BusEnvelope>) {
     std::thread::spawn(move || {
+        let dt = SimConfig::default().dt;
         let mut app = App::new();
         app.insert_resource(Assets::<Mesh>::default());
         app.insert_resource(SceneSpawner::default());
         app.add_plugins(MinimalPlugins)
+            .add_plugins((TransformPlugin, HierarchyPlugin))
             .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
             .insert_resource(RecRes(rec.clone()))
             .add_systems(Startup, {
@@ -131,16 +683,38 @@ pub fn spawn_sim(rec: RecordingStream, mut bus_rx: tokio::sync::broadcast::Recei
                     commands.spawn(Collider::cuboid(10.0, 0.1, 10.0));
                 }
             });
+        app.world.resource_mut::<RapierConfiguration>().timestep_mode = TimestepMode::Fixed { dt, substeps: 1 };
+        app.insert_resource(clock::SimClock::new(clock::ClockMode::Realtime, dt));
 
         // Set of links already logged
         let mut logged_links: HashSet<String> = HashSet::new();
         let mut mesh_store = MeshStore::new(tx.clone());
+        // Articulated body built from the latest description
+        let mut robot: Option<(urdf::Urdf, urdf::UrdfRobot)> = None;
 
         loop {
             // Non-blocking check for bus messages
             match bus_rx.try_recv() {
-                Ok(env) if env.topic == "/description/urdf" => {
+                Ok(env) if env.topic == URDF_TOPIC => {
                     if let Ok(urdf) = String::from_utf8(env.data) {
+                        match urdf::Urdf::parse(&urdf) {
+                            Ok(description) if robot.as_ref().map_or(true, |(d, _)| *d != description) => {
+                                if let Some((_, old)) = robot.take() {
+                                    old.despawn(&mut app.world);
+                                }
+                                let base = Transform::from_xyz(0.0, 1.0, 0.0);
+                                match urdf::spawn(&mut app.world, &description, base, &urdf::UrdfOptions::default()) {
+                                    Ok(spawned) => robot = Some((description, spawned)),
+                                    Err(e) => {
+                                        let _ = tx.send(BusEnvelope{topic:"/log/sim".into(),data:format!("urdf_spawn_fail {e:#}").into_bytes()});
+                                    }
+                                }
+                            }
+                            Ok(_) => {}
+                            Err(e) => {
+                                let _ = tx.send(BusEnvelope{topic:"/log/sim".into(),data:format!("urdf_parse_fail {e:#}").into_bytes()});
+                            }
+                        }
                         let infos = extract_links(&urdf);
                         for (link, mesh) in infos {
                             // let _ = tx.send(BusEnvelope{topic:"/log/sim".into(),data:format!("link {} mesh {:?}",link,mesh).into_bytes()});
@@ -158,18 +732,39 @@ pub fn spawn_sim(rec: RecordingStream, mut bus_rx: tokio::sync::broadcast::Recei
                         }
                     }
                 }
+                Ok(env) if env.topic.starts_with("/sim/clock/") => {
+                    if let Err(e) = app.world.resource_mut::<clock::SimClock>().handle_envelope(&env) {
+                        let _ = tx.send(BusEnvelope{topic:"/log/sim".into(),data:format!("{e:#}").into_bytes()});
+                    }
+                }
                 Ok(_) | Err(tokio::sync::broadcast::error::TryRecvError::Lagged(_)) => {}
                 Err(tokio::sync::broadcast::error::TryRecvError::Empty) => {}
                 Err(tokio::sync::broadcast::error::TryRecvError::Closed) => break,
             }
 
-            // log identity transform for each link for now
+            if !app.world.resource::<clock::SimClock>().wait(clock::POLL_INTERVAL) {
+                continue;
+            }
+
+            // log each link's simulated pose (identity for links without a body)
             for link in &logged_links {
-                let _ = rec.log(format!("sim/{link}"), &rerun::archetypes::Transform3D::from_translation([0.0,0.0,0.0]));
+                let pose = robot
+                    .as_ref()
+                    .and_then(|(_, r)| r.links.get(link))
+                    .and_then(|&e| app.world.get::<Transform>(e).copied())
+                    .unwrap_or_default();
+                let (t, q) = (pose.translation, pose.rotation);
+                let transform = rerun::archetypes::Transform3D::from_translation_rotation(
+                    [t.x, t.y, t.z],
+                    rerun::Quaternion::from_xyzw([q.x, q.y, q.z, q.w]),
+                );
+                let _ = rec.log(format!("sim/{link}"), &transform);
             }
 
             app.update();
-            std::thread::sleep(std::time::Duration::from_millis(20));
+            let mut clock = app.world.resource_mut::<clock::SimClock>();
+            clock.advance();
+            let _ = tx.send(clock.time_envelope());
         }
     });
 }
@@ -0,0 +1,552 @@
+//! Simulated sensors: IMU, joint encoders, depth camera and lidar
+//! -----------------------------------------------------------------------------
+//! A [`SensorSuite`] samples the robot after every physics step and returns the
+//! readings that are due at each sensor's rate.  Every channel has a [`Noise`]
+//! model (white noise, constant bias and bias random walk) driven by a seeded
+//! RNG, so runs are reproducible.
+//!
+//! • **IMU** – specific force and angular rate in the body frame of the root
+//!   link (at rest it reads +9.81 m/s² up), plus the true orientation.
+//! • **Encoders** – position and velocity of every actuated joint.
+//! • **Depth camera** – a pinhole camera on the root link, one CPU raycast per
+//!   pixel; values are z-depth in metres, 0 where nothing is hit.
+//! • **Lidar** – a spinning multi-layer scanner, one raycast per beam.  Layers
+//!   are interpolated in elevation up to the model's 64-row range image
+//!   (row 0 = top layer; see `notes/koi.md`).
+//!
+//! The robot's own colliders are excluded from the raycasts.  With the `bus`
+//! feature, [`SensorReading::to_envelopes`] encodes readings for `/sensor/*`.
+//! -----------------------------------------------------------------------------
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+const GRAVITY: Vec3 = Vec3::new(0.0, -9.81, 0.0);
+
+/// Small deterministic RNG (SplitMix64) for sensor noise and spawn jitter.
+#[derive(Debug, Clone)]
+pub struct SensorRng(u64);
+
+impl SensorRng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in (0, 1].
+    pub fn uniform(&mut self) -> f32 {
+        ((self.next_u64() >> 40) as f32 + 1.0) / (1u64 << 24) as f32
+    }
+
+    /// Standard normal (Box–Muller).
+    pub fn gaussian(&mut self) -> f32 {
+        let (u1, u2) = (self.uniform(), self.uniform());
+        (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+    }
+}
+
+/// Error model of one sensor channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Noise {
+    /// White noise standard deviation.
+    pub std_dev: f32,
+    /// Constant offset.
+    pub bias: f32,
+    /// Bias random walk, per √s.
+    pub bias_walk: f32,
+}
+
+impl Noise {
+    pub const NONE: Noise = Noise { std_dev: 0.0, bias: 0.0, bias_walk: 0.0 };
+
+    pub fn white(std_dev: f32) -> Self {
+        Noise { std_dev, ..Noise::NONE }
+    }
+}
+
+/// A noise model with its drifting bias state.
+#[derive(Debug, Clone, Copy)]
+struct Channel {
+    noise: Noise,
+    drift: f32,
+}
+
+impl Channel {
+    fn new(noise: Noise) -> Self {
+        Self { noise, drift: 0.0 }
+    }
+
+    fn apply(&mut self, value: f32, dt: f32, rng: &mut SensorRng) -> f32 {
+        if self.noise.bias_walk > 0.0 {
+            self.drift += self.noise.bias_walk * dt.sqrt() * rng.gaussian();
+        }
+        let white = if self.noise.std_dev > 0.0 { self.noise.std_dev * rng.gaussian() } else { 0.0 };
+        value + self.noise.bias + self.drift + white
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImuConfig {
+    pub rate_hz: f32,
+    /// m/s²
+    pub accel_noise: Noise,
+    /// rad/s
+    pub gyro_noise: Noise,
+}
+
+impl Default for ImuConfig {
+    fn default() -> Self {
+        Self {
+            rate_hz: 120.0,
+            accel_noise: Noise { std_dev: 0.02, bias: 0.0, bias_walk: 0.001 },
+            gyro_noise: Noise { std_dev: 0.002, bias: 0.0, bias_walk: 0.0001 },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EncoderConfig {
+    pub rate_hz: f32,
+    /// rad (m for prismatic joints)
+    pub position_noise: Noise,
+    /// rad/s (m/s)
+    pub velocity_noise: Noise,
+}
+
+impl Default for EncoderConfig {
+    fn default() -> Self {
+        Self { rate_hz: 120.0, position_noise: Noise::white(0.0005), velocity_noise: Noise::white(0.01) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DepthCameraConfig {
+    pub rate_hz: f32,
+    pub width: usize,
+    pub height: usize,
+    /// Vertical field of view (rad).
+    pub fov_y: f32,
+    pub max_range: f32,
+    /// Per-pixel noise (m).
+    pub noise: Noise,
+    /// Camera pose on the root link; the camera looks along its −Z with +Y up.
+    pub mount: Transform,
+}
+
+impl Default for DepthCameraConfig {
+    fn default() -> Self {
+        Self {
+            rate_hz: 30.0,
+            width: 80,
+            height: 60,
+            fov_y: 58f32.to_radians(),
+            max_range: 10.0,
+            noise: Noise::white(0.01),
+            // Front face of the body, looking along +X
+            mount: Transform::from_xyz(0.5, 0.0, 0.0).with_rotation(Quat::from_rotation_y(-std::f32::consts::FRAC_PI_2)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LidarConfig {
+    pub rate_hz: f32,
+    pub layers: usize,
+    /// Rows of the range image.  Layers are interpolated in elevation to this many rows; a
+    /// pixel next to a missing return takes the nearer layer instead of blending with 0.
+    pub rows: usize,
+    /// Beams per revolution.
+    pub columns: usize,
+    /// Elevation of the lowest and highest layer (rad).
+    pub min_elevation: f32,
+    pub max_elevation: f32,
+    pub max_range: f32,
+    pub noise: Noise,
+    /// Scanner pose on the root link; azimuth 0 is its +X, elevation towards +Y.
+    pub mount: Transform,
+}
+
+impl Default for LidarConfig {
+    fn default() -> Self {
+        // 16 layers over ±15°, like a VLP-16, upsampled to the model's 64 × 1024 range image
+        Self {
+            rate_hz: 10.0,
+            layers: 16,
+            rows: 64,
+            columns: 1024,
+            min_elevation: -15f32.to_radians(),
+            max_elevation: 15f32.to_radians(),
+            max_range: 100.0,
+            noise: Noise::white(0.02),
+            mount: Transform::from_xyz(0.0, 0.6, 0.0),
+        }
+    }
+}
+
+/// Which sensors to simulate; `None` disables one.
+#[derive(Debug, Clone)]
+pub struct SensorConfig {
+    pub imu: Option<ImuConfig>,
+    pub encoders: Option<EncoderConfig>,
+    pub depth: Option<DepthCameraConfig>,
+    pub lidar: Option<LidarConfig>,
+}
+
+impl Default for SensorConfig {
+    /// IMU and encoders only; the raycast sensors cost far more per step.
+    fn default() -> Self {
+        Self { imu: Some(ImuConfig::default()), encoders: Some(EncoderConfig::default()), depth: None, lidar: None }
+    }
+}
+
+impl SensorConfig {
+    /// Every sensor at its default settings.
+    pub fn full() -> Self {
+        Self { depth: Some(DepthCameraConfig::default()), lidar: Some(LidarConfig::default()), ..Self::default() }
+    }
+
+    pub fn none() -> Self {
+        Self { imu: None, encoders: None, depth: None, lidar: None }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImuReading {
+    /// Sim time (s).
+    pub time: f64,
+    /// Specific force in the body frame (m/s²).
+    pub accel: Vec3,
+    /// Angular rate in the body frame (rad/s).
+    pub gyro: Vec3,
+    /// Body orientation in the world frame (noise-free).
+    pub orientation: Quat,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JointReading {
+    pub name: String,
+    pub motor_id: u32,
+    pub prismatic: bool,
+    /// rad, or m for prismatic joints.
+    pub position: f32,
+    pub velocity: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthImage {
+    pub time: f64,
+    pub width: usize,
+    pub height: usize,
+    /// Row-major z-depth (m), 0 = no return.
+    pub depth: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeImage {
+    pub time: f64,
+    pub rows: usize,
+    pub cols: usize,
+    /// Row-major range (m), 0 = no return.
+    pub ranges: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SensorReading {
+    Imu(ImuReading),
+    Encoders { time: f64, joints: Vec<JointReading> },
+    Depth(DepthImage),
+    Lidar(RangeImage),
+}
+
+/// Body the sensors are mounted on and the joints the encoders read.
+pub struct SensorTarget<'a> {
+    pub root: Entity,
+    /// Entities whose colliders (and child colliders) raycasts ignore.
+    pub bodies: Vec<Entity>,
+    pub joints: &'a [crate::urdf::SpawnedJoint],
+}
+
+/// Next due time of a sensor running at `rate_hz`.
+#[derive(Debug, Clone, Copy)]
+struct Schedule {
+    period: f64,
+    next: f64,
+}
+
+impl Schedule {
+    fn new(rate_hz: f32) -> Self {
+        Self { period: if rate_hz > 0.0 { 1.0 / rate_hz as f64 } else { f64::INFINITY }, next: 0.0 }
+    }
+
+    fn due(&mut self, time: f64) -> bool {
+        // Small tolerance so a rate equal to the step rate fires every step
+        if time + 1e-9 < self.next {
+            return false;
+        }
+        self.next = (self.next + self.period).max(time);
+        true
+    }
+}
+
+/// Sensor state for one robot; reset it with the episode.
+pub struct SensorSuite {
+    config: SensorConfig,
+    rng: SensorRng,
+    seed: u64,
+    imu: Option<(Schedule, [Channel; 6], Option<Vec3>)>,
+    encoders: Option<(Schedule, Vec<(Channel, Channel)>)>,
+    depth: Option<Schedule>,
+    lidar: Option<Schedule>,
+    last_time: f64,
+}
+
+impl SensorSuite {
+    pub fn new(config: SensorConfig, seed: u64) -> Self {
+        let mut suite = Self {
+            config,
+            rng: SensorRng::new(seed),
+            seed,
+            imu: None,
+            encoders: None,
+            depth: None,
+            lidar: None,
+            last_time: 0.0,
+        };
+        suite.reset();
+        suite
+    }
+
+    pub fn config(&self) -> &SensorConfig {
+        &self.config
+    }
+
+    /// Restart schedules, biases and the noise sequence.
+    pub fn reset(&mut self) {
+        self.rng = SensorRng::new(self.seed);
+        self.imu = self.config.imu.as_ref().map(|c| {
+            let (a, g) = (Channel::new(c.accel_noise), Channel::new(c.gyro_noise));
+            (Schedule::new(c.rate_hz), [a, a, a, g, g, g], None)
+        });
+        self.encoders = self.config.encoders.as_ref().map(|c| (Schedule::new(c.rate_hz), Vec::new()));
+        self.depth = self.config.depth.as_ref().map(|c| Schedule::new(c.rate_hz));
+        self.lidar = self.config.lidar.as_ref().map(|c| Schedule::new(c.rate_hz));
+        self.last_time = 0.0;
+    }
+
+    /// Readings due at sim time `time`, taken from the current world state.
+    pub fn sample(&mut self, world: &World, target: &SensorTarget, time: f64) -> Vec<SensorReading> {
+        let dt = (time - self.last_time).max(0.0) as f32;
+        self.last_time = time;
+        let mut out = Vec::new();
+        let Some(pose) = world.get::<Transform>(target.root).copied() else { return out };
+        let velocity = world.get::<Velocity>(target.root).copied().unwrap_or_default();
+
+        if let Some((schedule, channels, last_velocity)) = self.imu.as_mut() {
+            // Acceleration needs the previous step even when this one isn't published
+            let accel_world = match last_velocity {
+                Some(v) if dt > 0.0 => (velocity.linvel - *v) / dt,
+                _ => Vec3::ZERO,
+            };
+            *last_velocity = Some(velocity.linvel);
+            if schedule.due(time) {
+                let inv = pose.rotation.inverse();
+                let accel = inv * (accel_world - GRAVITY);
+                let gyro = inv * velocity.angvel;
+                let period = schedule.period.min(1.0) as f32;
+                let rng = &mut self.rng;
+                let mut noisy = [accel.x, accel.y, accel.z, gyro.x, gyro.y, gyro.z];
+                for (v, ch) in noisy.iter_mut().zip(channels.iter_mut()) {
+                    *v = ch.apply(*v, period, rng);
+                }
+                out.push(SensorReading::Imu(ImuReading {
+                    time,
+                    accel: Vec3::new(noisy[0], noisy[1], noisy[2]),
+                    gyro: Vec3::new(noisy[3], noisy[4], noisy[5]),
+                    orientation: pose.rotation,
+                }));
+            }
+        }
+
+        if let (Some((schedule, channels)), Some(config)) = (self.encoders.as_mut(), self.config.encoders.as_ref()) {
+            if !target.joints.is_empty() && schedule.due(time) {
+                channels.resize(target.joints.len(), (Channel::new(config.position_noise), Channel::new(config.velocity_noise)));
+                let period = schedule.period.min(1.0) as f32;
+                let joints = target
+                    .joints
+                    .iter()
+                    .zip(channels.iter_mut())
+                    .map(|(joint, (pc, vc))| {
+                        let (position, velocity) = joint.state(world);
+                        JointReading {
+                            name: joint.name.clone(),
+                            motor_id: joint.motor_id,
+                            prismatic: joint.kind == crate::urdf::JointKind::Prismatic,
+                            position: pc.apply(position, period, &mut self.rng),
+                            velocity: vc.apply(velocity, period, &mut self.rng),
+                        }
+                    })
+                    .collect();
+                out.push(SensorReading::Encoders { time, joints });
+            }
+        }
+
+        let depth = self.config.depth.as_ref().filter(|_| self.depth.as_mut().is_some_and(|s| s.due(time)));
+        let lidar = self.config.lidar.as_ref().filter(|_| self.lidar.as_mut().is_some_and(|s| s.due(time)));
+        if depth.is_some() || lidar.is_some() {
+            let Some(context) = world.get_resource::<RapierContext>() else { return out };
+            let excluded = own_colliders(world, &target.bodies);
+            let caster = Raycaster { context, excluded: &excluded };
+            if let Some(config) = depth {
+                out.push(SensorReading::Depth(depth_image(&caster, config, pose, time, &mut self.rng)));
+            }
+            if let Some(config) = lidar {
+                out.push(SensorReading::Lidar(range_image(&caster, config, pose, time, &mut self.rng)));
+            }
+        }
+        out
+    }
+}
+
+fn own_colliders(world: &World, bodies: &[Entity]) -> HashSet<Entity> {
+    let mut out = HashSet::new();
+    for &body in bodies {
+        out.insert(body);
+        if let Some(children) = world.get::<Children>(body) {
+            out.extend(children.iter().copied());
+        }
+    }
+    out
+}
+
+struct Raycaster<'a> {
+    context: &'a RapierContext,
+    excluded: &'a HashSet<Entity>,
+}
+
+impl Raycaster<'_> {
+    /// Distance to the first hit along unit `dir`.
+    fn cast(&self, origin: Vec3, dir: Vec3, max_range: f32) -> Option<f32> {
+        let predicate = |e: Entity| !self.excluded.contains(&e);
+        let filter = QueryFilter::new().exclude_sensors().predicate(&predicate);
+        self.context.cast_ray(origin, dir, max_range, true, filter).map(|(_, toi)| toi)
+    }
+}
+
+fn depth_image(caster: &Raycaster, config: &DepthCameraConfig, body: Transform, time: f64, rng: &mut SensorRng) -> DepthImage {
+    let camera = body * config.mount;
+    let (w, h) = (config.width.max(1), config.height.max(1));
+    let focal = (h as f32 / 2.0) / (config.fov_y / 2.0).tan();
+    let mut channel = Channel::new(config.noise);
+    let mut depth = Vec::with_capacity(w * h);
+    for v in 0..h {
+        for u in 0..w {
+            let local = Vec3::new(u as f32 + 0.5 - w as f32 / 2.0, -(v as f32 + 0.5 - h as f32 / 2.0), -focal).normalize();
+            let dir = camera.rotation * local;
+            let z = caster.cast(camera.translation, dir, config.max_range).map_or(0.0, |toi| toi * -local.z);
+            depth.push(if z > 0.0 { channel.apply(z, 0.0, rng).max(0.0) } else { 0.0 });
+        }
+    }
+    DepthImage { time, width: w, height: h, depth }
+}
+
+fn range_image(caster: &Raycaster, config: &LidarConfig, body: Transform, time: f64, rng: &mut SensorRng) -> RangeImage {
+    let scanner = body * config.mount;
+    let (layers, cols) = (config.layers.max(1), config.columns.max(1));
+    let mut channel = Channel::new(config.noise);
+    let mut scan = Vec::with_capacity(layers * cols);
+    for layer in 0..layers {
+        // Layer 0 is the top one
+        let f = if layers > 1 { layer as f32 / (layers - 1) as f32 } else { 0.5 };
+        let elevation = config.max_elevation + (config.min_elevation - config.max_elevation) * f;
+        for col in 0..cols {
+            let azimuth = col as f32 / cols as f32 * std::f32::consts::TAU;
+            let local = Vec3::new(elevation.cos() * azimuth.cos(), elevation.sin(), -elevation.cos() * azimuth.sin());
+            let range = caster.cast(scanner.translation, scanner.rotation * local, config.max_range).unwrap_or(0.0);
+            scan.push(if range > 0.0 { channel.apply(range, 0.0, rng).max(0.0) } else { 0.0 });
+        }
+    }
+    let rows = config.rows.max(1);
+    RangeImage { time, rows, cols, ranges: resample_rows(&scan, layers, cols, rows) }
+}
+
+/// Linearly resamples a row-major `layers × cols` scan to `rows` rows, keeping the first and
+/// last layer.  Where either neighbour has no return (0), the nearer one is taken as is.
+fn resample_rows(scan: &[f32], layers: usize, cols: usize, rows: usize) -> Vec<f32> {
+    if rows == layers {
+        return scan.to_vec();
+    }
+    let mut ranges = Vec::with_capacity(rows * cols);
+    for row in 0..rows {
+        let pos = if rows > 1 { row as f32 * (layers - 1) as f32 / (rows - 1) as f32 } else { (layers - 1) as f32 / 2.0 };
+        let lo = (pos.floor() as usize).min(layers - 1);
+        let hi = (lo + 1).min(layers - 1);
+        let t = pos - lo as f32;
+        for col in 0..cols {
+            let (a, b) = (scan[lo * cols + col], scan[hi * cols + col]);
+            ranges.push(if a > 0.0 && b > 0.0 {
+                a + (b - a) * t
+            } else if t < 0.5 {
+                a
+            } else {
+                b
+            });
+        }
+    }
+    ranges
+}
+
+#[cfg(feature = "bus")]
+fn f32_payload(header: &[u32], values: impl IntoIterator<Item = f32>) -> Vec<u8> {
+    header.iter().flat_map(|h| h.to_le_bytes()).chain(values.into_iter().flat_map(f32::to_le_bytes)).collect()
+}
+
+#[cfg(feature = "bus")]
+impl SensorReading {
+    /// Bus messages for this reading, little-endian like the hardware bridges:
+    ///
+    /// * `/sensor/imu` – 10 × `f32`: accel xyz (m/s²), gyro xyz (rad/s), orientation wxyz
+    /// * `/sensor/motor/<id>/{angle,speed}` – `f32` degrees and deg/s per joint, as the CAN
+    ///   bridge publishes them for real motors (prismatic joints: mm and mm/s)
+    /// * `/sensor/depth` – `u32` width, `u32` height, then row-major `f32` metres
+    /// * `/sensor/lidar` – `u32` rows, `u32` cols, then row-major `f32` metres
+    pub fn to_envelopes(&self) -> Vec<crate::bus_types::Envelope> {
+        use crate::bus_types::Envelope;
+        match self {
+            SensorReading::Imu(r) => {
+                let (a, g, q) = (r.accel, r.gyro, r.orientation);
+                vec![Envelope {
+                    topic: "/sensor/imu".into(),
+                    data: f32_payload(&[], [a.x, a.y, a.z, g.x, g.y, g.z, q.w, q.x, q.y, q.z]),
+                }]
+            }
+            SensorReading::Encoders { joints, .. } => joints
+                .iter()
+                .flat_map(|j| {
+                    // Real motors report degrees; prismatic joints go out in millimetres
+                    let scale = if j.prismatic { 1000.0 } else { 180.0 / std::f32::consts::PI };
+                    [
+                        Envelope { topic: format!("/sensor/motor/{}/angle", j.motor_id), data: (j.position * scale).to_le_bytes().to_vec() },
+                        Envelope { topic: format!("/sensor/motor/{}/speed", j.motor_id), data: (j.velocity * scale).to_le_bytes().to_vec() },
+                    ]
+                })
+                .collect(),
+            SensorReading::Depth(d) => vec![Envelope {
+                topic: "/sensor/depth".into(),
+                data: f32_payload(&[d.width as u32, d.height as u32], d.depth.iter().copied()),
+            }],
+            SensorReading::Lidar(l) => vec![Envelope {
+                topic: "/sensor/lidar".into(),
+                data: f32_payload(&[l.rows as u32, l.cols as u32], l.ranges.iter().copied()),
+            }],
+        }
+    }
+}
@@ -2,6 +2,25 @@
 //!
 //! This module provides a WebSocket server that clients like sim-view can
 //! connect to for receiving real-time simulation state updates.
+//!
+//! Clients can also drive the simulation: every text message is a JSON
+//! [`Request`] carrying an `id` and a `cmd`, answered with an [`Ack`] of the
+//! same `id` once the sim has applied it:
+//!
+//! ```text
+//! → {"id": 1, "cmd": "pause"}
+//! ← {"type": "ack", "id": 1, "ok": true, "time": 3.25, "paused": true}
+//! → {"id": 2, "cmd": "spawn", "name": "rock", "shape": {"type": "sphere", "radius": 0.2}, "position": [1, 0.5, 0]}
+//! → {"id": 3, "cmd": "joint_targets", "targets": {"joint_motor_1": 0.5}}
+//! → {"id": 4, "cmd": "step", "count": 10}
+//! ← {"type": "ack", "id": 4, "ok": true, "time": 3.3333, "paused": true}
+//! → {"id": 5, "cmd": "teleport", "body": "robot", "position": [0, 1, 0]}
+//! ← {"type": "ack", "id": 5, "ok": false, "error": "...", ...}
+//! ```
+//!
+//! See [`Command`] for the full set.  Commands need a sim attached with
+//! [`ServerState::with_control`] and [`spawn_control_sim`]; otherwise they are
+//! rejected.
 
 use axum::{
     extract::{
@@ -12,11 +31,24 @@ use axum::{
     routing::get,
     Router,
 };
+use bevy::prelude::{Quat, Transform, Vec3};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
 use tower_http::cors::CorsLayer;
 
+use crate::clock::{ClockMode, POLL_INTERVAL};
+use crate::{ObjectShape, SimConfig, SimHandle};
+
+/// How long a client waits for the sim to apply a command.
+const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest `step` count.  Steps run on the sim thread, so a bigger batch would hold up
+/// every other client's commands.
+pub const MAX_STEP_COUNT: u32 = 10_000;
+
 /// Simulation state that gets broadcast to all connected clients
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimState {
@@ -37,11 +69,96 @@ impl Default for SimState {
     }
 }
 
+fn identity() -> [f32; 4] {
+    [1.0, 0.0, 0.0, 0.0]
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn yes() -> bool {
+    true
+}
+
+/// A control command from a client.  Rotations are quaternions (w, x, y, z) and default to
+/// identity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Command {
+    /// Put the robot back at its spawn pose; props stay.
+    Reset,
+    Pause,
+    Resume,
+    /// Advance `count` physics steps right away, paused or not; at most [`MAX_STEP_COUNT`].
+    Step {
+        #[serde(default = "one")]
+        count: u32,
+    },
+    /// Sim seconds per wall-clock second while running.
+    SetTimeScale { scale: f32 },
+    /// `realtime`, `scaled:<N>`, `free` or `lockstep` (see [`ClockMode`]).
+    SetClock { mode: String },
+    /// Position targets by joint name (rad, or m for prismatic joints).
+    JointTargets { targets: HashMap<String, f32> },
+    /// Move `robot` or a spawned object and bring it to rest.
+    Teleport {
+        body: String,
+        position: [f32; 3],
+        #[serde(default = "identity")]
+        rotation: [f32; 4],
+    },
+    Spawn {
+        name: String,
+        shape: ObjectShape,
+        position: [f32; 3],
+        #[serde(default = "identity")]
+        rotation: [f32; 4],
+        /// A fixed obstacle when false.
+        #[serde(default = "yes")]
+        dynamic: bool,
+    },
+    Remove { name: String },
+}
+
+/// A client message: a command and the ID its [`Ack`] will carry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub id: u64,
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+/// Reply to a [`Request`], sent only to the client that made it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "ack")]
+pub struct Ack {
+    /// `None` when the request could not be parsed far enough to find it.
+    pub id: Option<u64>,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Sim time after the command (s).
+    pub time: f64,
+    pub paused: bool,
+}
+
+impl Ack {
+    fn rejected(id: Option<u64>, error: String) -> Self {
+        Self { id, ok: false, error: Some(error), time: 0.0, paused: false }
+    }
+}
+
+/// A command on its way to the sim thread, with the channel for its [`Ack`].
+pub type ControlMessage = (Request, oneshot::Sender<Ack>);
+
 /// Shared state for the simulation server
 #[derive(Clone)]
 pub struct ServerState {
     pub sim_state: Arc<RwLock<SimState>>,
     pub tx: broadcast::Sender<SimState>,
+    /// Where client commands go; `None` when no sim accepts them.
+    pub control: Option<mpsc::Sender<ControlMessage>>,
 }
 
 impl ServerState {
@@ -50,9 +167,16 @@ impl ServerState {
         Self {
             sim_state: Arc::new(RwLock::new(SimState::default())),
             tx,
+            control: None,
         }
     }
 
+    /// Server state that accepts client commands; hand the receiver to [`spawn_control_sim`].
+    pub fn with_control() -> (Self, mpsc::Receiver<ControlMessage>) {
+        let (control, commands) = mpsc::channel(64);
+        (Self { control: Some(control), ..Self::new() }, commands)
+    }
+
     /// Update the simulation state and broadcast to all clients
     pub async fn update_state(&self, state: SimState) {
         *self.sim_state.write().await = state.clone();
@@ -110,9 +234,15 @@ async fn handle_socket(mut socket: WebSocket, state: ServerState) {
                             break;
                         }
                     }
-                    _ => {
-                        // Future: handle control commands from client
+                    Message::Text(text) => {
+                        let ack = handle_request(&state, &text).await;
+                        if let Ok(json) = serde_json::to_string(&ack) {
+                            if socket.send(Message::Text(json)).await.is_err() {
+                                break;
+                            }
+                        }
                     }
+                    _ => {}
                 }
             }
             else => break,
@@ -120,6 +250,146 @@ async fn handle_socket(mut socket: WebSocket, state: ServerState) {
     }
 }
 
+/// Parse a client message, pass it to the sim and wait for its acknowledgement.
+async fn handle_request(state: &ServerState, text: &str) -> Ack {
+    let request: Request = match serde_json::from_str(text) {
+        Ok(r) => r,
+        Err(e) => {
+            // Still echo the ID if there is one, so the client can match the failure
+            let id = serde_json::from_str::<serde_json::Value>(text).ok().and_then(|v| v.get("id")?.as_u64());
+            return Ack::rejected(id, format!("bad request: {e}"));
+        }
+    };
+    let id = request.id;
+    let Some(control) = &state.control else {
+        return Ack::rejected(Some(id), "this server does not accept commands".into());
+    };
+    let (reply, ack) = oneshot::channel();
+    if control.send((request, reply)).await.is_err() {
+        return Ack::rejected(Some(id), "simulation has stopped".into());
+    }
+    match tokio::time::timeout(ACK_TIMEOUT, ack).await {
+        Ok(Ok(ack)) => ack,
+        Ok(Err(_)) => Ack::rejected(Some(id), "simulation has stopped".into()),
+        Err(_) => Ack::rejected(Some(id), "timed out waiting for the simulation".into()),
+    }
+}
+
+fn pose(position: [f32; 3], [w, x, y, z]: [f32; 4]) -> Transform {
+    let rotation = Quat::from_xyzw(x, y, z, w);
+    let rotation = if rotation.length_squared() > 0.0 { rotation.normalize() } else { Quat::IDENTITY };
+    Transform::from_translation(Vec3::from(position)).with_rotation(rotation)
+}
+
+/// A sim driven by clients; pausing stops stepping whatever the clock mode.
+struct Runner {
+    sim: SimHandle,
+    paused: bool,
+}
+
+impl Runner {
+    fn apply(&mut self, command: Command) -> anyhow::Result<()> {
+        match command {
+            Command::Reset => {
+                crate::reset(&mut self.sim);
+            }
+            Command::Pause => self.paused = true,
+            Command::Resume => self.paused = false,
+            Command::Step { count } => {
+                anyhow::ensure!(count <= MAX_STEP_COUNT, "step count {count} is above the limit of {MAX_STEP_COUNT}");
+                for _ in 0..count {
+                    self.step();
+                }
+            }
+            Command::SetTimeScale { scale } => {
+                anyhow::ensure!(scale.is_finite() && scale > 0.0, "time scale must be positive");
+                self.sim.clock_mut().set_mode(ClockMode::Scaled(scale));
+            }
+            Command::SetClock { mode } => {
+                let mode: ClockMode = mode.parse()?;
+                self.sim.clock_mut().set_mode(mode);
+            }
+            Command::JointTargets { targets } => {
+                for (joint, target) in targets {
+                    self.sim.set_joint_target(&joint, target)?;
+                }
+            }
+            Command::Teleport { body, position, rotation } => self.sim.teleport(&body, pose(position, rotation))?,
+            Command::Spawn { name, shape, position, rotation, dynamic } => {
+                self.sim.spawn_object(&name, shape, pose(position, rotation), dynamic)?;
+            }
+            Command::Remove { name } => self.sim.remove_object(&name)?,
+        }
+        Ok(())
+    }
+
+    fn step(&mut self) {
+        // An empty action keeps the joint targets clients have set
+        if crate::step(&mut self.sim, &[]).done {
+            crate::reset(&mut self.sim);
+        }
+    }
+
+    fn state(&mut self) -> SimState {
+        let obs = self.sim.observe();
+        let joints = self.sim.robot().map_or(0, |r| r.joints.len());
+        SimState {
+            robot_position: [obs[0], obs[1], obs[2]],
+            robot_rotation: [obs[3], obs[4], obs[5], obs[6]],
+            joint_positions: obs[crate::OBS_DIM..crate::OBS_DIM + joints].to_vec(),
+            timestamp: self.sim.time(),
+        }
+    }
+}
+
+/// Runs a headless sim on its own thread that applies client commands from `commands` and
+/// publishes its state through `state` after every step.  Steps are paced by
+/// [`SimConfig::clock`]; episodes reset when `done`.
+pub fn spawn_control_sim(
+    config: SimConfig,
+    state: ServerState,
+    mut commands: mpsc::Receiver<ControlMessage>,
+) -> std::thread::JoinHandle<anyhow::Result<()>> {
+    std::thread::spawn(move || {
+        let mut runner = Runner { sim: crate::init_headless_with(config)?, paused: false };
+        loop {
+            let mut changed = false;
+            loop {
+                match commands.try_recv() {
+                    Ok((request, reply)) => {
+                        let result = runner.apply(request.command);
+                        let _ = reply.send(Ack {
+                            id: Some(request.id),
+                            ok: result.is_ok(),
+                            error: result.err().map(|e| format!("{e:#}")),
+                            time: runner.sim.time(),
+                            paused: runner.paused,
+                        });
+                        changed = true;
+                    }
+                    Err(mpsc::error::TryRecvError::Empty) => break,
+                    Err(mpsc::error::TryRecvError::Disconnected) => return Ok(()),
+                }
+            }
+
+            let stepped = if runner.paused {
+                std::thread::sleep(POLL_INTERVAL);
+                false
+            } else if runner.sim.clock().wait(POLL_INTERVAL) {
+                runner.step();
+                true
+            } else {
+                false
+            };
+            if changed || stepped {
+                let sim_state = runner.state();
+                *state.sim_state.blocking_write() = sim_state.clone();
+                let _ = state.tx.send(sim_state);
+            }
+        }
+    })
+}
+
 /// Start the WebSocket server
 pub async fn start_server(addr: &str, state: ServerState) -> anyhow::Result<()> {
     let app = create_router(state);
@@ -0,0 +1,755 @@
+//! URDF → Rapier articulated bodies
+//! -----------------------------------------------------------------------------
+//! [`Urdf::parse`] reads the parts of a URDF that matter for physics: links with
+//! their inertials and collision geometry, and joints (revolute, continuous,
+//! prismatic, fixed, floating) with origins, axes, limits and damping.  Visual
+//! elements are skipped; `extract_links` still handles those for Rerun.
+//!
+//! [`spawn`] turns the tree into one Rapier rigid body per link, connected by
+//! multibody joints with position motors.  A link attached to `world` by a fixed
+//! joint becomes a fixed base; otherwise the root link floats.
+//!
+//! Simplifications:
+//! • Off-diagonal inertia terms are ignored; the principal axes are taken from
+//!   the inertial `origin` rpy.
+//! • Links without `<inertial>` get [`UrdfOptions::default_mass`].
+//! • Only OBJ meshes are loaded, as convex hulls.  `package://` / `pkg://` URIs
+//!   resolve under [`UrdfOptions::mesh_root`]; unresolvable meshes are skipped.
+//! -----------------------------------------------------------------------------
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+/// Pseudo-link URDFs use as the parent of a base that is bolted to the ground.
+pub const WORLD_LINK: &str = "world";
+
+/// `<origin xyz=".." rpy=".."/>`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Origin {
+    pub xyz: [f32; 3],
+    /// Fixed-axis roll, pitch, yaw (rad).
+    pub rpy: [f32; 3],
+}
+
+impl Origin {
+    pub fn rotation(&self) -> Quat {
+        let [r, p, y] = self.rpy;
+        // URDF rpy: rotate about X, then Y, then Z (fixed axes) = Rz * Ry * Rx
+        Quat::from_rotation_z(y) * Quat::from_rotation_y(p) * Quat::from_rotation_x(r)
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(Vec3::from(self.xyz)).with_rotation(self.rotation())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inertial {
+    pub origin: Origin,
+    pub mass: f32,
+    /// Diagonal of the inertia tensor (ixx, iyy, izz).
+    pub inertia: [f32; 3],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Geometry {
+    Box { size: [f32; 3] },
+    /// Along the link's Z axis.
+    Cylinder { radius: f32, length: f32 },
+    Sphere { radius: f32 },
+    Mesh { filename: String, scale: [f32; 3] },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collision {
+    pub origin: Origin,
+    pub geometry: Geometry,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Link {
+    pub name: String,
+    pub inertial: Option<Inertial>,
+    pub collisions: Vec<Collision>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JointKind {
+    Revolute,
+    Continuous,
+    Prismatic,
+    Fixed,
+    Floating,
+}
+
+impl JointKind {
+    /// Has one degree of freedom driven by a motor.
+    pub fn is_actuated(self) -> bool {
+        matches!(self, JointKind::Revolute | JointKind::Continuous | JointKind::Prismatic)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    pub lower: f32,
+    pub upper: f32,
+    /// Maximum motor force/torque; 0 means unlimited.
+    pub effort: f32,
+    pub velocity: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Joint {
+    pub name: String,
+    pub kind: JointKind,
+    pub parent: String,
+    pub child: String,
+    /// Child link frame relative to the parent link frame at zero position.
+    pub origin: Origin,
+    /// Unit axis in the joint (child) frame.
+    pub axis: [f32; 3],
+    pub limit: Option<Limit>,
+    pub damping: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Urdf {
+    pub name: String,
+    pub links: Vec<Link>,
+    pub joints: Vec<Joint>,
+    /// Directory relative mesh paths resolve against (set by [`Urdf::from_file`]).
+    pub base_dir: Option<PathBuf>,
+}
+
+fn attributes(e: &BytesStart) -> HashMap<String, String> {
+    e.attributes()
+        .flatten()
+        .filter_map(|a| {
+            let key = String::from_utf8_lossy(a.key.as_ref()).into_owned();
+            a.unescape_value().ok().map(|v| (key, v.into_owned()))
+        })
+        .collect()
+}
+
+fn floats<const N: usize>(attrs: &HashMap<String, String>, key: &str, default: [f32; N]) -> Result<[f32; N]> {
+    let Some(text) = attrs.get(key) else { return Ok(default) };
+    let values: Vec<f32> = text
+        .split_whitespace()
+        .map(|v| v.parse::<f32>())
+        .collect::<Result<_, _>>()
+        .with_context(|| format!("urdf: bad {key}=\"{text}\""))?;
+    values.try_into().map_err(|_| anyhow!("urdf: {key}=\"{text}\" needs {N} values"))
+}
+
+fn float(attrs: &HashMap<String, String>, key: &str, default: f32) -> Result<f32> {
+    Ok(floats::<1>(attrs, key, [default])?[0])
+}
+
+fn origin(attrs: &HashMap<String, String>) -> Result<Origin> {
+    Ok(Origin { xyz: floats(attrs, "xyz", [0.0; 3])?, rpy: floats(attrs, "rpy", [0.0; 3])? })
+}
+
+impl Urdf {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let xml = std::fs::read_to_string(path).with_context(|| format!("urdf: read {path:?}"))?;
+        let mut urdf = Self::parse(&xml)?;
+        urdf.base_dir = path.parent().map(Path::to_path_buf);
+        Ok(urdf)
+    }
+
+    pub fn parse(xml: &str) -> Result<Self> {
+        let mut reader = Reader::from_str(xml);
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+        let mut urdf = Urdf::default();
+        // Element names from the root down to the current element
+        let mut path: Vec<String> = Vec::new();
+        let mut link: Option<Link> = None;
+        let mut joint: Option<Joint> = None;
+        let mut collision: Option<(Origin, Option<Geometry>)> = None;
+
+        loop {
+            let (e, empty) = match reader.read_event_into(&mut buf) {
+                Ok(Event::Start(e)) => (e.into_owned(), false),
+                Ok(Event::Empty(e)) => (e.into_owned(), true),
+                Ok(Event::End(_)) => {
+                    let name = path.pop().unwrap_or_default();
+                    Self::close(&mut urdf, &path, &name, &mut link, &mut joint, &mut collision)?;
+                    buf.clear();
+                    continue;
+                }
+                Ok(Event::Eof) => break,
+                Ok(_) => {
+                    buf.clear();
+                    continue;
+                }
+                Err(e) => bail!("urdf: xml error at {}: {e}", reader.buffer_position()),
+            };
+            let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+            let attrs = attributes(&e);
+            let parent = path.last().map(String::as_str).unwrap_or("");
+            let in_link = link.is_some() && path.len() >= 2 && path[1] == "link";
+
+            match (parent, name.as_str()) {
+                ("", "robot") => urdf.name = attrs.get("name").cloned().unwrap_or_default(),
+                ("robot", "link") => {
+                    let name = attrs.get("name").cloned().ok_or_else(|| anyhow!("urdf: link without name"))?;
+                    link = Some(Link { name, ..Default::default() });
+                }
+                ("robot", "joint") => {
+                    let name = attrs.get("name").cloned().ok_or_else(|| anyhow!("urdf: joint without name"))?;
+                    let kind = match attrs.get("type").map(String::as_str) {
+                        Some("revolute") => JointKind::Revolute,
+                        Some("continuous") => JointKind::Continuous,
+                        Some("prismatic") => JointKind::Prismatic,
+                        Some("fixed") => JointKind::Fixed,
+                        Some("floating") => JointKind::Floating,
+                        other => bail!("urdf: joint {name}: unsupported type {other:?}"),
+                    };
+                    joint = Some(Joint {
+                        name,
+                        kind,
+                        parent: String::new(),
+                        child: String::new(),
+                        origin: Origin::default(),
+                        axis: [1.0, 0.0, 0.0],
+                        limit: None,
+                        damping: 0.0,
+                    });
+                }
+                ("inertial", "origin") if in_link => {
+                    link.as_mut().unwrap().inertial.get_or_insert_with(default_inertial).origin = origin(&attrs)?
+                }
+                ("inertial", "mass") if in_link => {
+                    link.as_mut().unwrap().inertial.get_or_insert_with(default_inertial).mass = float(&attrs, "value", 0.0)?
+                }
+                ("inertial", "inertia") if in_link => {
+                    link.as_mut().unwrap().inertial.get_or_insert_with(default_inertial).inertia =
+                        [float(&attrs, "ixx", 0.0)?, float(&attrs, "iyy", 0.0)?, float(&attrs, "izz", 0.0)?]
+                }
+                ("link", "collision") if in_link => collision = Some((Origin::default(), None)),
+                ("collision", "origin") => {
+                    if let Some(c) = collision.as_mut() {
+                        c.0 = origin(&attrs)?;
+                    }
+                }
+                ("geometry", shape) if collision.is_some() => {
+                    let geometry = match shape {
+                        "box" => Some(Geometry::Box { size: floats(&attrs, "size", [0.0; 3])? }),
+                        "cylinder" => {
+                            Some(Geometry::Cylinder { radius: float(&attrs, "radius", 0.0)?, length: float(&attrs, "length", 0.0)? })
+                        }
+                        "sphere" => Some(Geometry::Sphere { radius: float(&attrs, "radius", 0.0)? }),
+                        "mesh" => Some(Geometry::Mesh {
+                            filename: attrs.get("filename").cloned().unwrap_or_default(),
+                            scale: floats(&attrs, "scale", [1.0; 3])?,
+                        }),
+                        _ => None,
+                    };
+                    collision.as_mut().unwrap().1 = geometry;
+                }
+                ("joint", field) if joint.is_some() => {
+                    let j = joint.as_mut().unwrap();
+                    match field {
+                        "origin" => j.origin = origin(&attrs)?,
+                        "parent" => j.parent = attrs.get("link").cloned().unwrap_or_default(),
+                        "child" => j.child = attrs.get("link").cloned().unwrap_or_default(),
+                        "axis" => {
+                            let axis = Vec3::from(floats(&attrs, "xyz", [1.0, 0.0, 0.0])?);
+                            if axis.length_squared() < 1e-12 {
+                                bail!("urdf: joint {}: zero axis", j.name);
+                            }
+                            j.axis = axis.normalize().into();
+                        }
+                        "limit" => {
+                            j.limit = Some(Limit {
+                                lower: float(&attrs, "lower", 0.0)?,
+                                upper: float(&attrs, "upper", 0.0)?,
+                                effort: float(&attrs, "effort", 0.0)?,
+                                velocity: float(&attrs, "velocity", 0.0)?,
+                            })
+                        }
+                        "dynamics" => j.damping = float(&attrs, "damping", 0.0)?,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+
+            if empty {
+                Self::close(&mut urdf, &path, &name, &mut link, &mut joint, &mut collision)?;
+            } else {
+                path.push(name);
+            }
+            buf.clear();
+        }
+        urdf.validate()?;
+        Ok(urdf)
+    }
+
+    /// Finish the element `name` whose parent is `path.last()`.
+    fn close(
+        urdf: &mut Urdf,
+        path: &[String],
+        name: &str,
+        link: &mut Option<Link>,
+        joint: &mut Option<Joint>,
+        collision: &mut Option<(Origin, Option<Geometry>)>,
+    ) -> Result<()> {
+        match (path.last().map(String::as_str), name) {
+            (Some("robot"), "link") => urdf.links.extend(link.take()),
+            (Some("robot"), "joint") => urdf.joints.extend(joint.take()),
+            (Some("link"), "collision") => {
+                if let (Some(l), Some((origin, Some(geometry)))) = (link.as_mut(), collision.take()) {
+                    l.collisions.push(Collision { origin, geometry });
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        let mut parent_of: HashMap<&str, &str> = HashMap::new();
+        for j in &self.joints {
+            if j.parent.is_empty() || j.child.is_empty() {
+                bail!("urdf: joint {} needs a parent and a child", j.name);
+            }
+            for l in [&j.parent, &j.child] {
+                if l != WORLD_LINK && self.link(l).is_none() {
+                    bail!("urdf: joint {} references unknown link {l}", j.name);
+                }
+            }
+            if parent_of.insert(&j.child, &j.parent).is_some() {
+                bail!("urdf: link {} has more than one parent joint", j.child);
+            }
+            if j.kind == JointKind::Floating && j.parent != WORLD_LINK {
+                bail!("urdf: floating joint {} is only supported from {WORLD_LINK}", j.name);
+            }
+        }
+        // A loop of links (B → C → B) next to the real tree has no world parent, so `root`
+        // cannot see it
+        let (root, _) = self.root()?;
+        let mut reached = vec![root.name.as_str()];
+        let mut i = 0;
+        while let Some(&link) = reached.get(i) {
+            reached.extend(self.joints.iter().filter(|j| j.parent == link).map(|j| j.child.as_str()));
+            i += 1;
+        }
+        if let Some(lost) = self.links.iter().find(|l| !reached.contains(&l.name.as_str())) {
+            bail!("urdf: link {} is not connected to the root link {}", lost.name, root.name);
+        }
+        Ok(())
+    }
+
+    pub fn link(&self, name: &str) -> Option<&Link> {
+        self.links.iter().find(|l| l.name == name)
+    }
+
+    /// Joint whose child is `link`.
+    pub fn parent_joint(&self, link: &str) -> Option<&Joint> {
+        self.joints.iter().find(|j| j.child == link)
+    }
+
+    /// The link every other link hangs off, and whether it is fixed to the world.
+    pub fn root(&self) -> Result<(&Link, bool)> {
+        let mut roots = self.links.iter().filter(|l| self.parent_joint(&l.name).map_or(true, |j| j.parent == WORLD_LINK));
+        let root = roots.next().ok_or_else(|| anyhow!("urdf: no root link (cycle?)"))?;
+        if let Some(other) = roots.next() {
+            bail!("urdf: more than one root link ({} and {})", root.name, other.name);
+        }
+        let fixed = self.parent_joint(&root.name).is_some_and(|j| j.kind == JointKind::Fixed);
+        Ok((root, fixed))
+    }
+
+    /// Joints with a motor, in document order; this is the action order of the sim.
+    pub fn actuated_joints(&self) -> impl Iterator<Item = &Joint> {
+        self.joints.iter().filter(|j| j.kind.is_actuated() && j.parent != WORLD_LINK)
+    }
+}
+
+fn default_inertial() -> Inertial {
+    Inertial { origin: Origin::default(), mass: 0.0, inertia: [0.0; 3] }
+}
+
+/// How [`spawn`] fills in what the URDF leaves out.
+#[derive(Debug, Clone)]
+pub struct UrdfOptions {
+    /// Mass of links without an `<inertial>` (kg).
+    pub default_mass: f32,
+    /// Position motor stiffness and damping (per rad or per m).
+    pub motor_stiffness: f32,
+    pub motor_damping: f32,
+    /// Where `package://<pkg>/…` and `pkg://<pkg>/…` meshes are looked up.
+    pub mesh_root: PathBuf,
+}
+
+impl Default for UrdfOptions {
+    fn default() -> Self {
+        Self { default_mass: 0.1, motor_stiffness: 50.0, motor_damping: 2.0, mesh_root: PathBuf::from("assets") }
+    }
+}
+
+/// One motorised joint of a spawned robot.
+#[derive(Debug, Clone)]
+pub struct SpawnedJoint {
+    pub name: String,
+    /// Bus motor ID: `<n>` of a `..._motor_<n>` joint name, else the 1-based joint index.
+    pub motor_id: u32,
+    pub kind: JointKind,
+    /// Entity of the child link, which carries the [`MultibodyJoint`].
+    pub entity: Entity,
+    pub parent: Entity,
+    pub origin: Origin,
+    pub axis: Vec3,
+    pub limit: Option<Limit>,
+    /// Position motor gains, including the URDF `<dynamics damping>`.
+    pub stiffness: f32,
+    pub damping: f32,
+}
+
+impl SpawnedJoint {
+    /// Range an action of −1…1 maps to: the URDF limits, or ±π for continuous joints.
+    pub fn range(&self) -> (f32, f32) {
+        match (self.kind, self.limit) {
+            (JointKind::Continuous, _) => (-std::f32::consts::PI, std::f32::consts::PI),
+            (_, Some(l)) if l.upper > l.lower => (l.lower, l.upper),
+            (JointKind::Prismatic, _) => (-0.1, 0.1),
+            _ => (-std::f32::consts::PI, std::f32::consts::PI),
+        }
+    }
+
+    /// Position (rad or m) and velocity of the joint from the two link bodies.
+    pub fn state(&self, world: &World) -> (f32, f32) {
+        let (Some(p), Some(c)) = (world.get::<Transform>(self.parent), world.get::<Transform>(self.entity)) else {
+            return (0.0, 0.0);
+        };
+        let pv = world.get::<Velocity>(self.parent).copied().unwrap_or_default();
+        let cv = world.get::<Velocity>(self.entity).copied().unwrap_or_default();
+        let world_axis = c.rotation * self.axis;
+        match self.kind {
+            JointKind::Prismatic => {
+                let local = p.rotation.inverse() * (c.translation - p.translation) - Vec3::from(self.origin.xyz);
+                let position = local.dot(self.origin.rotation() * self.axis);
+                (position, (cv.linvel - pv.linvel).dot(world_axis))
+            }
+            _ => {
+                let rel = self.origin.rotation().inverse() * p.rotation.inverse() * c.rotation;
+                let position = 2.0 * Vec3::new(rel.x, rel.y, rel.z).dot(self.axis).atan2(rel.w);
+                let position = (position + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+                (position, (cv.angvel - pv.angvel).dot(world_axis))
+            }
+        }
+    }
+
+    fn motor_axis(&self) -> JointAxis {
+        if self.kind == JointKind::Prismatic {
+            JointAxis::X
+        } else {
+            JointAxis::AngX
+        }
+    }
+
+    /// Drive the joint's position motor towards `target` (rad or m).
+    pub fn set_target(&self, world: &mut World, target: f32) {
+        if let Some(mut joint) = world.get_mut::<MultibodyJoint>(self.entity) {
+            joint.data.set_motor_position(self.motor_axis(), target, self.stiffness, self.damping);
+        }
+    }
+
+    /// Drive the joint at `velocity` (rad/s or m/s) instead of towards a position.
+    pub fn set_velocity(&self, world: &mut World, velocity: f32) {
+        if let Some(mut joint) = world.get_mut::<MultibodyJoint>(self.entity) {
+            joint.data.set_motor_velocity(self.motor_axis(), velocity, self.damping);
+        }
+    }
+}
+
+/// Entities of a robot built by [`spawn`].
+#[derive(Debug, Clone)]
+pub struct UrdfRobot {
+    pub name: String,
+    pub root: Entity,
+    pub links: HashMap<String, Entity>,
+    /// Actuated joints in [`Urdf::actuated_joints`] order.
+    pub joints: Vec<SpawnedJoint>,
+}
+
+impl UrdfRobot {
+    pub fn despawn(&self, world: &mut World) {
+        for &entity in self.links.values() {
+            if let Some(e) = world.get_entity_mut(entity) {
+                e.despawn_recursive();
+            }
+        }
+    }
+}
+
+fn resolve_mesh(filename: &str, urdf: &Urdf, options: &UrdfOptions) -> PathBuf {
+    for prefix in ["package://", "pkg://"] {
+        if let Some(rest) = filename.strip_prefix(prefix) {
+            return options.mesh_root.join(rest);
+        }
+    }
+    let path = PathBuf::from(filename.strip_prefix("file://").unwrap_or(filename));
+    match &urdf.base_dir {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path,
+    }
+}
+
+fn mesh_collider(path: &Path, scale: Vec3) -> Option<Collider> {
+    let (models, _) = tobj::load_obj(path, &tobj::LoadOptions::default()).ok()?;
+    let points: Vec<Vec3> = models
+        .iter()
+        .flat_map(|m| m.mesh.positions.chunks_exact(3).map(|p| Vec3::new(p[0], p[1], p[2]) * scale))
+        .collect();
+    Collider::convex_hull(&points)
+}
+
+fn collider(geometry: &Geometry, urdf: &Urdf, options: &UrdfOptions) -> Option<(Collider, Quat)> {
+    match geometry {
+        Geometry::Box { size } => Some((Collider::cuboid(size[0] / 2.0, size[1] / 2.0, size[2] / 2.0), Quat::IDENTITY)),
+        // Rapier cylinders run along Y, URDF ones along Z
+        Geometry::Cylinder { radius, length } => {
+            Some((Collider::cylinder(length / 2.0, *radius), Quat::from_rotation_x(std::f32::consts::FRAC_PI_2)))
+        }
+        Geometry::Sphere { radius } => Some((Collider::ball(*radius), Quat::IDENTITY)),
+        Geometry::Mesh { filename, scale } => {
+            let path = resolve_mesh(filename, urdf, options);
+            match mesh_collider(&path, Vec3::from(*scale)) {
+                Some(c) => Some((c, Quat::IDENTITY)),
+                None => {
+                    eprintln!("[sim] urdf: no collider for mesh {filename} ({path:?})");
+                    None
+                }
+            }
+        }
+    }
+}
+
+fn mass_properties(link: &Link, options: &UrdfOptions) -> MassProperties {
+    match &link.inertial {
+        Some(i) if i.mass > 0.0 => MassProperties {
+            local_center_of_mass: Vec3::from(i.origin.xyz),
+            mass: i.mass,
+            principal_inertia_local_frame: i.origin.rotation(),
+            principal_inertia: Vec3::from(i.inertia).max(Vec3::splat(1e-6)),
+        },
+        _ => {
+            // Solid 5 cm sphere of the default mass
+            let inertia = 0.4 * options.default_mass * 0.05 * 0.05;
+            MassProperties {
+                local_center_of_mass: Vec3::ZERO,
+                mass: options.default_mass,
+                principal_inertia_local_frame: Quat::IDENTITY,
+                principal_inertia: Vec3::splat(inertia),
+            }
+        }
+    }
+}
+
+fn multibody_joint(j: &Joint, parent: Entity, options: &UrdfOptions) -> MultibodyJoint {
+    let mask = match j.kind {
+        JointKind::Revolute | JointKind::Continuous => JointAxesMask::LOCKED_REVOLUTE_AXES,
+        JointKind::Prismatic => JointAxesMask::LOCKED_PRISMATIC_AXES,
+        JointKind::Fixed | JointKind::Floating => JointAxesMask::LOCKED_FIXED_AXES,
+    };
+    // Joint X axis along the URDF axis; the parent frame also carries the joint origin so that
+    // zero position is the URDF rest pose.
+    let axis = Vec3::from(j.axis);
+    let basis = Quat::from_rotation_arc(Vec3::X, axis);
+    let mut data = GenericJointBuilder::new(mask)
+        .local_anchor1(Vec3::from(j.origin.xyz))
+        .local_anchor2(Vec3::ZERO)
+        .build();
+    data.set_local_basis1(j.origin.rotation() * basis);
+    data.set_local_basis2(basis);
+    data.set_contacts_enabled(false);
+
+    if j.kind.is_actuated() {
+        let motor_axis = if j.kind == JointKind::Prismatic { JointAxis::X } else { JointAxis::AngX };
+        if let Some(limit) = j.limit.filter(|l| j.kind != JointKind::Continuous && l.upper > l.lower) {
+            data.set_limits(motor_axis, [limit.lower, limit.upper]);
+        }
+        let damping = options.motor_damping + j.damping;
+        data.set_motor_position(motor_axis, 0.0, options.motor_stiffness, damping);
+        if let Some(effort) = j.limit.map(|l| l.effort).filter(|e| *e > 0.0) {
+            data.set_motor_max_force(motor_axis, effort);
+        }
+    }
+    MultibodyJoint::new(parent, data)
+}
+
+/// Spawn `urdf` with its root link at `base`.  On error nothing of the robot is left in `world`.
+pub fn spawn(world: &mut World, urdf: &Urdf, base: Transform, options: &UrdfOptions) -> Result<UrdfRobot> {
+    let mut robot = UrdfRobot { name: urdf.name.clone(), root: Entity::PLACEHOLDER, links: HashMap::new(), joints: Vec::new() };
+    match spawn_into(world, urdf, base, options, &mut robot) {
+        Ok(()) => Ok(robot),
+        Err(e) => {
+            robot.despawn(world);
+            Err(e)
+        }
+    }
+}
+
+fn spawn_into(world: &mut World, urdf: &Urdf, base: Transform, options: &UrdfOptions, robot: &mut UrdfRobot) -> Result<()> {
+    let (root, root_fixed) = urdf.root()?;
+    // A joint from `world` places the root relative to `base`
+    let root_pose = base * urdf.parent_joint(&root.name).map(|j| j.origin.transform()).unwrap_or_default();
+
+    let links = &mut robot.links;
+    let entity_of = |links: &HashMap<String, Entity>, link: &str| {
+        links.get(link).copied().ok_or_else(|| anyhow!("urdf: link {link} is not connected to the root link"))
+    };
+    // Parents before children so each joint can name an existing entity
+    let mut queue = vec![(root, root_pose, None::<&Joint>)];
+    while let Some((link, pose, joint)) = queue.pop() {
+        let body = if joint.is_none() && root_fixed { RigidBody::Fixed } else { RigidBody::Dynamic };
+        if links.contains_key(&link.name) {
+            bail!("urdf: link {} has more than one parent joint", link.name);
+        }
+        let parent = joint.map(|j| entity_of(links, &j.parent)).transpose()?;
+        let mut entity = world.spawn((
+            Name::new(link.name.clone()),
+            TransformBundle::from(pose),
+            body,
+            Velocity::zero(),
+            AdditionalMassProperties::MassProperties(mass_properties(link, options)),
+            Sleeping::disabled(),
+        ));
+        if let (Some(j), Some(parent)) = (joint, parent) {
+            entity.insert(multibody_joint(j, parent, options));
+        }
+        let id = entity.id();
+        for c in &link.collisions {
+            if let Some((shape, correction)) = collider(&c.geometry, urdf, options) {
+                let local = c.origin.transform().with_rotation(c.origin.rotation() * correction);
+                // Mass comes from the inertial, not from collider volume
+                world
+                    .spawn((TransformBundle::from(local), shape, ColliderMassProperties::Density(0.0)))
+                    .set_parent(id);
+            }
+        }
+        links.insert(link.name.clone(), id);
+
+        for j in urdf.joints.iter().filter(|j| j.parent == link.name) {
+            let child = urdf.link(&j.child).ok_or_else(|| anyhow!("urdf: unknown link {}", j.child))?;
+            queue.push((child, pose * j.origin.transform(), Some(j)));
+        }
+    }
+
+    robot.root = entity_of(links, &root.name)?;
+    for (i, j) in urdf.actuated_joints().enumerate() {
+        robot.joints.push(SpawnedJoint {
+            name: j.name.clone(),
+            motor_id: motor_id(&j.name).unwrap_or(i as u32 + 1),
+            kind: j.kind,
+            entity: entity_of(&robot.links, &j.child)?,
+            parent: entity_of(&robot.links, &j.parent)?,
+            origin: j.origin,
+            axis: Vec3::from(j.axis),
+            limit: j.limit,
+            stiffness: options.motor_stiffness,
+            damping: options.motor_damping + j.damping,
+        });
+    }
+    Ok(())
+}
+
+/// `mind::morphology` names the joint of motor `n` `joint_motor_<n>`.
+fn motor_id(joint: &str) -> Option<u32> {
+    joint.rsplit_once("motor_").and_then(|(_, id)| id.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A robot with `links` and `joints` pasted in verbatim.
+    fn robot(links: &[&str], joints: &str) -> String {
+        let links: String = links.iter().map(|l| format!("<link name=\"{l}\"/>")).collect();
+        format!("<robot name=\"test\">{links}{joints}</robot>")
+    }
+
+    fn joint(name: &str, kind: &str, parent: &str, child: &str) -> String {
+        format!("<joint name=\"{name}\" type=\"{kind}\"><parent link=\"{parent}\"/><child link=\"{child}\"/></joint>")
+    }
+
+    fn error(xml: &str) -> String {
+        Urdf::parse(xml).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_limits_axis_and_dynamics() {
+        let xml = robot(
+            &["base", "arm"],
+            r#"<joint name="shoulder" type="revolute">
+                 <parent link="base"/><child link="arm"/>
+                 <origin xyz="0 0 0.5" rpy="0 0 1.5"/>
+                 <axis xyz="0 0 2"/>
+                 <limit lower="-1.5" upper="1.5" effort="20" velocity="3"/>
+                 <dynamics damping="0.1"/>
+               </joint>"#,
+        );
+        let urdf = Urdf::parse(&xml).unwrap();
+        let j = &urdf.joints[0];
+        assert_eq!(j.kind, JointKind::Revolute);
+        assert_eq!(j.origin, Origin { xyz: [0.0, 0.0, 0.5], rpy: [0.0, 0.0, 1.5] });
+        // Normalised
+        assert_eq!(j.axis, [0.0, 0.0, 1.0]);
+        assert_eq!(j.limit, Some(Limit { lower: -1.5, upper: 1.5, effort: 20.0, velocity: 3.0 }));
+        assert_eq!(j.damping, 0.1);
+        assert_eq!(urdf.root().unwrap().0.name, "base");
+        assert_eq!(urdf.actuated_joints().count(), 1);
+    }
+
+    #[test]
+    fn rejects_bad_axis_and_limit() {
+        let zero = robot(&["a", "b"], r#"<joint name="j" type="revolute"><parent link="a"/><child link="b"/><axis xyz="0 0 0"/></joint>"#);
+        assert!(error(&zero).contains("zero axis"));
+        let short = robot(&["a", "b"], r#"<joint name="j" type="revolute"><parent link="a"/><child link="b"/><axis xyz="0 1"/></joint>"#);
+        assert!(error(&short).contains("needs 3 values"));
+        let nan = robot(&["a", "b"], r#"<joint name="j" type="revolute"><parent link="a"/><child link="b"/><limit lower="low"/></joint>"#);
+        assert!(error(&nan).contains("bad lower"));
+    }
+
+    #[test]
+    fn rejects_unknown_link() {
+        let xml = robot(&["base"], &joint("j", "fixed", "base", "ghost"));
+        assert!(error(&xml).contains("unknown link ghost"));
+    }
+
+    #[test]
+    fn rejects_multiple_parents() {
+        let joints = joint("j1", "fixed", "a", "c") + &joint("j2", "fixed", "b", "c");
+        let xml = robot(&["a", "b", "c"], &joints);
+        assert!(error(&xml).contains("more than one parent joint"));
+    }
+
+    #[test]
+    fn rejects_detached_loop() {
+        // b → c → b has no world parent, so only the reachability check catches it
+        let joints = joint("j1", "fixed", "a", "d") + &joint("j2", "revolute", "b", "c") + &joint("j3", "revolute", "c", "b");
+        let xml = robot(&["a", "b", "c", "d"], &joints);
+        assert!(error(&xml).contains("not connected to the root link a"));
+    }
+
+    #[test]
+    fn floating_only_from_world() {
+        let xml = robot(&["a", "b"], &joint("j", "floating", "a", "b"));
+        assert!(error(&xml).contains("only supported from world"));
+
+        let urdf = Urdf::parse(&robot(&["a"], &joint("j", "floating", WORLD_LINK, "a"))).unwrap();
+        assert_eq!(urdf.root().unwrap(), (urdf.link("a").unwrap(), false));
+        let urdf = Urdf::parse(&robot(&["a"], &joint("j", "fixed", WORLD_LINK, "a"))).unwrap();
+        assert!(urdf.root().unwrap().1);
+    }
+}
@@ -0,0 +1,283 @@
+//! Water volumes: buoyancy, hydrodynamic drag and added mass
+//! -----------------------------------------------------------------------------
+//! [`WaterPlugin`] applies fluid forces to every rigid body carrying a
+//! [`Hydrodynamics`] component, from the [`WaterVolume`] entities in the world.
+//!
+//! Each collider of such a body is sampled once on a small grid of points
+//! inside its shape; every point stands for an equal share of the collider's
+//! volume.  Each physics step, the points below the (optionally wavy) surface
+//! of a volume contribute:
+//!
+//! • **Buoyancy** – ρ·g·V upward at the point, so partially submerged bodies get
+//!   the righting torque from their centre of buoyancy.
+//! • **Drag** – linear (`linear_drag`·V·v) plus quadratic (½·ρ·Cd·A·|v|·v)
+//!   against the point's velocity relative to the current, and a rotational
+//!   damping term on the whole body.
+//! • **Added mass** – Ca·ρ·V_submerged times the body's relative acceleration
+//!   over the last step, opposing it.  This is explicit, so it is capped at the
+//!   body's own mass to stay stable.
+//!
+//! Forces go through [`ExternalImpulse`] (force × dt), leaving
+//! [`ExternalForce`] to actuators.
+//! -----------------------------------------------------------------------------
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+const GRAVITY: f32 = 9.81;
+
+/// A sinusoidal surface wave.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wave {
+    /// Crest height above the mean surface (m).
+    pub amplitude: f32,
+    pub wavelength: f32,
+    /// Travel direction in the XZ plane.
+    pub direction: Vec2,
+}
+
+impl Wave {
+    /// Height offset at `(x, z)` and time `t`, travelling at the deep-water phase speed.
+    pub fn height(&self, x: f32, z: f32, t: f32) -> f32 {
+        if self.wavelength <= 0.0 {
+            return 0.0;
+        }
+        let k = std::f32::consts::TAU / self.wavelength;
+        let omega = (GRAVITY * k).sqrt();
+        let d = self.direction.normalize_or_zero();
+        self.amplitude * (k * (d.x * x + d.y * z) - omega * t).sin()
+    }
+}
+
+/// An axis-aligned body of water.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct WaterVolume {
+    /// XZ extent of the volume (world frame).
+    pub min: Vec2,
+    pub max: Vec2,
+    /// Mean surface height (m).
+    pub surface: f32,
+    /// Bottom of the volume (m).
+    pub floor: f32,
+    /// kg/m³; 1000 for fresh water.
+    pub density: f32,
+    /// Uniform flow velocity (m/s).
+    pub current: Vec3,
+    pub waves: Vec<Wave>,
+}
+
+impl WaterVolume {
+    /// Still fresh water over `min`..`max` from `floor` up to `surface`.
+    pub fn new(min: Vec2, max: Vec2, floor: f32, surface: f32) -> Self {
+        Self { min, max, surface, floor, density: 1000.0, current: Vec3::ZERO, waves: Vec::new() }
+    }
+
+    pub fn with_current(mut self, current: Vec3) -> Self {
+        self.current = current;
+        self
+    }
+
+    pub fn with_wave(mut self, wave: Wave) -> Self {
+        self.waves.push(wave);
+        self
+    }
+
+    /// Surface height at `(x, z)` at time `t`.
+    pub fn surface_height(&self, x: f32, z: f32, t: f32) -> f32 {
+        self.surface + self.waves.iter().map(|w| w.height(x, z, t)).sum::<f32>()
+    }
+
+    /// Whether `p` is under water at time `t`.
+    pub fn contains(&self, p: Vec3, t: f32) -> bool {
+        p.x >= self.min.x
+            && p.x <= self.max.x
+            && p.z >= self.min.y
+            && p.z <= self.max.y
+            && p.y >= self.floor
+            && p.y <= self.surface_height(p.x, p.z, t)
+    }
+}
+
+/// Fluid response of a rigid body; add it next to [`RigidBody`].
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Hydrodynamics {
+    /// Linear drag per submerged volume (N·s/m per m³).
+    pub linear_drag: f32,
+    /// Quadratic drag coefficient Cd.
+    pub drag_coefficient: f32,
+    /// Rotational damping per submerged volume (N·m·s per m³).
+    pub angular_drag: f32,
+    /// Added-mass coefficient Ca (0.5 for a sphere).
+    pub added_mass: f32,
+    /// Sample points per axis when discretising each collider.
+    pub resolution: u32,
+}
+
+impl Default for Hydrodynamics {
+    fn default() -> Self {
+        Self { linear_drag: 50.0, drag_coefficient: 1.0, angular_drag: 5.0, added_mass: 0.5, resolution: 4 }
+    }
+}
+
+/// Per-body state the plugin keeps between steps.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct HydroState {
+    /// Submerged volume at the last step (m³).
+    pub submerged_volume: f32,
+    /// Total fluid force and torque applied at the last step.
+    pub force: Vec3,
+    pub torque: Vec3,
+    last_velocity: Option<Vec3>,
+}
+
+/// Seconds of simulated time, advancing with the physics step; drives the waves.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct WaterClock(pub f32);
+
+pub struct WaterPlugin;
+
+impl Plugin for WaterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WaterClock>().add_systems(Update, (init_hydro_bodies, apply_water_forces).chain());
+    }
+}
+
+fn init_hydro_bodies(mut commands: Commands, added: Query<Entity, Added<Hydrodynamics>>) {
+    for entity in &added {
+        commands.entity(entity).insert((HydroState::default(), ReadMassProperties::default(), ExternalImpulse::default()));
+    }
+}
+
+/// Local sample points of a collider and the volume each stands for.
+struct Samples {
+    points: Vec<Vec3>,
+    volume_per_point: f32,
+}
+
+fn sample_collider(collider: &Collider, resolution: u32) -> Samples {
+    let n = resolution.max(1);
+    let aabb = collider.raw.compute_local_aabb();
+    let (lo, hi) = (Vec3::new(aabb.mins.x, aabb.mins.y, aabb.mins.z), Vec3::new(aabb.maxs.x, aabb.maxs.y, aabb.maxs.z));
+    let mut points = Vec::new();
+    for i in 0..n {
+        for j in 0..n {
+            for k in 0..n {
+                let f = (Vec3::new(i as f32, j as f32, k as f32) + 0.5) / n as f32;
+                let p = lo + (hi - lo) * f;
+                if collider.contains_local_point(p) {
+                    points.push(p);
+                }
+            }
+        }
+    }
+    if points.is_empty() {
+        points.push((lo + hi) * 0.5);
+    }
+    // Volume from the shape itself, so coarse grids still float at the right height
+    let volume = collider.raw.mass_properties(1.0).mass();
+    Samples { volume_per_point: volume / points.len() as f32, points }
+}
+
+fn physics_dt(config: &RapierConfiguration, time: &Time) -> f32 {
+    match config.timestep_mode {
+        TimestepMode::Fixed { dt, .. } => dt,
+        _ => time.delta_seconds(),
+    }
+}
+
+/// Fluid load on one body, summed over its colliders.
+#[derive(Default)]
+struct Load {
+    force: Vec3,
+    torque: Vec3,
+    volume: f32,
+    /// Σ ρ·V of the submerged points (displaced mass).
+    displaced: f32,
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_water_forces(
+    time: Res<Time>,
+    config: Res<RapierConfiguration>,
+    mut clock: ResMut<WaterClock>,
+    mut cache: Local<HashMap<Entity, Samples>>,
+    volumes: Query<&WaterVolume>,
+    colliders: Query<(Entity, &Collider, &GlobalTransform, Option<&Parent>)>,
+    mut bodies: Query<(
+        Entity,
+        &Hydrodynamics,
+        &GlobalTransform,
+        &Velocity,
+        &ReadMassProperties,
+        &mut HydroState,
+        &mut ExternalImpulse,
+    )>,
+) {
+    let dt = physics_dt(&config, &time);
+    if dt <= 0.0 {
+        return;
+    }
+    clock.0 += dt;
+    let t = clock.0;
+    cache.retain(|e, _| colliders.contains(*e));
+
+    let mut loads: HashMap<Entity, Load> = HashMap::new();
+    for (entity, collider, transform, parent) in &colliders {
+        // Colliders sit on the body itself or on a child entity of it
+        let owner = match parent {
+            _ if bodies.contains(entity) => entity,
+            Some(p) if bodies.contains(p.get()) => p.get(),
+            _ => continue,
+        };
+        let Ok((_, hydro, body_tf, velocity, mass, _, _)) = bodies.get(owner) else { continue };
+        let samples = cache.entry(entity).or_insert_with(|| sample_collider(collider, hydro.resolution));
+        let com = body_tf.transform_point(mass.get().local_center_of_mass);
+
+        let submerged: Vec<(Vec3, &WaterVolume)> = samples
+            .points
+            .iter()
+            .map(|&p| transform.transform_point(p))
+            .filter_map(|p| volumes.iter().find(|w| w.contains(p, t)).map(|w| (p, w)))
+            .collect();
+        if submerged.is_empty() {
+            continue;
+        }
+        let v_pt = samples.volume_per_point;
+        // Frontal area of the submerged part, shared between its points
+        let area_pt = (v_pt * submerged.len() as f32).powf(2.0 / 3.0) / submerged.len() as f32;
+        let load = loads.entry(owner).or_default();
+        for (p, water) in submerged {
+            let r = p - com;
+            let v_rel = velocity.linvel + velocity.angvel.cross(r) - water.current;
+            let buoyancy = Vec3::Y * water.density * GRAVITY * v_pt;
+            let drag = -hydro.linear_drag * v_pt * v_rel
+                - 0.5 * water.density * hydro.drag_coefficient * area_pt * v_rel.length() * v_rel;
+            let f = buoyancy + drag;
+            load.force += f;
+            load.torque += r.cross(f);
+            load.volume += v_pt;
+            load.displaced += water.density * v_pt;
+        }
+    }
+
+    for (entity, hydro, _, velocity, mass, mut state, mut impulse) in &mut bodies {
+        let load = loads.remove(&entity).unwrap_or_default();
+        let (mut force, mut torque) = (load.force, load.torque);
+        if load.volume > 0.0 {
+            torque -= hydro.angular_drag * load.volume * velocity.angvel;
+            if let Some(last) = state.last_velocity {
+                // Capped at the body's own mass: beyond that the explicit term overshoots
+                let added = (hydro.added_mass * load.displaced).min(mass.get().mass);
+                force -= added * (velocity.linvel - last) / dt;
+            }
+        }
+        state.submerged_volume = load.volume;
+        state.force = force;
+        state.torque = torque;
+        state.last_velocity = (load.volume > 0.0).then_some(velocity.linvel);
+        impulse.impulse = force * dt;
+        impulse.torque_impulse = torque * dt;
+    }
+}